@@ -1,6 +1,6 @@
 use anyhow::Result;
 
-use crate::storage::{StorageHandle, TagDeleteOutcome, TagRenameOutcome};
+use crate::storage::{Notebook, SavedSearch, StorageHandle, TagDeleteOutcome, TagRenameOutcome};
 
 pub struct ActionDispatcher<'a> {
     storage: &'a StorageHandle,
@@ -35,10 +35,20 @@ impl<'a> ActionDispatcher<'a> {
         self.storage.delete_tag(tag)
     }
 
-    pub fn rename_note(&self, note_id: i64, title: &str) -> Result<()> {
+    /// Returns how many of the tags existed and were deleted.
+    pub fn delete_tags(&self, tags: &[String]) -> Result<usize> {
+        self.storage.delete_tags(tags)
+    }
+
+    /// Returns the number of notes whose links to the old title were rewritten.
+    pub fn rename_note(&self, note_id: i64, title: &str) -> Result<usize> {
         self.storage.rename_note_title(note_id, title)
     }
 
+    pub fn restore_revision(&self, note_id: i64, revision_id: i64) -> Result<()> {
+        self.storage.restore_note_revision(note_id, revision_id)
+    }
+
     pub fn soft_delete(&self, note_id: i64) -> Result<()> {
         self.storage.soft_delete_note(note_id)
     }
@@ -50,4 +60,26 @@ impl<'a> ActionDispatcher<'a> {
     pub fn purge_all_trash(&self) -> Result<usize> {
         self.storage.purge_all_trash()
     }
+
+    /// Files a note in a notebook, or takes it out of any with `None`.
+    pub fn move_note(&self, note_id: i64, notebook_id: Option<i64>) -> Result<()> {
+        self.storage.move_note_to_notebook(note_id, notebook_id)
+    }
+
+    pub fn create_notebook(&self, path: &str) -> Result<Notebook> {
+        self.storage.create_notebook(path)
+    }
+
+    /// Returns the number of notes moved up to the parent notebook.
+    pub fn delete_notebook(&self, notebook_id: i64) -> Result<usize> {
+        self.storage.delete_notebook(notebook_id)
+    }
+
+    pub fn save_search(&self, name: &str, query: &str) -> Result<SavedSearch> {
+        self.storage.save_search(name, query)
+    }
+
+    pub fn delete_saved_search(&self, name: &str) -> Result<bool> {
+        self.storage.delete_saved_search(name)
+    }
 }
@@ -0,0 +1,361 @@
+use strum::IntoEnumIterator;
+
+use super::keymap::{Action, KeyContext, Keymap};
+
+/// The view a help page describes. Each topic reads its bindings from the key
+/// context that handles input in that view, so the page always matches the
+/// active profile and `[keymap]` overrides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HelpTopic {
+    List,
+    Reader,
+    Notebooks,
+    Trash,
+    Editor,
+    TagEditor,
+    Recovery,
+    Revisions,
+    SavedSearches,
+    TagBrowser,
+    Dialog,
+}
+
+impl HelpTopic {
+    pub fn title(self) -> &'static str {
+        match self {
+            HelpTopic::List => "Notes",
+            HelpTopic::Reader => "Reader",
+            HelpTopic::Notebooks => "Notebooks",
+            HelpTopic::Trash => "Trash",
+            HelpTopic::Editor => "Editor",
+            HelpTopic::TagEditor => "Tag editor",
+            HelpTopic::Recovery => "Autosave recovery",
+            HelpTopic::Revisions => "Revision history",
+            HelpTopic::SavedSearches => "Saved searches",
+            HelpTopic::TagBrowser => "Tags",
+            HelpTopic::Dialog => "Confirmation",
+        }
+    }
+
+    fn context(self) -> KeyContext {
+        match self {
+            HelpTopic::List | HelpTopic::Trash => KeyContext::List,
+            HelpTopic::Reader => KeyContext::Reader,
+            HelpTopic::Notebooks => KeyContext::Notebooks,
+            HelpTopic::Editor => KeyContext::Editor,
+            HelpTopic::TagEditor => KeyContext::TagEditor,
+            HelpTopic::TagBrowser => KeyContext::TagBrowser,
+            HelpTopic::Recovery
+            | HelpTopic::Revisions
+            | HelpTopic::SavedSearches
+            | HelpTopic::Dialog => KeyContext::Overlay,
+        }
+    }
+
+    /// The actions that do something in this view, with a description worded for it.
+    fn actions(self) -> Vec<(Action, &'static str)> {
+        let described = |actions: &[Action]| {
+            actions
+                .iter()
+                .map(|action| (*action, action.description()))
+                .collect::<Vec<_>>()
+        };
+        let mut actions = match self {
+            HelpTopic::List | HelpTopic::Reader => Action::iter()
+                .filter(|action| {
+                    KeyContext::List.accepts(*action)
+                        && !matches!(
+                            action,
+                            Action::RestoreAllTrash | Action::PurgeAllTrash | Action::ShowHelp
+                        )
+                })
+                .map(|action| (action, action.description()))
+                .collect(),
+            HelpTopic::Notebooks => described(&[
+                Action::SelectNext,
+                Action::SelectPrevious,
+                Action::SelectFirst,
+                Action::SelectLast,
+                Action::PageDown,
+                Action::PageUp,
+                Action::OpenNotebook,
+                Action::ToggleNotebookExpanded,
+                Action::ToggleNotebooks,
+                Action::ToggleFocus,
+                Action::OpenPalette,
+                Action::Quit,
+            ]),
+            HelpTopic::Trash => described(&[
+                Action::SelectNext,
+                Action::SelectPrevious,
+                Action::SelectFirst,
+                Action::SelectLast,
+                Action::PageDown,
+                Action::PageUp,
+                Action::ToggleFocus,
+                Action::RestoreNote,
+                Action::RestoreAllTrash,
+                Action::PurgeAllTrash,
+                Action::ToggleTrashView,
+                Action::StartSearch,
+                Action::ToggleRegex,
+                Action::Refresh,
+                Action::OpenPalette,
+                Action::Quit,
+            ]),
+            HelpTopic::Editor => Action::iter()
+                .filter(|action| KeyContext::Editor.accepts(*action) && *action != Action::ShowHelp)
+                .map(|action| (action, action.description()))
+                .collect(),
+            HelpTopic::TagEditor => {
+                let mut actions = described(&[
+                    Action::SelectNext,
+                    Action::SelectPrevious,
+                    Action::PageDown,
+                    Action::PageUp,
+                ]);
+                actions.extend(
+                    Action::iter()
+                        .filter(|action| KeyContext::TagEditor.accepts(*action))
+                        .filter(|action| !KeyContext::Overlay.accepts(*action))
+                        .map(|action| (action, action.description())),
+                );
+                actions.push((Action::Confirm, "Save tag changes (or confirm a delete)"));
+                actions.push((Action::Cancel, "Close without saving (or back out)"));
+                actions
+            }
+            HelpTopic::Recovery => {
+                let mut actions = described(&[
+                    Action::SelectNext,
+                    Action::SelectPrevious,
+                    Action::PageDown,
+                    Action::PageUp,
+                    Action::DiscardDraft,
+                    Action::DiscardAllDrafts,
+                ]);
+                actions.push((Action::Confirm, "Restore the selected draft"));
+                actions.push((Action::Cancel, "Close and keep the drafts"));
+                actions
+            }
+            HelpTopic::Revisions => {
+                let mut actions = described(&[
+                    Action::SelectNext,
+                    Action::SelectPrevious,
+                    Action::PageDown,
+                    Action::PageUp,
+                ]);
+                actions.push((Action::Confirm, "Restore the selected revision"));
+                actions.push((Action::Cancel, "Close the history"));
+                actions
+            }
+            HelpTopic::SavedSearches => {
+                let mut actions = described(&[
+                    Action::SelectNext,
+                    Action::SelectPrevious,
+                    Action::PageDown,
+                    Action::PageUp,
+                ]);
+                actions.push((Action::Confirm, "Run the selected search"));
+                actions.push((Action::Cancel, "Close without searching"));
+                actions
+            }
+            HelpTopic::TagBrowser => {
+                let mut actions = described(&[
+                    Action::SelectNext,
+                    Action::SelectPrevious,
+                    Action::PageDown,
+                    Action::PageUp,
+                ]);
+                actions.extend([
+                    (Action::TagMark, "Mark the selected tag"),
+                    (Action::TagClearMarks, "Clear all tag marks"),
+                    (Action::CycleTagSort, Action::CycleTagSort.description()),
+                    (
+                        Action::ToggleUnusedTags,
+                        Action::ToggleUnusedTags.description(),
+                    ),
+                    (
+                        Action::TagDelete,
+                        "Delete the marked tags (or the selected one) everywhere",
+                    ),
+                    (
+                        Action::PruneUnusedTags,
+                        Action::PruneUnusedTags.description(),
+                    ),
+                    (
+                        Action::Confirm,
+                        "List notes with every marked tag (or confirm a delete)",
+                    ),
+                    (
+                        Action::Cancel,
+                        "Close the browser (or back out of a delete)",
+                    ),
+                ]);
+                actions
+            }
+            HelpTopic::Dialog => described(&[Action::Confirm, Action::Cancel]),
+        };
+        actions.push((Action::ShowHelp, "Show this help"));
+        actions
+    }
+
+    /// Keys read directly by text prompts rather than through the keymap.
+    fn fixed(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            HelpTopic::List | HelpTopic::Reader | HelpTopic::Trash => &[
+                (
+                    "Enter",
+                    "While searching: keep the filter and leave the prompt",
+                ),
+                ("Esc", "While searching: clear the filter"),
+                ("↑ / ↓", "While searching: step through earlier searches"),
+                (
+                    "Ctrl-r",
+                    "While searching: find an earlier search by text (Ctrl-r again for older)",
+                ),
+            ],
+            HelpTopic::Editor => &[(
+                "Enter / Tab / Backspace / Delete",
+                "Edit text at the cursor",
+            )],
+            HelpTopic::TagEditor => &[
+                ("1-9", "Apply a suggested tag"),
+                ("Enter / Esc", "While typing a tag name: commit / cancel"),
+            ],
+            HelpTopic::Notebooks
+            | HelpTopic::Recovery
+            | HelpTopic::Revisions
+            | HelpTopic::SavedSearches
+            | HelpTopic::TagBrowser
+            | HelpTopic::Dialog => &[],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelpEntry {
+    pub keys: String,
+    pub description: &'static str,
+    /// Action name for `[keymap]` overrides; empty for keys handled by text prompts.
+    pub action: &'static str,
+}
+
+impl HelpEntry {
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = filter.trim().to_lowercase();
+        filter.is_empty()
+            || self.keys.to_lowercase().contains(&filter)
+            || self.description.to_lowercase().contains(&filter)
+            || self.action.contains(&filter)
+    }
+}
+
+/// One row per bound action in `topic`, listing every key sequence that reaches it,
+/// followed by the fixed text-prompt keys. Actions without a binding are left out.
+pub fn entries(keymap: &Keymap, topic: HelpTopic) -> Vec<HelpEntry> {
+    let context = topic.context();
+    let mut entries: Vec<HelpEntry> = topic
+        .actions()
+        .into_iter()
+        .filter_map(|(action, description)| {
+            let keys = keymap.keys_for(context, action);
+            (!keys.is_empty()).then(|| HelpEntry {
+                keys: keys.join(" / "),
+                description,
+                action: action.name(),
+            })
+        })
+        .collect();
+    entries.extend(topic.fixed().iter().map(|(keys, description)| HelpEntry {
+        keys: keys.to_string(),
+        description,
+        action: "",
+    }));
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::keymap::parse_sequence;
+    use crate::config::KeybindingProfile;
+
+    fn keys_for(entries: &[HelpEntry], action: Action) -> Option<&str> {
+        entries
+            .iter()
+            .find(|entry| entry.action == action.name())
+            .map(|entry| entry.keys.as_str())
+    }
+
+    #[test]
+    fn entries_follow_the_active_keymap() {
+        let vim = Keymap::preset(&KeybindingProfile::Vim);
+        let list = entries(&vim, HelpTopic::List);
+        assert_eq!(keys_for(&list, Action::SelectFirst), Some("home / g g"));
+        assert_eq!(keys_for(&list, Action::ShowHelp), Some("? / f1"));
+        assert_eq!(keys_for(&list, Action::PurgeAllTrash), None);
+
+        let trash = entries(&vim, HelpTopic::Trash);
+        assert_eq!(keys_for(&trash, Action::PurgeAllTrash), Some("P"));
+        assert_eq!(keys_for(&trash, Action::TogglePin), None);
+
+        let emacs = Keymap::preset(&KeybindingProfile::Emacs);
+        let reader = entries(&emacs, HelpTopic::Reader);
+        assert_eq!(keys_for(&reader, Action::FollowLink), Some("enter"));
+        assert_eq!(
+            keys_for(&reader, Action::EnterEdit),
+            None,
+            "shadowed by enter"
+        );
+        assert_eq!(keys_for(&list, Action::NextLink), None);
+
+        let editor = entries(&emacs, HelpTopic::Editor);
+        assert_eq!(
+            keys_for(&editor, Action::Undo),
+            Some("ctrl-/ / ctrl-7 / ctrl-x u")
+        );
+        assert_eq!(keys_for(&editor, Action::ShowHelp), Some("f1"));
+        assert!(editor.iter().any(|entry| entry.action.is_empty()));
+
+        let recovery = entries(&emacs, HelpTopic::Recovery);
+        let confirm = recovery
+            .iter()
+            .find(|entry| entry.action == "confirm")
+            .unwrap();
+        assert_eq!(confirm.description, "Restore the selected draft");
+        assert_eq!(confirm.keys, "Y / enter / y");
+
+        let mut custom = Keymap::preset(&KeybindingProfile::Custom);
+        let bound: Vec<_> = entries(&custom, HelpTopic::List)
+            .into_iter()
+            .filter(|entry| !entry.action.is_empty())
+            .map(|entry| entry.action)
+            .collect();
+        assert_eq!(bound, vec!["quit", "show-help"]);
+        custom.bind(
+            KeyContext::List,
+            parse_sequence("x").unwrap(),
+            Action::DeleteNote,
+        );
+        assert_eq!(
+            keys_for(&entries(&custom, HelpTopic::List), Action::DeleteNote),
+            Some("x")
+        );
+    }
+
+    #[test]
+    fn filter_matches_keys_descriptions_and_action_names() {
+        let vim = Keymap::preset(&KeybindingProfile::Vim);
+        let list = entries(&vim, HelpTopic::List);
+        let hits: Vec<_> = list
+            .iter()
+            .filter(|entry| entry.matches("ARCHIVE"))
+            .map(|entry| entry.action)
+            .collect();
+        assert_eq!(hits, vec!["toggle-archive", "toggle-archive-view"]);
+        assert!(list.iter().all(|entry| entry.matches("  ")));
+        assert!(list
+            .iter()
+            .any(|entry| entry.matches("ctrl-s") && entry.action == "manual-save"));
+    }
+}
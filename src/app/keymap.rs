@@ -0,0 +1,1046 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use strum::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::config::{KeybindingProfile, KeymapOverrides};
+
+/// Everything a key binding can trigger. Names are the kebab-case strings used in
+/// `[keymap.*]` config tables (`toggle-pin`, `select-first`, ...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, EnumString, IntoStaticStr)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Action {
+    Quit,
+    SelectNext,
+    SelectPrevious,
+    SelectFirst,
+    SelectLast,
+    PageDown,
+    PageUp,
+    ToggleFocus,
+    Refresh,
+    NewNote,
+    RenameNote,
+    EnterEdit,
+    StartSearch,
+    TogglePin,
+    ToggleArchive,
+    DeleteNote,
+    ToggleRegex,
+    ToggleTrashView,
+    ToggleArchiveView,
+    CycleSort,
+    RestoreNote,
+    RestoreAllTrash,
+    PurgeAllTrash,
+    ShowTagEditor,
+    ShowRevisions,
+    ShowSavedSearches,
+    ShowTagBrowser,
+    FollowLink,
+    NextLink,
+    PreviousLink,
+    ToggleNotebooks,
+    OpenNotebook,
+    ToggleNotebookExpanded,
+    ToggleWrap,
+    ManualSave,
+    OpenPalette,
+    ShowHelp,
+    ExitEdit,
+    Undo,
+    Redo,
+    CursorLeft,
+    CursorRight,
+    CursorUp,
+    CursorDown,
+    WordLeft,
+    WordRight,
+    LineStart,
+    LineEnd,
+    Confirm,
+    Cancel,
+    DiscardDraft,
+    DiscardAllDrafts,
+    TagToggle,
+    TagMark,
+    TagClearMarks,
+    TagAdd,
+    TagRename,
+    TagMerge,
+    TagMergeMarked,
+    TagDelete,
+    CycleTagSort,
+    ToggleUnusedTags,
+    PruneUnusedTags,
+}
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        self.into()
+    }
+
+    /// One-line explanation shown next to the action in the command palette.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit the application",
+            Action::SelectNext => "Select the next note",
+            Action::SelectPrevious => "Select the previous note",
+            Action::SelectFirst => "Jump to the first note",
+            Action::SelectLast => "Jump to the last note",
+            Action::PageDown => "Move the selection down a page",
+            Action::PageUp => "Move the selection up a page",
+            Action::ToggleFocus => "Switch focus between the list and the reader",
+            Action::Refresh => "Reload notes from the database",
+            Action::NewNote => "Create a new note",
+            Action::RenameNote => "Rename the selected note",
+            Action::EnterEdit => "Edit the selected note",
+            Action::StartSearch => "Search notes",
+            Action::TogglePin => "Pin or unpin the selected note",
+            Action::ToggleArchive => "Archive or unarchive the selected note",
+            Action::DeleteNote => "Move the selected note to the trash",
+            Action::ToggleRegex => "Toggle regex search",
+            Action::ToggleTrashView => "Show or hide the trash",
+            Action::ToggleArchiveView => "Show or hide archived notes",
+            Action::CycleSort => "Cycle the list order (updated, created, title; each both ways)",
+            Action::RestoreNote => "Restore the selected note from the trash",
+            Action::RestoreAllTrash => "Restore every note in the trash",
+            Action::PurgeAllTrash => "Permanently delete every note in the trash",
+            Action::ShowTagEditor => "Edit tags of the selected note",
+            Action::ShowRevisions => "Browse revisions of the selected note",
+            Action::ShowSavedSearches => "Pick a saved search to run",
+            Action::ShowTagBrowser => "Browse every tag with its usage",
+            Action::FollowLink => "Open the note the selected link points to",
+            Action::NextLink => "Select the next link in the reader",
+            Action::PreviousLink => "Select the previous link in the reader",
+            Action::ToggleNotebooks => "Show or hide the notebook tree",
+            Action::OpenNotebook => "List the notes in the selected notebook",
+            Action::ToggleNotebookExpanded => "Collapse or expand the selected notebook",
+            Action::ToggleWrap => "Toggle line wrapping in the reader",
+            Action::ManualSave => "Save the current note now",
+            Action::OpenPalette => "Open the command palette",
+            Action::ShowHelp => "Show the key bindings for the current view",
+            Action::ExitEdit => "Stop editing",
+            Action::Undo => "Undo the last edit",
+            Action::Redo => "Redo the last undone edit",
+            Action::CursorLeft => "Move the cursor left",
+            Action::CursorRight => "Move the cursor right",
+            Action::CursorUp => "Move the cursor up",
+            Action::CursorDown => "Move the cursor down",
+            Action::WordLeft => "Move the cursor to the previous word",
+            Action::WordRight => "Move the cursor to the next word",
+            Action::LineStart => "Move the cursor to the start of the line",
+            Action::LineEnd => "Move the cursor to the end of the line",
+            Action::Confirm => "Confirm",
+            Action::Cancel => "Cancel",
+            Action::DiscardDraft => "Discard the selected draft",
+            Action::DiscardAllDrafts => "Discard all drafts",
+            Action::TagToggle => "Toggle the selected tag on the note",
+            Action::TagMark => "Mark the selected tag for merging",
+            Action::TagClearMarks => "Clear all tag marks",
+            Action::TagAdd => "Add a new tag",
+            Action::TagRename => "Rename the selected tag everywhere",
+            Action::TagMerge => "Merge the selected tag into another",
+            Action::TagMergeMarked => "Merge the marked tags into one",
+            Action::TagDelete => "Delete the selected tag everywhere",
+            Action::CycleTagSort => "Sort tags by name, usage or last use",
+            Action::ToggleUnusedTags => "Show only tags no note outside the trash uses",
+            Action::PruneUnusedTags => "Delete every unused tag",
+        }
+    }
+
+    fn is_editor(self) -> bool {
+        matches!(
+            self,
+            Action::ExitEdit
+                | Action::Undo
+                | Action::Redo
+                | Action::CursorLeft
+                | Action::CursorRight
+                | Action::CursorUp
+                | Action::CursorDown
+                | Action::WordLeft
+                | Action::WordRight
+                | Action::LineStart
+                | Action::LineEnd
+        )
+    }
+
+    fn is_overlay(self) -> bool {
+        matches!(
+            self,
+            Action::Confirm
+                | Action::Cancel
+                | Action::SelectNext
+                | Action::SelectPrevious
+                | Action::PageDown
+                | Action::PageUp
+                | Action::DiscardDraft
+                | Action::DiscardAllDrafts
+                | Action::ShowHelp
+        )
+    }
+
+    fn is_tag_editor(self) -> bool {
+        matches!(
+            self,
+            Action::TagToggle
+                | Action::TagMark
+                | Action::TagClearMarks
+                | Action::TagAdd
+                | Action::TagRename
+                | Action::TagMerge
+                | Action::TagMergeMarked
+                | Action::TagDelete
+        )
+    }
+
+    fn is_tag_browser(self) -> bool {
+        matches!(
+            self,
+            Action::TagMark
+                | Action::TagClearMarks
+                | Action::TagDelete
+                | Action::CycleTagSort
+                | Action::ToggleUnusedTags
+                | Action::PruneUnusedTags
+        )
+    }
+
+    fn is_main(self) -> bool {
+        !self.is_editor()
+            && !self.is_tag_editor()
+            && !self.is_tag_browser()
+            && !matches!(
+                self,
+                Action::Confirm | Action::Cancel | Action::DiscardDraft | Action::DiscardAllDrafts
+            )
+    }
+}
+
+/// Where a key press lands. Lookups fall back along [`KeyContext::fallback`], so
+/// list bindings also work from the reader and notebook panes and global ones
+/// work everywhere outside popups.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, EnumIter, EnumString, IntoStaticStr,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum KeyContext {
+    Global,
+    List,
+    Reader,
+    Notebooks,
+    Editor,
+    TagEditor,
+    TagBrowser,
+    Overlay,
+}
+
+impl KeyContext {
+    pub fn name(self) -> &'static str {
+        self.into()
+    }
+
+    fn fallback(self) -> Option<KeyContext> {
+        match self {
+            KeyContext::Reader | KeyContext::Notebooks => Some(KeyContext::List),
+            KeyContext::List | KeyContext::Editor => Some(KeyContext::Global),
+            KeyContext::TagEditor | KeyContext::TagBrowser => Some(KeyContext::Overlay),
+            KeyContext::Global | KeyContext::Overlay => None,
+        }
+    }
+
+    pub(super) fn accepts(self, action: Action) -> bool {
+        match self {
+            KeyContext::Global | KeyContext::List | KeyContext::Reader | KeyContext::Notebooks => {
+                action.is_main()
+            }
+            // Only the actions `App::handle_action` lets through while a note is being edited.
+            KeyContext::Editor => {
+                action.is_editor()
+                    || matches!(
+                        action,
+                        Action::Quit | Action::ManualSave | Action::ToggleWrap | Action::ShowHelp
+                    )
+            }
+            KeyContext::TagEditor => action.is_tag_editor() || action.is_overlay(),
+            KeyContext::TagBrowser => action.is_tag_browser() || action.is_overlay(),
+            KeyContext::Overlay => action.is_overlay(),
+        }
+    }
+}
+
+/// A single key press with its modifiers, normalised so that `Shift` never
+/// appears alongside a character (the case of the character carries it).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers = modifiers
+            & (KeyModifiers::CONTROL
+                | KeyModifiers::ALT
+                | KeyModifiers::SHIFT
+                | KeyModifiers::SUPER);
+        let code = match code {
+            KeyCode::Char(ch) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(ch.to_ascii_uppercase())
+            }
+            KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::BackTab
+            }
+            KeyCode::BackTab => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::BackTab
+            }
+            other => other,
+        };
+        Self { code, modifiers }
+    }
+
+    pub fn from_event(key: &KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = anyhow::Error;
+
+    /// Parses `ctrl-s`, `C-x`, `alt-<`, `M-f`, `shift-tab`, `pgdn`, `G`, `ctrl--`...
+    fn from_str(spec: &str) -> Result<Self> {
+        let mut rest = spec;
+        let mut modifiers = KeyModifiers::empty();
+        while let Some((prefix, tail)) = rest.split_once('-') {
+            if tail.is_empty() {
+                break;
+            }
+            let modifier = match prefix.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" | "c" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                "super" | "cmd" => KeyModifiers::SUPER,
+                _ => break,
+            };
+            modifiers |= modifier;
+            rest = tail;
+        }
+        let code = match rest.to_ascii_lowercase().as_str() {
+            "enter" | "return" | "ret" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "space" | "spc" => KeyCode::Char(' '),
+            "backspace" | "bs" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            lower => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => KeyCode::Char(ch),
+                    _ => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                        Some(n @ 1..=12) => KeyCode::F(n),
+                        _ => bail!("unknown key `{rest}` in `{spec}`"),
+                    },
+                }
+            }
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, label) in [
+            (KeyModifiers::CONTROL, "ctrl-"),
+            (KeyModifiers::ALT, "alt-"),
+            (KeyModifiers::SUPER, "super-"),
+            (KeyModifiers::SHIFT, "shift-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(label)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(ch) => write!(f, "{ch}"),
+            KeyCode::Enter => f.write_str("enter"),
+            KeyCode::Esc => f.write_str("esc"),
+            KeyCode::Tab => f.write_str("tab"),
+            KeyCode::BackTab => f.write_str("shift-tab"),
+            KeyCode::Backspace => f.write_str("backspace"),
+            KeyCode::Delete => f.write_str("delete"),
+            KeyCode::Insert => f.write_str("insert"),
+            KeyCode::Up => f.write_str("up"),
+            KeyCode::Down => f.write_str("down"),
+            KeyCode::Left => f.write_str("left"),
+            KeyCode::Right => f.write_str("right"),
+            KeyCode::Home => f.write_str("home"),
+            KeyCode::End => f.write_str("end"),
+            KeyCode::PageUp => f.write_str("pgup"),
+            KeyCode::PageDown => f.write_str("pgdn"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            other => write!(f, "{other:?}"),
+        }
+    }
+}
+
+pub fn parse_sequence(spec: &str) -> Result<Vec<KeyChord>> {
+    let chords = spec
+        .split_whitespace()
+        .map(KeyChord::from_str)
+        .collect::<Result<Vec<_>>>()?;
+    if chords.is_empty() {
+        bail!("empty key sequence");
+    }
+    Ok(chords)
+}
+
+pub fn format_sequence(chords: &[KeyChord]) -> String {
+    chords
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyResolution {
+    Action(Action),
+    /// The keys so far are the start of a longer binding; wait for the next press.
+    Pending,
+    Unbound,
+}
+
+type Bindings = &'static [(&'static str, Action)];
+
+const GLOBAL_BINDINGS: Bindings = &[("ctrl-c", Action::Quit), ("f1", Action::ShowHelp)];
+
+const EDITOR_COMMON: Bindings = &[
+    ("esc", Action::ExitEdit),
+    ("left", Action::CursorLeft),
+    ("right", Action::CursorRight),
+    ("up", Action::CursorUp),
+    ("down", Action::CursorDown),
+    ("home", Action::LineStart),
+    ("end", Action::LineEnd),
+    ("ctrl-left", Action::WordLeft),
+    ("ctrl-right", Action::WordRight),
+];
+
+const TAG_EDITOR_COMMON: Bindings = &[
+    ("space", Action::TagToggle),
+    ("v", Action::TagMark),
+    ("V", Action::TagClearMarks),
+    ("a", Action::TagAdd),
+    ("r", Action::TagRename),
+    ("m", Action::TagMerge),
+    ("M", Action::TagMergeMarked),
+    ("x", Action::TagDelete),
+];
+
+const TAG_BROWSER_COMMON: Bindings = &[
+    ("space", Action::TagMark),
+    ("v", Action::TagMark),
+    ("V", Action::TagClearMarks),
+    ("x", Action::TagDelete),
+    ("s", Action::CycleTagSort),
+    ("u", Action::ToggleUnusedTags),
+    ("U", Action::PruneUnusedTags),
+];
+
+const OVERLAY_COMMON: Bindings = &[
+    ("esc", Action::Cancel),
+    ("enter", Action::Confirm),
+    ("y", Action::Confirm),
+    ("Y", Action::Confirm),
+    ("n", Action::Cancel),
+    ("N", Action::Cancel),
+    ("down", Action::SelectNext),
+    ("up", Action::SelectPrevious),
+    ("pgdn", Action::PageDown),
+    ("pgup", Action::PageUp),
+    ("d", Action::DiscardDraft),
+    ("D", Action::DiscardAllDrafts),
+    ("?", Action::ShowHelp),
+    ("f1", Action::ShowHelp),
+];
+
+const VIM_LIST: Bindings = &[
+    ("q", Action::Quit),
+    ("j", Action::SelectNext),
+    ("down", Action::SelectNext),
+    ("k", Action::SelectPrevious),
+    ("up", Action::SelectPrevious),
+    ("g g", Action::SelectFirst),
+    ("home", Action::SelectFirst),
+    ("G", Action::SelectLast),
+    ("end", Action::SelectLast),
+    ("pgdn", Action::PageDown),
+    ("pgup", Action::PageUp),
+    ("tab", Action::ToggleFocus),
+    ("ctrl-r", Action::Refresh),
+    ("a", Action::NewNote),
+    ("r", Action::RenameNote),
+    ("e", Action::EnterEdit),
+    ("/", Action::StartSearch),
+    ("p", Action::TogglePin),
+    ("A", Action::ToggleArchive),
+    ("d", Action::DeleteNote),
+    ("R", Action::ToggleRegex),
+    ("T", Action::ToggleTrashView),
+    ("V", Action::ToggleArchiveView),
+    ("s", Action::CycleSort),
+    ("u", Action::RestoreNote),
+    ("U", Action::RestoreAllTrash),
+    ("P", Action::PurgeAllTrash),
+    ("t", Action::ShowTagEditor),
+    ("H", Action::ShowRevisions),
+    ("S", Action::ShowSavedSearches),
+    ("#", Action::ShowTagBrowser),
+    ("N", Action::ToggleNotebooks),
+    ("W", Action::ToggleWrap),
+    ("ctrl-s", Action::ManualSave),
+    (":", Action::OpenPalette),
+    ("?", Action::ShowHelp),
+];
+
+/// Wiki link navigation; only the reader pane has links to pick from.
+const READER_LINKS: Bindings = &[
+    ("enter", Action::FollowLink),
+    ("]", Action::NextLink),
+    ("[", Action::PreviousLink),
+];
+
+/// The notebook pane moves with the list keys and adds these on top.
+const NOTEBOOK_TREE: Bindings = &[
+    ("enter", Action::OpenNotebook),
+    ("space", Action::ToggleNotebookExpanded),
+];
+
+const VIM_EDITOR: Bindings = &[
+    ("ctrl-s", Action::ManualSave),
+    ("ctrl-z", Action::Undo),
+    ("ctrl-y", Action::Redo),
+];
+
+const VIM_NAVIGATION: Bindings = &[("j", Action::SelectNext), ("k", Action::SelectPrevious)];
+
+const EMACS_GLOBAL: Bindings = &[("ctrl-x ctrl-c", Action::Quit)];
+
+const EMACS_LIST: Bindings = &[
+    ("q", Action::Quit),
+    ("ctrl-n", Action::SelectNext),
+    ("down", Action::SelectNext),
+    ("ctrl-p", Action::SelectPrevious),
+    ("up", Action::SelectPrevious),
+    ("alt-<", Action::SelectFirst),
+    ("home", Action::SelectFirst),
+    ("alt->", Action::SelectLast),
+    ("end", Action::SelectLast),
+    ("ctrl-v", Action::PageDown),
+    ("pgdn", Action::PageDown),
+    ("alt-v", Action::PageUp),
+    ("pgup", Action::PageUp),
+    ("tab", Action::ToggleFocus),
+    ("ctrl-x o", Action::ToggleFocus),
+    ("g", Action::Refresh),
+    ("ctrl-x ctrl-f", Action::NewNote),
+    ("alt-r", Action::RenameNote),
+    ("enter", Action::EnterEdit),
+    ("ctrl-s", Action::StartSearch),
+    ("/", Action::StartSearch),
+    ("alt-p", Action::TogglePin),
+    ("alt-a", Action::ToggleArchive),
+    ("ctrl-d", Action::DeleteNote),
+    ("ctrl-x r", Action::ToggleRegex),
+    ("ctrl-x t", Action::ToggleTrashView),
+    ("ctrl-x v", Action::ToggleArchiveView),
+    ("ctrl-x s", Action::CycleSort),
+    ("alt-u", Action::RestoreNote),
+    ("ctrl-x U", Action::RestoreAllTrash),
+    ("ctrl-x P", Action::PurgeAllTrash),
+    ("alt-t", Action::ShowTagEditor),
+    ("ctrl-x h", Action::ShowRevisions),
+    ("alt-s", Action::ShowSavedSearches),
+    ("ctrl-x #", Action::ShowTagBrowser),
+    ("ctrl-x n", Action::ToggleNotebooks),
+    ("ctrl-x w", Action::ToggleWrap),
+    ("ctrl-x ctrl-s", Action::ManualSave),
+    ("alt-x", Action::OpenPalette),
+    ("?", Action::ShowHelp),
+];
+
+const EMACS_EDITOR: Bindings = &[
+    ("ctrl-g", Action::ExitEdit),
+    ("ctrl-x ctrl-s", Action::ManualSave),
+    ("ctrl-f", Action::CursorRight),
+    ("ctrl-b", Action::CursorLeft),
+    ("ctrl-n", Action::CursorDown),
+    ("ctrl-p", Action::CursorUp),
+    ("ctrl-a", Action::LineStart),
+    ("ctrl-e", Action::LineEnd),
+    ("alt-f", Action::WordRight),
+    ("alt-b", Action::WordLeft),
+    ("ctrl-/", Action::Undo),
+    // Most terminals send C-/ and C-_ as the same byte, which crossterm reports as C-7.
+    ("ctrl-7", Action::Undo),
+    ("ctrl-x u", Action::Undo),
+    ("ctrl-x r", Action::Redo),
+];
+
+const EMACS_NAVIGATION: Bindings = &[
+    ("ctrl-n", Action::SelectNext),
+    ("ctrl-p", Action::SelectPrevious),
+    ("ctrl-v", Action::PageDown),
+    ("alt-v", Action::PageUp),
+    ("ctrl-g", Action::Cancel),
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    contexts: HashMap<KeyContext, HashMap<Vec<KeyChord>, Action>>,
+}
+
+impl Keymap {
+    /// The built-in bindings for `profile`. `Custom` keeps only the editor, tag
+    /// editor, tag browser and overlay essentials plus `ctrl-c`, leaving the list
+    /// to `[keymap]`.
+    pub fn preset(profile: &KeybindingProfile) -> Self {
+        let mut layers: Vec<(KeyContext, Bindings)> = vec![
+            (KeyContext::Global, GLOBAL_BINDINGS),
+            (KeyContext::Editor, EDITOR_COMMON),
+            (KeyContext::TagEditor, TAG_EDITOR_COMMON),
+            (KeyContext::TagBrowser, TAG_BROWSER_COMMON),
+            (KeyContext::Overlay, OVERLAY_COMMON),
+        ];
+        match profile {
+            KeybindingProfile::Vim => layers.extend([
+                (KeyContext::List, VIM_LIST),
+                (KeyContext::Reader, READER_LINKS),
+                (KeyContext::Notebooks, NOTEBOOK_TREE),
+                (KeyContext::Editor, VIM_EDITOR),
+                (KeyContext::Overlay, VIM_NAVIGATION),
+            ]),
+            KeybindingProfile::Emacs => layers.extend([
+                (KeyContext::Global, EMACS_GLOBAL),
+                (KeyContext::List, EMACS_LIST),
+                (KeyContext::Reader, READER_LINKS),
+                (KeyContext::Notebooks, NOTEBOOK_TREE),
+                (KeyContext::Editor, EMACS_EDITOR),
+                (KeyContext::Overlay, EMACS_NAVIGATION),
+            ]),
+            KeybindingProfile::Custom => {}
+        }
+
+        let mut keymap = Self::default();
+        for (context, bindings) in layers {
+            for (spec, action) in bindings {
+                let sequence = parse_sequence(spec).expect("preset key specs are valid");
+                keymap.bind(context, sequence, *action);
+            }
+        }
+        keymap
+    }
+
+    /// The preset for `profile` with the user's `[keymap.<context>]` tables applied
+    /// on top. Problems (unknown names, bad key specs, bindings that shadow each
+    /// other) are returned as human-readable messages; the offending entry is
+    /// skipped or, for shadowing, the user's binding wins.
+    pub fn from_config(
+        profile: &KeybindingProfile,
+        overrides: &KeymapOverrides,
+    ) -> (Self, Vec<String>) {
+        let mut keymap = Self::preset(profile);
+        let mut issues = Vec::new();
+        for (context_name, table) in overrides {
+            let Ok(context) = KeyContext::from_str(context_name) else {
+                issues.push(format!("keymap.{context_name}: unknown context"));
+                continue;
+            };
+            let mut seen: HashMap<Vec<KeyChord>, &str> = HashMap::new();
+            for (spec, action_name) in table {
+                let sequence = match parse_sequence(spec) {
+                    Ok(sequence) => sequence,
+                    Err(err) => {
+                        issues.push(format!("keymap.{context_name}: `{spec}`: {err}"));
+                        continue;
+                    }
+                };
+                if let Some(previous) = seen.insert(sequence.clone(), spec) {
+                    issues.push(format!(
+                        "keymap.{context_name}: `{previous}` and `{spec}` are the same keys"
+                    ));
+                }
+                if matches!(action_name.as_str(), "" | "none" | "unbound") {
+                    keymap.unbind(context, &sequence);
+                    continue;
+                }
+                let action = match Action::from_str(action_name) {
+                    Ok(action) if context.accepts(action) => action,
+                    Ok(_) => {
+                        issues.push(format!(
+                            "keymap.{context_name}: `{action_name}` is not available in this context"
+                        ));
+                        continue;
+                    }
+                    Err(_) => {
+                        issues.push(format!(
+                            "keymap.{context_name}: unknown action `{action_name}` for `{spec}`"
+                        ));
+                        continue;
+                    }
+                };
+                for (shadowed, other) in keymap.prefix_conflicts(context, &sequence) {
+                    issues.push(format!(
+                        "keymap.{context_name}: `{spec}` ({}) shadows `{}` ({})",
+                        action.name(),
+                        format_sequence(&shadowed),
+                        other.name()
+                    ));
+                    keymap.unbind(context, &shadowed);
+                }
+                keymap.bind(context, sequence, action);
+            }
+        }
+        (keymap, issues)
+    }
+
+    pub fn bind(&mut self, context: KeyContext, sequence: Vec<KeyChord>, action: Action) {
+        self.contexts
+            .entry(context)
+            .or_default()
+            .insert(sequence, action);
+    }
+
+    pub fn unbind(&mut self, context: KeyContext, sequence: &[KeyChord]) {
+        if let Some(bindings) = self.contexts.get_mut(&context) {
+            bindings.remove(sequence);
+        }
+    }
+
+    /// Looks up `pending` followed by `key`, walking from `context` through its fallbacks.
+    pub fn resolve(
+        &self,
+        context: KeyContext,
+        pending: &[KeyChord],
+        key: KeyChord,
+    ) -> KeyResolution {
+        let mut sequence = pending.to_vec();
+        sequence.push(key);
+        let mut current = Some(context);
+        while let Some(ctx) = current {
+            if let Some(bindings) = self.contexts.get(&ctx) {
+                if let Some(action) = bindings.get(&sequence) {
+                    return KeyResolution::Action(*action);
+                }
+                if bindings
+                    .keys()
+                    .any(|bound| bound.len() > sequence.len() && bound.starts_with(&sequence))
+                {
+                    return KeyResolution::Pending;
+                }
+            }
+            current = ctx.fallback();
+        }
+        KeyResolution::Unbound
+    }
+
+    /// Every key sequence that triggers `action` from `context`, including ones
+    /// inherited from fallback contexts unless a nearer context rebinds them.
+    /// Shortest sequences come first.
+    pub fn keys_for(&self, context: KeyContext, action: Action) -> Vec<String> {
+        let mut chain = Vec::new();
+        let mut current = Some(context);
+        while let Some(ctx) = current {
+            chain.push(ctx);
+            current = ctx.fallback();
+        }
+        let mut found: Vec<&Vec<KeyChord>> = Vec::new();
+        for (depth, ctx) in chain.iter().enumerate() {
+            let Some(bindings) = self.contexts.get(ctx) else {
+                continue;
+            };
+            for (sequence, bound) in bindings {
+                let overridden = chain[..depth].iter().any(|nearer| {
+                    self.contexts
+                        .get(nearer)
+                        .is_some_and(|inner| inner.contains_key(sequence))
+                });
+                if *bound == action && !overridden {
+                    found.push(sequence);
+                }
+            }
+        }
+        let mut keys: Vec<(usize, String)> = found
+            .into_iter()
+            .map(|sequence| (sequence.len(), format_sequence(sequence)))
+            .collect();
+        keys.sort();
+        keys.dedup();
+        keys.into_iter().map(|(_, spec)| spec).collect()
+    }
+
+    /// Existing bindings in `context` that can never fire alongside `sequence`
+    /// because one is a strict prefix of the other.
+    fn prefix_conflicts(
+        &self,
+        context: KeyContext,
+        sequence: &[KeyChord],
+    ) -> Vec<(Vec<KeyChord>, Action)> {
+        let Some(bindings) = self.contexts.get(&context) else {
+            return Vec::new();
+        };
+        let mut conflicts: Vec<_> = bindings
+            .iter()
+            .filter(|(bound, _)| {
+                bound.len() != sequence.len()
+                    && (bound.starts_with(sequence) || sequence.starts_with(bound))
+            })
+            .map(|(bound, action)| (bound.clone(), *action))
+            .collect();
+        conflicts.sort_by_key(|(bound, _)| format_sequence(bound));
+        conflicts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use strum::IntoEnumIterator;
+
+    fn chord(spec: &str) -> KeyChord {
+        spec.parse().expect("valid chord")
+    }
+
+    /// Prefix conflicts inside the built-in tables.
+    fn internal_conflicts(keymap: &Keymap) -> Vec<String> {
+        let mut found = Vec::new();
+        for context in KeyContext::iter() {
+            let Some(bindings) = keymap.contexts.get(&context) else {
+                continue;
+            };
+            for sequence in bindings.keys() {
+                for (other, _) in keymap.prefix_conflicts(context, sequence) {
+                    if other.len() > sequence.len() {
+                        found.push(format!(
+                            "{}: `{}` vs `{}`",
+                            context.name(),
+                            format_sequence(sequence),
+                            format_sequence(&other)
+                        ));
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    fn overrides(context: &str, entries: &[(&str, &str)]) -> KeymapOverrides {
+        let table = entries
+            .iter()
+            .map(|(keys, action)| (keys.to_string(), action.to_string()))
+            .collect::<BTreeMap<_, _>>();
+        BTreeMap::from([(context.to_string(), table)])
+    }
+
+    #[test]
+    fn chords_parse_and_normalise_shift() {
+        assert_eq!(
+            chord("ctrl-s"),
+            KeyChord::new(KeyCode::Char('s'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(chord("C-s"), chord("ctrl-s"));
+        assert_eq!(
+            chord("M-<"),
+            KeyChord::new(KeyCode::Char('<'), KeyModifiers::ALT)
+        );
+        assert_eq!(chord("shift-g"), chord("G"));
+        assert_eq!(
+            KeyChord::new(KeyCode::Char('G'), KeyModifiers::SHIFT),
+            chord("G")
+        );
+        assert_eq!(
+            chord("shift-tab"),
+            KeyChord::new(KeyCode::BackTab, KeyModifiers::SHIFT)
+        );
+        assert_eq!(
+            chord("ctrl--"),
+            KeyChord::new(KeyCode::Char('-'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            chord("f5"),
+            KeyChord::new(KeyCode::F(5), KeyModifiers::NONE)
+        );
+        assert!("ctrl-foo".parse::<KeyChord>().is_err());
+        assert_eq!(
+            format_sequence(&parse_sequence("C-x  C-s").unwrap()),
+            "ctrl-x ctrl-s"
+        );
+    }
+
+    #[test]
+    fn presets_have_no_internal_conflicts() {
+        for profile in [
+            KeybindingProfile::Vim,
+            KeybindingProfile::Emacs,
+            KeybindingProfile::Custom,
+        ] {
+            let keymap = Keymap::preset(&profile);
+            assert!(
+                internal_conflicts(&keymap).is_empty(),
+                "{profile:?}: {:?}",
+                internal_conflicts(&keymap)
+            );
+        }
+    }
+
+    #[test]
+    fn sequences_resolve_through_pending_state_and_fallbacks() {
+        let keymap = Keymap::preset(&KeybindingProfile::Vim);
+        let g = chord("g");
+        assert_eq!(
+            keymap.resolve(KeyContext::List, &[], g),
+            KeyResolution::Pending
+        );
+        assert_eq!(
+            keymap.resolve(KeyContext::List, &[g], g),
+            KeyResolution::Action(Action::SelectFirst)
+        );
+        assert_eq!(
+            keymap.resolve(KeyContext::List, &[g], chord("x")),
+            KeyResolution::Unbound
+        );
+        // Reader falls back to list bindings, list falls back to global ones.
+        assert_eq!(
+            keymap.resolve(KeyContext::Reader, &[], chord("p")),
+            KeyResolution::Action(Action::TogglePin)
+        );
+        assert_eq!(
+            keymap.resolve(KeyContext::Reader, &[], chord("ctrl-c")),
+            KeyResolution::Action(Action::Quit)
+        );
+        // Overlays do not see list bindings.
+        assert_eq!(
+            keymap.resolve(KeyContext::Overlay, &[], chord("p")),
+            KeyResolution::Unbound
+        );
+
+        let emacs = Keymap::preset(&KeybindingProfile::Emacs);
+        let ctrl_x = chord("ctrl-x");
+        assert_eq!(
+            emacs.resolve(KeyContext::Editor, &[], ctrl_x),
+            KeyResolution::Pending
+        );
+        assert_eq!(
+            emacs.resolve(KeyContext::Editor, &[ctrl_x], chord("ctrl-s")),
+            KeyResolution::Action(Action::ManualSave)
+        );
+        assert_eq!(
+            emacs.resolve(KeyContext::List, &[ctrl_x], chord("ctrl-c")),
+            KeyResolution::Action(Action::Quit)
+        );
+    }
+
+    #[test]
+    fn keys_for_lists_bindings_through_fallbacks() {
+        let vim = Keymap::preset(&KeybindingProfile::Vim);
+        assert_eq!(
+            vim.keys_for(KeyContext::Reader, Action::SelectFirst),
+            vec!["home", "g g"]
+        );
+        assert_eq!(
+            vim.keys_for(KeyContext::List, Action::Quit),
+            vec!["ctrl-c", "q"]
+        );
+        assert!(vim
+            .keys_for(KeyContext::Editor, Action::TogglePin)
+            .is_empty());
+
+        let mut emacs = Keymap::preset(&KeybindingProfile::Emacs);
+        assert_eq!(
+            emacs.keys_for(KeyContext::List, Action::OpenPalette),
+            vec!["alt-x"]
+        );
+        emacs.bind(
+            KeyContext::List,
+            parse_sequence("ctrl-c").unwrap(),
+            Action::Refresh,
+        );
+        assert_eq!(
+            emacs.keys_for(KeyContext::List, Action::Quit),
+            vec!["q", "ctrl-x ctrl-c"]
+        );
+    }
+
+    #[test]
+    fn overrides_replace_unbind_and_report_conflicts() {
+        let config = overrides(
+            "list",
+            &[
+                ("x", "delete-note"),
+                ("d", "none"),
+                ("g", "refresh"),
+                ("C-r", "toggle-regex"),
+                ("ctrl-r", "toggle-regex"),
+                ("z", "warp-drive"),
+                ("ctrl-", "quit"),
+                ("y", "tag-add"),
+            ],
+        );
+        let (keymap, issues) = Keymap::from_config(&KeybindingProfile::Vim, &config);
+        assert_eq!(
+            keymap.resolve(KeyContext::List, &[], chord("x")),
+            KeyResolution::Action(Action::DeleteNote)
+        );
+        assert_eq!(
+            keymap.resolve(KeyContext::List, &[], chord("d")),
+            KeyResolution::Unbound
+        );
+        // `g` now refreshes immediately; the shadowed `g g` binding is dropped.
+        assert_eq!(
+            keymap.resolve(KeyContext::List, &[], chord("g")),
+            KeyResolution::Action(Action::Refresh)
+        );
+        assert_eq!(
+            keymap.resolve(KeyContext::List, &[], chord("ctrl-r")),
+            KeyResolution::Action(Action::ToggleRegex)
+        );
+
+        let joined = issues.join("\n");
+        assert!(
+            joined.contains("`g` (refresh) shadows `g g` (select-first)"),
+            "{joined}"
+        );
+        assert!(
+            joined.contains("`C-r` and `ctrl-r` are the same keys"),
+            "{joined}"
+        );
+        assert!(joined.contains("unknown action `warp-drive`"), "{joined}");
+        assert!(joined.contains("`ctrl-`"), "{joined}");
+        assert!(joined.contains("`tag-add` is not available"), "{joined}");
+        assert_eq!(issues.len(), 5, "{joined}");
+
+        let (_, issues) = Keymap::from_config(
+            &KeybindingProfile::Vim,
+            &overrides("sidebar", &[("x", "quit")]),
+        );
+        assert_eq!(issues, vec!["keymap.sidebar: unknown context".to_string()]);
+    }
+}
@@ -19,10 +19,17 @@ use time::format_description::well_known::Rfc3339;
 
 use crate::config::{AppConfig, ConfigPaths};
 use crate::journaling::{AutoSaveEvent, AutoSaveRuntime, AutoSaveStatus};
-use crate::storage::{StorageHandle, TagDeleteOutcome, TagRenameOutcome, WalCheckpointStats};
+use crate::search::{parse_query, SearchHistory};
+use crate::storage::{
+    BackupKind, BackupRotation, StorageHandle, TagDeleteOutcome, TagRenameOutcome,
+    WalCheckpointStats, NOTEBOOK_SEPARATOR,
+};
 use crate::ui;
 
 mod actions;
+pub mod help;
+mod keymap;
+pub mod palette;
 pub mod state;
 
 pub use state::{
@@ -30,41 +37,28 @@ pub use state::{
     TagInputKind,
 };
 
-enum Action {
-    Quit,
-    SelectNext,
-    SelectPrevious,
-    ToggleFocus,
-    Refresh,
-    NewNote,
-    RenameNote,
-    EnterEdit,
-    StartSearch,
-    TogglePin,
-    ToggleArchive,
-    DeleteNote,
-    ToggleRegex,
-    ToggleTrashView,
-    RestoreNote,
-    RestoreAllTrash,
-    PurgeAllTrash,
-    ShowTagEditor,
-    ToggleWrap,
-    ManualSave,
-}
+use help::HelpTopic;
+use keymap::{format_sequence, Action, KeyChord, KeyContext, KeyResolution, Keymap};
 
 const WAL_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 10);
+const LIST_PAGE_STEP: isize = 10;
 
 pub struct App {
     pub config: Arc<AppConfig>,
     pub storage: StorageHandle,
     state: AppState,
     list_state: ListState,
+    /// `state.list_offset` at the last draw, used to keep the viewport steady when
+    /// the list window pages rows in or out above it.
+    drawn_list_offset: usize,
     should_quit: bool,
     tick_rate: Duration,
     auto_save: AutoSaveRuntime,
     wal_check_interval: Duration,
     last_wal_check: Instant,
+    keymap: Keymap,
+    pending_keys: Vec<KeyChord>,
+    pending_context: KeyContext,
 }
 
 impl App {
@@ -73,8 +67,23 @@ impl App {
         if let Err(err) = storage.purge_expired_trash(config.retention_days) {
             tracing::warn!(?err, "failed to purge expired trash on startup");
         }
-        let mut state = AppState::load(&storage, preview_lines, config.retention_days)
-            .context("loading note summaries for initial state")?;
+        let mut state = AppState::load(
+            &storage,
+            preview_lines,
+            config.retention_days,
+            config.default_sort,
+        )
+        .context("loading note summaries for initial state")?;
+        state.search.fuzzy_threshold = config.search.fuzzy_threshold;
+        state.search.history = SearchHistory::load(
+            &paths.state_dir,
+            storage.database_path(),
+            config.search.history_size,
+        )
+        .unwrap_or_else(|err| {
+            tracing::warn!(?err, "failed to load search history");
+            SearchHistory::in_memory(config.search.history_size)
+        });
         let mut list_state = ListState::default();
         if !state.is_empty() {
             list_state.select(Some(state.selected));
@@ -91,16 +100,30 @@ impl App {
                 .open_recovery_overlay(&storage, recovery_snapshots)
                 .context("preparing autosave recovery overlay")?;
         }
+        let (keymap, keymap_issues) = Keymap::from_config(&config.keybindings, &config.keymap);
+        for issue in &keymap_issues {
+            tracing::warn!(%issue, "keymap conflict");
+        }
+        if let Some(first) = keymap_issues.first() {
+            state.set_status_message(Some(format!(
+                "Keymap: {first} ({} issue(s), see log)",
+                keymap_issues.len()
+            )));
+        }
         let mut app = Self {
             config,
             storage,
             state,
             list_state,
+            drawn_list_offset: 0,
             should_quit: false,
             tick_rate: Duration::from_millis(250),
             auto_save,
             wal_check_interval: WAL_CHECK_INTERVAL,
             last_wal_check: Instant::now(),
+            keymap,
+            pending_keys: Vec::new(),
+            pending_context: KeyContext::Global,
         };
         app.last_wal_check = Instant::now()
             .checked_sub(app.wal_check_interval)
@@ -113,9 +136,28 @@ impl App {
         let mut terminal = setup_terminal()?;
         let result = self.event_loop(&mut terminal);
         restore_terminal(&mut terminal)?;
+        if result.is_ok() {
+            self.backup_on_exit();
+        }
         result
     }
 
+    fn backup_on_exit(&self) {
+        if !self.config.storage.backup_on_exit {
+            return;
+        }
+        match self.storage.create_backup(BackupKind::Exit) {
+            Ok(record) => {
+                tracing::info!(path = %record.path.display(), "wrote exit backup");
+                let rotation = BackupRotation::from_options(&self.config.storage);
+                if let Err(err) = self.storage.prune_backups(rotation) {
+                    tracing::warn!(?err, "failed to rotate backups");
+                }
+            }
+            Err(err) => tracing::error!(?err, "failed to write exit backup"),
+        }
+    }
+
     pub fn autosave_status(&self) -> AutoSaveStatus {
         self.auto_save.status()
     }
@@ -127,14 +169,24 @@ impl App {
     fn event_loop(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
         let mut last_tick = Instant::now();
         loop {
+            self.sync_backlinks();
             terminal
                 .draw(|frame| {
+                    let shifted = self.state.list_offset as isize - self.drawn_list_offset as isize;
+                    let offset = self.list_state.offset() as isize - shifted;
+                    *self.list_state.offset_mut() = offset.max(0) as usize;
+                    self.drawn_list_offset = self.state.list_offset;
                     if !self.state.is_empty() {
                         self.list_state.select(Some(self.state.selected));
                     } else {
                         self.list_state.select(None);
                     }
-                    ui::draw_app(frame, &self.state, &mut self.list_state);
+                    ui::draw_app(
+                        frame,
+                        &self.state,
+                        &mut self.list_state,
+                        &self.config.palette,
+                    );
                 })
                 .context("rendering frame")?;
 
@@ -170,33 +222,106 @@ impl App {
             return;
         }
 
-        if self.handle_overlay_key(key) {
+        if self.handle_text_input_key(key) {
             return;
         }
 
-        if self.state.is_editing() {
-            if self.handle_editor_key(key) {
-                return;
+        let context = self.key_context();
+        if context != self.pending_context {
+            self.pending_keys.clear();
+        }
+        let chord = KeyChord::from_event(&key);
+        match self.keymap.resolve(context, &self.pending_keys, chord) {
+            KeyResolution::Pending => {
+                self.pending_keys.push(chord);
+                self.pending_context = context;
+                self.state
+                    .set_pending_keys(Some(format_sequence(&self.pending_keys)));
+            }
+            KeyResolution::Action(action) => {
+                self.clear_pending_keys();
+                self.handle_action(action);
+            }
+            KeyResolution::Unbound => {
+                // A broken-off sequence swallows the key that broke it, like vim and emacs do.
+                if self.clear_pending_keys() {
+                    return;
+                }
+                self.handle_unbound_key(context, key);
+            }
+        }
+    }
+
+    fn clear_pending_keys(&mut self) -> bool {
+        let had_pending = !self.pending_keys.is_empty();
+        self.pending_keys.clear();
+        self.state.set_pending_keys(None::<String>);
+        had_pending
+    }
+
+    fn key_context(&self) -> KeyContext {
+        match self.state.overlay() {
+            Some(OverlayState::TagEditor(_))
+                if matches!(self.state.tag_editor_mode(), TagEditorMode::Browse) =>
+            {
+                KeyContext::TagEditor
             }
+            Some(OverlayState::TagBrowser(_)) => KeyContext::TagBrowser,
+            Some(_) => KeyContext::Overlay,
+            None if self.state.is_editing() => KeyContext::Editor,
+            None => match self.state.focus {
+                FocusPane::List => KeyContext::List,
+                FocusPane::Reader => KeyContext::Reader,
+                FocusPane::Notebooks => KeyContext::Notebooks,
+            },
+        }
+    }
+
+    /// Prompts that take free text (titles, tag names, the search query) consume
+    /// keys before the keymap sees them.
+    fn handle_text_input_key(&mut self, key: KeyEvent) -> bool {
+        if self.handle_text_overlay_key(key) {
+            return true;
+        }
+
+        if self.state.reverse_search().is_some() && self.handle_reverse_search_key(key) {
+            return true;
         }
 
         if self.state.is_search_active() {
+            let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
             match key.code {
+                KeyCode::Up => {
+                    if let Err(err) = self.state.recall_older_search(&self.storage) {
+                        tracing::error!(?err, "failed to recall search history");
+                    }
+                    return true;
+                }
+                KeyCode::Down => {
+                    if let Err(err) = self.state.recall_newer_search(&self.storage) {
+                        tracing::error!(?err, "failed to recall search history");
+                    }
+                    return true;
+                }
+                KeyCode::Char('r') if ctrl => {
+                    self.state.begin_reverse_search();
+                    return true;
+                }
                 KeyCode::Esc => {
                     if let Err(err) = self.state.cancel_search(&self.storage) {
                         tracing::error!(?err, "failed to cancel search");
                     }
-                    return;
+                    return true;
                 }
                 KeyCode::Enter => {
                     self.state.finish_search();
-                    return;
+                    return true;
                 }
                 KeyCode::Backspace => {
                     if let Err(err) = self.state.pop_search_char(&self.storage) {
                         tracing::error!(?err, "failed to trim search query");
                     }
-                    return;
+                    return true;
                 }
                 KeyCode::Char(ch)
                     if !key.modifiers.intersects(
@@ -206,118 +331,86 @@ impl App {
                     if let Err(err) = self.state.push_search_char(&self.storage, ch) {
                         tracing::error!(?err, "failed to extend search query");
                     }
-                    return;
+                    return true;
                 }
                 _ => {}
             }
         }
+        false
+    }
 
-        let action = match key.code {
-            KeyCode::Char('q') => Some(Action::Quit),
-            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                Some(Action::Quit)
-            }
-            KeyCode::Char('j') | KeyCode::Down => Some(Action::SelectNext),
-            KeyCode::Char('k') | KeyCode::Up => Some(Action::SelectPrevious),
-            KeyCode::Tab => Some(Action::ToggleFocus),
-            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                Some(Action::Refresh)
-            }
-            KeyCode::Char('a')
-                if !key.modifiers.intersects(
-                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                ) =>
-            {
-                Some(Action::NewNote)
-            }
-            KeyCode::Char('r')
-                if !key.modifiers.intersects(
-                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                ) =>
-            {
-                Some(Action::RenameNote)
-            }
-            KeyCode::Char('e')
-                if !key.modifiers.intersects(
-                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                ) =>
-            {
-                Some(Action::EnterEdit)
-            }
-            KeyCode::Char('p')
-                if !key.modifiers.intersects(
-                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                ) =>
-            {
-                Some(Action::TogglePin)
-            }
-            KeyCode::Char('d')
-                if !key.modifiers.intersects(
-                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                ) =>
-            {
-                Some(Action::DeleteNote)
-            }
-            KeyCode::Char('R')
-                if !key.modifiers.intersects(
-                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                ) =>
-            {
-                Some(Action::ToggleRegex)
-            }
-            KeyCode::Char('T') => Some(Action::ToggleTrashView),
-            KeyCode::Char('u')
-                if !key.modifiers.intersects(
-                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                ) =>
-            {
-                Some(Action::RestoreNote)
-            }
-            KeyCode::Char('U')
-                if !key.modifiers.intersects(
-                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                ) =>
-            {
-                Some(Action::RestoreAllTrash)
-            }
-            KeyCode::Char('P')
-                if !key.modifiers.intersects(
-                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                ) =>
-            {
-                Some(Action::PurgeAllTrash)
+    /// Keys while Ctrl-r reverse search is on: typing narrows the match, Ctrl-r
+    /// steps to older matches, Enter runs the match and Esc / Ctrl-g go back to
+    /// the query typed before. Any other key keeps the match and is handled by
+    /// the search prompt as usual.
+    fn handle_reverse_search_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let result = match key.code {
+            KeyCode::Char('r') if ctrl => self.state.next_reverse_search_match(&self.storage),
+            KeyCode::Char('g') if ctrl => self.state.cancel_reverse_search(&self.storage),
+            KeyCode::Esc => self.state.cancel_reverse_search(&self.storage),
+            KeyCode::Enter => {
+                self.state.accept_reverse_search();
+                self.state.finish_search();
+                Ok(())
             }
-            KeyCode::Char('W') => Some(Action::ToggleWrap),
-            KeyCode::Char('t')
+            KeyCode::Backspace => self.state.pop_reverse_search_char(&self.storage),
+            KeyCode::Char(ch)
                 if !key.modifiers.intersects(
                     KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
                 ) =>
             {
-                Some(Action::ShowTagEditor)
-            }
-            KeyCode::Char('A') => Some(Action::ToggleArchive),
-            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                Some(Action::ManualSave)
+                self.state.push_reverse_search_char(&self.storage, ch)
             }
-            KeyCode::Char('/')
-                if !key.modifiers.intersects(
-                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                ) =>
-            {
-                Some(Action::StartSearch)
+            _ => {
+                self.state.accept_reverse_search();
+                return false;
             }
-            _ => None,
         };
+        if let Err(err) = result {
+            tracing::error!(?err, "reverse history search failed");
+        }
+        true
+    }
 
-        if let Some(action) = action {
-            self.handle_action(action);
+    fn handle_unbound_key(&mut self, context: KeyContext, key: KeyEvent) {
+        match context {
+            KeyContext::Editor => {
+                self.handle_editor_key(key);
+            }
+            KeyContext::TagEditor => {
+                if let KeyCode::Char(ch @ '1'..='9') = key.code {
+                    if !key
+                        .modifiers
+                        .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER)
+                    {
+                        let idx = (ch as u8 - b'1') as usize;
+                        self.state.tag_editor_apply_suggestion(idx);
+                    }
+                }
+            }
+            KeyContext::Global
+            | KeyContext::List
+            | KeyContext::Reader
+            | KeyContext::Notebooks
+            | KeyContext::TagBrowser
+            | KeyContext::Overlay => {}
         }
     }
 
     fn handle_action(&mut self, action: Action) {
+        if action == Action::ShowHelp {
+            self.open_help();
+            return;
+        }
+        if self.state.overlay().is_some() {
+            self.handle_overlay_action(action);
+            return;
+        }
         if self.state.is_editing() {
             match action {
                 Action::ManualSave | Action::Quit | Action::ToggleWrap => {}
+                action if self.handle_editor_action(action) => return,
                 _ => {
                     self.state.set_status_message(Some(
                         "Finish editing (Esc to exit, Ctrl-s to save) before performing other actions.",
@@ -333,8 +426,22 @@ impl App {
                 }
                 self.should_quit = true;
             }
-            Action::SelectNext => self.state.move_selection(1),
-            Action::SelectPrevious => self.state.move_selection(-1),
+            Action::SelectNext
+            | Action::SelectPrevious
+            | Action::SelectFirst
+            | Action::SelectLast
+            | Action::PageDown
+            | Action::PageUp
+                if self.state.focus == FocusPane::Notebooks =>
+            {
+                self.navigate_notebooks(action)
+            }
+            Action::SelectNext
+            | Action::SelectPrevious
+            | Action::SelectFirst
+            | Action::SelectLast
+            | Action::PageDown
+            | Action::PageUp => self.navigate_list(action),
             Action::ToggleFocus => self.state.toggle_focus(),
             Action::Refresh => {
                 if self.state.show_trash {
@@ -345,11 +452,9 @@ impl App {
                 }
             }
             Action::NewNote => {
-                if self.state.overlay().is_none() {
-                    self.state.open_new_note();
-                    self.state
-                        .set_status_message(Some("Enter a title and press Enter"));
-                }
+                self.state.open_new_note();
+                self.state
+                    .set_status_message(Some("Enter a title and press Enter"));
             }
             Action::RenameNote => self.handle_rename_note(),
             Action::EnterEdit => self.handle_enter_edit(),
@@ -361,14 +466,30 @@ impl App {
             Action::DeleteNote => self.handle_delete_note(),
             Action::ToggleRegex => self.handle_toggle_regex(),
             Action::ToggleTrashView => self.handle_toggle_trash_view(),
+            Action::ToggleArchiveView => self.handle_toggle_archive_view(),
+            Action::CycleSort => self.handle_cycle_sort(),
             Action::RestoreNote => self.handle_restore_note(),
             Action::RestoreAllTrash => self.handle_restore_all_trash_request(),
             Action::PurgeAllTrash => self.handle_purge_all_trash_request(),
             Action::ShowTagEditor => self.handle_show_tag_editor(),
+            Action::ShowRevisions => self.handle_show_revisions(),
+            Action::ShowSavedSearches => self.handle_show_saved_searches(),
+            Action::ShowTagBrowser => self.handle_show_tag_browser(),
+            Action::FollowLink => self.handle_follow_link(),
+            Action::NextLink => self.handle_move_link(1),
+            Action::PreviousLink => self.handle_move_link(-1),
+            Action::ToggleNotebooks => self.handle_toggle_notebooks(),
+            Action::OpenNotebook => self.handle_open_notebook(),
+            Action::ToggleNotebookExpanded => self.handle_toggle_notebook_expanded(),
             Action::ToggleWrap => self.handle_toggle_wrap(),
             Action::ManualSave => {
                 self.handle_manual_save();
             }
+            Action::OpenPalette => {
+                self.state.open_palette(palette::entries(&self.keymap));
+            }
+            // Editor, overlay and tag editor actions only apply in their own contexts.
+            _ => {}
         }
     }
 
@@ -381,6 +502,9 @@ impl App {
             }
         }
         self.state.set_autosave_status(self.auto_save.status());
+        if let Err(err) = self.storage.sync_mirror() {
+            tracing::warn!(?err, "failed to update the git mirror");
+        }
         self.maybe_run_wal_health_check();
     }
 
@@ -414,7 +538,7 @@ impl App {
         }
     }
 
-    fn handle_overlay_key(&mut self, key: KeyEvent) -> bool {
+    fn handle_text_overlay_key(&mut self, key: KeyEvent) -> bool {
         match self.state.overlay() {
             Some(OverlayState::NewNote(_)) => {
                 match key.code {
@@ -474,216 +598,286 @@ impl App {
                 }
                 true
             }
-            Some(OverlayState::DeleteNote(_)) => {
+            Some(OverlayState::Help(overlay)) => {
+                let filtering = !overlay.filter.is_empty();
+                match key.code {
+                    KeyCode::Esc if filtering => self.state.help_clear_filter(),
+                    KeyCode::Esc | KeyCode::F(1) => self.state.close_help(),
+                    KeyCode::Backspace => self.state.help_pop_char(),
+                    KeyCode::Down => self.state.help_scroll(1),
+                    KeyCode::Up => self.state.help_scroll(-1),
+                    KeyCode::PageDown => self.state.help_scroll(LIST_PAGE_STEP),
+                    KeyCode::PageUp => self.state.help_scroll(-LIST_PAGE_STEP),
+                    KeyCode::Home => self.state.help_scroll(isize::MIN),
+                    KeyCode::End => self.state.help_scroll(isize::MAX),
+                    KeyCode::Char(ch)
+                        if !key.modifiers.intersects(
+                            KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
+                        ) =>
+                    {
+                        self.state.help_push_char(ch);
+                    }
+                    _ => {}
+                }
+                true
+            }
+            Some(OverlayState::Palette(_)) => {
+                let control = key.modifiers.contains(KeyModifiers::CONTROL);
                 match key.code {
                     KeyCode::Esc => {
                         self.state.close_overlay();
-                        self.state.set_status_message(Some("Delete canceled"));
                     }
                     KeyCode::Enter => {
-                        self.submit_delete_note();
+                        self.submit_palette();
+                    }
+                    KeyCode::Backspace => {
+                        self.state.palette_pop_char();
+                    }
+                    KeyCode::Tab => {
+                        let completion = self
+                            .state
+                            .palette_overlay()
+                            .and_then(|overlay| overlay.selected_entry())
+                            .map(|entry| format!("{} ", entry.name));
+                        if let Some(completion) = completion {
+                            self.state.palette_set_input(completion);
+                        }
+                    }
+                    KeyCode::Down => self.state.palette_move_selection(1),
+                    KeyCode::Up => self.state.palette_move_selection(-1),
+                    KeyCode::PageDown => self.state.palette_move_selection(LIST_PAGE_STEP),
+                    KeyCode::PageUp => self.state.palette_move_selection(-LIST_PAGE_STEP),
+                    KeyCode::Char('n') if control => self.state.palette_move_selection(1),
+                    KeyCode::Char('p') if control => self.state.palette_move_selection(-1),
+                    KeyCode::Char(ch)
+                        if !key.modifiers.intersects(
+                            KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
+                        ) =>
+                    {
+                        self.state.palette_push_char(ch);
                     }
                     _ => {}
                 }
                 true
             }
-            Some(OverlayState::BulkTrash(_)) => self.handle_bulk_trash_overlay_key(key),
             Some(OverlayState::TagEditor(_)) => {
-                let mode = self.state.tag_editor_mode();
-                match mode {
-                    TagEditorMode::Input(kind) => {
-                        match key.code {
-                            KeyCode::Esc => {
-                                self.state.tag_editor_cancel_input();
-                                self.state.tag_editor_set_status("Input canceled");
-                            }
-                            KeyCode::Enter => match kind {
-                                TagInputKind::Add => {
-                                    self.state.tag_editor_commit_input();
-                                }
-                                TagInputKind::Rename { original } => {
-                                    self.handle_tag_editor_rename(original);
-                                }
-                                TagInputKind::Merge { sources } => {
-                                    self.handle_tag_editor_merge(sources.clone());
-                                }
-                            },
-                            KeyCode::Backspace => {
-                                self.state.tag_editor_pop_char();
-                            }
-                            KeyCode::Char(ch)
-                                if !key.modifiers.intersects(
-                                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                                ) =>
-                            {
-                                self.state.tag_editor_push_char(ch);
-                            }
-                            _ => {}
-                        }
-                        true
+                let TagEditorMode::Input(kind) = self.state.tag_editor_mode() else {
+                    return false;
+                };
+                match key.code {
+                    KeyCode::Esc => {
+                        self.state.tag_editor_cancel_input();
+                        self.state.tag_editor_set_status("Input canceled");
                     }
-                    TagEditorMode::ConfirmDelete { tag } => {
-                        match key.code {
-                            KeyCode::Esc | KeyCode::Char('n') => {
-                                self.state.tag_editor_cancel_input();
-                                self.state.tag_editor_set_status("Delete canceled");
-                            }
-                            KeyCode::Char('y') | KeyCode::Enter => {
-                                self.handle_tag_editor_delete(tag);
-                            }
-                            _ => {}
+                    KeyCode::Enter => match kind {
+                        TagInputKind::Add => {
+                            self.state.tag_editor_commit_input();
                         }
-                        true
-                    }
-                    TagEditorMode::Browse => {
-                        match key.code {
-                            KeyCode::Esc => {
-                                self.state.close_overlay();
-                                self.state.set_status_message(Some("Canceled tag changes"));
-                            }
-                            KeyCode::Enter => {
-                                self.apply_tag_editor_changes();
-                            }
-                            KeyCode::Char('a')
-                                if !key.modifiers.intersects(
-                                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                                ) =>
-                            {
-                                self.state.tag_editor_begin_add();
-                            }
-                            KeyCode::Char(' ')
-                                if !key.modifiers.intersects(
-                                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                                ) =>
-                            {
-                                self.state.tag_editor_toggle_selection();
-                            }
-                            KeyCode::Char('r')
-                                if !key.modifiers.intersects(
-                                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                                ) =>
-                            {
-                                self.state.tag_editor_begin_rename();
-                            }
-                            KeyCode::Char('m')
-                                if !key.modifiers.intersects(
-                                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                                ) =>
-                            {
-                                self.state.tag_editor_begin_merge();
-                            }
-                            KeyCode::Char('M')
-                                if !key.modifiers.intersects(
-                                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                                ) =>
-                            {
-                                self.state.tag_editor_begin_marked_merge();
-                            }
-                            KeyCode::Char('x')
-                                if !key.modifiers.intersects(
-                                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                                ) =>
-                            {
-                                self.state.tag_editor_begin_delete();
-                            }
-                            KeyCode::Char('v')
-                                if !key.modifiers.intersects(
-                                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                                ) =>
-                            {
-                                self.state.tag_editor_toggle_bulk_mark();
-                            }
-                            KeyCode::Char('V')
-                                if !key.modifiers.intersects(
-                                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                                ) =>
-                            {
-                                self.state.tag_editor_clear_bulk_marks();
-                            }
-                            KeyCode::Char(ch @ '1'..='9')
-                                if !key.modifiers.intersects(
-                                    KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
-                                ) =>
-                            {
-                                let idx = (ch as u8 - b'1') as usize;
-                                self.state.tag_editor_apply_suggestion(idx);
-                            }
-                            KeyCode::Char('j') | KeyCode::Down => {
-                                self.state.tag_editor_move_selection(1);
-                            }
-                            KeyCode::Char('k') | KeyCode::Up => {
-                                self.state.tag_editor_move_selection(-1);
-                            }
-                            KeyCode::PageDown => {
-                                self.state.tag_editor_move_selection(5);
-                            }
-                            KeyCode::PageUp => {
-                                self.state.tag_editor_move_selection(-5);
-                            }
-                            _ => {}
+                        TagInputKind::Rename { original } => {
+                            self.handle_tag_editor_rename(original);
+                        }
+                        TagInputKind::Merge { sources } => {
+                            self.handle_tag_editor_merge(sources.clone());
                         }
-                        true
+                    },
+                    KeyCode::Backspace => {
+                        self.state.tag_editor_pop_char();
+                    }
+                    KeyCode::Char(ch)
+                        if !key.modifiers.intersects(
+                            KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SUPER,
+                        ) =>
+                    {
+                        self.state.tag_editor_push_char(ch);
                     }
+                    _ => {}
                 }
+                true
             }
-            Some(OverlayState::Recovery(_)) => self.handle_recovery_overlay_key(key),
-            None => false,
+            _ => false,
         }
     }
 
-    fn handle_bulk_trash_overlay_key(&mut self, key: KeyEvent) -> bool {
-        match key.code {
-            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+    fn handle_overlay_action(&mut self, action: Action) {
+        match self.state.overlay() {
+            Some(OverlayState::DeleteNote(_)) => match action {
+                Action::Confirm => self.submit_delete_note(),
+                Action::Cancel => {
+                    self.state.close_overlay();
+                    self.state.set_status_message(Some("Delete canceled"));
+                }
+                _ => {}
+            },
+            Some(OverlayState::BulkTrash(_)) => match action {
+                Action::Confirm => self.execute_bulk_trash_action(),
+                Action::Cancel => {
+                    self.state.close_overlay();
+                    self.state
+                        .set_status_message(Some("Bulk trash action canceled"));
+                }
+                _ => {}
+            },
+            Some(OverlayState::TagEditor(_)) => self.handle_tag_editor_action(action),
+            Some(OverlayState::Recovery(_)) => self.handle_recovery_action(action),
+            Some(OverlayState::Revisions(_)) => self.handle_revisions_action(action),
+            Some(OverlayState::SavedSearches(_)) => self.handle_saved_searches_action(action),
+            Some(OverlayState::TagBrowser(_)) => self.handle_tag_browser_action(action),
+            Some(OverlayState::NewNote(_))
+            | Some(OverlayState::RenameNote(_))
+            | Some(OverlayState::Palette(_))
+            | Some(OverlayState::Help(_))
+            | None => {}
+        }
+    }
+
+    fn handle_tag_editor_action(&mut self, action: Action) {
+        if let TagEditorMode::ConfirmDelete { tag } = self.state.tag_editor_mode() {
+            match action {
+                Action::Confirm => self.handle_tag_editor_delete(tag),
+                Action::Cancel => {
+                    self.state.tag_editor_cancel_input();
+                    self.state.tag_editor_set_status("Delete canceled");
+                }
+                _ => {}
+            }
+            return;
+        }
+        match action {
+            Action::Cancel => {
                 self.state.close_overlay();
-                self.state
-                    .set_status_message(Some("Bulk trash action canceled"));
-                true
+                self.state.set_status_message(Some("Canceled tag changes"));
             }
-            KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => {
-                self.execute_bulk_trash_action();
-                true
+            Action::Confirm => self.apply_tag_editor_changes(),
+            Action::TagAdd => self.state.tag_editor_begin_add(),
+            Action::TagToggle => self.state.tag_editor_toggle_selection(),
+            Action::TagRename => self.state.tag_editor_begin_rename(),
+            Action::TagMerge => self.state.tag_editor_begin_merge(),
+            Action::TagMergeMarked => {
+                self.state.tag_editor_begin_marked_merge();
             }
-            _ => false,
+            Action::TagDelete => self.state.tag_editor_begin_delete(),
+            Action::TagMark => self.state.tag_editor_toggle_bulk_mark(),
+            Action::TagClearMarks => self.state.tag_editor_clear_bulk_marks(),
+            Action::SelectNext => self.state.tag_editor_move_selection(1),
+            Action::SelectPrevious => self.state.tag_editor_move_selection(-1),
+            Action::PageDown => self.state.tag_editor_move_selection(5),
+            Action::PageUp => self.state.tag_editor_move_selection(-5),
+            _ => {}
         }
     }
 
-    fn handle_recovery_overlay_key(&mut self, key: KeyEvent) -> bool {
-        match key.code {
-            KeyCode::Esc => {
-                self.state.close_overlay();
-                self.state
-                    .set_status_message(Some("Autosave review dismissed"));
-                true
+    fn handle_tag_browser_action(&mut self, action: Action) {
+        let pending = self
+            .state
+            .tag_browser_overlay()
+            .is_some_and(|overlay| overlay.pending_delete.is_some());
+        if pending {
+            match action {
+                Action::Confirm => self.submit_tag_browser_delete(),
+                Action::Cancel => {
+                    self.state.tag_browser_cancel_delete();
+                    self.state.set_status_message(Some("Delete canceled"));
+                }
+                _ => {}
             }
-            KeyCode::Enter => {
-                self.handle_recovery_restore();
-                true
+            return;
+        }
+        match action {
+            Action::Cancel => {
+                self.state.close_overlay();
+                self.state.set_status_message(Some("Tag browser closed"));
+            }
+            Action::Confirm => self.submit_tag_browser_filter(),
+            Action::TagMark => self.state.tag_browser_toggle_mark(),
+            Action::TagClearMarks => self.state.tag_browser_clear_marks(),
+            Action::TagDelete => {
+                let count = self.state.tag_browser_begin_delete();
+                if count > 0 {
+                    self.state.set_status_message(Some(format!(
+                        "Delete {count} tag{} from every note? y confirm • n cancel",
+                        if count == 1 { "" } else { "s" }
+                    )));
+                }
             }
-            KeyCode::Char('d') if key.modifiers.is_empty() => {
-                self.handle_recovery_discard(false);
-                true
+            Action::PruneUnusedTags => {
+                let message = match self.state.tag_browser_begin_prune() {
+                    0 => "No unused tags".to_string(),
+                    count => format!(
+                        "Delete {count} unused tag{}? y confirm • n cancel",
+                        if count == 1 { "" } else { "s" }
+                    ),
+                };
+                self.state.set_status_message(Some(message));
             }
-            KeyCode::Char('D') if key.modifiers.is_empty() => {
-                self.handle_recovery_discard(true);
-                true
+            Action::CycleTagSort => {
+                if let Some(sort) = self.state.tag_browser_cycle_sort() {
+                    self.state
+                        .set_status_message(Some(format!("Tags sorted by {}", sort.label())));
+                }
             }
-            KeyCode::Char('j') if key.modifiers.is_empty() => {
-                self.state.recovery_move_selection(1);
-                true
+            Action::ToggleUnusedTags => {
+                if let Some(unused_only) = self.state.tag_browser_toggle_unused() {
+                    self.state.set_status_message(Some(if unused_only {
+                        "Showing unused tags"
+                    } else {
+                        "Showing all tags"
+                    }));
+                }
             }
-            KeyCode::Char('k') if key.modifiers.is_empty() => {
-                self.state.recovery_move_selection(-1);
-                true
+            Action::SelectNext => self.state.tag_browser_move_selection(1),
+            Action::SelectPrevious => self.state.tag_browser_move_selection(-1),
+            Action::PageDown => self.state.tag_browser_move_selection(5),
+            Action::PageUp => self.state.tag_browser_move_selection(-5),
+            _ => {}
+        }
+    }
+
+    fn handle_recovery_action(&mut self, action: Action) {
+        match action {
+            Action::Cancel => {
+                self.state.close_overlay();
+                self.state
+                    .set_status_message(Some("Autosave review dismissed"));
             }
-            KeyCode::Down => {
-                self.state.recovery_move_selection(1);
-                true
+            Action::Confirm => self.handle_recovery_restore(),
+            Action::DiscardDraft => self.handle_recovery_discard(false),
+            Action::DiscardAllDrafts => self.handle_recovery_discard(true),
+            Action::SelectNext => self.state.recovery_move_selection(1),
+            Action::SelectPrevious => self.state.recovery_move_selection(-1),
+            Action::PageDown => self.state.recovery_move_selection(5),
+            Action::PageUp => self.state.recovery_move_selection(-5),
+            _ => {}
+        }
+    }
+
+    fn handle_revisions_action(&mut self, action: Action) {
+        match action {
+            Action::Cancel => {
+                self.state.close_overlay();
+                self.state
+                    .set_status_message(Some("Revision history closed"));
             }
-            KeyCode::Up => {
-                self.state.recovery_move_selection(-1);
-                true
+            Action::Confirm => self.submit_revision_restore(),
+            Action::SelectNext => self.state.revisions_move_selection(1),
+            Action::SelectPrevious => self.state.revisions_move_selection(-1),
+            Action::PageDown => self.state.revisions_move_selection(5),
+            Action::PageUp => self.state.revisions_move_selection(-5),
+            _ => {}
+        }
+    }
+
+    fn handle_saved_searches_action(&mut self, action: Action) {
+        match action {
+            Action::Cancel => {
+                self.state.close_overlay();
+                self.state.set_status_message(Some("Saved searches closed"));
             }
-            _ => false,
+            Action::Confirm => self.submit_saved_search(),
+            Action::SelectNext => self.state.saved_searches_move_selection(1),
+            Action::SelectPrevious => self.state.saved_searches_move_selection(-1),
+            Action::PageDown => self.state.saved_searches_move_selection(5),
+            Action::PageUp => self.state.saved_searches_move_selection(-5),
+            _ => {}
         }
     }
 
@@ -761,15 +955,13 @@ impl App {
 
         self.state.recovery_remove_selected();
 
-        if let Err(err) = self.state.refresh(&self.storage) {
+        if let Err(err) = self.state.refresh_selecting(&self.storage, target_id) {
             tracing::error!(?err, "failed to refresh after recovery");
             self.state
                 .set_status_message(Some("Recovered draft applied, refresh failed"));
             return;
         }
 
-        self.state.select_note_by_id(target_id);
-
         if let Some(note) = self.state.selected().cloned() {
             if let Err(err) = self.start_editing_internal(&note) {
                 tracing::error!(?err, "failed to enter edit mode for recovered note");
@@ -861,10 +1053,62 @@ impl App {
         }
     }
 
-    fn handle_restore_note(&mut self) {
+    fn handle_toggle_archive_view(&mut self) {
+        let enabled = !self.state.show_archive;
+        match self.state.set_archive_view(enabled, &self.storage) {
+            Ok(()) => {
+                if enabled {
+                    self.state.set_status_message(Some(
+                        "Archive view: j/k browse • u or Shift+A unarchive • V exit",
+                    ));
+                } else {
+                    self.state.set_status_message(Some("Back to active notes"));
+                }
+            }
+            Err(err) => {
+                tracing::error!(?err, "failed to toggle archive view");
+                self.state
+                    .set_status_message(Some("Failed to toggle archive view"));
+            }
+        }
+    }
+
+    fn handle_cycle_sort(&mut self) {
+        match self.state.cycle_sort(&self.storage) {
+            Ok(sort) => {
+                let message = if self.state.show_trash {
+                    format!(
+                        "Sort: {} (the trash stays newest-deleted first)",
+                        sort.label()
+                    )
+                } else {
+                    format!("Sort: {}", sort.label())
+                };
+                self.state.set_status_message(Some(message));
+            }
+            Err(err) => {
+                tracing::error!(?err, "failed to change sort order");
+                self.state
+                    .set_status_message(Some("Could not change sort order"));
+            }
+        }
+    }
+
+    fn handle_restore_note(&mut self) {
+        if self.state.show_archive {
+            match self.state.unarchive_selected_note(&self.storage) {
+                Ok(()) => self.state.set_status_message(Some("Note unarchived")),
+                Err(err) => {
+                    tracing::error!(?err, "failed to unarchive note");
+                    self.state
+                        .set_status_message(Some("Failed to unarchive note"));
+                }
+            }
+            return;
+        }
         if !self.state.show_trash {
             self.state
-                .set_status_message(Some("Restore only available in trash view"));
+                .set_status_message(Some("Restore only available in trash or archive view"));
             return;
         }
         match self.state.restore_selected_note(&self.storage) {
@@ -885,7 +1129,7 @@ impl App {
                 .set_status_message(Some("Restore-all available only in trash view"));
             return;
         }
-        if self.state.len() == 0 {
+        if self.state.is_empty() {
             self.state.set_status_message(Some("Trash already empty"));
             return;
         }
@@ -905,7 +1149,7 @@ impl App {
                 .set_status_message(Some("Purge-all available only in trash view"));
             return;
         }
-        if self.state.len() == 0 {
+        if self.state.is_empty() {
             self.state.set_status_message(Some("Trash already empty"));
             return;
         }
@@ -989,13 +1233,12 @@ impl App {
 
         match self.storage.create_note(title, "", false) {
             Ok(note_id) => {
-                if let Err(err) = self.state.refresh(&self.storage) {
+                if let Err(err) = self.state.refresh_selecting(&self.storage, note_id) {
                     tracing::error!(?err, "failed to refresh after note creation");
                     self.state
                         .set_status_message(Some("Note created, refresh failed"));
                 } else {
                     self.state.close_overlay();
-                    self.state.select_note_by_id(note_id);
                     self.state.set_status_message(Some("Note created"));
                 }
             }
@@ -1020,12 +1263,12 @@ impl App {
         }
         let dispatcher = actions::ActionDispatcher::new(&self.storage);
         match dispatcher.rename_note(note_id, &title) {
-            Ok(()) => {
+            Ok(rewritten) => {
                 self.state.close_overlay();
-                match self.state.refresh(&self.storage) {
+                match self.state.refresh_selecting(&self.storage, note_id) {
                     Ok(()) => {
-                        self.state.select_note_by_id(note_id);
-                        self.state.set_status_message(Some("Note renamed"));
+                        self.state
+                            .set_status_message(Some(rename_message(rewritten)));
                     }
                     Err(err) => {
                         tracing::error!(?err, "failed to refresh after rename");
@@ -1073,6 +1316,10 @@ impl App {
             return;
         };
         let should_pin = self.state.selected().map(|n| !n.pinned).unwrap_or(true);
+        self.set_note_pinned(note_id, should_pin);
+    }
+
+    fn set_note_pinned(&mut self, note_id: i64, should_pin: bool) {
         let dispatcher = actions::ActionDispatcher::new(&self.storage);
         if let Err(err) = dispatcher.toggle_pin(note_id, should_pin) {
             tracing::error!(?err, "failed to toggle pin");
@@ -1080,12 +1327,11 @@ impl App {
                 .set_status_message(Some("Failed to update pin state"));
             return;
         }
-        if let Err(err) = self.state.refresh(&self.storage) {
+        if let Err(err) = self.state.refresh_selecting(&self.storage, note_id) {
             tracing::error!(?err, "failed to refresh after pin toggle");
             self.state
                 .set_status_message(Some("Could not refresh notes"));
         } else {
-            self.state.select_note_by_id(note_id);
             let message = if should_pin {
                 "Note pinned"
             } else {
@@ -1100,6 +1346,10 @@ impl App {
             return;
         };
         let should_archive = self.state.selected().map(|n| !n.archived).unwrap_or(true);
+        self.set_note_archived(note_id, should_archive);
+    }
+
+    fn set_note_archived(&mut self, note_id: i64, should_archive: bool) {
         let dispatcher = actions::ActionDispatcher::new(&self.storage);
         if let Err(err) = dispatcher.toggle_archive(note_id, should_archive) {
             tracing::error!(?err, "failed to toggle archive");
@@ -1114,8 +1364,278 @@ impl App {
         } else if should_archive {
             self.state.set_status_message(Some("Note archived"));
         } else {
-            self.state.select_note_by_id(note_id);
-            self.state.set_status_message(Some("Note restored"));
+            self.state.set_status_message(Some("Note unarchived"));
+        }
+    }
+
+    fn navigate_list(&mut self, action: Action) {
+        let storage = &self.storage;
+        let result = match action {
+            Action::SelectNext => self.state.move_selection(storage, 1),
+            Action::SelectPrevious => self.state.move_selection(storage, -1),
+            Action::SelectFirst => self.state.select_first(storage),
+            Action::SelectLast => self.state.select_last(storage),
+            Action::PageDown => self.state.move_selection(storage, LIST_PAGE_STEP),
+            Action::PageUp => self.state.move_selection(storage, -LIST_PAGE_STEP),
+            _ => Ok(()),
+        };
+        if let Err(err) = result {
+            tracing::error!(?err, "failed to page notes from storage");
+            self.state
+                .set_status_message(Some("Could not load more notes"));
+        }
+    }
+
+    fn navigate_notebooks(&mut self, action: Action) {
+        match action {
+            Action::SelectNext => self.state.move_notebook_selection(1),
+            Action::SelectPrevious => self.state.move_notebook_selection(-1),
+            Action::SelectFirst => self.state.select_first_notebook(),
+            Action::SelectLast => self.state.select_last_notebook(),
+            Action::PageDown => self.state.move_notebook_selection(LIST_PAGE_STEP),
+            Action::PageUp => self.state.move_notebook_selection(-LIST_PAGE_STEP),
+            _ => {}
+        }
+    }
+
+    fn open_help(&mut self) {
+        let topic = match self.state.overlay() {
+            Some(OverlayState::Help(_)) => return,
+            Some(OverlayState::TagEditor(_)) => HelpTopic::TagEditor,
+            Some(OverlayState::Recovery(_)) => HelpTopic::Recovery,
+            Some(OverlayState::Revisions(_)) => HelpTopic::Revisions,
+            Some(OverlayState::SavedSearches(_)) => HelpTopic::SavedSearches,
+            Some(OverlayState::TagBrowser(_)) => HelpTopic::TagBrowser,
+            Some(_) => HelpTopic::Dialog,
+            None if self.state.is_editing() => HelpTopic::Editor,
+            None if self.state.show_trash => HelpTopic::Trash,
+            None if self.state.focus == FocusPane::Reader => HelpTopic::Reader,
+            None if self.state.focus == FocusPane::Notebooks => HelpTopic::Notebooks,
+            None => HelpTopic::List,
+        };
+        self.state
+            .open_help(topic, help::entries(&self.keymap, topic));
+    }
+
+    fn submit_palette(&mut self) {
+        let Some(overlay) = self.state.palette_overlay() else {
+            return;
+        };
+        let (entry, rest) = match palette::parse_invocation(&overlay.entries, &overlay.input) {
+            Some((idx, rest)) => (overlay.entries[idx].clone(), rest.to_string()),
+            None => match overlay.selected_entry() {
+                Some(entry) => (entry.clone(), String::new()),
+                None => {
+                    self.state.set_status_message(Some("No matching command"));
+                    return;
+                }
+            },
+        };
+        if entry.takes_arguments() && rest.is_empty() {
+            self.state.palette_set_input(format!("{} ", entry.name));
+            self.state
+                .set_status_message(Some(format!("Usage: {} {}", entry.name, entry.usage)));
+            return;
+        }
+        let args = match entry.bind_arguments(&rest) {
+            Ok(args) => args,
+            Err(err) => {
+                self.state.set_status_message(Some(format!(
+                    "{err} (usage: {} {})",
+                    entry.name, entry.usage
+                )));
+                return;
+            }
+        };
+        self.state.close_overlay();
+        match entry.command {
+            palette::Command::Action(action) => self.handle_action(action),
+            palette::Command::Operation(operation) => self.run_palette_operation(operation, args),
+        }
+    }
+
+    fn run_palette_operation(&mut self, operation: palette::Operation, args: Vec<String>) {
+        use palette::Operation;
+
+        let dispatcher = actions::ActionDispatcher::new(&self.storage);
+        match operation {
+            Operation::TagRename => {
+                match dispatcher.rename_tag(&args[0], &args[1]) {
+                    Ok(TagRenameOutcome::Renamed { from, to, .. }) => {
+                        self.refresh_after_tag_update();
+                        self.state
+                            .set_status_message(Some(format!("Renamed '{from}' to '{to}'")));
+                    }
+                    Ok(TagRenameOutcome::Merged {
+                        from,
+                        to,
+                        reassigned,
+                        ..
+                    }) => {
+                        self.refresh_after_tag_update();
+                        self.state.set_status_message(Some(format!(
+                            "Merged '{from}' into '{to}' ({reassigned} notes updated)"
+                        )));
+                    }
+                    Err(err) => {
+                        tracing::error!(?err, "palette tag rename failed");
+                        self.state
+                            .set_status_message(Some(format!("Rename failed: {err}")));
+                    }
+                }
+                return;
+            }
+            Operation::TagDelete => {
+                match dispatcher.delete_tag(&args[0]) {
+                    Ok(TagDeleteOutcome { tag, detached }) => {
+                        self.refresh_after_tag_update();
+                        self.state.set_status_message(Some(format!(
+                            "Deleted '{tag}' (removed from {detached} notes)"
+                        )));
+                    }
+                    Err(err) => {
+                        tracing::error!(?err, "palette tag delete failed");
+                        self.state
+                            .set_status_message(Some(format!("Delete failed: {err}")));
+                    }
+                }
+                return;
+            }
+            Operation::RestoreTrash => {
+                self.state
+                    .open_bulk_trash_overlay(BulkTrashAction::RestoreAll);
+                self.state.set_status_message(Some(
+                    "Restore all notes from trash? Enter confirms • Esc cancels",
+                ));
+                return;
+            }
+            Operation::PurgeTrash => {
+                self.state
+                    .open_bulk_trash_overlay(BulkTrashAction::PurgeAll);
+                self.state.set_status_message(Some(
+                    "Permanently delete all trashed notes? Enter confirms • Esc cancels",
+                ));
+                return;
+            }
+            Operation::SaveSearch => {
+                self.save_current_search(&args[0]);
+                return;
+            }
+            Operation::DeleteSavedSearch => {
+                let message = match dispatcher.delete_saved_search(&args[0]) {
+                    Ok(true) => format!("Deleted saved search '{}'", args[0]),
+                    Ok(false) => format!("No saved search named '{}'", args[0]),
+                    Err(err) => {
+                        tracing::error!(?err, "failed to delete saved search");
+                        format!("Delete failed: {err}")
+                    }
+                };
+                self.state.set_status_message(Some(message));
+                return;
+            }
+            Operation::NotebookNew => {
+                let message = match dispatcher.create_notebook(&args[0]) {
+                    Ok(notebook) => format!("Created notebook '{}'", notebook.path),
+                    Err(err) => {
+                        tracing::error!(?err, "failed to create notebook");
+                        format!("Create failed: {err}")
+                    }
+                };
+                self.refresh_notebooks();
+                self.state.set_status_message(Some(message));
+                return;
+            }
+            Operation::NotebookDelete => {
+                let message = match self.storage.find_notebook(&args[0]) {
+                    Ok(Some(notebook)) => match dispatcher.delete_notebook(notebook.id) {
+                        Ok(moved) => {
+                            format!(
+                                "Deleted notebook '{}' ({moved} notes moved up)",
+                                notebook.path
+                            )
+                        }
+                        Err(err) => {
+                            tracing::error!(?err, "failed to delete notebook");
+                            format!("Delete failed: {err}")
+                        }
+                    },
+                    Ok(None) => format!("No notebook named '{}'", args[0]),
+                    Err(err) => {
+                        tracing::error!(?err, "failed to look up notebook");
+                        format!("Delete failed: {err}")
+                    }
+                };
+                if let Err(err) = self.state.refresh(&self.storage) {
+                    tracing::error!(?err, "failed to refresh after deleting a notebook");
+                }
+                self.state.set_status_message(Some(message));
+                return;
+            }
+            _ => {}
+        }
+
+        let Some(note_id) = self.state.selected().map(|n| n.id) else {
+            self.state.set_status_message(Some("No note selected"));
+            return;
+        };
+        let (result, message) = match operation {
+            Operation::Pin | Operation::Unpin => {
+                self.set_note_pinned(note_id, operation == Operation::Pin);
+                return;
+            }
+            Operation::Archive | Operation::Unarchive => {
+                self.set_note_archived(note_id, operation == Operation::Archive);
+                return;
+            }
+            Operation::TagAdd => (
+                dispatcher.add_tag(note_id, &args[0]),
+                format!("Tagged '{}'", args[0]),
+            ),
+            Operation::TagRemove => (
+                dispatcher.remove_tag(note_id, &args[0]),
+                format!("Removed tag '{}'", args[0]),
+            ),
+            Operation::Rename => match dispatcher.rename_note(note_id, &args[0]) {
+                Ok(rewritten) => (Ok(()), rename_message(rewritten)),
+                Err(err) => (Err(err), String::new()),
+            },
+            Operation::MoveToNotebook => {
+                let path = &args[0];
+                if path
+                    .chars()
+                    .all(|ch| ch == NOTEBOOK_SEPARATOR || ch.is_whitespace())
+                {
+                    (
+                        dispatcher.move_note(note_id, None),
+                        "Removed from its notebook".to_string(),
+                    )
+                } else {
+                    match dispatcher.create_notebook(path) {
+                        Ok(notebook) => (
+                            dispatcher.move_note(note_id, Some(notebook.id)),
+                            format!("Moved to '{}'", notebook.path),
+                        ),
+                        Err(err) => (Err(err), String::new()),
+                    }
+                }
+            }
+            _ => return,
+        };
+        if let Err(err) = result {
+            tracing::error!(?err, ?operation, "palette command failed");
+            self.state
+                .set_status_message(Some(format!("Command failed: {err}")));
+            return;
+        }
+        match self.state.refresh_selecting(&self.storage, note_id) {
+            Ok(()) => {
+                self.state.set_status_message(Some(message));
+            }
+            Err(err) => {
+                tracing::error!(?err, "failed to refresh after palette command");
+                self.state
+                    .set_status_message(Some("Could not refresh notes"));
+            }
         }
     }
 
@@ -1188,6 +1708,297 @@ impl App {
         }
     }
 
+    fn handle_show_revisions(&mut self) {
+        if self.state.show_trash {
+            self.state
+                .set_status_message(Some("Restore the note before browsing its history"));
+            return;
+        }
+        if self.state.selected().is_none() {
+            self.state.set_status_message(Some("No note selected"));
+            return;
+        }
+        match self.state.open_revisions_overlay(&self.storage) {
+            Ok(true) => {
+                self.state.set_status_message(Some(
+                    "History: j/k step through revisions • Enter restore • Esc close",
+                ));
+            }
+            Ok(false) => {
+                self.state
+                    .set_status_message(Some("No earlier revisions for this note"));
+            }
+            Err(err) => {
+                tracing::error!(?err, "failed to load note revisions");
+                self.state
+                    .set_status_message(Some("Failed to load revision history"));
+            }
+        }
+    }
+
+    fn handle_move_link(&mut self, delta: isize) {
+        if !self.state.move_link_selection(delta) {
+            self.state.set_status_message(Some("No links in this note"));
+            return;
+        }
+        if let Some(link) = self.state.selected_link() {
+            self.state
+                .set_status_message(Some(format!("Link {}", link.target)));
+        }
+    }
+
+    fn handle_follow_link(&mut self) {
+        let Some(link) = self.state.selected_link() else {
+            self.state.set_status_message(Some("No links in this note"));
+            return;
+        };
+        let target = match self.storage.resolve_link(&link.target) {
+            Ok(Some(target)) => target,
+            Ok(None) => {
+                self.state
+                    .set_status_message(Some(format!("No note matches {}", link.target)));
+                return;
+            }
+            Err(err) => {
+                tracing::error!(?err, "failed to resolve link");
+                self.state.set_status_message(Some("Failed to follow link"));
+                return;
+            }
+        };
+        match self.state.reveal_note(&self.storage, &target) {
+            Ok(()) => {
+                self.state
+                    .set_status_message(Some(format!("Opened '{}'", target.title)));
+            }
+            Err(err) => {
+                tracing::error!(?err, note_id = target.id, "failed to open linked note");
+                self.state
+                    .set_status_message(Some("Failed to open linked note"));
+            }
+        }
+    }
+
+    fn refresh_notebooks(&mut self) {
+        if let Err(err) = self.state.reload_notebooks(&self.storage) {
+            tracing::error!(?err, "failed to reload notebooks");
+        }
+    }
+
+    fn handle_toggle_notebooks(&mut self) {
+        match self.state.toggle_notebooks(&self.storage) {
+            Ok(true) => {
+                self.state.set_status_message(Some(
+                    "Notebooks: Enter lists a notebook • Space folds • Tab to the list",
+                ));
+            }
+            Ok(false) => self.state.clear_status_message(),
+            Err(err) => {
+                tracing::error!(?err, "failed to load notebooks");
+                self.state
+                    .set_status_message(Some("Failed to load notebooks"));
+            }
+        }
+    }
+
+    fn handle_toggle_notebook_expanded(&mut self) {
+        if self.state.focus == FocusPane::Notebooks && !self.state.toggle_notebook_expanded() {
+            self.state
+                .set_status_message(Some("No nested notebooks to collapse"));
+        }
+    }
+
+    fn handle_open_notebook(&mut self) {
+        if self.state.focus != FocusPane::Notebooks {
+            return;
+        }
+        let label = match self.state.notebook_tree().selected_notebook() {
+            Some(notebook) => format!("Notebook '{}'", notebook.path),
+            None => "All notes".to_string(),
+        };
+        match self.state.open_selected_notebook(&self.storage) {
+            Ok(()) => {
+                self.state.focus = FocusPane::List;
+                self.state.set_status_message(Some(label));
+            }
+            Err(err) => {
+                tracing::error!(?err, "failed to list notebook");
+                self.state
+                    .set_status_message(Some("Failed to list notebook"));
+            }
+        }
+    }
+
+    fn sync_backlinks(&mut self) {
+        if let Err(err) = self.state.sync_backlinks(&self.storage) {
+            tracing::error!(?err, "failed to load backlinks");
+        }
+    }
+
+    fn handle_show_saved_searches(&mut self) {
+        match self.state.open_saved_searches_overlay(&self.storage) {
+            Ok(true) => {
+                self.state
+                    .set_status_message(Some("Saved searches: j/k select • Enter run • Esc close"));
+            }
+            Ok(false) => {
+                self.state.set_status_message(Some(
+                    "No saved searches yet; save one with `:save search <name>`",
+                ));
+            }
+            Err(err) => {
+                tracing::error!(?err, "failed to load saved searches");
+                self.state
+                    .set_status_message(Some("Failed to load saved searches"));
+            }
+        }
+    }
+
+    fn handle_show_tag_browser(&mut self) {
+        match self.state.open_tag_browser(&self.storage) {
+            Ok(true) => {
+                self.state.set_status_message(Some(
+                    "Tags: Enter list notes • v mark • s sort • u unused • x delete • Esc close",
+                ));
+            }
+            Ok(false) => {
+                self.state.set_status_message(Some("No tags yet"));
+            }
+            Err(err) => {
+                tracing::error!(?err, "failed to load tag usage");
+                self.state.set_status_message(Some("Failed to load tags"));
+            }
+        }
+    }
+
+    fn submit_tag_browser_filter(&mut self) {
+        match self.state.apply_tag_browser_filter(&self.storage) {
+            Ok(tags) if tags.is_empty() => {}
+            Ok(tags) => {
+                let count = self.state.len();
+                self.state.set_status_message(Some(format!(
+                    "Tagged {}: {count} note{}",
+                    tags.join(", "),
+                    if count == 1 { "" } else { "s" }
+                )));
+            }
+            Err(err) => {
+                tracing::error!(?err, "failed to filter by tags");
+                self.state
+                    .set_status_message(Some(format!("Search failed: {err}")));
+            }
+        }
+    }
+
+    fn submit_tag_browser_delete(&mut self) {
+        let Some(tags) = self
+            .state
+            .tag_browser_overlay()
+            .and_then(|overlay| overlay.pending_delete.clone())
+        else {
+            return;
+        };
+        let dispatcher = actions::ActionDispatcher::new(&self.storage);
+        match dispatcher.delete_tags(&tags) {
+            Ok(deleted) => {
+                self.refresh_after_tag_update();
+                if let Err(err) = self.state.reload_tag_browser(&self.storage) {
+                    tracing::error!(?err, "failed to reload tag usage");
+                }
+                self.state.set_status_message(Some(format!(
+                    "Deleted {deleted} tag{}",
+                    if deleted == 1 { "" } else { "s" }
+                )));
+            }
+            Err(err) => {
+                tracing::error!(?err, "tag delete failed");
+                self.state.tag_browser_cancel_delete();
+                self.state
+                    .set_status_message(Some(format!("Delete failed: {err}")));
+            }
+        }
+    }
+
+    fn submit_saved_search(&mut self) {
+        let Some(entry) = self.state.saved_searches_selected_entry().cloned() else {
+            return;
+        };
+        self.state.close_overlay();
+        if let Err(err) = self.state.apply_saved_search(&self.storage, &entry.query) {
+            tracing::error!(?err, name = %entry.name, "failed to run saved search");
+            self.state
+                .set_status_message(Some(format!("Search failed: {err}")));
+            return;
+        }
+        let message = match self.state.search_error() {
+            Some(err) => format!("Saved search '{}' is invalid: {err}", entry.name),
+            None if self.state.len() == 1 => format!("Search '{}': 1 note", entry.name),
+            None => format!("Search '{}': {} notes", entry.name, self.state.len()),
+        };
+        self.state.set_status_message(Some(message));
+    }
+
+    fn save_current_search(&mut self, name: &str) {
+        let query = self.state.search_query().trim().to_string();
+        let problem = if query.is_empty() {
+            Some("Type a search with / before saving it".to_string())
+        } else if self.state.is_regex_enabled() {
+            Some("Regex searches cannot be saved".to_string())
+        } else {
+            parse_query(&query)
+                .err()
+                .map(|err| format!("Cannot save an invalid search: {err}"))
+        };
+        if let Some(problem) = problem {
+            self.state.set_status_message(Some(problem));
+            return;
+        }
+        let dispatcher = actions::ActionDispatcher::new(&self.storage);
+        let message = match dispatcher.save_search(name, &query) {
+            Ok(saved) => format!("Saved search '{}'", saved.name),
+            Err(err) => {
+                tracing::error!(?err, "failed to save search");
+                format!("Save failed: {err}")
+            }
+        };
+        self.state.set_status_message(Some(message));
+    }
+
+    fn submit_revision_restore(&mut self) {
+        let Some(note_id) = self
+            .state
+            .revisions_overlay()
+            .map(|overlay| overlay.note_id)
+        else {
+            return;
+        };
+        let Some(entry) = self.state.revisions_selected_entry().cloned() else {
+            return;
+        };
+        let dispatcher = actions::ActionDispatcher::new(&self.storage);
+        if let Err(err) = dispatcher.restore_revision(note_id, entry.id) {
+            tracing::error!(
+                ?err,
+                note_id,
+                revision_id = entry.id,
+                "failed to restore revision"
+            );
+            self.state
+                .set_status_message(Some("Failed to restore revision"));
+            return;
+        }
+        self.state.close_overlay();
+        if let Err(err) = self.state.refresh_selecting(&self.storage, note_id) {
+            tracing::error!(?err, "failed to refresh after restoring revision");
+            self.state
+                .set_status_message(Some("Revision restored, refresh failed"));
+            return;
+        }
+        self.state.set_status_message(Some(format!(
+            "Restored note #{note_id} to revision from {}",
+            entry.saved_relative
+        )));
+    }
+
     fn handle_toggle_wrap(&mut self) {
         let enabled = self.state.toggle_wrap();
         let message = if enabled {
@@ -1246,71 +2057,20 @@ impl App {
         self.state.set_autosave_status(self.auto_save.status());
     }
 
-    fn handle_editor_key(&mut self, key: KeyEvent) -> bool {
-        if !self.state.is_editing() {
-            return false;
-        }
-
-        if key.modifiers.contains(KeyModifiers::CONTROL) {
-            match key.code {
-                KeyCode::Char('s') => {
-                    self.handle_manual_save();
-                    return true;
-                }
-                KeyCode::Char('z') => {
-                    if self.editor_undo() {
-                        self.state.set_status_message(Some("Undid change"));
-                    } else {
-                        self.state.set_status_message(Some("Nothing to undo"));
-                    }
-                    return true;
-                }
-                KeyCode::Char('y') => {
-                    if self.editor_redo() {
-                        self.state.set_status_message(Some("Redid change"));
-                    } else {
-                        self.state.set_status_message(Some("Nothing to redo"));
-                    }
-                    return true;
-                }
-                KeyCode::Left => {
-                    if let Some(editor) = self.state.editor_mut() {
-                        editor.move_word_left();
-                    }
-                    return true;
-                }
-                KeyCode::Right => {
-                    if let Some(editor) = self.state.editor_mut() {
-                        editor.move_word_right();
-                    }
-                    return true;
-                }
-                _ => {}
-            }
-        }
-
+    /// Text entry for keys the editor keymap leaves unbound.
+    fn handle_editor_key(&mut self, key: KeyEvent) {
         match key.code {
-            KeyCode::Esc => {
-                if self.exit_editing() {
-                    self.state.set_status_message(Some("Exited edit mode"));
-                }
-                true
-            }
             KeyCode::Enter => {
                 self.apply_editor_change(|editor| editor.insert_newline());
-                true
             }
             KeyCode::Backspace => {
                 self.apply_editor_change(|editor| editor.backspace());
-                true
             }
             KeyCode::Delete => {
                 self.apply_editor_change(|editor| editor.delete());
-                true
             }
             KeyCode::Tab => {
                 self.apply_editor_change(|editor| editor.insert_char('\t'));
-                true
             }
             KeyCode::Char(ch)
                 if !key.modifiers.intersects(
@@ -1318,46 +2078,57 @@ impl App {
                 ) =>
             {
                 self.apply_editor_change(|editor| editor.insert_char(ch));
-                true
-            }
-            KeyCode::Left => {
-                if let Some(editor) = self.state.editor_mut() {
-                    editor.move_left();
-                }
-                true
             }
-            KeyCode::Right => {
-                if let Some(editor) = self.state.editor_mut() {
-                    editor.move_right();
-                }
-                true
-            }
-            KeyCode::Up => {
-                if let Some(editor) = self.state.editor_mut() {
-                    editor.move_up();
+            _ => {}
+        }
+    }
+
+    /// Runs an editor-only action; returns false for actions outside the editor.
+    fn handle_editor_action(&mut self, action: Action) -> bool {
+        match action {
+            Action::ExitEdit => {
+                if self.exit_editing() {
+                    self.state.set_status_message(Some("Exited edit mode"));
                 }
-                true
             }
-            KeyCode::Down => {
-                if let Some(editor) = self.state.editor_mut() {
-                    editor.move_down();
+            Action::Undo => {
+                if self.editor_undo() {
+                    self.state.set_status_message(Some("Undid change"));
+                } else {
+                    self.state.set_status_message(Some("Nothing to undo"));
                 }
-                true
             }
-            KeyCode::Home => {
-                if let Some(editor) = self.state.editor_mut() {
-                    editor.move_home();
+            Action::Redo => {
+                if self.editor_redo() {
+                    self.state.set_status_message(Some("Redid change"));
+                } else {
+                    self.state.set_status_message(Some("Nothing to redo"));
                 }
-                true
             }
-            KeyCode::End => {
+            Action::CursorLeft
+            | Action::CursorRight
+            | Action::CursorUp
+            | Action::CursorDown
+            | Action::WordLeft
+            | Action::WordRight
+            | Action::LineStart
+            | Action::LineEnd => {
                 if let Some(editor) = self.state.editor_mut() {
-                    editor.move_end();
+                    match action {
+                        Action::CursorLeft => editor.move_left(),
+                        Action::CursorRight => editor.move_right(),
+                        Action::CursorUp => editor.move_up(),
+                        Action::CursorDown => editor.move_down(),
+                        Action::WordLeft => editor.move_word_left(),
+                        Action::WordRight => editor.move_word_right(),
+                        Action::LineStart => editor.move_home(),
+                        _ => editor.move_end(),
+                    };
                 }
-                true
             }
-            _ => false,
+            _ => return false,
         }
+        true
     }
 
     fn editor_undo(&mut self) -> bool {
@@ -1541,9 +2312,8 @@ impl App {
         }
 
         self.state.close_overlay();
-        match self.state.refresh(&self.storage) {
+        match self.state.refresh_selecting(&self.storage, note_id) {
             Ok(()) => {
-                self.state.select_note_by_id(note_id);
                 self.state.set_status_message(Some("Tags updated"));
             }
             Err(err) => {
@@ -1566,7 +2336,7 @@ impl App {
         }
         let dispatcher = actions::ActionDispatcher::new(&self.storage);
         match dispatcher.rename_tag(&original, &target) {
-            Ok(TagRenameOutcome::Renamed { from, to }) => {
+            Ok(TagRenameOutcome::Renamed { from, to, .. }) => {
                 self.state.tag_editor_finish_rename(&from, &to);
                 self.refresh_after_tag_update();
             }
@@ -1574,6 +2344,7 @@ impl App {
                 from,
                 to,
                 reassigned,
+                ..
             }) => {
                 self.state.tag_editor_finish_merge(&from, &to);
                 self.refresh_after_tag_update();
@@ -1646,12 +2417,13 @@ impl App {
                     from,
                     to,
                     reassigned,
+                    ..
                 }) => {
                     self.state.tag_editor_finish_merge(&from, &to);
                     merged_count += 1;
                     reassigned_total += reassigned;
                 }
-                Ok(TagRenameOutcome::Renamed { from, to }) => {
+                Ok(TagRenameOutcome::Renamed { from, to, .. }) => {
                     self.state.tag_editor_finish_rename(&from, &to);
                     merged_count += 1;
                 }
@@ -1705,13 +2477,50 @@ impl App {
             self.state
                 .set_status_message(Some("Tag updated but refresh failed"));
         }
+        if let Err(err) = self.state.tag_editor_reload_counts(&self.storage) {
+            tracing::error!(?err, "failed to reload tag counts");
+        }
+    }
+}
+
+fn rename_message(rewritten: usize) -> String {
+    match rewritten {
+        0 => "Note renamed".to_string(),
+        1 => "Note renamed; updated links in 1 note".to_string(),
+        count => format!("Note renamed; updated links in {count} notes"),
     }
 }
 
+fn setup_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>> {
+    enable_raw_mode().context("enabling raw mode")?;
+    let mut stdout = std::io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)
+        .context("switching to alternate screen")?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend).context("creating terminal backend")?;
+    terminal.hide_cursor().context("hiding cursor")?;
+    Ok(terminal)
+}
+
+fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+    terminal.show_cursor().ok();
+    disable_raw_mode().context("disabling raw mode")?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )
+    .context("restoring screen state")?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{AppConfig, ConfigPaths, StorageOptions};
+    use crate::config::{
+        AppConfig, ConfigPaths, SortDirection, SortField, SortSpec, StorageOptions,
+    };
+    use crate::search::NoteScope;
     use crate::storage;
     use anyhow::Result;
     use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
@@ -1728,18 +2537,27 @@ mod tests {
             backup_dir: base.join("backups"),
             log_dir: base.join("logs"),
             state_dir: base.join("state"),
+            theme_dir: base.join("config/themes"),
         }
     }
 
     fn storage_options(paths: &ConfigPaths) -> StorageOptions {
-        let mut opts = StorageOptions::default();
-        opts.database_path = paths.database_path.clone();
-        opts.backup_dir = paths.backup_dir.clone();
-        opts.backup_on_exit = false;
-        opts
+        StorageOptions {
+            database_path: paths.database_path.clone(),
+            backup_dir: paths.backup_dir.clone(),
+            backup_on_exit: false,
+            ..StorageOptions::default()
+        }
     }
 
     fn setup_app_with_note(tags: &[&str]) -> Result<(TempDir, App, i64)> {
+        setup_app_with_config(tags, |_| {})
+    }
+
+    fn setup_app_with_config(
+        tags: &[&str],
+        configure: impl FnOnce(&mut AppConfig),
+    ) -> Result<(TempDir, App, i64)> {
         let temp = TempDir::new()?;
         let paths = temp_paths(&temp);
         paths.ensure_directories()?;
@@ -1759,6 +2577,7 @@ mod tests {
         config.storage.backup_on_exit = false;
         config.auto_save.enabled = false;
         config.auto_save.crash_recovery = false;
+        configure(&mut config);
 
         let app = App::new(Arc::new(config), storage.clone(), paths)?;
 
@@ -1861,6 +2680,56 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn tag_editor_shows_a_tree_and_renames_branches() -> Result<()> {
+        let (_temp, mut app, note_id) = setup_app_with_note(&["project/alpha", "project/beta"])?;
+        app.state.select_note_by_id(note_id);
+        app.state.open_tag_editor(&app.storage)?;
+        let rows = |app: &App| -> Vec<(String, usize, usize)> {
+            app.state
+                .tag_editor_overlay()
+                .expect("overlay open")
+                .items
+                .iter()
+                .map(|item| (item.label().to_string(), item.depth, item.note_count))
+                .collect()
+        };
+        assert_eq!(
+            rows(&app),
+            vec![
+                ("project".to_string(), 0, 1),
+                ("alpha".to_string(), 1, 1),
+                ("beta".to_string(), 1, 1),
+            ]
+        );
+
+        app.state.tag_editor_begin_rename();
+        {
+            let editor = app.state.tag_editor_overlay_mut().expect("overlay open");
+            editor.input.clear();
+            editor.input.push_str("work");
+        }
+        app.handle_tag_editor_rename("project".to_string());
+
+        let mut tags = app
+            .storage
+            .fetch_note_by_id(note_id)?
+            .expect("note present")
+            .tags;
+        tags.sort();
+        assert_eq!(tags, vec!["work/alpha", "work/beta"]);
+        let names: Vec<String> = app
+            .state
+            .tag_editor_overlay()
+            .expect("overlay open")
+            .items
+            .iter()
+            .map(|item| item.name.clone())
+            .collect();
+        assert_eq!(names, vec!["work", "work/alpha", "work/beta"]);
+        Ok(())
+    }
+
     #[test]
     fn tag_editor_delete_flow_removes_tag() -> Result<()> {
         let (_temp, mut app, note_id) = setup_app_with_note(&["obsolete"])?;
@@ -2003,14 +2872,11 @@ mod tests {
             .tags;
         assert_eq!(tags, vec!["gamma".to_string()]);
         let overlay = app.state.tag_editor_overlay().expect("overlay present");
-        assert_eq!(
-            overlay
-                .status
-                .as_deref()
-                .unwrap_or("")
-                .starts_with("Merged"),
-            true
-        );
+        assert!(overlay
+            .status
+            .as_deref()
+            .unwrap_or("")
+            .starts_with("Merged"));
         Ok(())
     }
 
@@ -2036,27 +2902,644 @@ mod tests {
         assert!(tags.iter().any(|tag| tag == "beta"));
         Ok(())
     }
-}
 
-fn setup_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>> {
-    enable_raw_mode().context("enabling raw mode")?;
-    let mut stdout = std::io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)
-        .context("switching to alternate screen")?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend).context("creating terminal backend")?;
-    terminal.hide_cursor().context("hiding cursor")?;
-    Ok(terminal)
-}
+    #[test]
+    fn revision_overlay_restores_selected_revision() -> Result<()> {
+        let (_temp, mut app, note_id) = setup_app_with_note(&[])?;
+        app.storage
+            .update_note_body(note_id, "overwritten by autosave")?;
+        app.state.refresh(&app.storage)?;
+        app.state.select_note_by_id(note_id);
 
-fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
-    terminal.show_cursor().ok();
-    disable_raw_mode().context("disabling raw mode")?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )
-    .context("restoring screen state")?;
-    Ok(())
+        press(&mut app, KeyCode::Char('H'));
+        let overlay = app.state.revisions_overlay().expect("history overlay open");
+        assert_eq!(overlay.note_id, note_id);
+        assert_eq!(overlay.entries.len(), 1);
+        assert_eq!(overlay.entries[0].body, "body");
+
+        press(&mut app, KeyCode::Enter);
+        assert!(app.state.overlay().is_none());
+        let note = app.storage.fetch_note_by_id(note_id)?.expect("note exists");
+        assert_eq!(note.body, "body");
+        assert_eq!(app.state.selected().map(|note| note.id), Some(note_id));
+
+        // The overwritten text is kept so the restore can itself be undone.
+        let revisions = app.storage.list_note_revisions(note_id)?;
+        assert_eq!(revisions[0].body, "overwritten by autosave");
+        Ok(())
+    }
+
+    #[test]
+    fn revision_overlay_requires_history() -> Result<()> {
+        let (_temp, mut app, note_id) = setup_app_with_note(&[])?;
+        app.state.select_note_by_id(note_id);
+        press(&mut app, KeyCode::Char('H'));
+        assert!(app.state.overlay().is_none());
+        assert_eq!(
+            app.state.status_message.as_deref(),
+            Some("No earlier revisions for this note")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn archive_view_lists_and_unarchives_notes() -> Result<()> {
+        let (_temp, mut app, note_id) = setup_app_with_note(&[])?;
+        app.state.select_note_by_id(note_id);
+        press(&mut app, KeyCode::Char('A'));
+        assert!(app.state.notes.iter().all(|note| note.id != note_id));
+
+        press(&mut app, KeyCode::Char('V'));
+        assert!(app.state.show_archive);
+        assert_eq!(app.state.selected_note_id(), Some(note_id));
+
+        // Searches inside the archive view stay scoped to archived notes.
+        press(&mut app, KeyCode::Char('/'));
+        type_text(&mut app, "tagged");
+        assert_eq!(
+            app.state
+                .notes
+                .iter()
+                .map(|note| note.id)
+                .collect::<Vec<_>>(),
+            vec![note_id]
+        );
+        press(&mut app, KeyCode::Esc);
+
+        press(&mut app, KeyCode::Char('u'));
+        assert_eq!(app.state.status_message.as_deref(), Some("Note unarchived"));
+        assert!(app.state.is_empty());
+
+        press(&mut app, KeyCode::Char('V'));
+        assert!(!app.state.show_archive);
+        assert!(app.state.notes.iter().any(|note| note.id == note_id));
+        Ok(())
+    }
+
+    #[test]
+    fn emacs_keymap_sequences_and_overrides() -> Result<()> {
+        let (_temp, mut app, note_id) = setup_app_with_config(&[], |config| {
+            config.keybindings = crate::config::KeybindingProfile::Emacs;
+            let list = [("alt-p", "none"), ("P", "toggle-pin"), ("ctrl-x z", "warp")]
+                .into_iter()
+                .map(|(keys, action)| (keys.to_string(), action.to_string()))
+                .collect();
+            config.keymap.insert("list".into(), list);
+        })?;
+        let status = app.state.status_message.clone().unwrap_or_default();
+        assert!(status.contains("unknown action `warp`"), "{status}");
+        assert!(status.contains("1 issue(s)"), "{status}");
+
+        app.state.select_note_by_id(note_id);
+        app.handle_key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::ALT));
+        assert!(!app.storage.fetch_note_by_id(note_id)?.expect("note").pinned);
+        press(&mut app, KeyCode::Char('P'));
+        assert!(app.storage.fetch_note_by_id(note_id)?.expect("note").pinned);
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL));
+        assert_eq!(app.state.pending_keys.as_deref(), Some("ctrl-x"));
+        assert!(!app.should_quit);
+        app.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        assert!(app.state.pending_keys.is_none());
+        assert!(app.should_quit);
+        Ok(())
+    }
+
+    #[test]
+    fn vim_sequence_jumps_to_first_note() -> Result<()> {
+        let (_temp, mut app, _note_id) = setup_app_with_note(&[])?;
+        assert!(app.state.len() > 1, "seed notes plus the test note");
+        press(&mut app, KeyCode::Char('G'));
+        assert_eq!(app.state.selected, app.state.len() - 1);
+        press(&mut app, KeyCode::Char('g'));
+        assert_eq!(app.state.selected, app.state.len() - 1);
+        press(&mut app, KeyCode::Char('g'));
+        assert_eq!(app.state.selected, 0);
+
+        // A broken sequence drops both keys instead of firing `d` (delete).
+        press(&mut app, KeyCode::Char('g'));
+        press(&mut app, KeyCode::Char('d'));
+        assert!(app.state.overlay().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn palette_runs_commands_with_arguments() -> Result<()> {
+        let (_temp, mut app, note_id) = setup_app_with_note(&[])?;
+        app.state.select_note_by_id(note_id);
+
+        press(&mut app, KeyCode::Char(':'));
+        type_text(&mut app, "tag add \"next up\"");
+        press(&mut app, KeyCode::Enter);
+        assert!(app.state.overlay().is_none());
+        let note = app
+            .storage
+            .fetch_note_by_id(note_id)?
+            .expect("note present");
+        assert_eq!(note.tags, vec!["next up".to_string()]);
+
+        // A command that needs arguments is completed instead of run.
+        press(&mut app, KeyCode::Char(':'));
+        type_text(&mut app, "tag rem");
+        press(&mut app, KeyCode::Enter);
+        let overlay = app.state.palette_overlay().expect("palette still open");
+        assert_eq!(overlay.input, "tag remove ");
+        assert_eq!(
+            app.state.status_message.as_deref(),
+            Some("Usage: tag remove <tag>")
+        );
+        press(&mut app, KeyCode::Esc);
+        assert!(app.state.overlay().is_none());
+
+        // Fuzzy matches run the best hit.
+        press(&mut app, KeyCode::Char(':'));
+        type_text(&mut app, "arch");
+        press(&mut app, KeyCode::Enter);
+        let note = app
+            .storage
+            .fetch_note_by_id(note_id)?
+            .expect("note present");
+        assert!(note.archived);
+
+        press(&mut app, KeyCode::Char(':'));
+        type_text(&mut app, "purge trash");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(
+            app.state.bulk_trash_action(),
+            Some(BulkTrashAction::PurgeAll)
+        );
+        press(&mut app, KeyCode::Esc);
+
+        press(&mut app, KeyCode::Char(':'));
+        type_text(&mut app, "zzqx");
+        press(&mut app, KeyCode::Enter);
+        assert!(app.state.palette_overlay().is_some());
+        assert_eq!(
+            app.state.status_message.as_deref(),
+            Some("No matching command")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn help_overlay_tracks_context_and_returns_to_popup() -> Result<()> {
+        let (_temp, mut app, note_id) = setup_app_with_config(&["alpha"], |config| {
+            config
+                .keymap
+                .entry("list".to_string())
+                .or_default()
+                .insert("x".to_string(), "delete-note".to_string());
+        })?;
+        app.state.select_note_by_id(note_id);
+
+        press(&mut app, KeyCode::Char('?'));
+        let help = app.state.help_overlay().expect("help open");
+        assert_eq!(help.topic, HelpTopic::List);
+        let delete = help
+            .entries
+            .iter()
+            .find(|entry| entry.action == "delete-note")
+            .expect("delete listed");
+        assert_eq!(delete.keys, "d / x");
+
+        // Letters filter instead of triggering bindings; Esc clears, then closes.
+        type_text(&mut app, "trash");
+        let help = app.state.help_overlay().expect("help still open");
+        assert!(!help.visible_entries().is_empty());
+        assert!(help
+            .visible_entries()
+            .iter()
+            .all(|entry| entry.matches("trash")));
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(
+            app.state.help_overlay().map(|h| h.filter.as_str()),
+            Some("")
+        );
+        press(&mut app, KeyCode::Esc);
+        assert!(app.state.overlay().is_none());
+
+        open_tag_editor_via_keys(&mut app);
+        press(&mut app, KeyCode::Char('?'));
+        assert_eq!(
+            app.state.help_overlay().map(|help| help.topic),
+            Some(HelpTopic::TagEditor)
+        );
+        press(&mut app, KeyCode::Esc);
+        assert!(app.state.tag_editor_overlay().is_some());
+        press(&mut app, KeyCode::Esc);
+
+        press(&mut app, KeyCode::Char('e'));
+        assert!(app.state.is_editing());
+        press(&mut app, KeyCode::F(1));
+        assert_eq!(
+            app.state.help_overlay().map(|help| help.topic),
+            Some(HelpTopic::Editor)
+        );
+        press(&mut app, KeyCode::F(1));
+        assert!(app.state.overlay().is_none());
+        assert!(app.state.is_editing());
+        Ok(())
+    }
+
+    #[test]
+    fn list_window_pages_rows_and_keeps_selection_on_refresh() -> Result<()> {
+        let (_temp, mut app, _note_id) = setup_app_with_note(&[])?;
+        for idx in 0..450 {
+            app.storage
+                .create_note(&format!("Bulk {idx}"), "body", false)?;
+        }
+        app.state.refresh(&app.storage)?;
+        let order: Vec<i64> = app
+            .storage
+            .fetch_note_page(
+                NoteScope::Active,
+                &app.state.sort,
+                &storage::PageAnchor::Start,
+                10_000,
+            )?
+            .iter()
+            .map(|note| note.id)
+            .collect();
+        let selected = |app: &App| app.state.selected_note_id().expect("selection");
+
+        assert_eq!(app.state.total(), order.len());
+        assert!(app.state.len() < order.len());
+        app.handle_action(Action::SelectLast);
+        assert_eq!(app.state.selected_position(), order.len() - 1);
+        assert_eq!(selected(&app), *order.last().unwrap());
+        app.handle_action(Action::SelectFirst);
+        assert_eq!(selected(&app), order[0]);
+
+        for _ in 0..42 {
+            app.handle_action(Action::PageDown);
+        }
+        assert_eq!(app.state.selected_position(), 420);
+        assert_eq!(selected(&app), order[420]);
+        assert!(app.state.list_offset > 0, "early rows paged out");
+        for _ in 0..40 {
+            app.handle_action(Action::PageUp);
+        }
+        app.handle_action(Action::SelectPrevious);
+        assert_eq!(selected(&app), order[19]);
+
+        app.handle_action(Action::PageDown);
+        let note_id = selected(&app);
+        app.state.refresh(&app.storage)?;
+        assert_eq!(selected(&app), note_id);
+        assert_eq!(app.state.selected_position(), 29);
+
+        // A note leaving the list hands the selection to the row that replaced it.
+        app.storage.soft_delete_note(note_id)?;
+        app.state.refresh(&app.storage)?;
+        assert_eq!(selected(&app), order[30]);
+        assert_eq!(app.state.total(), order.len() - 1);
+        Ok(())
+    }
+
+    #[test]
+    fn default_sort_applies_and_sort_key_cycles() -> Result<()> {
+        let (_temp, mut app, note_id) = setup_app_with_config(&[], |config| {
+            config.default_sort = SortSpec {
+                field: SortField::Title,
+                direction: SortDirection::Ascending,
+            };
+        })?;
+        let titles = |app: &App| -> Vec<String> {
+            app.state
+                .notes
+                .iter()
+                .map(|note| note.title.to_lowercase())
+                .collect()
+        };
+        let mut ascending = titles(&app);
+        ascending.sort();
+        assert_eq!(titles(&app), ascending);
+
+        app.state.select_note_by_id(note_id);
+        press(&mut app, KeyCode::Char('s'));
+        assert_eq!(app.state.sort.direction, SortDirection::Descending);
+        assert_eq!(app.state.status_message.as_deref(), Some("Sort: title ↓"));
+        ascending.reverse();
+        assert_eq!(titles(&app), ascending);
+        assert_eq!(app.state.selected_note_id(), Some(note_id));
+
+        // Searches follow the active order too.
+        press(&mut app, KeyCode::Char('/'));
+        type_text(&mut app, "note");
+        let results = titles(&app);
+        assert!(results.len() > 1);
+        assert!(results.windows(2).all(|pair| pair[0] >= pair[1]));
+        Ok(())
+    }
+
+    #[test]
+    fn sort_survives_reloads_and_search_refreshes() -> Result<()> {
+        let configured = SortSpec {
+            field: SortField::Created,
+            direction: SortDirection::Ascending,
+        };
+        let (_temp, mut app, note_id) = setup_app_with_config(&[], |config| {
+            config.default_sort = configured;
+        })?;
+        let shown =
+            |app: &App| -> Vec<i64> { app.state.notes.iter().map(|note| note.id).collect() };
+        let stored = |app: &App, sort: &SortSpec| -> Result<Vec<i64>> {
+            Ok(app
+                .storage
+                .fetch_note_page(NoteScope::Active, sort, &storage::PageAnchor::Start, 10_000)?
+                .iter()
+                .map(|note| note.id)
+                .collect())
+        };
+        // The configured order is in place from the first frame.
+        assert_eq!(app.state.sort, configured);
+        assert_eq!(shown(&app), stored(&app, &configured)?);
+
+        press(&mut app, KeyCode::Char('s'));
+        let cycled = configured.next();
+        assert_eq!(app.state.sort, cycled);
+
+        // A reload after an edit keeps the cycled order.
+        app.storage.create_note("Zebra", "note body", false)?;
+        app.storage.rename_note_title(note_id, "Aardvark")?;
+        app.state.refresh(&app.storage)?;
+        assert_eq!(app.state.sort, cycled);
+        assert_eq!(shown(&app), stored(&app, &cycled)?);
+
+        // So does leaving a search, which reloads the list.
+        press(&mut app, KeyCode::Char('/'));
+        type_text(&mut app, "note");
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.state.sort, cycled);
+        assert_eq!(shown(&app), stored(&app, &cycled)?);
+        Ok(())
+    }
+
+    #[test]
+    fn search_parse_errors_show_in_the_status_bar() -> Result<()> {
+        let (_temp, mut app, note_id) = setup_app_with_note(&["alpha"])?;
+        press(&mut app, KeyCode::Char('/'));
+        type_text(&mut app, "body \"tag");
+        assert_eq!(app.state.search_error(), Some("unclosed quote (column 6)"));
+        // The half-typed query leaves the previous results in place.
+        assert!(app.state.notes.iter().any(|note| note.id == note_id));
+
+        type_text(&mut app, "ged\" -tag:alpha");
+        assert_eq!(app.state.search_error(), None);
+        assert!(app.state.is_empty());
+        assert!(app
+            .state
+            .search_filter_chips()
+            .contains(&"-tag:alpha".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn saved_searches_are_saved_from_the_palette_and_run_from_the_picker() -> Result<()> {
+        let (_temp, mut app, note_id) = setup_app_with_note(&["alpha"])?;
+        app.storage.create_note("Other note", "unrelated", false)?;
+        app.state.refresh(&app.storage)?;
+
+        press(&mut app, KeyCode::Char('S'));
+        assert!(app.state.overlay().is_none(), "nothing saved yet");
+
+        press(&mut app, KeyCode::Char('/'));
+        type_text(&mut app, "tag:alpha");
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char(':'));
+        type_text(&mut app, "save search Alpha notes");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(
+            app.state.status_message.as_deref(),
+            Some("Saved search 'Alpha notes'")
+        );
+        app.storage.save_search("everything", "-zzz")?;
+
+        press(&mut app, KeyCode::Char('/'));
+        press(&mut app, KeyCode::Esc);
+        let total = app.state.len().to_string();
+
+        press(&mut app, KeyCode::Char('S'));
+        let overlay = app.state.saved_searches_overlay().expect("picker open");
+        let listed: Vec<(&str, &str)> = overlay
+            .entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.count.as_str()))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![("Alpha notes", "1"), ("everything", total.as_str())]
+        );
+
+        press(&mut app, KeyCode::Enter);
+        assert!(app.state.overlay().is_none());
+        assert_eq!(app.state.search_query(), "tag:alpha");
+        assert_eq!(app.state.len(), 1);
+        assert_eq!(app.state.selected_note_id(), Some(note_id));
+        assert_eq!(
+            app.state.status_message.as_deref(),
+            Some("Search 'Alpha notes': 1 note")
+        );
+
+        press(&mut app, KeyCode::Char(':'));
+        type_text(&mut app, "delete search alpha notes");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.storage.list_saved_searches()?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn tag_browser_sorts_filters_and_prunes_unused_tags() -> Result<()> {
+        let (_temp, mut app, note_id) = setup_app_with_note(&["alpha", "beta"])?;
+        let other = app.storage.create_note("Other note", "body", false)?;
+        app.storage.add_tag_to_note(other, "beta")?;
+        let binned = app.storage.create_note("Binned", "body", false)?;
+        app.storage.add_tag_to_note(binned, "old")?;
+        app.storage.soft_delete_note(binned)?;
+        app.state.refresh(&app.storage)?;
+
+        let names = |app: &App| -> Vec<String> {
+            app.state
+                .tag_browser_overlay()
+                .expect("browser open")
+                .visible()
+                .iter()
+                .map(|entry| entry.usage.name.clone())
+                .collect()
+        };
+        press(&mut app, KeyCode::Char('#'));
+        assert_eq!(names(&app), vec!["alpha", "beta", "old"]);
+        press(&mut app, KeyCode::Char('s'));
+        assert_eq!(names(&app), vec!["beta", "alpha", "old"]);
+        press(&mut app, KeyCode::Char('u'));
+        assert_eq!(names(&app), vec!["old"]);
+
+        press(&mut app, KeyCode::Char('U'));
+        press(&mut app, KeyCode::Char('n'));
+        assert!(app.storage.tag_exists("old")?);
+        press(&mut app, KeyCode::Char('U'));
+        press(&mut app, KeyCode::Char('y'));
+        assert!(!app.storage.tag_exists("old")?);
+        assert_eq!(app.state.status_message.as_deref(), Some("Deleted 1 tag"));
+        assert!(names(&app).is_empty());
+
+        press(&mut app, KeyCode::Char('u'));
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('v'));
+        press(&mut app, KeyCode::Char('k'));
+        press(&mut app, KeyCode::Char('v'));
+        press(&mut app, KeyCode::Enter);
+        assert!(app.state.overlay().is_none());
+        assert_eq!(app.state.search_query(), "tag:beta tag:alpha");
+        assert_eq!(app.state.len(), 1);
+        assert_eq!(app.state.selected_note_id(), Some(note_id));
+        Ok(())
+    }
+
+    #[test]
+    fn search_prompt_recalls_history_with_arrows_and_ctrl_r() -> Result<()> {
+        let (temp, mut app, _note_id) = setup_app_with_note(&["alpha"])?;
+        for query in ["tag:alpha", "body", "budget"] {
+            press(&mut app, KeyCode::Char('/'));
+            type_text(&mut app, query);
+            press(&mut app, KeyCode::Enter);
+            press(&mut app, KeyCode::Char('/'));
+            press(&mut app, KeyCode::Esc);
+        }
+
+        // A fresh session over the same database sees the history.
+        let mut app = App::new(app.config.clone(), app.storage.clone(), temp_paths(&temp))?;
+        press(&mut app, KeyCode::Char('/'));
+        type_text(&mut app, "draft");
+        press(&mut app, KeyCode::Up);
+        assert_eq!(app.state.search_query(), "budget");
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Up);
+        assert_eq!(app.state.search_query(), "tag:alpha");
+        assert_eq!(app.state.len(), 1);
+        press(&mut app, KeyCode::Up);
+        assert_eq!(app.state.search_query(), "tag:alpha", "oldest entry");
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.state.search_query(), "draft");
+
+        let ctrl_r = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        app.handle_key(ctrl_r);
+        type_text(&mut app, "b");
+        assert_eq!(app.state.search_query(), "budget");
+        app.handle_key(ctrl_r);
+        assert_eq!(app.state.search_query(), "body");
+        app.handle_key(ctrl_r);
+        assert!(app
+            .state
+            .reverse_search()
+            .is_some_and(|reverse| reverse.failed));
+        press(&mut app, KeyCode::Esc);
+        assert!(app.state.reverse_search().is_none());
+        assert_eq!(app.state.search_query(), "draft");
+
+        app.handle_key(ctrl_r);
+        type_text(&mut app, "tag");
+        press(&mut app, KeyCode::Enter);
+        assert!(!app.state.is_search_active());
+        assert_eq!(app.state.search_query(), "tag:alpha");
+        assert_eq!(app.state.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn reader_follows_wiki_links_and_lists_backlinks() -> Result<()> {
+        let (_temp, mut app, _note_id) = setup_app_with_note(&[])?;
+        let target = app.storage.create_note("Target", "linked", false)?;
+        app.storage.set_note_archived(target, true)?;
+        let hub = app
+            .storage
+            .create_note("Hub", "See [[Missing]] then [[target]].", false)?;
+        press(&mut app, KeyCode::Char('/'));
+        type_text(&mut app, "hub");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.state.selected_note_id(), Some(hub));
+
+        assert_eq!(
+            app.state.selected_link_index(),
+            None,
+            "links are picked in the reader"
+        );
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(app.state.selected_link_index(), Some(0));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(
+            app.state.status_message.as_deref(),
+            Some("No note matches [[Missing]]")
+        );
+        press(&mut app, KeyCode::Char(']'));
+        assert_eq!(app.state.status_message.as_deref(), Some("Link [[target]]"));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.state.selected_note_id(), Some(target));
+        assert!(
+            app.state.show_archive,
+            "archived targets open in the archive view"
+        );
+        assert!(app.state.search_query().is_empty());
+        assert_eq!(app.state.status_message.as_deref(), Some("Opened 'Target'"));
+
+        app.sync_backlinks();
+        let backlinks: Vec<&str> = app
+            .state
+            .backlinks()
+            .iter()
+            .map(|note| note.title.as_str())
+            .collect();
+        assert_eq!(backlinks, vec!["Hub"]);
+        Ok(())
+    }
+
+    #[test]
+    fn notebook_tree_files_notes_and_filters_the_list() -> Result<()> {
+        let (_temp, mut app, note_id) = setup_app_with_note(&[])?;
+        app.storage.create_note("Loose", "body", false)?;
+        app.state.refresh_selecting(&app.storage, note_id)?;
+        let all_notes = app.state.len();
+
+        press(&mut app, KeyCode::Char(':'));
+        type_text(&mut app, "move to Work / Projects");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(
+            app.state.status_message.as_deref(),
+            Some("Moved to 'Work/Projects'")
+        );
+        assert_eq!(app.state.selected_notebook_path(), Some("Work/Projects"));
+
+        press(&mut app, KeyCode::Char('N'));
+        assert!(app.state.show_notebooks);
+        assert_eq!(app.state.focus, FocusPane::Notebooks);
+        assert_eq!(app.state.notebook_tree().visible().len(), 2);
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.state.focus, FocusPane::List);
+        assert_eq!(app.state.search_query(), "notebook:Work");
+        let listed: Vec<i64> = app.state.notes.iter().map(|note| note.id).collect();
+        assert_eq!(listed, vec![note_id]);
+
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(app.state.focus, FocusPane::Notebooks);
+        press(&mut app, KeyCode::Char(' '));
+        assert_eq!(app.state.notebook_tree().visible().len(), 1);
+        press(&mut app, KeyCode::Char('k'));
+        press(&mut app, KeyCode::Enter);
+        assert!(app.state.search_query().is_empty());
+        assert_eq!(app.state.len(), all_notes);
+
+        press(&mut app, KeyCode::Char(':'));
+        type_text(&mut app, "notebook delete work");
+        press(&mut app, KeyCode::Enter);
+        assert!(app
+            .state
+            .status_message
+            .as_deref()
+            .is_some_and(|message| message.contains("still contains other notebooks")));
+        Ok(())
+    }
 }
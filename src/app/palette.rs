@@ -0,0 +1,441 @@
+use anyhow::{bail, Result};
+use strum::IntoEnumIterator;
+
+use super::keymap::{Action, KeyContext, Keymap};
+
+/// Dispatcher operations that only make sense from the palette because they take
+/// arguments or pick one side of a toggle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Operation {
+    TagAdd,
+    TagRemove,
+    TagRename,
+    TagDelete,
+    Rename,
+    Pin,
+    Unpin,
+    Archive,
+    Unarchive,
+    RestoreTrash,
+    PurgeTrash,
+    SaveSearch,
+    DeleteSavedSearch,
+    MoveToNotebook,
+    NotebookNew,
+    NotebookDelete,
+}
+
+impl Operation {
+    const ALL: [Operation; 16] = [
+        Operation::TagAdd,
+        Operation::TagRemove,
+        Operation::TagRename,
+        Operation::TagDelete,
+        Operation::Rename,
+        Operation::Pin,
+        Operation::Unpin,
+        Operation::Archive,
+        Operation::Unarchive,
+        Operation::RestoreTrash,
+        Operation::PurgeTrash,
+        Operation::SaveSearch,
+        Operation::DeleteSavedSearch,
+        Operation::MoveToNotebook,
+        Operation::NotebookNew,
+        Operation::NotebookDelete,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Operation::TagAdd => "tag add",
+            Operation::TagRemove => "tag remove",
+            Operation::TagRename => "tag rename",
+            Operation::TagDelete => "tag delete",
+            Operation::Rename => "rename",
+            Operation::Pin => "pin",
+            Operation::Unpin => "unpin",
+            Operation::Archive => "archive",
+            Operation::Unarchive => "unarchive",
+            Operation::RestoreTrash => "restore trash",
+            Operation::PurgeTrash => "purge trash",
+            Operation::SaveSearch => "save search",
+            Operation::DeleteSavedSearch => "delete search",
+            Operation::MoveToNotebook => "move to",
+            Operation::NotebookNew => "notebook new",
+            Operation::NotebookDelete => "notebook delete",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Operation::TagAdd => "Add a tag to the selected note",
+            Operation::TagRemove => "Remove a tag from the selected note",
+            Operation::TagRename => "Rename a tag on every note (merges into an existing tag)",
+            Operation::TagDelete => "Delete a tag from every note",
+            Operation::Rename => "Set the title of the selected note",
+            Operation::Pin => "Pin the selected note",
+            Operation::Unpin => "Unpin the selected note",
+            Operation::Archive => "Archive the selected note",
+            Operation::Unarchive => "Unarchive the selected note",
+            Operation::RestoreTrash => "Restore every note in the trash (asks first)",
+            Operation::PurgeTrash => "Permanently delete every note in the trash (asks first)",
+            Operation::SaveSearch => "Save the current search under a name",
+            Operation::DeleteSavedSearch => "Delete a saved search",
+            Operation::MoveToNotebook => {
+                "File the selected note in a notebook, creating it if needed (`/` for none)"
+            }
+            Operation::NotebookNew => "Create a notebook; `/` separates nested names",
+            Operation::NotebookDelete => {
+                "Delete a notebook without sub-notebooks; its notes move up a level"
+            }
+        }
+    }
+
+    fn parameters(self) -> Parameters {
+        match self {
+            Operation::TagAdd | Operation::TagRemove | Operation::TagDelete => {
+                Parameters::Words(&["tag"])
+            }
+            Operation::TagRename => Parameters::Words(&["from", "to"]),
+            Operation::Rename => Parameters::Text("title"),
+            Operation::SaveSearch | Operation::DeleteSavedSearch => Parameters::Text("name"),
+            Operation::MoveToNotebook => Parameters::Text("notebook"),
+            Operation::NotebookNew | Operation::NotebookDelete => Parameters::Text("path"),
+            _ => Parameters::Words(&[]),
+        }
+    }
+}
+
+/// What a palette entry runs once its arguments are in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Command {
+    Action(Action),
+    Operation(Operation),
+}
+
+/// Argument shape of a command: a fixed list of words (quote a word to include
+/// spaces) or everything after the command name as one free-form text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Parameters {
+    Words(&'static [&'static str]),
+    Text(&'static str),
+}
+
+impl Parameters {
+    fn is_empty(self) -> bool {
+        matches!(self, Parameters::Words(names) if names.is_empty())
+    }
+
+    fn usage(self) -> String {
+        match self {
+            Parameters::Words(names) => names
+                .iter()
+                .map(|name| format!("<{name}>"))
+                .collect::<Vec<_>>()
+                .join(" "),
+            Parameters::Text(name) => format!("<{name}...>"),
+        }
+    }
+
+    fn bind(self, text: &str) -> Result<Vec<String>> {
+        match self {
+            Parameters::Text(name) => {
+                let text = text.trim();
+                if text.is_empty() {
+                    bail!("missing <{name}>");
+                }
+                Ok(vec![text.to_string()])
+            }
+            Parameters::Words(names) => {
+                let words = split_words(text)?;
+                if words.len() < names.len() {
+                    bail!("missing <{}>", names[words.len()]);
+                }
+                if words.len() > names.len() {
+                    bail!("unexpected `{}`", words[names.len()]);
+                }
+                Ok(words)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PaletteEntry {
+    pub name: String,
+    pub usage: String,
+    pub description: &'static str,
+    /// Current bindings for the command in the list context, joined for display.
+    pub keys: String,
+    pub(crate) command: Command,
+    parameters: Parameters,
+}
+
+impl PaletteEntry {
+    pub fn takes_arguments(&self) -> bool {
+        !self.parameters.is_empty()
+    }
+
+    /// Parses the text typed after the command name into its arguments.
+    pub fn bind_arguments(&self, text: &str) -> Result<Vec<String>> {
+        self.parameters.bind(text)
+    }
+}
+
+/// Every command the palette offers: the argument-taking operations first, then
+/// each main-screen [`Action`] with its current key bindings.
+pub fn entries(keymap: &Keymap) -> Vec<PaletteEntry> {
+    let operations = Operation::ALL.into_iter().map(|operation| {
+        let parameters = operation.parameters();
+        PaletteEntry {
+            name: operation.name().to_string(),
+            usage: parameters.usage(),
+            description: operation.description(),
+            keys: String::new(),
+            command: Command::Operation(operation),
+            parameters,
+        }
+    });
+    let actions = Action::iter()
+        .filter(|action| *action != Action::OpenPalette && KeyContext::List.accepts(*action))
+        .map(|action| PaletteEntry {
+            name: action.name().replace('-', " "),
+            usage: String::new(),
+            description: action.description(),
+            keys: keymap.keys_for(KeyContext::List, action).join(" / "),
+            command: Command::Action(action),
+            parameters: Parameters::Words(&[]),
+        });
+    operations.chain(actions).collect()
+}
+
+/// Finds the command whose full name starts `input` (longest name wins) and
+/// returns it with the remaining argument text. Commands without parameters only
+/// match when nothing follows their name.
+pub fn parse_invocation<'a>(entries: &[PaletteEntry], input: &'a str) -> Option<(usize, &'a str)> {
+    let mut best: Option<(usize, usize, &'a str)> = None;
+    for (idx, entry) in entries.iter().enumerate() {
+        let words: Vec<&str> = entry.name.split(' ').collect();
+        let Some(rest) = strip_words(input, &words) else {
+            continue;
+        };
+        if !rest.is_empty() && !entry.takes_arguments() {
+            continue;
+        }
+        if best.map_or(true, |(_, len, _)| words.len() > len) {
+            best = Some((idx, words.len(), rest));
+        }
+    }
+    best.map(|(idx, _, rest)| (idx, rest))
+}
+
+/// Indices of `entries` matching `input`, best first. An exact invocation (command
+/// name followed by arguments) always ranks at the top; everything else is scored
+/// with [`fuzzy_score`] against the name, then the description.
+pub fn filter(entries: &[PaletteEntry], input: &str) -> Vec<usize> {
+    let query = input.trim();
+    if query.is_empty() {
+        return (0..entries.len()).collect();
+    }
+    let invoked = parse_invocation(entries, input).map(|(idx, _)| idx);
+    let mut scored: Vec<(i64, usize)> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, entry)| {
+            if Some(idx) == invoked {
+                return Some((i64::MAX, idx));
+            }
+            fuzzy_score(query, &entry.name)
+                .or_else(|| fuzzy_score(query, entry.description).map(|score| score - 100))
+                .map(|score| (score, idx))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    scored.into_iter().map(|(_, idx)| idx).collect()
+}
+
+/// Scores `candidate` against `query` when every non-space query character
+/// appears in order (case-insensitive). Matches at word starts, runs of
+/// consecutive characters and prefix matches score higher; skipped characters
+/// cost a point each.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    if query.trim().is_empty() {
+        return None;
+    }
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0i64;
+    let mut position = 0usize;
+    let mut previous: Option<usize> = None;
+    for needle in query
+        .to_lowercase()
+        .chars()
+        .filter(|ch| !ch.is_whitespace())
+    {
+        let offset = candidate[position..].iter().position(|ch| *ch == needle)?;
+        let found = position + offset;
+        score -= offset as i64;
+        let word_start = found == 0 || matches!(candidate[found - 1], ' ' | '-' | '_');
+        if word_start {
+            score += 10;
+        }
+        if previous.is_some_and(|prev| prev + 1 == found) {
+            score += 5;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    if candidate.len() == position {
+        score += 3;
+    }
+    let compact: String = query.to_lowercase().split_whitespace().collect();
+    if candidate
+        .iter()
+        .filter(|ch| !ch.is_whitespace())
+        .collect::<String>()
+        .starts_with(&compact)
+    {
+        score += 20;
+    }
+    Some(score)
+}
+
+fn strip_words<'a>(input: &'a str, words: &[&str]) -> Option<&'a str> {
+    let mut rest = input.trim_start();
+    for word in words {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let (head, tail) = rest.split_at(end);
+        if !head.eq_ignore_ascii_case(word) {
+            return None;
+        }
+        rest = tail.trim_start();
+    }
+    Some(rest.trim_end())
+}
+
+/// Splits on whitespace, keeping `"double quoted"` runs together.
+fn split_words(text: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_word = false;
+    for ch in text.chars() {
+        match ch {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_word = true;
+            }
+            ch if ch.is_whitespace() && !in_quotes => {
+                if has_word {
+                    words.push(std::mem::take(&mut current));
+                    has_word = false;
+                }
+            }
+            ch => {
+                current.push(ch);
+                has_word = true;
+            }
+        }
+    }
+    if in_quotes {
+        bail!("unterminated quote");
+    }
+    if has_word {
+        words.push(current);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::KeybindingProfile;
+
+    fn vim_entries() -> Vec<PaletteEntry> {
+        entries(&Keymap::preset(&KeybindingProfile::Vim))
+    }
+
+    fn names(entries: &[PaletteEntry], indices: &[usize]) -> Vec<String> {
+        indices
+            .iter()
+            .map(|idx| entries[*idx].name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn fuzzy_score_prefers_prefixes_and_word_starts() {
+        assert!(fuzzy_score("xyz", "archive").is_none());
+        assert!(fuzzy_score("pin", "unpin").is_some());
+        let prefix = fuzzy_score("arch", "archive").unwrap();
+        let inner = fuzzy_score("arch", "toggle archive").unwrap();
+        let scattered = fuzzy_score("arch", "restore all trash").unwrap_or(i64::MIN);
+        assert!(prefix > inner, "{prefix} vs {inner}");
+        assert!(inner > scattered, "{inner} vs {scattered}");
+        assert!(fuzzy_score("tp", "toggle pin").unwrap() > fuzzy_score("tp", "tag-op").unwrap());
+
+        let entries = vim_entries();
+        let ranked = filter(&entries, "arch");
+        assert_eq!(entries[ranked[0]].name, "archive");
+        let ranked = filter(&entries, "purge");
+        assert_eq!(
+            names(&entries, &ranked[..2]),
+            vec!["purge trash", "purge all trash"]
+        );
+        assert_eq!(filter(&entries, "").len(), entries.len());
+    }
+
+    #[test]
+    fn invocations_split_name_and_arguments() {
+        let entries = vim_entries();
+        let (idx, rest) = parse_invocation(&entries, "tag add urgent").unwrap();
+        assert_eq!(entries[idx].name, "tag add");
+        assert_eq!(entries[idx].bind_arguments(rest).unwrap(), vec!["urgent"]);
+        assert_eq!(filter(&entries, "tag add urgent")[0], idx);
+
+        let (idx, rest) = parse_invocation(&entries, "Tag Rename \"next up\" focus").unwrap();
+        assert_eq!(entries[idx].name, "tag rename");
+        assert_eq!(
+            entries[idx].bind_arguments(rest).unwrap(),
+            vec!["next up", "focus"]
+        );
+        let err = entries[idx].bind_arguments("solo").unwrap_err();
+        assert_eq!(err.to_string(), "missing <to>");
+        let err = entries[idx].bind_arguments("\"open").unwrap_err();
+        assert_eq!(err.to_string(), "unterminated quote");
+
+        let (idx, rest) = parse_invocation(&entries, "rename  Weekly plan ").unwrap();
+        assert_eq!(entries[idx].name, "rename");
+        assert_eq!(
+            entries[idx].bind_arguments(rest).unwrap(),
+            vec!["Weekly plan"]
+        );
+
+        let (idx, _) = parse_invocation(&entries, "toggle archive view").unwrap();
+        assert_eq!(
+            entries[idx].command,
+            Command::Action(Action::ToggleArchiveView)
+        );
+        assert!(parse_invocation(&entries, "pin now").is_none());
+        assert!(parse_invocation(&entries, "tag").is_none());
+    }
+
+    #[test]
+    fn entries_show_current_bindings() {
+        let entries = vim_entries();
+        let pin = entries
+            .iter()
+            .find(|entry| entry.command == Command::Action(Action::TogglePin))
+            .unwrap();
+        assert_eq!(pin.name, "toggle pin");
+        assert_eq!(pin.keys, "p");
+        let add = entries
+            .iter()
+            .find(|entry| entry.name == "tag add")
+            .unwrap();
+        assert_eq!(add.usage, "<tag>");
+        assert!(add.takes_arguments());
+        assert!(entries.iter().all(
+            |entry| entry.command != Command::Action(Action::OpenPalette)
+                && entry.command != Command::Action(Action::Undo)
+        ));
+    }
+}
@@ -3,14 +3,32 @@ use std::collections::HashSet;
 use time::{format_description::well_known::Rfc3339, Duration, OffsetDateTime};
 use unicode_segmentation::UnicodeSegmentation;
 
+use super::help::{HelpEntry, HelpTopic};
+use super::palette::{self, PaletteEntry};
+use crate::config::SortSpec;
 use crate::journaling::{AutoSaveStatus, RecoverySnapshot};
-use crate::search::{parse_query, regex_pattern_from_input, RangeFilter, SearchQuery};
-use crate::storage::{NoteRecord, StorageHandle};
+use crate::markdown::{parse_links, WikiLink};
+use crate::search::{
+    parse_query, regex_pattern_from_input, Calendar, NoteScope, RangeFilter, SearchHistory,
+    SearchQuery,
+};
+use crate::storage::{
+    tag_ancestors, tag_sort_key, LinkedNote, ListCursor, NoteRecord, NoteRevision, Notebook,
+    PageAnchor, StorageHandle, TagUsage, TAG_SEPARATOR,
+};
+
+/// Rows fetched from storage per page when the list scrolls past its window.
+const LIST_PAGE_SIZE: usize = 100;
+/// Rows kept in memory; scrolling further drops pages from the far end of the window.
+const LIST_WINDOW_MAX: usize = 400;
+/// Most notes a search lists; saved search counts stop here as well.
+const SEARCH_RESULT_LIMIT: usize = 200;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FocusPane {
     List,
     Reader,
+    Notebooks,
 }
 
 #[derive(Debug, Clone)]
@@ -26,6 +44,11 @@ pub struct NoteSummary {
     pub deleted_at: Option<i64>,
     pub deleted_label: Option<String>,
     pub trash_status: Option<TrashStatus>,
+    pub notebook_id: Option<i64>,
+    /// Found only by a fuzzy expansion of the search words.
+    pub fuzzy: bool,
+    /// Where this note sorts in its scope, used to page the rows around it.
+    pub cursor: ListCursor,
 }
 
 #[derive(Debug, Clone)]
@@ -35,6 +58,58 @@ pub struct TrashStatus {
     pub indefinite: bool,
 }
 
+/// The notebook pane. Row 0 is "All notes"; below it come the notebooks
+/// whose parents are all expanded, in [`StorageHandle::list_notebooks`] order.
+#[derive(Debug, Clone, Default)]
+pub struct NotebookTree {
+    notebooks: Vec<Notebook>,
+    collapsed: HashSet<i64>,
+    pub selected: usize,
+}
+
+impl NotebookTree {
+    /// Notebooks shown below the "All notes" row.
+    pub fn visible(&self) -> Vec<&Notebook> {
+        let mut hidden_below: Option<usize> = None;
+        self.notebooks
+            .iter()
+            .filter(|notebook| {
+                if let Some(depth) = hidden_below {
+                    if notebook.depth > depth {
+                        return false;
+                    }
+                    hidden_below = None;
+                }
+                if self.collapsed.contains(&notebook.id) {
+                    hidden_below = Some(notebook.depth);
+                }
+                true
+            })
+            .collect()
+    }
+
+    pub fn has_children(&self, notebook_id: i64) -> bool {
+        self.notebooks
+            .iter()
+            .any(|notebook| notebook.parent_id == Some(notebook_id))
+    }
+
+    pub fn is_collapsed(&self, notebook_id: i64) -> bool {
+        self.collapsed.contains(&notebook_id)
+    }
+
+    /// The selected notebook, `None` on the "All notes" row.
+    pub fn selected_notebook(&self) -> Option<&Notebook> {
+        self.selected
+            .checked_sub(1)
+            .and_then(|index| self.visible().get(index).copied())
+    }
+
+    fn row_count(&self) -> usize {
+        self.visible().len() + 1
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct SearchState {
     pub active: bool,
@@ -42,9 +117,33 @@ pub struct SearchState {
     pub last_error: Option<String>,
     pub terms: Vec<String>,
     pub tags: Vec<String>,
+    /// The `notebook:` path of the current query, marked in the notebook tree.
+    pub notebook: Option<String>,
     pub filter_chips: Vec<String>,
     pub regex_enabled: bool,
     pub regex_pattern: Option<String>,
+    /// From `search.fuzzy_threshold`; copied into each storage query.
+    pub fuzzy_threshold: f32,
+    /// Queries left by Enter or Esc, recalled in the prompt with Up/Down and Ctrl-r.
+    pub history: SearchHistory,
+    /// History entry shown in the prompt while Up/Down step through it.
+    pub history_index: Option<usize>,
+    /// The query as typed before stepping into history; Down past the newest
+    /// entry brings it back.
+    pub history_draft: String,
+    pub reverse_search: Option<ReverseSearch>,
+}
+
+/// Ctrl-r in the search prompt: each typed character narrows to the newest
+/// history entry containing `needle`, and Ctrl-r again steps to older ones.
+#[derive(Debug, Clone, Default)]
+pub struct ReverseSearch {
+    pub needle: String,
+    pub matched: Option<usize>,
+    /// No entry (older than the current match) contains `needle`.
+    pub failed: bool,
+    /// The query from before Ctrl-r, restored when reverse search is cancelled.
+    original: String,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -70,13 +169,38 @@ pub struct TagEditorItem {
     pub selected: bool,
     pub original: bool,
     pub bulk_selected: bool,
+    /// How many levels the tag is nested; zero for top-level tags.
+    pub depth: usize,
+    /// Notes tagged with this tag or any tag nested under it.
+    pub note_count: usize,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl TagEditorItem {
+    fn new(name: &str, selected: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            selected,
+            original: selected,
+            bulk_selected: false,
+            depth: 0,
+            note_count: 0,
+        }
+    }
+
+    /// The last level of the tag's name, shown indented under its parent.
+    pub fn label(&self) -> &str {
+        self.name.rsplit(TAG_SEPARATOR).next().unwrap_or(&self.name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub enum TagEditorMode {
+    #[default]
     Browse,
     Input(TagInputKind),
-    ConfirmDelete { tag: String },
+    ConfirmDelete {
+        tag: String,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -86,12 +210,6 @@ pub enum TagInputKind {
     Merge { sources: Vec<String> },
 }
 
-impl Default for TagEditorMode {
-    fn default() -> Self {
-        TagEditorMode::Browse
-    }
-}
-
 #[derive(Debug, Clone, Default)]
 pub struct TagEditorOverlay {
     pub note_id: i64,
@@ -131,6 +249,182 @@ pub struct RecoveryOverlay {
     pub selected: usize,
 }
 
+#[derive(Debug, Clone)]
+pub struct RevisionEntry {
+    pub id: i64,
+    pub title: String,
+    pub body: String,
+    pub saved_at: String,
+    pub saved_relative: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RevisionsOverlay {
+    pub note_id: i64,
+    pub note_title: String,
+    pub entries: Vec<RevisionEntry>,
+    pub selected: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct SavedSearchEntry {
+    pub name: String,
+    pub query: String,
+    /// Matches in the current view when the picker opened: a number, `200+`
+    /// past the search limit, or `invalid` when the query no longer parses.
+    pub count: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SavedSearchesOverlay {
+    pub entries: Vec<SavedSearchEntry>,
+    pub selected: usize,
+}
+
+/// Orders the tag browser cycles through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TagSort {
+    #[default]
+    Name,
+    /// Most notes outside the trash first.
+    Usage,
+    /// Most recently updated note first; tags on no note last.
+    LastUsed,
+}
+
+impl TagSort {
+    pub fn label(self) -> &'static str {
+        match self {
+            TagSort::Name => "name",
+            TagSort::Usage => "usage",
+            TagSort::LastUsed => "last used",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            TagSort::Name => TagSort::Usage,
+            TagSort::Usage => TagSort::LastUsed,
+            TagSort::LastUsed => TagSort::Name,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TagBrowserEntry {
+    pub usage: TagUsage,
+    pub marked: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TagBrowserOverlay {
+    /// Every tag, in `sort` order.
+    pub entries: Vec<TagBrowserEntry>,
+    /// Index into [`TagBrowserOverlay::visible`].
+    pub selected: usize,
+    pub sort: TagSort,
+    pub unused_only: bool,
+    /// Tags waiting for a confirmation to be deleted.
+    pub pending_delete: Option<Vec<String>>,
+}
+
+impl TagBrowserOverlay {
+    /// The rows on screen: every tag, or only unused ones.
+    pub fn visible(&self) -> Vec<&TagBrowserEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| !self.unused_only || entry.usage.is_unused())
+            .collect()
+    }
+
+    pub fn selected_entry(&self) -> Option<&TagBrowserEntry> {
+        self.visible().get(self.selected).copied()
+    }
+
+    /// Marked tags, or the highlighted one when nothing is marked.
+    fn targets(&self) -> Vec<String> {
+        let marked: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.marked)
+            .map(|entry| entry.usage.name.clone())
+            .collect();
+        if !marked.is_empty() {
+            return marked;
+        }
+        self.selected_entry()
+            .map(|entry| vec![entry.usage.name.clone()])
+            .unwrap_or_default()
+    }
+
+    /// Re-sorts and re-filters, keeping the highlighted tag when it is still shown.
+    fn arrange(&mut self) {
+        let current = self.selected_entry().map(|entry| entry.usage.name.clone());
+        let by_name = |entry: &TagBrowserEntry| entry.usage.name.to_lowercase();
+        match self.sort {
+            TagSort::Name => self.entries.sort_by_key(by_name),
+            TagSort::Usage => self.entries.sort_by(|a, b| {
+                let used = |entry: &TagBrowserEntry| entry.usage.active + entry.usage.archived;
+                used(b)
+                    .cmp(&used(a))
+                    .then_with(|| by_name(a).cmp(&by_name(b)))
+            }),
+            TagSort::LastUsed => self.entries.sort_by(|a, b| {
+                b.usage
+                    .last_used
+                    .cmp(&a.usage.last_used)
+                    .then_with(|| by_name(a).cmp(&by_name(b)))
+            }),
+        }
+        let visible = self.visible();
+        self.selected = current
+            .and_then(|name| visible.iter().position(|entry| entry.usage.name == name))
+            .unwrap_or(0)
+            .min(visible.len().saturating_sub(1));
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PaletteOverlay {
+    pub input: String,
+    pub entries: Vec<PaletteEntry>,
+    /// Indices into `entries` that match `input`, best first.
+    pub matches: Vec<usize>,
+    pub selected: usize,
+}
+
+impl PaletteOverlay {
+    pub fn selected_entry(&self) -> Option<&PaletteEntry> {
+        self.matches
+            .get(self.selected)
+            .and_then(|idx| self.entries.get(*idx))
+    }
+
+    fn refilter(&mut self) {
+        self.matches = palette::filter(&self.entries, &self.input);
+        self.selected = 0;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HelpOverlay {
+    pub topic: HelpTopic,
+    pub entries: Vec<HelpEntry>,
+    pub filter: String,
+    pub scroll: usize,
+    /// The popup help was opened from; closing help returns to it.
+    pub previous: Option<Box<OverlayState>>,
+}
+
+impl HelpOverlay {
+    pub fn visible_entries(&self) -> Vec<&HelpEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.matches(&self.filter))
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum OverlayState {
     NewNote(NewNoteOverlay),
@@ -139,6 +433,11 @@ pub enum OverlayState {
     TagEditor(TagEditorOverlay),
     BulkTrash(BulkTrashOverlay),
     Recovery(RecoveryOverlay),
+    Revisions(RevisionsOverlay),
+    SavedSearches(SavedSearchesOverlay),
+    TagBrowser(TagBrowserOverlay),
+    Palette(PaletteOverlay),
+    Help(HelpOverlay),
 }
 
 #[derive(Debug, Clone)]
@@ -453,16 +752,35 @@ impl EditorState {
 pub struct AppState {
     pub focus: FocusPane,
     pub show_trash: bool,
+    pub show_archive: bool,
     pub selected: usize,
     pub preview_lines: usize,
     pub retention_days: u32,
+    /// A window of the list: `notes[0]` sits at `list_offset` out of `list_total`
+    /// rows. Plain listings page rows in and out as the selection moves; search
+    /// results are loaded whole.
     pub notes: Vec<NoteSummary>,
+    pub list_offset: usize,
+    pub list_total: usize,
+    /// Scope the window was paged from, `None` while it holds search results.
+    list_scope: Option<NoteScope>,
+    /// Order of the list and of search results; starts from `default_sort`.
+    pub sort: SortSpec,
     pub search: SearchState,
     pub status_message: Option<String>,
     pub overlay: Option<OverlayState>,
     pub editor: Option<EditorState>,
     pub autosave_status: AutoSaveStatus,
     pub wrap_enabled: bool,
+    /// Keys typed so far of an unfinished multi-key binding, shown in the status bar.
+    pub pending_keys: Option<String>,
+    /// The wiki link picked with `]`/`[` in the reader, as (note id, link index).
+    reader_link: Option<(i64, usize)>,
+    /// Backlinks of the note with this id, loaded by `sync_backlinks` and
+    /// dropped whenever the list reloads.
+    backlinks: Option<(i64, Vec<LinkedNote>)>,
+    pub show_notebooks: bool,
+    notebook_tree: NotebookTree,
 }
 
 impl AppState {
@@ -470,33 +788,52 @@ impl AppState {
         storage: &StorageHandle,
         preview_lines: usize,
         retention_days: u32,
+        sort: SortSpec,
     ) -> Result<Self> {
-        let records = storage.fetch_recent_notes(50)?;
-        let notes = records
-            .into_iter()
-            .map(|record| summarize_record(record, preview_lines, retention_days))
-            .collect::<Vec<_>>();
-
-        Ok(Self {
+        let mut state = Self {
             focus: FocusPane::List,
             show_trash: false,
+            show_archive: false,
             selected: 0,
             preview_lines,
             retention_days,
-            notes,
+            notes: Vec::new(),
+            list_offset: 0,
+            list_total: 0,
+            list_scope: None,
+            sort,
             search: SearchState::default(),
             status_message: None,
             overlay: None,
             editor: None,
             autosave_status: AutoSaveStatus::Inactive,
             wrap_enabled: true,
-        })
+            pending_keys: None,
+            reader_link: None,
+            backlinks: None,
+            show_notebooks: false,
+            notebook_tree: NotebookTree::default(),
+        };
+        state.reload_notebooks(storage)?;
+        state.reload_list(storage, None)?;
+        Ok(state)
     }
 
+    /// Rows loaded in the current window.
     pub fn len(&self) -> usize {
         self.notes.len()
     }
 
+    /// Rows in the whole list, loaded or not.
+    pub fn total(&self) -> usize {
+        self.list_total
+    }
+
+    /// Position of the selected row in the whole list.
+    pub fn selected_position(&self) -> usize {
+        self.list_offset + self.selected
+    }
+
     pub fn is_empty(&self) -> bool {
         self.notes.is_empty()
     }
@@ -604,48 +941,219 @@ impl AppState {
         }
     }
 
-    pub fn move_selection(&mut self, delta: isize) {
+    pub fn move_selection(&mut self, storage: &StorageHandle, delta: isize) -> Result<()> {
         if self.notes.is_empty() {
-            return;
+            return Ok(());
+        }
+        let last = self.list_total.saturating_sub(1) as isize;
+        let target = (self.selected_position() as isize + delta).clamp(0, last);
+        self.reveal_position(storage, target as usize)
+    }
+
+    pub fn select_first(&mut self, storage: &StorageHandle) -> Result<()> {
+        if self.list_offset > 0 {
+            self.load_window(storage, PageAnchor::Start)?;
+        }
+        self.selected = 0;
+        Ok(())
+    }
+
+    pub fn select_last(&mut self, storage: &StorageHandle) -> Result<()> {
+        if self.list_offset + self.notes.len() < self.list_total {
+            self.load_window(storage, PageAnchor::End)?;
         }
-        let len = self.notes.len() as isize;
-        let current = self.selected as isize;
-        let mut next = current + delta;
-        if next < 0 {
-            next = 0;
-        } else if next >= len {
-            next = len - 1;
+        self.selected = self.notes.len().saturating_sub(1);
+        Ok(())
+    }
+
+    /// Switches the list to the next sort mode, keeping the selected note selected.
+    pub fn cycle_sort(&mut self, storage: &StorageHandle) -> Result<SortSpec> {
+        let previous = self.sort;
+        self.sort = previous.next();
+        // Cursors from the old order mean nothing in the new one.
+        self.list_scope = None;
+        if let Err(err) = self.refresh(storage) {
+            self.sort = previous;
+            return Err(err);
         }
-        self.selected = next as usize;
+        Ok(self.sort)
     }
 
+    /// Reloads the list, keeping the selected note selected wherever it now sorts.
     pub fn refresh(&mut self, storage: &StorageHandle) -> Result<()> {
-        if !self.search.query.is_empty() {
-            return self.apply_search(storage);
+        self.reload(storage, self.selected_note_id())
+    }
+
+    /// Reloads the list and selects `note_id`, paging its rows in if needed.
+    pub fn refresh_selecting(&mut self, storage: &StorageHandle, note_id: i64) -> Result<()> {
+        self.reload(storage, Some(note_id))
+    }
+
+    /// When `note_id` is no longer listed, the row that took its place is selected.
+    fn reload(&mut self, storage: &StorageHandle, note_id: Option<i64>) -> Result<()> {
+        self.backlinks = None;
+        self.reload_notebooks(storage)?;
+        if self.search.query.is_empty() {
+            return self.reload_list(storage, note_id);
+        }
+        self.apply_search(storage)?;
+        if let Some(note_id) = note_id {
+            self.select_note_by_id(note_id);
         }
+        Ok(())
+    }
 
-        let records = if self.show_trash {
-            storage.fetch_trashed_notes(50)?
-        } else {
-            storage.fetch_recent_notes(50)?
+    fn reload_list(&mut self, storage: &StorageHandle, note_id: Option<i64>) -> Result<()> {
+        self.backlinks = None;
+        let scope = self.view_scope();
+        let target = match note_id {
+            Some(note_id) => storage.list_cursor(scope, &self.sort, note_id)?,
+            None => None,
         };
-        self.notes = records
-            .into_iter()
-            .map(|record| summarize_record(record, self.preview_lines, self.retention_days))
-            .collect();
+        let previous = self
+            .selected()
+            .filter(|_| self.list_scope == Some(scope))
+            .map(|note| note.cursor.clone());
+        let anchor = match target.or(previous) {
+            Some(cursor) => PageAnchor::From(cursor),
+            None => PageAnchor::Start,
+        };
+        self.load_window(storage, anchor)?;
         self.search.terms.clear();
         self.search.tags.clear();
         self.search.filter_chips.clear();
+        self.search.notebook = None;
         self.search.regex_pattern = None;
+        Ok(())
+    }
+
+    /// Replaces the window with the rows around `anchor` and selects the anchor row
+    /// (or the nearest one that still exists).
+    fn load_window(&mut self, storage: &StorageHandle, anchor: PageAnchor) -> Result<()> {
+        let scope = self.view_scope();
+        let sort = self.sort;
+        let page =
+            |anchor: &PageAnchor, limit| storage.fetch_note_page(scope, &sort, anchor, limit);
+        let (before, after) = match &anchor {
+            PageAnchor::From(cursor) => (
+                page(&PageAnchor::Before(cursor.clone()), LIST_PAGE_SIZE / 2)?,
+                page(&anchor, LIST_PAGE_SIZE)?,
+            ),
+            PageAnchor::End => (page(&anchor, LIST_PAGE_SIZE)?, Vec::new()),
+            _ => (Vec::new(), page(&anchor, LIST_PAGE_SIZE)?),
+        };
+        self.list_total = storage.count_notes(scope, &sort, None)?;
+        self.list_offset = match &anchor {
+            PageAnchor::From(cursor) => storage
+                .count_notes(scope, &sort, Some(cursor))?
+                .saturating_sub(before.len()),
+            PageAnchor::End => self.list_total.saturating_sub(before.len()),
+            _ => 0,
+        };
+        self.selected = before.len();
+        self.notes = before
+            .into_iter()
+            .chain(after)
+            .map(|record| self.summarize(record))
+            .collect();
+        self.list_scope = Some(scope);
+        self.normalize_selection();
+        Ok(())
+    }
+
+    /// Pages rows into the window until `position` is loaded, then selects it.
+    fn reveal_position(&mut self, storage: &StorageHandle, position: usize) -> Result<()> {
+        let Some(scope) = self.list_scope else {
+            self.selected = position.min(self.notes.len().saturating_sub(1));
+            return Ok(());
+        };
+        while position >= self.list_offset + self.notes.len() {
+            let Some(last) = self.notes.last() else {
+                break;
+            };
+            let anchor = PageAnchor::After(last.cursor.clone());
+            let page = storage.fetch_note_page(scope, &self.sort, &anchor, LIST_PAGE_SIZE)?;
+            if page.is_empty() {
+                // Rows vanished since the total was counted.
+                self.list_total = self.list_offset + self.notes.len();
+                break;
+            }
+            let page: Vec<_> = page
+                .into_iter()
+                .map(|record| self.summarize(record))
+                .collect();
+            self.notes.extend(page);
+            let excess = self.notes.len().saturating_sub(LIST_WINDOW_MAX);
+            self.notes.drain(..excess);
+            self.list_offset += excess;
+        }
+        while position < self.list_offset {
+            let Some(first) = self.notes.first() else {
+                break;
+            };
+            let anchor = PageAnchor::Before(first.cursor.clone());
+            let page = storage.fetch_note_page(scope, &self.sort, &anchor, LIST_PAGE_SIZE)?;
+            if page.is_empty() {
+                self.list_total = self.list_total.saturating_sub(self.list_offset);
+                self.list_offset = 0;
+                break;
+            }
+            self.list_offset = self.list_offset.saturating_sub(page.len());
+            let page: Vec<_> = page
+                .into_iter()
+                .map(|record| self.summarize(record))
+                .collect();
+            self.notes.splice(..0, page);
+            self.notes.truncate(LIST_WINDOW_MAX);
+        }
+        self.selected = position.saturating_sub(self.list_offset);
         self.normalize_selection();
         Ok(())
     }
 
+    fn summarize(&self, record: NoteRecord) -> NoteSummary {
+        summarize_record(record, &self.sort, self.preview_lines, self.retention_days)
+    }
+
+    /// The notes the list is currently showing; searches without an explicit
+    /// `is:archived` / `in:trash` qualifier stay inside this scope.
+    pub fn view_scope(&self) -> NoteScope {
+        if self.show_trash {
+            NoteScope::Trash
+        } else if self.show_archive {
+            NoteScope::Archived
+        } else {
+            NoteScope::Active
+        }
+    }
+
     pub fn set_trash_view(&mut self, enabled: bool, storage: &StorageHandle) -> Result<()> {
         if self.show_trash == enabled {
             return Ok(());
         }
         self.show_trash = enabled;
+        if enabled {
+            self.show_archive = false;
+        }
+        self.refresh(storage)
+    }
+
+    pub fn set_archive_view(&mut self, enabled: bool, storage: &StorageHandle) -> Result<()> {
+        if self.show_archive == enabled {
+            return Ok(());
+        }
+        self.show_archive = enabled;
+        if enabled {
+            self.show_trash = false;
+        }
+        self.refresh(storage)
+    }
+
+    pub fn unarchive_selected_note(&mut self, storage: &StorageHandle) -> Result<()> {
+        let Some(note) = self.selected() else {
+            return Ok(());
+        };
+        storage.set_note_archived(note.id, false)?;
         self.refresh(storage)
     }
 
@@ -657,16 +1165,197 @@ impl AppState {
         self.refresh(storage)
     }
 
+    /// Wiki links in the selected note's body, in order.
+    fn selected_note_links(&self) -> Vec<WikiLink> {
+        self.selected()
+            .map(|note| parse_links(&note.body))
+            .unwrap_or_default()
+    }
+
+    /// Which of the selected note's links Enter follows: the one last picked
+    /// with `]`/`[`, else the first. `None` unless the reader has focus and the
+    /// note has links.
+    pub fn selected_link_index(&self) -> Option<usize> {
+        if self.focus != FocusPane::Reader {
+            return None;
+        }
+        let note_id = self.selected_note_id()?;
+        let count = self.selected_note_links().len();
+        if count == 0 {
+            return None;
+        }
+        Some(match self.reader_link {
+            Some((id, index)) if id == note_id => index.min(count - 1),
+            _ => 0,
+        })
+    }
+
+    pub fn selected_link(&self) -> Option<WikiLink> {
+        let index = self.selected_link_index()?;
+        self.selected_note_links().into_iter().nth(index)
+    }
+
+    /// Steps the reader's link selection, wrapping at either end. Returns
+    /// `false` when there is no link to select.
+    pub fn move_link_selection(&mut self, delta: isize) -> bool {
+        let Some(index) = self.selected_link_index() else {
+            return false;
+        };
+        let count = self.selected_note_links().len() as isize;
+        let next = (index as isize + delta).rem_euclid(count) as usize;
+        self.reader_link = self.selected_note_id().map(|id| (id, next));
+        true
+    }
+
+    /// Selects a note reached through a link. When it is not in the current
+    /// list the search filter is dropped and the list switches to the main or
+    /// archive view, whichever holds the note.
+    pub fn reveal_note(&mut self, storage: &StorageHandle, target: &LinkedNote) -> Result<()> {
+        self.reader_link = None;
+        if self.notes.iter().any(|note| note.id == target.id) {
+            self.select_note_by_id(target.id);
+            return Ok(());
+        }
+        if !self.search.query.is_empty() {
+            self.record_search_history();
+            self.clear_search_filter();
+        }
+        self.show_trash = false;
+        self.show_archive = target.archived;
+        self.reload_list(storage, Some(target.id))
+    }
+
+    /// Notes linking to the selected one, as of the last `sync_backlinks`.
+    pub fn backlinks(&self) -> &[LinkedNote] {
+        match &self.backlinks {
+            Some((id, notes)) if Some(*id) == self.selected_note_id() => notes,
+            _ => &[],
+        }
+    }
+
+    /// Loads the backlinks of the selected note unless they are cached already.
+    /// Notes in the trash show none, and so does a note whose lookup failed,
+    /// until the list next reloads.
+    pub fn sync_backlinks(&mut self, storage: &StorageHandle) -> Result<()> {
+        let Some(note_id) = self.selected_note_id() else {
+            self.backlinks = None;
+            return Ok(());
+        };
+        if matches!(&self.backlinks, Some((id, _)) if *id == note_id) {
+            return Ok(());
+        }
+        self.backlinks = Some((note_id, Vec::new()));
+        if !self.show_trash {
+            let notes = storage.backlinks(note_id)?;
+            self.backlinks = Some((note_id, notes));
+        }
+        Ok(())
+    }
+
+    /// Cycles focus through the notebook pane (when shown), list and reader.
     pub fn toggle_focus(&mut self) {
         self.focus = match self.focus {
+            FocusPane::Notebooks => FocusPane::List,
             FocusPane::List => FocusPane::Reader,
+            FocusPane::Reader if self.show_notebooks => FocusPane::Notebooks,
             FocusPane::Reader => FocusPane::List,
         };
     }
 
+    pub fn notebook_tree(&self) -> &NotebookTree {
+        &self.notebook_tree
+    }
+
+    /// Shows the notebook pane and focuses it, or hides it again. Returns
+    /// whether the pane is now shown.
+    pub fn toggle_notebooks(&mut self, storage: &StorageHandle) -> Result<bool> {
+        if self.show_notebooks {
+            self.show_notebooks = false;
+            if self.focus == FocusPane::Notebooks {
+                self.focus = FocusPane::List;
+            }
+            return Ok(false);
+        }
+        self.reload_notebooks(storage)?;
+        self.show_notebooks = true;
+        self.focus = FocusPane::Notebooks;
+        Ok(true)
+    }
+
+    /// Re-reads the notebooks, keeping the selected row on the same notebook
+    /// while it exists.
+    pub fn reload_notebooks(&mut self, storage: &StorageHandle) -> Result<()> {
+        let selected_id = self.notebook_tree.selected_notebook().map(|nb| nb.id);
+        let tree = &mut self.notebook_tree;
+        tree.notebooks = storage.list_notebooks()?;
+        let ids: HashSet<i64> = tree.notebooks.iter().map(|notebook| notebook.id).collect();
+        tree.collapsed.retain(|id| ids.contains(id));
+        if let Some(id) = selected_id {
+            if let Some(row) = tree.visible().iter().position(|nb| nb.id == id) {
+                tree.selected = row + 1;
+            }
+        }
+        tree.selected = tree.selected.min(tree.row_count() - 1);
+        Ok(())
+    }
+
+    pub fn move_notebook_selection(&mut self, delta: isize) {
+        let tree = &mut self.notebook_tree;
+        let last = tree.row_count() as isize - 1;
+        tree.selected = (tree.selected as isize + delta).clamp(0, last) as usize;
+    }
+
+    pub fn select_first_notebook(&mut self) {
+        self.notebook_tree.selected = 0;
+    }
+
+    pub fn select_last_notebook(&mut self) {
+        self.notebook_tree.selected = self.notebook_tree.row_count() - 1;
+    }
+
+    /// Collapses or expands the selected notebook. Returns `false` when it has
+    /// no nested notebooks (or "All notes" is selected).
+    pub fn toggle_notebook_expanded(&mut self) -> bool {
+        let Some(id) = self.notebook_tree.selected_notebook().map(|nb| nb.id) else {
+            return false;
+        };
+        if !self.notebook_tree.has_children(id) {
+            return false;
+        }
+        let tree = &mut self.notebook_tree;
+        if !tree.collapsed.remove(&id) {
+            tree.collapsed.insert(id);
+        }
+        true
+    }
+
+    /// Lists the notes of the selected notebook and the ones nested in it by
+    /// running a `notebook:` search; "All notes" clears the search instead.
+    pub fn open_selected_notebook(&mut self, storage: &StorageHandle) -> Result<()> {
+        let query = match self.notebook_tree.selected_notebook() {
+            Some(notebook) => qualified("notebook", &notebook.path),
+            None => String::new(),
+        };
+        if !self.search.query.is_empty() {
+            self.record_search_history();
+        }
+        self.apply_saved_search(storage, &query)
+    }
+
+    /// Path of the notebook holding the selected note.
+    pub fn selected_notebook_path(&self) -> Option<&str> {
+        let notebook_id = self.selected()?.notebook_id?;
+        self.notebook_tree
+            .notebooks
+            .iter()
+            .find(|notebook| notebook.id == notebook_id)
+            .map(|notebook| notebook.path.as_str())
+    }
+
     pub fn begin_search(&mut self) {
         self.search.active = true;
         self.search.last_error = None;
+        self.reset_history_recall();
         self.focus = FocusPane::List;
     }
 
@@ -674,26 +1363,35 @@ impl AppState {
         if !self.search.active {
             return Ok(());
         }
+        self.record_search_history();
+        self.clear_search_filter();
+        self.refresh(storage)
+    }
+
+    fn clear_search_filter(&mut self) {
         self.search.active = false;
         self.search.query.clear();
         self.search.last_error = None;
         self.search.terms.clear();
         self.search.tags.clear();
         self.search.filter_chips.clear();
+        self.search.notebook = None;
         self.search.regex_pattern = None;
-        self.refresh(storage)
     }
 
     pub fn finish_search(&mut self) {
+        self.record_search_history();
         self.search.active = false;
     }
 
     pub fn push_search_char(&mut self, storage: &StorageHandle, ch: char) -> Result<()> {
+        self.search.history_index = None;
         self.search.query.push(ch);
         self.apply_search(storage)
     }
 
     pub fn pop_search_char(&mut self, storage: &StorageHandle) -> Result<()> {
+        self.search.history_index = None;
         if self.search.query.pop().is_some() {
             if self.search.query.is_empty() {
                 self.search.last_error = None;
@@ -709,21 +1407,166 @@ impl AppState {
         &self.search.query
     }
 
-    pub fn search_error(&self) -> Option<&str> {
-        self.search.last_error.as_deref()
+    fn record_search_history(&mut self) {
+        self.reset_history_recall();
+        if let Err(err) = self.search.history.record(&self.search.query) {
+            tracing::warn!(?err, "failed to save search history");
+        }
     }
 
-    pub fn is_search_active(&self) -> bool {
-        self.search.active
+    fn reset_history_recall(&mut self) {
+        self.search.history_index = None;
+        self.search.history_draft.clear();
+        self.search.reverse_search = None;
     }
 
-    pub fn is_regex_enabled(&self) -> bool {
-        self.search.regex_enabled
+    /// Up in the search prompt: shows the previous history entry, skipping one
+    /// identical to the query already shown. Returns `false` at the oldest entry.
+    pub fn recall_older_search(&mut self, storage: &StorageHandle) -> Result<bool> {
+        let mut index = match self.search.history_index {
+            Some(0) => return Ok(false),
+            Some(index) => index - 1,
+            None if self.search.history.is_empty() => return Ok(false),
+            None => {
+                self.search.history_draft = self.search.query.clone();
+                self.search.history.len() - 1
+            }
+        };
+        if index > 0 && self.search.history.get(index) == Some(self.search.query.trim()) {
+            index -= 1;
+        }
+        self.show_history_entry(storage, Some(index))?;
+        Ok(true)
     }
 
-    pub fn toggle_regex_mode(&mut self, storage: &StorageHandle) -> Result<bool> {
-        let previous = self.search.regex_enabled;
-        self.search.regex_enabled = !previous;
+    /// Down in the search prompt: shows the next newer history entry, then the
+    /// query as it was typed. Returns `false` when not browsing history.
+    pub fn recall_newer_search(&mut self, storage: &StorageHandle) -> Result<bool> {
+        let Some(index) = self.search.history_index else {
+            return Ok(false);
+        };
+        let next = (index + 1 < self.search.history.len()).then_some(index + 1);
+        self.show_history_entry(storage, next)?;
+        Ok(true)
+    }
+
+    fn show_history_entry(&mut self, storage: &StorageHandle, index: Option<usize>) -> Result<()> {
+        self.search.history_index = index;
+        self.search.query = match index.and_then(|index| self.search.history.get(index)) {
+            Some(query) => query.to_string(),
+            None => self.search.history_draft.clone(),
+        };
+        self.apply_search(storage)
+    }
+
+    pub fn begin_reverse_search(&mut self) {
+        self.search.history_index = None;
+        self.search.reverse_search = Some(ReverseSearch {
+            original: self.search.query.clone(),
+            ..ReverseSearch::default()
+        });
+    }
+
+    pub fn reverse_search(&self) -> Option<&ReverseSearch> {
+        self.search.reverse_search.as_ref()
+    }
+
+    pub fn push_reverse_search_char(&mut self, storage: &StorageHandle, ch: char) -> Result<()> {
+        let Some(reverse) = self.search.reverse_search.as_mut() else {
+            return Ok(());
+        };
+        reverse.needle.push(ch);
+        // A longer needle may still match the current entry, so look from it.
+        let from = reverse
+            .matched
+            .map_or(self.search.history.len(), |index| index + 1);
+        self.find_reverse_search_match(storage, from)
+    }
+
+    pub fn pop_reverse_search_char(&mut self, storage: &StorageHandle) -> Result<()> {
+        let Some(reverse) = self.search.reverse_search.as_mut() else {
+            return Ok(());
+        };
+        if reverse.needle.pop().is_none() {
+            return Ok(());
+        }
+        if reverse.needle.is_empty() {
+            reverse.matched = None;
+            reverse.failed = false;
+            self.search.query = reverse.original.clone();
+            return self.apply_search(storage);
+        }
+        let from = self.search.history.len();
+        self.find_reverse_search_match(storage, from)
+    }
+
+    /// Ctrl-r again: steps to the next older entry containing the needle.
+    pub fn next_reverse_search_match(&mut self, storage: &StorageHandle) -> Result<()> {
+        let Some(reverse) = self.search.reverse_search.as_ref() else {
+            return Ok(());
+        };
+        let from = reverse.matched.unwrap_or(self.search.history.len());
+        self.find_reverse_search_match(storage, from)
+    }
+
+    fn find_reverse_search_match(&mut self, storage: &StorageHandle, before: usize) -> Result<()> {
+        let Some(reverse) = self.search.reverse_search.as_mut() else {
+            return Ok(());
+        };
+        if reverse.needle.is_empty() {
+            return Ok(());
+        }
+        match self.search.history.find_before(&reverse.needle, before) {
+            Some(index) => {
+                reverse.matched = Some(index);
+                reverse.failed = false;
+                self.search.query = self
+                    .search
+                    .history
+                    .get(index)
+                    .unwrap_or_default()
+                    .to_string();
+                self.apply_search(storage)
+            }
+            None => {
+                reverse.failed = true;
+                Ok(())
+            }
+        }
+    }
+
+    /// Leaves reverse search keeping the matched query in the prompt.
+    pub fn accept_reverse_search(&mut self) {
+        self.search.reverse_search = None;
+    }
+
+    /// Leaves reverse search and puts back the query from before Ctrl-r.
+    pub fn cancel_reverse_search(&mut self, storage: &StorageHandle) -> Result<()> {
+        let Some(reverse) = self.search.reverse_search.take() else {
+            return Ok(());
+        };
+        if self.search.query == reverse.original {
+            return Ok(());
+        }
+        self.search.query = reverse.original;
+        self.apply_search(storage)
+    }
+
+    pub fn search_error(&self) -> Option<&str> {
+        self.search.last_error.as_deref()
+    }
+
+    pub fn is_search_active(&self) -> bool {
+        self.search.active
+    }
+
+    pub fn is_regex_enabled(&self) -> bool {
+        self.search.regex_enabled
+    }
+
+    pub fn toggle_regex_mode(&mut self, storage: &StorageHandle) -> Result<bool> {
+        let previous = self.search.regex_enabled;
+        self.search.regex_enabled = !previous;
         if self.search.query.trim().is_empty() {
             self.search.regex_pattern = None;
             self.search.last_error = None;
@@ -745,11 +1588,19 @@ impl AppState {
             self.search.terms.clear();
             self.search.tags.clear();
             self.search.filter_chips.clear();
+            self.search.notebook = None;
             self.search.regex_pattern = None;
-            return self.refresh(storage);
+            return self.reload_list(storage, self.selected_note_id());
         }
 
-        let mut query = parse_query(trimmed);
+        // A half-typed query (an open quote or bracket) keeps the previous results.
+        let mut query = match parse_query(trimmed) {
+            Ok(query) => query,
+            Err(err) => {
+                self.search.last_error = Some(err.to_string());
+                return Ok(());
+            }
+        };
         if self.search.regex_enabled {
             query.regex_pattern = regex_pattern_from_input(trimmed);
         }
@@ -757,27 +1608,27 @@ impl AppState {
             self.search.terms.clear();
             self.search.tags.clear();
             self.search.filter_chips.clear();
+            self.search.notebook = None;
             self.search.regex_pattern = None;
-            return self.refresh(storage);
+            return self.reload_list(storage, self.selected_note_id());
         }
 
         self.search.terms = query.highlight_terms();
         self.search.tags = query.tags.clone();
+        self.search.notebook = query.notebook.clone();
         self.search.filter_chips = build_filter_chips(&query);
         self.search.regex_pattern = query.regex_pattern.clone();
 
-        let mut storage_query = query.clone();
-        if self.search.regex_enabled && storage_query.regex_pattern.is_some() {
-            storage_query.terms.clear();
-            storage_query.title_terms.clear();
-        }
-
-        match storage.search_notes(&storage_query, 200) {
+        let storage_query = self.storage_query(&query);
+        match storage.search_notes(&storage_query, SEARCH_RESULT_LIMIT) {
             Ok(records) => {
                 self.notes = records
                     .into_iter()
-                    .map(|record| summarize_record(record, self.preview_lines, self.retention_days))
+                    .map(|record| self.summarize(record))
                     .collect();
+                self.list_offset = 0;
+                self.list_total = self.notes.len();
+                self.list_scope = None;
                 self.selected = 0;
                 self.normalize_selection();
                 self.search.last_error = None;
@@ -790,6 +1641,30 @@ impl AppState {
         }
     }
 
+    /// `query` as this view runs it: within the current view unless it names a
+    /// scope, in the current sort order, with the configured typo tolerance.
+    fn storage_query(&self, query: &SearchQuery) -> SearchQuery {
+        let mut storage_query = query.clone();
+        if storage_query.scope.is_none() {
+            storage_query.scope = Some(self.view_scope());
+        }
+        storage_query.sort = Some(self.sort);
+        storage_query.fuzzy_threshold = self.search.fuzzy_threshold;
+        if self.search.regex_enabled && storage_query.regex_pattern.is_some() {
+            storage_query.text = None;
+        }
+        storage_query
+    }
+
+    /// Runs a saved query as if it had been typed and confirmed with Enter.
+    /// Saved searches are plain queries, so regex mode is switched off.
+    pub fn apply_saved_search(&mut self, storage: &StorageHandle, query: &str) -> Result<()> {
+        self.search.active = false;
+        self.search.regex_enabled = false;
+        self.search.query = query.to_string();
+        self.apply_search(storage)
+    }
+
     pub fn search_tokens(&self) -> Vec<String> {
         let mut tokens = self.search.terms.clone();
         tokens.extend(self.search.tags.iter().cloned());
@@ -820,6 +1695,10 @@ impl AppState {
         self.status_message = message.map(Into::into);
     }
 
+    pub fn set_pending_keys<S: Into<String>>(&mut self, keys: Option<S>) {
+        self.pending_keys = keys.map(Into::into);
+    }
+
     pub fn clear_status_message(&mut self) {
         self.status_message = None;
     }
@@ -861,37 +1740,26 @@ impl AppState {
             Some(note) => note,
             None => return Ok(()),
         };
-        let mut tags = storage.list_all_tags()?;
-        tags.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()));
+        let tree = storage.tag_tree()?;
         let note_tags: HashSet<String> = note.tags.iter().cloned().collect();
-        let mut items = Vec::new();
-        let mut seen = HashSet::new();
-        for tag in &tags {
-            let selected = note_tags.contains(tag);
-            items.push(TagEditorItem {
-                name: tag.clone(),
-                selected,
-                original: selected,
-                bulk_selected: false,
-            });
-            seen.insert(tag.clone());
-        }
+        let mut items: Vec<TagEditorItem> = tree
+            .iter()
+            .map(|node| TagEditorItem {
+                note_count: node.note_count,
+                ..TagEditorItem::new(&node.name, note_tags.contains(&node.name))
+            })
+            .collect();
         for tag in &note.tags {
-            if !seen.contains(tag) {
-                items.push(TagEditorItem {
-                    name: tag.clone(),
-                    selected: true,
-                    original: true,
-                    bulk_selected: false,
-                });
+            if !items.iter().any(|item| &item.name == tag) {
+                items.push(TagEditorItem::new(tag, true));
             }
         }
-        items.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
-        let suggestions = tags
+        arrange_tag_items(&mut items);
+        let suggestions = tree
             .iter()
-            .filter(|tag| !note_tags.contains(*tag))
+            .filter(|node| node.exists && !note_tags.contains(&node.name))
             .take(5)
-            .cloned()
+            .map(|node| node.name.clone())
             .collect();
 
         let overlay = TagEditorOverlay {
@@ -943,6 +1811,62 @@ impl AppState {
         Ok(())
     }
 
+    /// Opens the revision browser for the selected note. Returns `false` when
+    /// the note has no recorded revisions yet.
+    pub fn open_revisions_overlay(&mut self, storage: &StorageHandle) -> Result<bool> {
+        let Some(note) = self.selected().cloned() else {
+            return Ok(false);
+        };
+        let revisions = storage.list_note_revisions(note.id)?;
+        if revisions.is_empty() {
+            return Ok(false);
+        }
+        let entries = revisions.into_iter().map(build_revision_entry).collect();
+        self.overlay = Some(OverlayState::Revisions(RevisionsOverlay {
+            note_id: note.id,
+            note_title: note.title,
+            entries,
+            selected: 0,
+        }));
+        Ok(true)
+    }
+
+    /// Opens the saved search picker, counting each search's matches in the
+    /// current view. Returns `false` when nothing has been saved yet.
+    pub fn open_saved_searches_overlay(&mut self, storage: &StorageHandle) -> Result<bool> {
+        let saved = storage.list_saved_searches()?;
+        if saved.is_empty() {
+            return Ok(false);
+        }
+        let entries = saved
+            .into_iter()
+            .map(|saved| SavedSearchEntry {
+                count: self.count_saved_search(storage, &saved.query),
+                name: saved.name,
+                query: saved.query,
+            })
+            .collect();
+        self.overlay = Some(OverlayState::SavedSearches(SavedSearchesOverlay {
+            entries,
+            selected: 0,
+        }));
+        Ok(true)
+    }
+
+    fn count_saved_search(&self, storage: &StorageHandle, query: &str) -> String {
+        let Ok(query) = parse_query(query) else {
+            return "invalid".to_string();
+        };
+        match storage.search_notes(&self.storage_query(&query), SEARCH_RESULT_LIMIT + 1) {
+            Ok(notes) if notes.len() > SEARCH_RESULT_LIMIT => format!("{SEARCH_RESULT_LIMIT}+"),
+            Ok(notes) => notes.len().to_string(),
+            Err(err) => {
+                tracing::warn!(?err, "failed to count saved search results");
+                "?".to_string()
+            }
+        }
+    }
+
     pub fn close_overlay(&mut self) {
         self.overlay = None;
     }
@@ -1107,6 +2031,354 @@ impl AppState {
         }
     }
 
+    pub fn revisions_overlay(&self) -> Option<&RevisionsOverlay> {
+        match self.overlay() {
+            Some(OverlayState::Revisions(ref overlay)) => Some(overlay),
+            _ => None,
+        }
+    }
+
+    pub fn revisions_overlay_mut(&mut self) -> Option<&mut RevisionsOverlay> {
+        match self.overlay_mut() {
+            Some(OverlayState::Revisions(ref mut overlay)) => Some(overlay),
+            _ => None,
+        }
+    }
+
+    pub fn revisions_move_selection(&mut self, delta: isize) {
+        if let Some(overlay) = self.revisions_overlay_mut() {
+            if overlay.entries.is_empty() {
+                overlay.selected = 0;
+                return;
+            }
+            let last = overlay.entries.len() as isize - 1;
+            let next = (overlay.selected as isize + delta).clamp(0, last);
+            overlay.selected = next as usize;
+        }
+    }
+
+    pub fn revisions_selected_entry(&self) -> Option<&RevisionEntry> {
+        self.revisions_overlay()
+            .and_then(|overlay| overlay.entries.get(overlay.selected))
+    }
+
+    pub fn saved_searches_overlay(&self) -> Option<&SavedSearchesOverlay> {
+        match self.overlay() {
+            Some(OverlayState::SavedSearches(ref overlay)) => Some(overlay),
+            _ => None,
+        }
+    }
+
+    pub fn saved_searches_overlay_mut(&mut self) -> Option<&mut SavedSearchesOverlay> {
+        match self.overlay_mut() {
+            Some(OverlayState::SavedSearches(ref mut overlay)) => Some(overlay),
+            _ => None,
+        }
+    }
+
+    pub fn saved_searches_move_selection(&mut self, delta: isize) {
+        if let Some(overlay) = self.saved_searches_overlay_mut() {
+            if overlay.entries.is_empty() {
+                overlay.selected = 0;
+                return;
+            }
+            let last = overlay.entries.len() as isize - 1;
+            let next = (overlay.selected as isize + delta).clamp(0, last);
+            overlay.selected = next as usize;
+        }
+    }
+
+    pub fn saved_searches_selected_entry(&self) -> Option<&SavedSearchEntry> {
+        self.saved_searches_overlay()
+            .and_then(|overlay| overlay.entries.get(overlay.selected))
+    }
+
+    /// Opens the tag browser. Returns `false` when there are no tags.
+    pub fn open_tag_browser(&mut self, storage: &StorageHandle) -> Result<bool> {
+        let usage = storage.tag_usage()?;
+        if usage.is_empty() {
+            return Ok(false);
+        }
+        let mut overlay = TagBrowserOverlay {
+            entries: usage
+                .into_iter()
+                .map(|usage| TagBrowserEntry {
+                    usage,
+                    marked: false,
+                })
+                .collect(),
+            ..TagBrowserOverlay::default()
+        };
+        overlay.arrange();
+        self.overlay = Some(OverlayState::TagBrowser(overlay));
+        Ok(true)
+    }
+
+    /// Re-reads tag usage after tags changed, keeping the sort, the filter and
+    /// the marks on tags that still exist.
+    pub fn reload_tag_browser(&mut self, storage: &StorageHandle) -> Result<()> {
+        let usage = storage.tag_usage()?;
+        let Some(overlay) = self.tag_browser_overlay_mut() else {
+            return Ok(());
+        };
+        let marked: HashSet<String> = overlay
+            .entries
+            .iter()
+            .filter(|entry| entry.marked)
+            .map(|entry| entry.usage.name.clone())
+            .collect();
+        overlay.entries = usage
+            .into_iter()
+            .map(|usage| TagBrowserEntry {
+                marked: marked.contains(&usage.name),
+                usage,
+            })
+            .collect();
+        overlay.pending_delete = None;
+        overlay.arrange();
+        Ok(())
+    }
+
+    pub fn tag_browser_overlay(&self) -> Option<&TagBrowserOverlay> {
+        match self.overlay() {
+            Some(OverlayState::TagBrowser(ref overlay)) => Some(overlay),
+            _ => None,
+        }
+    }
+
+    pub fn tag_browser_overlay_mut(&mut self) -> Option<&mut TagBrowserOverlay> {
+        match self.overlay_mut() {
+            Some(OverlayState::TagBrowser(ref mut overlay)) => Some(overlay),
+            _ => None,
+        }
+    }
+
+    pub fn tag_browser_move_selection(&mut self, delta: isize) {
+        if let Some(overlay) = self.tag_browser_overlay_mut() {
+            let last = overlay.visible().len() as isize - 1;
+            overlay.selected = (overlay.selected as isize + delta).clamp(0, last.max(0)) as usize;
+        }
+    }
+
+    pub fn tag_browser_toggle_mark(&mut self) {
+        if let Some(overlay) = self.tag_browser_overlay_mut() {
+            let Some(name) = overlay
+                .selected_entry()
+                .map(|entry| entry.usage.name.clone())
+            else {
+                return;
+            };
+            if let Some(entry) = overlay
+                .entries
+                .iter_mut()
+                .find(|entry| entry.usage.name == name)
+            {
+                entry.marked = !entry.marked;
+            }
+        }
+    }
+
+    pub fn tag_browser_clear_marks(&mut self) {
+        if let Some(overlay) = self.tag_browser_overlay_mut() {
+            for entry in &mut overlay.entries {
+                entry.marked = false;
+            }
+        }
+    }
+
+    pub fn tag_browser_cycle_sort(&mut self) -> Option<TagSort> {
+        let overlay = self.tag_browser_overlay_mut()?;
+        overlay.sort = overlay.sort.next();
+        overlay.arrange();
+        Some(overlay.sort)
+    }
+
+    pub fn tag_browser_toggle_unused(&mut self) -> Option<bool> {
+        let overlay = self.tag_browser_overlay_mut()?;
+        overlay.unused_only = !overlay.unused_only;
+        overlay.arrange();
+        Some(overlay.unused_only)
+    }
+
+    /// Asks to delete the marked tags, or the highlighted one. Returns how many
+    /// tags wait for confirmation.
+    pub fn tag_browser_begin_delete(&mut self) -> usize {
+        let Some(overlay) = self.tag_browser_overlay_mut() else {
+            return 0;
+        };
+        let targets = overlay.targets();
+        let count = targets.len();
+        overlay.pending_delete = (count > 0).then_some(targets);
+        count
+    }
+
+    /// Asks to delete every unused tag. Returns how many there are.
+    pub fn tag_browser_begin_prune(&mut self) -> usize {
+        let Some(overlay) = self.tag_browser_overlay_mut() else {
+            return 0;
+        };
+        let unused: Vec<String> = overlay
+            .entries
+            .iter()
+            .filter(|entry| entry.usage.is_unused())
+            .map(|entry| entry.usage.name.clone())
+            .collect();
+        let count = unused.len();
+        overlay.pending_delete = (count > 0).then_some(unused);
+        count
+    }
+
+    pub fn tag_browser_cancel_delete(&mut self) {
+        if let Some(overlay) = self.tag_browser_overlay_mut() {
+            overlay.pending_delete = None;
+        }
+    }
+
+    /// Closes the browser and lists the notes carrying every marked tag (or the
+    /// highlighted one). Returns the tags filtered by.
+    pub fn apply_tag_browser_filter(&mut self, storage: &StorageHandle) -> Result<Vec<String>> {
+        let Some(tags) = self.tag_browser_overlay().map(TagBrowserOverlay::targets) else {
+            return Ok(Vec::new());
+        };
+        if tags.is_empty() {
+            return Ok(tags);
+        }
+        self.close_overlay();
+        let query = tags
+            .iter()
+            .map(|tag| qualified("tag", tag))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !self.search.query.is_empty() {
+            self.record_search_history();
+        }
+        self.apply_saved_search(storage, &query)?;
+        Ok(tags)
+    }
+
+    pub fn open_palette(&mut self, entries: Vec<PaletteEntry>) {
+        let mut overlay = PaletteOverlay {
+            entries,
+            ..PaletteOverlay::default()
+        };
+        overlay.refilter();
+        self.overlay = Some(OverlayState::Palette(overlay));
+    }
+
+    pub fn palette_overlay(&self) -> Option<&PaletteOverlay> {
+        match self.overlay() {
+            Some(OverlayState::Palette(ref overlay)) => Some(overlay),
+            _ => None,
+        }
+    }
+
+    pub fn palette_overlay_mut(&mut self) -> Option<&mut PaletteOverlay> {
+        match self.overlay_mut() {
+            Some(OverlayState::Palette(ref mut overlay)) => Some(overlay),
+            _ => None,
+        }
+    }
+
+    pub fn palette_push_char(&mut self, ch: char) {
+        if let Some(overlay) = self.palette_overlay_mut() {
+            if overlay.input.len() < 200 {
+                overlay.input.push(ch);
+                overlay.refilter();
+            }
+        }
+    }
+
+    pub fn palette_pop_char(&mut self) {
+        if let Some(overlay) = self.palette_overlay_mut() {
+            overlay.input.pop();
+            overlay.refilter();
+        }
+    }
+
+    pub fn palette_set_input<S: Into<String>>(&mut self, input: S) {
+        if let Some(overlay) = self.palette_overlay_mut() {
+            overlay.input = input.into();
+            overlay.refilter();
+        }
+    }
+
+    pub fn palette_move_selection(&mut self, delta: isize) {
+        if let Some(overlay) = self.palette_overlay_mut() {
+            if overlay.matches.is_empty() {
+                overlay.selected = 0;
+                return;
+            }
+            let last = overlay.matches.len() as isize - 1;
+            let next = (overlay.selected as isize + delta).clamp(0, last);
+            overlay.selected = next as usize;
+        }
+    }
+
+    /// Opens help on top of whatever popup is showing.
+    pub fn open_help(&mut self, topic: HelpTopic, entries: Vec<HelpEntry>) {
+        let previous = self.overlay.take().map(Box::new);
+        self.overlay = Some(OverlayState::Help(HelpOverlay {
+            topic,
+            entries,
+            filter: String::new(),
+            scroll: 0,
+            previous,
+        }));
+    }
+
+    pub fn close_help(&mut self) {
+        if let Some(OverlayState::Help(help)) = self.overlay.take() {
+            self.overlay = help.previous.map(|overlay| *overlay);
+        }
+    }
+
+    pub fn help_overlay(&self) -> Option<&HelpOverlay> {
+        match self.overlay() {
+            Some(OverlayState::Help(ref overlay)) => Some(overlay),
+            _ => None,
+        }
+    }
+
+    pub fn help_overlay_mut(&mut self) -> Option<&mut HelpOverlay> {
+        match self.overlay_mut() {
+            Some(OverlayState::Help(ref mut overlay)) => Some(overlay),
+            _ => None,
+        }
+    }
+
+    pub fn help_push_char(&mut self, ch: char) {
+        if let Some(overlay) = self.help_overlay_mut() {
+            if overlay.filter.len() < 80 {
+                overlay.filter.push(ch);
+                overlay.scroll = 0;
+            }
+        }
+    }
+
+    pub fn help_pop_char(&mut self) {
+        if let Some(overlay) = self.help_overlay_mut() {
+            overlay.filter.pop();
+            overlay.scroll = 0;
+        }
+    }
+
+    pub fn help_clear_filter(&mut self) {
+        if let Some(overlay) = self.help_overlay_mut() {
+            overlay.filter.clear();
+            overlay.scroll = 0;
+        }
+    }
+
+    pub fn help_scroll(&mut self, delta: isize) {
+        if let Some(overlay) = self.help_overlay_mut() {
+            let last = overlay.visible_entries().len().saturating_sub(1) as isize;
+            let next = (overlay.scroll as isize)
+                .saturating_add(delta)
+                .clamp(0, last);
+            overlay.scroll = next as usize;
+        }
+    }
+
     pub fn tag_editor_mode(&self) -> TagEditorMode {
         self.tag_editor_overlay()
             .map(|overlay| overlay.mode.clone())
@@ -1260,14 +2532,10 @@ impl AppState {
             selected_index = Some(idx);
         } else {
             editor.items.push(TagEditorItem {
-                name: tag.clone(),
-                selected: true,
                 original: false,
-                bulk_selected: false,
+                ..TagEditorItem::new(&tag, true)
             });
-            editor
-                .items
-                .sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+            arrange_tag_items(&mut editor.items);
             if let Some(idx) = editor
                 .items
                 .iter()
@@ -1336,14 +2604,10 @@ impl AppState {
                 message = String::from("Tag selected");
             } else {
                 editor.items.push(TagEditorItem {
-                    name: normalized.clone(),
-                    selected: true,
                     original: false,
-                    bulk_selected: false,
+                    ..TagEditorItem::new(&normalized, true)
                 });
-                editor
-                    .items
-                    .sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+                arrange_tag_items(&mut editor.items);
                 if let Some(idx) = editor
                     .items
                     .iter()
@@ -1387,21 +2651,7 @@ impl AppState {
 
     pub fn tag_editor_finish_rename(&mut self, from: &str, to: &str) {
         if let Some(editor) = self.tag_editor_overlay_mut() {
-            for item in &mut editor.items {
-                if item.name == from {
-                    let was_selected = item.selected;
-                    let was_original = item.original;
-                    let was_bulk = item.bulk_selected;
-                    item.name = to.to_string();
-                    item.selected = was_selected;
-                    item.original = was_original;
-                    item.bulk_selected = was_bulk;
-                    break;
-                }
-            }
-            editor
-                .items
-                .sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+            move_tag_branch(&mut editor.items, from, to);
             if let Some(idx) = editor
                 .items
                 .iter()
@@ -1417,51 +2667,12 @@ impl AppState {
 
     pub fn tag_editor_finish_merge(&mut self, from: &str, to: &str) {
         if let Some(editor) = self.tag_editor_overlay_mut() {
-            let mut carried_selected = false;
-            let mut carried_original = false;
-            editor.items.retain(|item| {
-                if item.name == from {
-                    carried_selected = item.selected;
-                    carried_original = item.original;
-                    false
-                } else {
-                    true
-                }
-            });
-
-            if let Some(target) = editor
+            move_tag_branch(&mut editor.items, from, to);
+            editor.selected_index = editor
                 .items
-                .iter_mut()
-                .find(|item| item.name.eq_ignore_ascii_case(to))
-            {
-                if carried_selected {
-                    target.selected = true;
-                }
-                if carried_original {
-                    target.original = true;
-                }
-                target.bulk_selected = false;
-            } else {
-                editor.items.push(TagEditorItem {
-                    name: to.to_string(),
-                    selected: carried_selected,
-                    original: carried_original,
-                    bulk_selected: false,
-                });
-            }
-
-            if editor.items.is_empty() {
-                editor.selected_index = 0;
-            } else {
-                editor
-                    .items
-                    .sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
-                editor.selected_index = editor
-                    .items
-                    .iter()
-                    .position(|item| item.name.eq_ignore_ascii_case(to))
-                    .unwrap_or(0);
-            }
+                .iter()
+                .position(|item| item.name.eq_ignore_ascii_case(to))
+                .unwrap_or(0);
 
             editor.mode = TagEditorMode::Browse;
             editor.input.clear();
@@ -1472,6 +2683,34 @@ impl AppState {
         }
     }
 
+    /// Re-reads the per-branch note counts after tags changed in storage.
+    /// Rows that are neither stored, on the note nor queued for it go away.
+    pub fn tag_editor_reload_counts(&mut self, storage: &StorageHandle) -> Result<()> {
+        let Some(editor) = self.tag_editor_overlay_mut() else {
+            return Ok(());
+        };
+        let tree = storage.tag_tree()?;
+        let selected_name = editor
+            .items
+            .get(editor.selected_index)
+            .map(|item| item.name.clone());
+        editor.items.retain(|item| {
+            item.selected || item.original || tree.iter().any(|node| node.name == item.name)
+        });
+        for item in &mut editor.items {
+            item.note_count = tree
+                .iter()
+                .find(|node| node.name.eq_ignore_ascii_case(&item.name))
+                .map_or(0, |node| node.note_count);
+        }
+        arrange_tag_items(&mut editor.items);
+        editor.selected_index = selected_name
+            .and_then(|name| editor.items.iter().position(|item| item.name == name))
+            .unwrap_or(editor.selected_index)
+            .min(editor.items.len().saturating_sub(1));
+        Ok(())
+    }
+
     pub fn tag_editor_finish_delete(&mut self, tag: &str) {
         if let Some(editor) = self.tag_editor_overlay_mut() {
             editor.items.retain(|item| item.name != tag);
@@ -1577,79 +2816,13 @@ fn position_for_column(text: &str, line_start: usize, column: usize) -> usize {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::{compute_trash_status, EditorState};
-    use time::OffsetDateTime;
-
-    #[test]
-    fn editor_undo_redo_cycles() {
-        let mut editor = EditorState::new(1, "hello".to_string());
-        assert!(editor.insert_char('!'));
-        assert_eq!(editor.buffer(), "hello!");
-        assert!(editor.undo());
-        assert_eq!(editor.buffer(), "hello");
-        assert!(!editor.undo());
-        assert!(editor.redo());
-        assert_eq!(editor.buffer(), "hello!");
-    }
-
-    #[test]
-    fn editor_word_navigation_skips_whitespace() {
-        let mut editor = EditorState::new(1, "alpha  beta".to_string());
-        editor.move_end();
-        assert!(editor.move_word_left());
-        assert_eq!(editor.cursor(), 7); // start of "beta"
-        assert!(editor.move_word_left());
-        assert_eq!(editor.cursor(), 0);
-        assert!(editor.move_word_right());
-        assert_eq!(editor.cursor(), 7); // start of "beta"
-    }
-
-    #[test]
-    fn editor_mark_clean_resets_history() {
-        let mut editor = EditorState::new(1, "seed".to_string());
-        editor.insert_char('s');
-        editor.mark_clean();
-        assert!(!editor.is_dirty());
-        assert!(!editor.undo());
-    }
-
-    #[test]
-    fn trash_status_manual_purge_only_when_retention_zero() {
-        let now = OffsetDateTime::now_utc().unix_timestamp();
-        let status = compute_trash_status(Some(now), 0).expect("status");
-        assert_eq!(status.label, "Manual purge only");
-        assert!(!status.expired);
-        assert!(status.indefinite);
-    }
-
-    #[test]
-    fn trash_status_marks_expired_when_past_retention_window() {
-        let now = OffsetDateTime::now_utc().unix_timestamp();
-        let deleted_at = now - (86_400 * 2);
-        let status = compute_trash_status(Some(deleted_at), 1).expect("status");
-        assert!(status.expired);
-        assert!(status.label.contains("Expired"));
-        assert!(!status.indefinite);
-    }
-
-    #[test]
-    fn trash_status_reports_remaining_time() {
-        let now = OffsetDateTime::now_utc().unix_timestamp();
-        let deleted_at = now - (86_400 - 3_600); // about 1 hour remaining in a 1 day window
-        let status = compute_trash_status(Some(deleted_at), 1).expect("status");
-        assert!(!status.expired);
-        assert!(!status.indefinite);
-        assert!(
-            status.label.contains("left"),
-            "expected countdown label, got {}",
-            status.label
-        );
-    }
-}
-
-fn summarize_record(record: NoteRecord, preview_lines: usize, retention_days: u32) -> NoteSummary {
+fn summarize_record(
+    record: NoteRecord,
+    sort: &SortSpec,
+    preview_lines: usize,
+    retention_days: u32,
+) -> NoteSummary {
+    let cursor = ListCursor::of(&record, sort);
     let NoteRecord {
         id,
         title,
@@ -1660,6 +2833,8 @@ fn summarize_record(record: NoteRecord, preview_lines: usize, retention_days: u3
         archived,
         tags,
         deleted_at,
+        notebook_id,
+        fuzzy,
         ..
     } = record;
 
@@ -1688,6 +2863,9 @@ fn summarize_record(record: NoteRecord, preview_lines: usize, retention_days: u3
         deleted_at,
         deleted_label: deleted_at.map(format_timestamp),
         trash_status: compute_trash_status(deleted_at, retention_days),
+        notebook_id,
+        fuzzy,
+        cursor,
     }
 }
 
@@ -1738,11 +2916,82 @@ fn compute_trash_status(deleted_at: Option<i64>, retention_days: u32) -> Option<
     })
 }
 
+/// A `notebook:` qualifier for `path`, quoted when the path has spaces or
+/// brackets.
+/// A `key:value` search qualifier, quoting values the parser would split.
+fn qualified(key: &str, value: &str) -> String {
+    if value.contains(|ch: char| ch.is_whitespace() || matches!(ch, '(' | ')')) {
+        format!("{key}:\"{value}\"")
+    } else {
+        format!("{key}:{value}")
+    }
+}
+
+/// Puts tag editor rows in tree order, adding any missing parent tag so a
+/// nested tag always sits under its branch.
+fn arrange_tag_items(items: &mut Vec<TagEditorItem>) {
+    let mut known: HashSet<String> = items.iter().map(|item| item.name.to_lowercase()).collect();
+    let mut parents = Vec::new();
+    for item in items.iter() {
+        for ancestor in tag_ancestors(&item.name) {
+            if known.insert(ancestor.to_lowercase()) {
+                parents.push(TagEditorItem::new(ancestor, false));
+            }
+        }
+    }
+    items.extend(parents);
+    items.sort_by_cached_key(|item| tag_sort_key(&item.name));
+    for item in items.iter_mut() {
+        item.depth = item.name.matches(TAG_SEPARATOR).count();
+    }
+}
+
+/// Mirrors a stored tag rename: `from` and the rows nested under it take the
+/// `to` prefix, and rows that now share a name are folded together.
+fn move_tag_branch(items: &mut Vec<TagEditorItem>, from: &str, to: &str) {
+    let nested = format!("{from}{TAG_SEPARATOR}");
+    for item in items.iter_mut() {
+        if item.name == from || item.name.starts_with(&nested) {
+            item.name = format!("{to}{}", &item.name[from.len()..]);
+        }
+    }
+    let mut folded: Vec<TagEditorItem> = Vec::with_capacity(items.len());
+    for item in items.drain(..) {
+        match folded
+            .iter_mut()
+            .find(|kept| kept.name.eq_ignore_ascii_case(&item.name))
+        {
+            Some(kept) => {
+                kept.selected |= item.selected;
+                kept.original |= item.original;
+                kept.bulk_selected = false;
+            }
+            None => folded.push(item),
+        }
+    }
+    *items = folded;
+    arrange_tag_items(items);
+}
+
 fn build_filter_chips(query: &SearchQuery) -> Vec<String> {
     let mut chips = Vec::new();
+    match query.scope {
+        Some(NoteScope::Archived) => chips.push("is:archived".to_string()),
+        Some(NoteScope::Trash) => chips.push("in:trash".to_string()),
+        Some(NoteScope::Active) | None => {}
+    }
+    if query.pinned_only {
+        chips.push("is:pinned".to_string());
+    }
+    if let Some(notebook) = &query.notebook {
+        chips.push(format!("notebook:{notebook}"));
+    }
     for tag in &query.tags {
         chips.push(format!("tag:{}", tag));
     }
+    for tag in &query.excluded_tags {
+        chips.push(format!("-tag:{}", tag));
+    }
     if let Some(created) = format_range_chip("created", &query.created) {
         chips.push(created);
     }
@@ -1802,10 +3051,12 @@ fn format_timestamp(epoch: i64) -> String {
         .unwrap_or_else(|_| epoch.to_string())
 }
 
+/// Local date, matching how `created:` / `updated:` filters read dates.
 fn format_epoch_date(epoch: i64) -> String {
-    OffsetDateTime::from_unix_timestamp(epoch)
-        .map(|dt| dt.date().to_string())
-        .unwrap_or_else(|_| epoch.to_string())
+    Calendar::local()
+        .date_of(epoch)
+        .map(|date| date.to_string())
+        .unwrap_or_else(|| epoch.to_string())
 }
 
 fn build_preview(body: &str, preview_lines: usize) -> String {
@@ -1832,6 +3083,18 @@ fn build_preview(body: &str, preview_lines: usize) -> String {
     preview
 }
 
+fn build_revision_entry(revision: NoteRevision) -> RevisionEntry {
+    let saved = OffsetDateTime::from_unix_timestamp(revision.created_at)
+        .unwrap_or(OffsetDateTime::UNIX_EPOCH);
+    RevisionEntry {
+        id: revision.id,
+        title: revision.title,
+        body: revision.body,
+        saved_at: format_datetime(saved),
+        saved_relative: format_relative_time(saved),
+    }
+}
+
 fn build_recovery_preview(body: &str) -> Vec<String> {
     const MAX_LINES: usize = 4;
     const MAX_COLS: usize = 80;
@@ -1855,3 +3118,75 @@ fn build_recovery_preview(body: &str) -> Vec<String> {
     }
     preview
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{compute_trash_status, EditorState};
+    use time::OffsetDateTime;
+
+    #[test]
+    fn editor_undo_redo_cycles() {
+        let mut editor = EditorState::new(1, "hello".to_string());
+        assert!(editor.insert_char('!'));
+        assert_eq!(editor.buffer(), "hello!");
+        assert!(editor.undo());
+        assert_eq!(editor.buffer(), "hello");
+        assert!(!editor.undo());
+        assert!(editor.redo());
+        assert_eq!(editor.buffer(), "hello!");
+    }
+
+    #[test]
+    fn editor_word_navigation_skips_whitespace() {
+        let mut editor = EditorState::new(1, "alpha  beta".to_string());
+        editor.move_end();
+        assert!(editor.move_word_left());
+        assert_eq!(editor.cursor(), 7); // start of "beta"
+        assert!(editor.move_word_left());
+        assert_eq!(editor.cursor(), 0);
+        assert!(editor.move_word_right());
+        assert_eq!(editor.cursor(), 7); // start of "beta"
+    }
+
+    #[test]
+    fn editor_mark_clean_resets_history() {
+        let mut editor = EditorState::new(1, "seed".to_string());
+        editor.insert_char('s');
+        editor.mark_clean();
+        assert!(!editor.is_dirty());
+        assert!(!editor.undo());
+    }
+
+    #[test]
+    fn trash_status_manual_purge_only_when_retention_zero() {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let status = compute_trash_status(Some(now), 0).expect("status");
+        assert_eq!(status.label, "Manual purge only");
+        assert!(!status.expired);
+        assert!(status.indefinite);
+    }
+
+    #[test]
+    fn trash_status_marks_expired_when_past_retention_window() {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let deleted_at = now - (86_400 * 2);
+        let status = compute_trash_status(Some(deleted_at), 1).expect("status");
+        assert!(status.expired);
+        assert!(status.label.contains("Expired"));
+        assert!(!status.indefinite);
+    }
+
+    #[test]
+    fn trash_status_reports_remaining_time() {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let deleted_at = now - (86_400 - 3_600); // about 1 hour remaining in a 1 day window
+        let status = compute_trash_status(Some(deleted_at), 1).expect("status");
+        assert!(!status.expired);
+        assert!(!status.indefinite);
+        assert!(
+            status.label.contains("left"),
+            "expected countdown label, got {}",
+            status.label
+        );
+    }
+}
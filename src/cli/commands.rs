@@ -1,10 +1,11 @@
 use std::collections::HashSet;
 use std::fmt::Write as _;
 use std::io::{self, Read};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::{bail, Context, Result};
-use clap::{Args, Subcommand};
+use clap::{Args, Subcommand, ValueEnum};
 use regex::{Regex, RegexBuilder};
 use rusqlite::{params, Connection, OptionalExtension};
 use time::{format_description::well_known::Rfc3339, OffsetDateTime};
@@ -13,7 +14,10 @@ use crate::app::App;
 use crate::config::AppConfig;
 use crate::highlight::build_highlight_regex;
 use crate::search::{parse_query, regex_pattern_from_input};
-use crate::storage::{NoteRecord, StorageHandle, TagRenameOutcome};
+use crate::storage::{
+    ArchiveImportMode, BackupKind, BackupRotation, NoteRecord, StorageHandle, TagRenameOutcome,
+};
+use crate::transfer;
 
 #[derive(Args, Debug, Clone)]
 pub struct NewArgs {
@@ -26,6 +30,9 @@ pub struct NewArgs {
     /// Pin the new note
     #[arg(long)]
     pub pin: bool,
+    /// File the note in this notebook, e.g. Work/Projects (created if missing)
+    #[arg(long, value_name = "PATH")]
+    pub notebook: Option<String>,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -39,6 +46,12 @@ pub struct SearchArgs {
     /// Limit the number of results printed
     #[arg(long, default_value_t = 20)]
     pub limit: usize,
+    /// Also save the query under NAME (replacing a saved search of that name)
+    #[arg(long, value_name = "NAME")]
+    pub save: Option<String>,
+    /// Only match notes in this notebook or the notebooks nested under it
+    #[arg(long, value_name = "PATH")]
+    pub notebook: Option<String>,
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -49,6 +62,8 @@ pub enum TagCommand {
     Remove(TagRemoveArgs),
     /// List tags associated with a note
     List(TagListArgs),
+    /// List every tag, optionally with how many notes use it
+    ListAll(TagListAllArgs),
     /// Rename a tag across all notes
     Rename(TagRenameArgs),
     /// Merge one tag into another existing tag
@@ -79,6 +94,13 @@ pub struct TagListArgs {
     pub note_id: i64,
 }
 
+#[derive(Args, Debug, Clone)]
+pub struct TagListAllArgs {
+    /// Show active, archived and trashed note counts and when each tag was last used
+    #[arg(long)]
+    pub counts: bool,
+}
+
 #[derive(Args, Debug, Clone)]
 pub struct TagRenameArgs {
     /// Existing tag name (will be renamed)
@@ -108,6 +130,145 @@ pub struct TagArgs {
     pub command: TagCommand,
 }
 
+#[derive(Subcommand, Debug, Clone)]
+pub enum BackupCommand {
+    /// Copy the database into the backup directory now
+    Create,
+    /// List recorded backups, newest first
+    List,
+    /// Replace the database with a backup (the current state is backed up first)
+    Restore(BackupRestoreArgs),
+    /// Delete backups outside the rotation policy
+    Prune(BackupPruneArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct BackupRestoreArgs {
+    /// Backup id from `backup list`, or a path to a backup file
+    pub backup: String,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct BackupPruneArgs {
+    /// Keep the newest backup for this many recent days (defaults to storage.backup_keep_daily)
+    #[arg(long)]
+    pub keep_daily: Option<u32>,
+    /// Keep the newest backup for this many recent weeks (defaults to storage.backup_keep_weekly)
+    #[arg(long)]
+    pub keep_weekly: Option<u32>,
+    /// Rotate manual and pre-restore backups too, not just exit backups
+    #[arg(long)]
+    pub include_manual: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct BackupArgs {
+    #[command(subcommand)]
+    pub command: BackupCommand,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One Markdown file per note with YAML front matter
+    Markdown,
+    /// The whole database as one lossless JSON archive file
+    Json,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ExportArgs {
+    /// Output format
+    #[arg(long, value_enum, default_value_t = ExportFormat::Markdown)]
+    pub format: ExportFormat,
+    /// Directory (markdown) or file (json) to write (created if missing)
+    #[arg(long, value_name = "PATH")]
+    pub out: PathBuf,
+    /// Only rewrite notes whose file changed, and remove the old file of a renamed note
+    #[arg(long)]
+    pub incremental: bool,
+    /// Only export notes matching this query (same syntax as `search`)
+    #[arg()]
+    pub query: Vec<String>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// A directory tree of Markdown files, with or without front matter
+    Markdown,
+    /// An archive file written by `export --format json`
+    Json,
+    /// An Obsidian vault; folders become notebooks and inline #tags are kept
+    Obsidian,
+    /// A Joplin RAW export directory, with its notebooks and tags
+    Joplin,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Add the imported notes to the ones already here
+    Merge,
+    /// Replace every note, tag, notebook and saved search (json only)
+    Replace,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ImportArgs {
+    /// Directory (markdown, obsidian, joplin) or archive file (json)
+    pub path: PathBuf,
+    /// Input format
+    #[arg(long, value_enum, default_value_t = ImportFormat::Markdown)]
+    pub format: ImportFormat,
+    /// How imported notes meet the existing ones
+    #[arg(long, value_enum, default_value_t = ImportMode::Merge)]
+    pub mode: ImportMode,
+    /// Report what would be imported without creating any notes
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum MirrorCommand {
+    /// Rewrite every note file in the mirror from the database and commit
+    Rebuild,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct MirrorArgs {
+    #[command(subcommand)]
+    pub command: MirrorCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum SavedCommand {
+    /// List saved searches by name
+    List,
+    /// Run a saved search and print the matching notes
+    Run(SavedRunArgs),
+    /// Delete a saved search
+    Delete(SavedDeleteArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct SavedRunArgs {
+    /// Saved search name (case-insensitive)
+    pub name: String,
+    /// Limit the number of results printed
+    #[arg(long, default_value_t = 20)]
+    pub limit: usize,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct SavedDeleteArgs {
+    /// Saved search name (case-insensitive)
+    pub name: String,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct SavedArgs {
+    #[command(subcommand)]
+    pub command: SavedCommand,
+}
+
 pub fn run_tui(app: &mut App) -> Result<()> {
     app.run()
 }
@@ -130,31 +291,52 @@ pub fn new_note(_config: Arc<AppConfig>, storage: StorageHandle, args: NewArgs)
     let note_id = storage
         .create_note(&title, &body, args.pin)
         .context("creating note")?;
+    let mut filed = String::new();
+    if let Some(path) = &args.notebook {
+        let notebook = storage.create_notebook(path).context("creating notebook")?;
+        storage.move_note_to_notebook(note_id, Some(notebook.id))?;
+        filed = format!(" in {}", notebook.path);
+    }
     println!(
-        "Created note #{note_id}{}",
+        "Created note #{note_id}{filed}{}",
         if args.pin { " (pinned)" } else { "" }
     );
     Ok(())
 }
 
 pub fn search_notes(
-    _config: Arc<AppConfig>,
+    config: Arc<AppConfig>,
     storage: StorageHandle,
     args: SearchArgs,
 ) -> Result<()> {
-    let output = run_search(&storage, &args)?;
+    let output = run_search(&storage, &args, config.search.fuzzy_threshold)?;
     print!("{output}");
     Ok(())
 }
 
-fn run_search(storage: &StorageHandle, args: &SearchArgs) -> Result<String> {
+fn run_search(storage: &StorageHandle, args: &SearchArgs, fuzzy_threshold: f32) -> Result<String> {
     let raw_query = args.query.join(" ");
     let trimmed = raw_query.trim();
-    if trimmed.is_empty() {
+    if trimmed.is_empty() && args.notebook.is_none() {
         bail!("search query cannot be empty");
     }
+    if args.regex && args.save.is_some() {
+        bail!("--save cannot be combined with --regex; saved searches are plain queries");
+    }
+    if args.notebook.is_some() && args.save.is_some() {
+        bail!("--save cannot be combined with --notebook; put notebook:PATH in the query instead");
+    }
 
-    let mut query = parse_query(trimmed);
+    let mut query = parse_query(trimmed).context("parsing search query")?;
+    if let Some(path) = &args.notebook {
+        if query.notebook.is_some() {
+            bail!("--notebook cannot be combined with a notebook: qualifier");
+        }
+        let Some(notebook) = storage.find_notebook(path)? else {
+            bail!("no notebook named '{}'", path.trim());
+        };
+        query.notebook = Some(notebook.path);
+    }
     if !query.has_terms() && !query.has_filters() {
         bail!("search query must contain terms or filters");
     }
@@ -163,9 +345,9 @@ fn run_search(storage: &StorageHandle, args: &SearchArgs) -> Result<String> {
     }
 
     let mut storage_query = query.clone();
+    storage_query.fuzzy_threshold = fuzzy_threshold;
     if args.regex && storage_query.regex_pattern.is_some() {
-        storage_query.terms.clear();
-        storage_query.title_terms.clear();
+        storage_query.text = None;
     }
 
     let results = storage
@@ -182,11 +364,14 @@ fn run_search(storage: &StorageHandle, args: &SearchArgs) -> Result<String> {
         build_highlight_regex(&query.highlight_terms())
     };
     let colorize = atty::is(atty::Stream::Stdout);
-    Ok(format_search_results(
-        &results,
-        highlight_regex.as_ref(),
-        colorize,
-    ))
+    let mut out = format_search_results(&results, highlight_regex.as_ref(), colorize);
+    if let Some(name) = &args.save {
+        let saved = storage
+            .save_search(name, trimmed)
+            .context("saving search")?;
+        let _ = writeln!(&mut out, "Saved search '{}'.", saved.name);
+    }
+    Ok(out)
 }
 
 fn format_search_results(
@@ -206,6 +391,9 @@ fn format_search_results(
         if note.archived {
             headline.push_str("  [ARCHIVED]");
         }
+        if note.fuzzy {
+            headline.push_str("  [FUZZY]");
+        }
         let _ = writeln!(&mut out, "{headline}");
         let _ = writeln!(
             &mut out,
@@ -233,12 +421,416 @@ pub fn handle_tag_command(
         TagCommand::Add(args) => tag_add(&storage, args),
         TagCommand::Remove(args) => tag_remove(&storage, args),
         TagCommand::List(args) => tag_list(&storage, args),
+        TagCommand::ListAll(args) => {
+            print!("{}", tag_list_all(&storage, args)?);
+            Ok(())
+        }
         TagCommand::Rename(args) => tag_rename(&storage, args),
         TagCommand::Merge(args) => tag_merge(&storage, args),
         TagCommand::Delete(args) => tag_delete(&storage, args),
     }
 }
 
+pub fn handle_saved_command(
+    config: Arc<AppConfig>,
+    storage: StorageHandle,
+    args: SavedArgs,
+) -> Result<()> {
+    let output = run_saved(&config, &storage, args.command)?;
+    print!("{output}");
+    Ok(())
+}
+
+fn run_saved(config: &AppConfig, storage: &StorageHandle, command: SavedCommand) -> Result<String> {
+    let mut out = String::new();
+    match command {
+        SavedCommand::List => {
+            let saved = storage
+                .list_saved_searches()
+                .context("listing saved searches")?;
+            if saved.is_empty() {
+                out.push_str("No saved searches.\n");
+            }
+            let width = saved
+                .iter()
+                .map(|search| search.name.chars().count())
+                .max()
+                .unwrap_or(0);
+            for search in saved {
+                let _ = writeln!(&mut out, "{:<width$}  {}", search.name, search.query);
+            }
+        }
+        SavedCommand::Run(args) => {
+            let Some(saved) = storage.fetch_saved_search(&args.name)? else {
+                bail!("no saved search named '{}'", args.name.trim());
+            };
+            let search = SearchArgs {
+                query: vec![saved.query],
+                regex: false,
+                limit: args.limit,
+                save: None,
+                notebook: None,
+            };
+            out = run_search(storage, &search, config.search.fuzzy_threshold)
+                .with_context(|| format!("running saved search '{}'", saved.name))?;
+        }
+        SavedCommand::Delete(args) => {
+            if !storage.delete_saved_search(&args.name)? {
+                bail!("no saved search named '{}'", args.name.trim());
+            }
+            let _ = writeln!(&mut out, "Deleted saved search '{}'.", args.name.trim());
+        }
+    }
+    Ok(out)
+}
+
+pub fn handle_backup_command(
+    config: Arc<AppConfig>,
+    storage: StorageHandle,
+    args: BackupArgs,
+) -> Result<()> {
+    let output = run_backup(&config, &storage, args.command)?;
+    print!("{output}");
+    Ok(())
+}
+
+pub fn handle_export_command(
+    _config: Arc<AppConfig>,
+    storage: StorageHandle,
+    args: ExportArgs,
+) -> Result<()> {
+    let output = run_export(&storage, &args)?;
+    print!("{output}");
+    Ok(())
+}
+
+fn run_export(storage: &StorageHandle, args: &ExportArgs) -> Result<String> {
+    match args.format {
+        ExportFormat::Markdown => export_markdown(storage, args),
+        ExportFormat::Json => export_json(storage, args),
+    }
+}
+
+fn export_markdown(storage: &StorageHandle, args: &ExportArgs) -> Result<String> {
+    let raw_query = args.query.join(" ");
+    let query = parse_query(raw_query.trim()).context("parsing export query")?;
+    let notes = transfer::select_notes(storage, &query).context("selecting notes to export")?;
+    let report = transfer::markdown::export_markdown(&notes, &args.out, args.incremental)?;
+    let mut out = String::new();
+    let _ = write!(
+        &mut out,
+        "Exported {} note{} to {} ({} written",
+        notes.len(),
+        if notes.len() == 1 { "" } else { "s" },
+        args.out.display(),
+        report.written
+    );
+    if args.incremental {
+        let _ = write!(
+            &mut out,
+            ", {} unchanged, {} removed",
+            report.unchanged, report.removed
+        );
+    }
+    out.push_str(").\n");
+    Ok(out)
+}
+
+fn export_json(storage: &StorageHandle, args: &ExportArgs) -> Result<String> {
+    if !args.query.is_empty() {
+        bail!("--format json archives the whole database and takes no query");
+    }
+    if args.incremental {
+        bail!("--incremental only applies to --format markdown");
+    }
+    let archive = storage.export_archive().context("reading the database")?;
+    let summary = format!(
+        "{} note{}, {} tag{}, {} notebook{}",
+        archive.notes.len(),
+        if archive.notes.len() == 1 { "" } else { "s" },
+        archive.tags.len(),
+        if archive.tags.len() == 1 { "" } else { "s" },
+        archive.notebooks.len(),
+        if archive.notebooks.len() == 1 {
+            ""
+        } else {
+            "s"
+        }
+    );
+    transfer::json::write_archive(archive, &args.out)?;
+    Ok(format!("Archived {summary} to {}.\n", args.out.display()))
+}
+
+pub fn handle_mirror_command(
+    config: Arc<AppConfig>,
+    storage: StorageHandle,
+    args: MirrorArgs,
+) -> Result<()> {
+    let output = run_mirror(&config, &storage, args.command)?;
+    print!("{output}");
+    Ok(())
+}
+
+fn run_mirror(
+    config: &AppConfig,
+    storage: &StorageHandle,
+    command: MirrorCommand,
+) -> Result<String> {
+    match command {
+        MirrorCommand::Rebuild => {
+            let rebuild = storage
+                .rebuild_mirror()
+                .context("rebuilding the git mirror")?;
+            Ok(format!(
+                "Rebuilt the mirror in {} ({} written, {} unchanged, {} removed); {}.\n",
+                config.storage.mirror.repo_dir.display(),
+                rebuild.files.written,
+                rebuild.files.unchanged,
+                rebuild.files.removed,
+                if rebuild.committed {
+                    "committed"
+                } else {
+                    "nothing to commit"
+                }
+            ))
+        }
+    }
+}
+
+pub fn handle_import_command(
+    _config: Arc<AppConfig>,
+    storage: StorageHandle,
+    args: ImportArgs,
+) -> Result<()> {
+    let output = run_import(&storage, &args)?;
+    print!("{output}");
+    Ok(())
+}
+
+fn run_import(storage: &StorageHandle, args: &ImportArgs) -> Result<String> {
+    match args.format {
+        ImportFormat::Json => import_json(storage, args),
+        ImportFormat::Markdown | ImportFormat::Obsidian | ImportFormat::Joplin => {
+            import_files(storage, args)
+        }
+    }
+}
+
+fn import_files(storage: &StorageHandle, args: &ImportArgs) -> Result<String> {
+    if args.mode == ImportMode::Replace {
+        bail!("--mode replace only applies to --format json");
+    }
+    let batch = match args.format {
+        ImportFormat::Obsidian => transfer::obsidian::read_vault(&args.path)?,
+        ImportFormat::Joplin => transfer::joplin::read_raw_export(&args.path)?,
+        _ => transfer::markdown::read_markdown_dir(&args.path)?,
+    };
+    let report = transfer::import_batch(storage, batch, args.dry_run).context("importing notes")?;
+    let mut out = String::new();
+    let _ = writeln!(
+        &mut out,
+        "{} {} note{} from {}{}",
+        if args.dry_run {
+            "Would import"
+        } else {
+            "Imported"
+        },
+        report.imported,
+        if report.imported == 1 { "" } else { "s" },
+        args.path.display(),
+        if args.dry_run {
+            " (dry run; nothing was written)."
+        } else {
+            "."
+        }
+    );
+    if !report.duplicates.is_empty() {
+        let _ = writeln!(
+            &mut out,
+            "Skipped {} duplicate{}:",
+            report.duplicates.len(),
+            if report.duplicates.len() == 1 {
+                ""
+            } else {
+                "s"
+            }
+        );
+        for duplicate in &report.duplicates {
+            let original = match &duplicate.of {
+                transfer::DuplicateOf::Note(id) => format!("note #{id}"),
+                transfer::DuplicateOf::File(path) => path.display().to_string(),
+            };
+            let _ = writeln!(
+                &mut out,
+                "  {}: same as {original}",
+                duplicate.path.display()
+            );
+        }
+    }
+    if !report.skipped.is_empty() {
+        let _ = writeln!(
+            &mut out,
+            "Skipped {} unreadable file{}:",
+            report.skipped.len(),
+            if report.skipped.len() == 1 { "" } else { "s" }
+        );
+        for skipped in &report.skipped {
+            let _ = writeln!(&mut out, "  {}: {}", skipped.path.display(), skipped.reason);
+        }
+    }
+    Ok(out)
+}
+
+fn import_json(storage: &StorageHandle, args: &ImportArgs) -> Result<String> {
+    let archive = transfer::json::read_archive(&args.path)?;
+    let mode = match args.mode {
+        ImportMode::Merge => ArchiveImportMode::Merge,
+        ImportMode::Replace => ArchiveImportMode::Replace,
+    };
+    let mut out = String::new();
+    if mode == ArchiveImportMode::Replace && !args.dry_run {
+        let safety = storage
+            .create_backup(BackupKind::PreRestore)
+            .context("backing up before replacing the database")?;
+        let _ = writeln!(
+            &mut out,
+            "Previous state saved as backup #{} ({})",
+            safety.id,
+            safety.path.display()
+        );
+    }
+    let summary = storage
+        .import_archive(&archive, mode, args.dry_run)
+        .context("importing archive")?;
+    let _ = writeln!(
+        &mut out,
+        "{} {} note{}, {} tag{}, {} notebook{}, {} revision{} and {} saved search{} from {}{}",
+        match (args.dry_run, mode) {
+            (true, _) => "Would import",
+            (false, ArchiveImportMode::Merge) => "Merged",
+            (false, ArchiveImportMode::Replace) => "Restored",
+        },
+        summary.notes,
+        if summary.notes == 1 { "" } else { "s" },
+        summary.tags,
+        if summary.tags == 1 { "" } else { "s" },
+        summary.notebooks,
+        if summary.notebooks == 1 { "" } else { "s" },
+        summary.revisions,
+        if summary.revisions == 1 { "" } else { "s" },
+        summary.saved_searches,
+        if summary.saved_searches == 1 {
+            ""
+        } else {
+            "es"
+        },
+        args.path.display(),
+        if args.dry_run {
+            " (dry run; nothing was written)."
+        } else {
+            "."
+        }
+    );
+    if summary.duplicates > 0 {
+        let _ = writeln!(
+            &mut out,
+            "Skipped {} note{} already here (same title, body and creation time).",
+            summary.duplicates,
+            if summary.duplicates == 1 { "" } else { "s" }
+        );
+    }
+    Ok(out)
+}
+
+fn run_backup(
+    config: &AppConfig,
+    storage: &StorageHandle,
+    command: BackupCommand,
+) -> Result<String> {
+    let mut out = String::new();
+    match command {
+        BackupCommand::Create => {
+            let record = storage
+                .create_backup(BackupKind::Manual)
+                .context("creating backup")?;
+            let _ = writeln!(
+                &mut out,
+                "Created backup #{} at {} ({})",
+                record.id,
+                record.path.display(),
+                format_size(record.size_bytes())
+            );
+        }
+        BackupCommand::List => {
+            let records = storage.list_backups().context("listing backups")?;
+            if records.is_empty() {
+                out.push_str("No backups recorded.\n");
+            }
+            for record in records {
+                let _ = writeln!(
+                    &mut out,
+                    "#{}  {}  {:<11}  {:>9}  {}",
+                    record.id,
+                    format_timestamp(record.created_at),
+                    record.kind.as_str(),
+                    format_size(record.size_bytes()),
+                    record.path.display()
+                );
+            }
+        }
+        BackupCommand::Restore(args) => {
+            let target = args.backup.trim();
+            let path = match target.parse::<i64>() {
+                Ok(id) => {
+                    storage
+                        .fetch_backup(id)?
+                        .ok_or_else(|| anyhow::anyhow!("backup #{id} not found"))?
+                        .path
+                }
+                Err(_) => PathBuf::from(target),
+            };
+            let safety = storage
+                .restore_backup(&path)
+                .with_context(|| format!("restoring {}", path.display()))?;
+            let _ = writeln!(&mut out, "Restored database from {}", path.display());
+            let _ = writeln!(
+                &mut out,
+                "Previous state saved as backup #{} ({})",
+                safety.id,
+                safety.path.display()
+            );
+        }
+        BackupCommand::Prune(args) => {
+            let defaults = BackupRotation::from_options(&config.storage);
+            let rotation = BackupRotation {
+                keep_daily: args.keep_daily.unwrap_or(defaults.keep_daily),
+                keep_weekly: args.keep_weekly.unwrap_or(defaults.keep_weekly),
+                include_manual: args.include_manual,
+            };
+            let pruned = storage.prune_backups(rotation).context("pruning backups")?;
+            if pruned.is_empty() {
+                out.push_str("Nothing to prune.\n");
+            } else {
+                for record in &pruned {
+                    let _ = writeln!(
+                        &mut out,
+                        "Removed #{}  {}",
+                        record.id,
+                        record.path.display()
+                    );
+                }
+                let _ = writeln!(
+                    &mut out,
+                    "Pruned {} backup{}",
+                    pruned.len(),
+                    if pruned.len() == 1 { "" } else { "s" }
+                );
+            }
+        }
+    }
+    Ok(out)
+}
+
 fn prompt(label: &str) -> Result<String> {
     use std::io::Write;
     let mut stdout = io::stdout();
@@ -340,6 +932,45 @@ fn tag_list(storage: &StorageHandle, args: TagListArgs) -> Result<()> {
     Ok(())
 }
 
+fn tag_list_all(storage: &StorageHandle, args: TagListAllArgs) -> Result<String> {
+    let usage = storage.tag_usage().context("reading tag usage")?;
+    let mut out = String::new();
+    if usage.is_empty() {
+        out.push_str("(no tags)\n");
+        return Ok(out);
+    }
+    if !args.counts {
+        for tag in usage {
+            let _ = writeln!(&mut out, "{}", tag.name);
+        }
+        return Ok(out);
+    }
+    let width = usage
+        .iter()
+        .map(|tag| tag.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(3);
+    let _ = writeln!(
+        &mut out,
+        "{:<width$}  {:>6}  {:>8}  {:>5}  LAST USED",
+        "TAG", "ACTIVE", "ARCHIVED", "TRASH"
+    );
+    for tag in usage {
+        let _ = writeln!(
+            &mut out,
+            "{:<width$}  {:>6}  {:>8}  {:>5}  {}",
+            tag.name,
+            tag.active,
+            tag.archived,
+            tag.trashed,
+            tag.last_used
+                .map_or_else(|| "never".to_string(), format_timestamp)
+        );
+    }
+    Ok(out)
+}
+
 fn tag_rename(storage: &StorageHandle, args: TagRenameArgs) -> Result<()> {
     let from = args.from.trim();
     if from.is_empty() {
@@ -360,16 +991,25 @@ fn tag_rename(storage: &StorageHandle, args: TagRenameArgs) -> Result<()> {
         .rename_tag(from, &to)
         .with_context(|| format!("renaming tag '{from}' to '{to}'"))?;
     match outcome {
-        TagRenameOutcome::Renamed { from, to } => {
-            println!("Renamed tag '{from}' to '{to}'");
+        TagRenameOutcome::Renamed {
+            from,
+            to,
+            descendants,
+        } => {
+            println!(
+                "Renamed tag '{from}' to '{to}'{}",
+                nested_suffix(descendants)
+            );
         }
         TagRenameOutcome::Merged {
             from,
             to,
             reassigned,
+            descendants,
         } => {
             println!(
-                "Merged tag '{from}' into '{to}' (relinked {} note{})",
+                "Merged tag '{from}' into '{to}'{} (relinked {} note{})",
+                nested_suffix(descendants),
                 reassigned,
                 if reassigned == 1 { "" } else { "s" }
             );
@@ -417,16 +1057,18 @@ fn tag_merge(storage: &StorageHandle, args: TagMergeArgs) -> Result<()> {
                 from,
                 to,
                 reassigned,
+                descendants,
             } => {
                 println!(
-                    "Merged tag '{from}' into '{to}' (relinked {} note{})",
+                    "Merged tag '{from}' into '{to}'{} (relinked {} note{})",
+                    nested_suffix(descendants),
                     reassigned,
                     if reassigned == 1 { "" } else { "s" }
                 );
                 merged += 1;
                 reassigned_total += reassigned;
             }
-            TagRenameOutcome::Renamed { from, to } => {
+            TagRenameOutcome::Renamed { from, to, .. } => {
                 println!("Renamed tag '{from}' to '{to}' (target differed only by case)");
                 merged += 1;
             }
@@ -444,6 +1086,15 @@ fn tag_merge(storage: &StorageHandle, args: TagMergeArgs) -> Result<()> {
     Ok(())
 }
 
+/// " with 2 nested tags" when a rename carried nested tags along.
+fn nested_suffix(descendants: usize) -> String {
+    match descendants {
+        0 => String::new(),
+        1 => " with 1 nested tag".to_string(),
+        count => format!(" with {count} nested tags"),
+    }
+}
+
 fn tag_delete(storage: &StorageHandle, args: TagDeleteArgs) -> Result<()> {
     let tag = args.tag.trim();
     if tag.is_empty() {
@@ -508,6 +1159,17 @@ fn format_tags(tags: &[String]) -> String {
         .join(" ")
 }
 
+fn format_size(bytes: Option<u64>) -> String {
+    match bytes {
+        None => "missing".to_string(),
+        Some(bytes) if bytes >= 1024 * 1024 => {
+            format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+        }
+        Some(bytes) if bytes >= 1024 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        Some(bytes) => format!("{bytes} B"),
+    }
+}
+
 fn format_timestamp(epoch: i64) -> String {
     OffsetDateTime::from_unix_timestamp(epoch)
         .map(|dt| dt.format(&Rfc3339).unwrap_or_else(|_| epoch.to_string()))
@@ -559,8 +1221,10 @@ mod tests {
             query: vec!["tag:project".into()],
             regex: false,
             limit: 10,
+            save: None,
+            notebook: None,
         };
-        let output = run_search(&storage, &args)?;
+        let output = run_search(&storage, &args, 0.0)?;
 
         assert!(output.contains("Project Plan"));
         assert!(output.contains("[PINNED]"));
@@ -581,14 +1245,125 @@ mod tests {
             query: vec!["tag:regex".into(), "foo[0-9]+bar".into()],
             regex: true,
             limit: 10,
+            save: None,
+            notebook: None,
         };
-        let output = run_search(&storage, &args)?;
+        let output = run_search(&storage, &args, 0.0)?;
 
         assert!(output.contains("Regex Note"));
         assert!(!output.contains("Regex Miss"));
         Ok(())
     }
 
+    #[test]
+    fn cli_search_flags_fuzzy_matches() -> TestResult {
+        let (_temp_dir, storage) = setup_storage()?;
+        storage.create_note("Quarterly budget", "numbers", false)?;
+
+        let args = SearchArgs {
+            query: vec!["budgte".into()],
+            regex: false,
+            limit: 10,
+            save: None,
+            notebook: None,
+        };
+        assert!(run_search(&storage, &args, 0.0)?.contains("No matches found."));
+        let output = run_search(&storage, &args, 0.4)?;
+        assert!(output.contains("Quarterly budget  [FUZZY]"), "{output}");
+        Ok(())
+    }
+
+    #[test]
+    fn cli_saved_searches_save_run_and_delete() -> TestResult {
+        let (_temp_dir, storage) = setup_storage()?;
+        let config = AppConfig::default();
+        let plan_id = storage.create_note("Project Plan", "Timeline overview", false)?;
+        storage.add_tag_to_note(plan_id, "project")?;
+        storage.create_note("Misc Note", "Just chatter", false)?;
+
+        let args = SearchArgs {
+            query: vec!["tag:project".into(), "timeline".into()],
+            regex: false,
+            limit: 10,
+            save: Some("Plans".into()),
+            notebook: None,
+        };
+        let output = run_search(&storage, &args, 0.0)?;
+        assert!(output.ends_with("Saved search 'Plans'.\n"), "{output}");
+
+        let listed = run_saved(&config, &storage, SavedCommand::List)?;
+        assert_eq!(listed, "Plans  tag:project timeline\n");
+
+        let run = SavedCommand::Run(SavedRunArgs {
+            name: "plans".into(),
+            limit: 10,
+        });
+        let output = run_saved(&config, &storage, run)?;
+        assert!(output.contains("Project Plan"));
+        assert!(!output.contains("Misc Note"));
+
+        let delete = |name: &str| {
+            run_saved(
+                &config,
+                &storage,
+                SavedCommand::Delete(SavedDeleteArgs { name: name.into() }),
+            )
+        };
+        assert!(delete("Plans")?.contains("Deleted saved search 'Plans'."));
+        assert!(delete("Plans").is_err());
+        assert_eq!(
+            run_saved(&config, &storage, SavedCommand::List)?,
+            "No saved searches.\n"
+        );
+
+        let regex = SearchArgs {
+            regex: true,
+            ..args
+        };
+        assert!(run_search(&storage, &regex, 0.0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn cli_search_limits_results_to_a_notebook() -> TestResult {
+        let (_temp_dir, storage) = setup_storage()?;
+        let projects = storage.create_notebook("Work/Big Projects")?;
+        let plan_id = storage.create_note("Launch plan", "Timeline", false)?;
+        storage.move_note_to_notebook(plan_id, Some(projects.id))?;
+        storage.create_note("Holiday plan", "Timeline", false)?;
+
+        let args = SearchArgs {
+            query: Vec::new(),
+            regex: false,
+            limit: 10,
+            save: None,
+            notebook: Some("work".into()),
+        };
+        let output = run_search(&storage, &args, 0.0)?;
+        assert!(output.contains("Launch plan"), "{output}");
+        assert!(!output.contains("Holiday plan"));
+
+        let output = run_search(
+            &storage,
+            &SearchArgs {
+                query: vec!["timeline".into(), "notebook:\"work/big projects\"".into()],
+                notebook: None,
+                ..args.clone()
+            },
+            0.0,
+        )?;
+        assert!(output.contains("Launch plan"), "{output}");
+        assert!(!output.contains("Holiday plan"));
+
+        let missing = SearchArgs {
+            notebook: Some("Home".into()),
+            ..args
+        };
+        let err = run_search(&storage, &missing, 0.0).unwrap_err();
+        assert_eq!(err.to_string(), "no notebook named 'Home'");
+        Ok(())
+    }
+
     #[test]
     fn highlight_cli_text_wraps_matches_when_color_enabled() {
         let regex = Regex::new("note").expect("regex");
@@ -718,10 +1493,524 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn cli_tag_list_all_reports_counts() -> TestResult {
+        let (_temp_dir, storage) = setup_storage()?;
+        let live = storage.create_note("Live", "body", false)?;
+        let shelved = storage.create_note("Shelved", "body", false)?;
+        let binned = storage.create_note("Binned", "body", false)?;
+        storage.add_tag_to_note(live, "work")?;
+        storage.add_tag_to_note(shelved, "work")?;
+        storage.add_tag_to_note(binned, "work")?;
+        storage.add_tag_to_note(binned, "stale")?;
+        storage.set_note_archived(shelved, true)?;
+        storage.soft_delete_note(binned)?;
+
+        let plain = tag_list_all(&storage, TagListAllArgs { counts: false })?;
+        assert_eq!(plain, "stale\nwork\n");
+
+        let counted = tag_list_all(&storage, TagListAllArgs { counts: true })?;
+        let lines: Vec<&str> = counted.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("TAG  "));
+        let columns = |line: &str| -> Vec<String> {
+            line.split_whitespace()
+                .take(4)
+                .map(str::to_string)
+                .collect()
+        };
+        assert_eq!(columns(lines[1]), vec!["stale", "0", "0", "1"]);
+        assert_eq!(columns(lines[2]), vec!["work", "1", "1", "1"]);
+
+        let usage = storage.tag_usage()?;
+        assert!(usage[0].is_unused() && !usage[1].is_unused());
+        assert!(usage[1].last_used.is_some());
+        assert_eq!(storage.delete_tags(&["stale".into(), "missing".into()])?, 1);
+        assert_eq!(storage.list_all_tags()?, vec!["work"]);
+        Ok(())
+    }
+
+    #[test]
+    fn cli_export_markdown_filters_and_skips_trash() -> TestResult {
+        let (temp_dir, storage) = setup_storage()?;
+        let plan = storage.create_note("Project Plan", "Timeline overview", false)?;
+        storage.add_tag_to_note(plan, "project")?;
+        let shelved = storage.create_note("Old Plan", "Superseded", false)?;
+        storage.add_tag_to_note(shelved, "project")?;
+        storage.set_note_archived(shelved, true)?;
+        let binned = storage.create_note("Scratch", "gone", false)?;
+        storage.add_tag_to_note(binned, "project")?;
+        storage.soft_delete_note(binned)?;
+        let out = temp_dir.path().join("export");
+
+        let mut args = ExportArgs {
+            format: ExportFormat::Markdown,
+            out: out.clone(),
+            incremental: false,
+            query: vec!["tag:project".into()],
+        };
+        let output = run_export(&storage, &args)?;
+        assert!(output.starts_with("Exported 2 notes to "), "{output}");
+        let exported = std::fs::read_to_string(out.join(format!("old-plan-{shelved}.md")))?;
+        assert!(exported.contains("archived: true\n"), "{exported}");
+        assert!(!out.join(format!("scratch-{binned}.md")).exists());
+
+        args.query = vec!["tag:project".into(), "timeline".into()];
+        args.incremental = true;
+        let output = run_export(&storage, &args)?;
+        assert!(
+            output.ends_with("(0 written, 1 unchanged, 0 removed).\n"),
+            "{output}"
+        );
+        // Notes leaving the selection keep their files; renamed ones do not.
+        assert!(out.join(format!("old-plan-{shelved}.md")).exists());
+        storage.rename_note_title(plan, "Project Timeline")?;
+        let output = run_export(&storage, &args)?;
+        assert!(
+            output.ends_with("(1 written, 0 unchanged, 1 removed).\n"),
+            "{output}"
+        );
+        assert!(!out.join(format!("project-plan-{plan}.md")).exists());
+        assert!(out.join(format!("project-timeline-{plan}.md")).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn cli_import_markdown_dry_runs_and_skips_duplicates() -> TestResult {
+        let (temp_dir, storage) = setup_storage()?;
+        let existing = storage.create_note("Groceries", "milk\neggs", false)?;
+        let dir = temp_dir.path().join("vault");
+        std::fs::create_dir_all(dir.join("work/q1"))?;
+        std::fs::create_dir_all(dir.join(".obsidian"))?;
+        std::fs::write(dir.join(".obsidian/config.md"), "# hidden")?;
+        std::fs::write(
+            dir.join("groceries.md"),
+            "---\ntitle: Groceries\n---\nmilk\neggs\n",
+        )?;
+        std::fs::write(
+            dir.join("work/q1/plan.md"),
+            "---\ntitle: Plan\ntags: [roadmap]\ncreated: 2024-01-02\n\
+             updated: 2024-01-03T00:00:00Z\narchived: true\n---\n\nShip it.\n",
+        )?;
+        std::fs::write(dir.join("work/q1/plan-copy.md"), "# Plan draft\nShip it.")?;
+        std::fs::write(
+            dir.join("work/q1/z-copy.md"),
+            "---\ntitle: Plan\n---\nShip it.",
+        )?;
+        std::fs::write(dir.join("work/broken.md"), "---\npinned: maybe\n---\n")?;
+        std::fs::write(dir.join("work/binary.md"), [0xff, 0xfe, 0x00])?;
+        std::fs::write(dir.join("photo.png"), "not markdown")?;
+
+        let mut args = ImportArgs {
+            path: dir.clone(),
+            format: ImportFormat::Markdown,
+            mode: ImportMode::Merge,
+            dry_run: true,
+        };
+        let output = run_import(&storage, &args)?;
+        assert!(output.starts_with("Would import 2 notes from "), "{output}");
+        assert!(
+            output.contains("(dry run; nothing was written)"),
+            "{output}"
+        );
+        let before = transfer::select_notes(&storage, &Default::default())?.len();
+
+        args.dry_run = false;
+        let output = run_import(&storage, &args)?;
+        assert!(output.starts_with("Imported 2 notes from "), "{output}");
+        assert!(
+            output.contains(&format!("  groceries.md: same as note #{existing}\n")),
+            "{output}"
+        );
+        assert!(output.contains("Skipped 2 unreadable files:\n"), "{output}");
+        assert!(
+            output.contains("  work/binary.md: not UTF-8 text\n"),
+            "{output}"
+        );
+        assert!(
+            output.contains("  work/broken.md: invalid `pinned` value 'maybe'\n"),
+            "{output}"
+        );
+        let notes = transfer::select_notes(&storage, &Default::default())?;
+        assert_eq!(notes.len(), before + 2);
+
+        let plan = notes
+            .iter()
+            .find(|note| note.title == "Plan")
+            .expect("imported");
+        assert_eq!(plan.body, "Ship it.\n");
+        assert!(plan.archived);
+        let mut tags = plan.tags.clone();
+        tags.sort();
+        assert_eq!(tags, vec!["roadmap", "work/q1"]);
+        assert_eq!(plan.created_at, 1_704_153_600);
+        assert_eq!(plan.updated_at, 1_704_240_000);
+        assert!(
+            output.contains("  work/q1/z-copy.md: same as work/q1/plan.md\n"),
+            "{output}"
+        );
+
+        // Importing again finds nothing new.
+        let output = run_import(&storage, &args)?;
+        assert!(output.starts_with("Imported 0 notes from "), "{output}");
+        Ok(())
+    }
+
+    #[test]
+    fn cli_import_obsidian_vault_fills_notebooks_and_links() -> TestResult {
+        let (temp_dir, storage) = setup_storage()?;
+        let vault = temp_dir.path().join("vault");
+        std::fs::create_dir_all(vault.join("Work/Projects"))?;
+        std::fs::create_dir_all(vault.join(".obsidian"))?;
+        std::fs::write(vault.join(".obsidian/workspace.md"), "settings")?;
+        std::fs::write(
+            vault.join("Work/Projects/Roadmap.md"),
+            "Q3 goals #planning\n",
+        )?;
+        std::fs::write(
+            vault.join("Work/Standup.md"),
+            "Discussed [[Projects/Roadmap|the roadmap]]. #team/core\n",
+        )?;
+
+        let args = ImportArgs {
+            path: vault.clone(),
+            format: ImportFormat::Obsidian,
+            mode: ImportMode::Merge,
+            dry_run: false,
+        };
+        let output = run_import(&storage, &args)?;
+        assert!(output.starts_with("Imported 2 notes from "), "{output}");
+
+        let standup = storage.search_notes(&parse_query("title:standup")?, 10)?;
+        let standup = &standup[0];
+        assert_eq!(standup.body, "Discussed [[Roadmap]]. #team/core\n");
+        assert_eq!(standup.tags, vec!["team/core"]);
+        assert_eq!(storage.outgoing_links(standup.id)?.len(), 1);
+        assert_eq!(
+            storage.find_notebook("Work")?.map(|nb| nb.note_count),
+            Some(1)
+        );
+        assert_eq!(
+            storage
+                .find_notebook("Work/Projects")?
+                .map(|nb| nb.note_count),
+            Some(1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cli_json_archive_merges_with_remapped_ids_and_replaces() -> TestResult {
+        let (source_dir, source) = setup_storage()?;
+        let target_note = source.create_note("Target", "linked to", false)?;
+        let linking = source.create_note("Linking", "draft", true)?;
+        source.update_note_body(linking, &format!("see [[#{target_note}]]"))?;
+        source.add_tag_to_note(linking, "project/alpha")?;
+        let notebook = source.create_notebook("Work/Projects")?;
+        source.move_note_to_notebook(linking, Some(notebook.id))?;
+        let binned = source.create_note("Binned", "gone", false)?;
+        source.soft_delete_note(binned)?;
+        source.save_search("alpha", "tag:project/alpha")?;
+        let archive_path = source_dir.path().join("notes.json");
+
+        let export = ExportArgs {
+            format: ExportFormat::Json,
+            out: archive_path.clone(),
+            incremental: false,
+            query: Vec::new(),
+        };
+        let output = run_export(&source, &export)?;
+        assert!(output.starts_with("Archived "), "{output}");
+        let archive = source.export_archive()?;
+        let rejected = ExportArgs {
+            query: vec!["tag:work".into()],
+            ..export
+        };
+        assert!(run_export(&source, &rejected).is_err());
+
+        // Merging into the same database finds every note already there.
+        let mut import = ImportArgs {
+            path: archive_path.clone(),
+            format: ImportFormat::Json,
+            mode: ImportMode::Merge,
+            dry_run: false,
+        };
+        let output = run_import(&source, &import)?;
+        assert!(output.starts_with("Merged 0 notes, 0 tags"), "{output}");
+        assert!(output.contains(&format!(
+            "Skipped {} notes already here",
+            archive.notes.len()
+        )));
+
+        let (_target_dir, target) = setup_storage()?;
+        let existing = target.create_note("Already here", "local", false)?;
+        import.dry_run = true;
+        let output = run_import(&target, &import)?;
+        assert!(output.starts_with("Would import "), "{output}");
+        assert!(target.fetch_note_by_id(existing + 1)?.is_none());
+
+        import.dry_run = false;
+        let output = run_import(&target, &import)?;
+        // The first-run notes of both databases may match each other, so
+        // only the rows the archive alone brings are counted exactly.
+        assert!(output.starts_with("Merged "), "{output}");
+        assert!(
+            output.contains(" notes, 1 tag, 2 notebooks, 1 revision and 1 saved search from"),
+            "{output}"
+        );
+        let merged = target.search_notes(&parse_query("linking")?, 10)?;
+        let merged = &merged[0];
+        assert!(merged.pinned);
+        assert_eq!(merged.tags, vec!["project/alpha"]);
+        let new_target = target
+            .search_notes(&parse_query("title:target")?, 10)?
+            .first()
+            .map(|note| note.id)
+            .expect("target note merged");
+        assert_ne!(new_target, target_note);
+        assert_eq!(merged.body, format!("see [[#{new_target}]]"));
+        assert_eq!(target.outgoing_links(merged.id)?.len(), 1);
+        assert!(!target.list_note_revisions(merged.id)?.is_empty());
+        assert_eq!(
+            target
+                .find_notebook("work/projects")?
+                .map(|nb| nb.note_count),
+            Some(1)
+        );
+
+        import.mode = ImportMode::Replace;
+        let output = run_import(&target, &import)?;
+        assert!(
+            output.starts_with("Previous state saved as backup #"),
+            "{output}"
+        );
+        assert_eq!(target.export_archive()?, archive);
+        let restored = target.fetch_note_by_id(linking)?.expect("original id kept");
+        assert_eq!(restored.body, format!("see [[#{target_note}]]"));
+
+        import.format = ImportFormat::Markdown;
+        assert!(run_import(&target, &import).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn cli_mirror_batches_commits_and_rebuilds() -> TestResult {
+        let has_git = std::process::Command::new("git")
+            .arg("--version")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if !has_git {
+            eprintln!("skipping: git is not installed");
+            return Ok(());
+        }
+        let (temp_dir, storage) = setup_storage_with(|options| {
+            options.mirror.enabled = true;
+            options.mirror.commit_interval_secs = 3_600;
+        })?;
+        let repo = temp_dir.path().join("mirror");
+        let git = |args: &[&str]| -> TestResult<String> {
+            let output = std::process::Command::new("git")
+                .arg("-C")
+                .arg(&repo)
+                .args(args)
+                .output()?;
+            assert!(
+                output.status.success(),
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        };
+        let files = || -> TestResult<Vec<String>> {
+            let mut names = Vec::new();
+            for entry in std::fs::read_dir(&repo)? {
+                let name = entry?.file_name().to_string_lossy().into_owned();
+                if name.ends_with(".md") {
+                    names.push(name);
+                }
+            }
+            names.sort();
+            Ok(names)
+        };
+
+        // Files the mirror did not write are never removed, even when their
+        // name ends in a number.
+        std::fs::create_dir_all(&repo)?;
+        std::fs::write(repo.join("meeting-2024.md"), "# Meeting\n")?;
+        let plan = storage.create_note("Plan", "first draft", false)?;
+        storage.add_tag_to_note(plan, "work")?;
+        let scratch = storage.create_note("Scratch", "temporary", false)?;
+        // Saves only queue their notes; the next sync writes them.
+        assert_eq!(files()?, vec!["meeting-2024.md"]);
+        storage.sync_mirror()?;
+        let plan_file = format!("plan-{plan}.md");
+        assert!(files()?.contains(&plan_file));
+        assert!(files()?.contains(&format!("scratch-{scratch}.md")));
+        let text = std::fs::read_to_string(repo.join(&plan_file))?;
+        assert!(text.contains("tags: [\"work\"]"), "{text}");
+        // Saves inside the batch interval wait for the flush.
+        assert_eq!(git(&["rev-list", "--count", "--all"])?, "0");
+        assert!(storage.flush_mirror()?);
+        assert_eq!(git(&["rev-list", "--count", "HEAD"])?, "1");
+        assert!(!storage.flush_mirror()?);
+
+        storage.rename_note_title(plan, "Roadmap")?;
+        storage.soft_delete_note(scratch)?;
+        storage.sync_mirror()?;
+        let names = files()?;
+        assert!(names.contains(&format!("roadmap-{plan}.md")), "{names:?}");
+        assert!(!names.contains(&plan_file), "{names:?}");
+        assert!(!names.iter().any(|name| name.starts_with("scratch-")));
+        assert!(names.contains(&"meeting-2024.md".to_string()));
+
+        // A file edited by hand is put back by a rebuild.
+        std::fs::write(repo.join(format!("roadmap-{plan}.md")), "edited")?;
+        std::fs::write(
+            repo.join("stray-999999.md"),
+            "---\nid: 999999\ntitle: Stray\n---\n",
+        )?;
+        let mut config = AppConfig::default();
+        config.storage.mirror.repo_dir = repo.clone();
+        let output = run_mirror(&config, &storage, MirrorCommand::Rebuild)?;
+        assert!(output.starts_with("Rebuilt the mirror in "), "{output}");
+        assert!(output.contains(" (1 written, "), "{output}");
+        assert!(output.contains(" 1 removed); committed."), "{output}");
+        assert_eq!(git(&["rev-list", "--count", "HEAD"])?, "2");
+        assert_eq!(git(&["log", "-1", "--format=%s"])?, "Rebuild mirror");
+        assert_eq!(git(&["status", "--porcelain"])?, "");
+        let output = run_mirror(&config, &storage, MirrorCommand::Rebuild)?;
+        assert!(output.ends_with("nothing to commit.\n"), "{output}");
+        assert!(repo.join("meeting-2024.md").exists());
+
+        // Once the interval has passed, a sync starts the commit without
+        // waiting for it and the flush collects it.
+        let (eager_dir, eager) = setup_storage_with(|options| {
+            options.mirror.enabled = true;
+            options.mirror.commit_interval_secs = 0;
+        })?;
+        eager.create_note("Inbox", "", false)?;
+        eager.sync_mirror()?;
+        assert!(eager.flush_mirror()?);
+        let log = std::process::Command::new("git")
+            .arg("-C")
+            .arg(eager_dir.path().join("mirror"))
+            .args(["log", "--format=%s"])
+            .output()?;
+        assert!(log.status.success());
+        // The new mirror's single commit holds the seed notes and the inbox.
+        let log = String::from_utf8_lossy(&log.stdout);
+        assert_eq!(log.lines().count(), 1, "{log}");
+        assert!(log.starts_with("Update "), "{log}");
+
+        let (_plain_dir, plain) = setup_storage()?;
+        assert!(run_mirror(&config, &plain, MirrorCommand::Rebuild).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn cli_backup_create_list_and_restore_by_id() -> TestResult {
+        let (_temp_dir, storage) = setup_storage()?;
+        let config = AppConfig::default();
+        let note_id = storage.create_note("Ledger", "balanced", false)?;
+
+        let created = run_backup(&config, &storage, BackupCommand::Create)?;
+        assert!(created.starts_with("Created backup #1 at "));
+        let listed = run_backup(&config, &storage, BackupCommand::List)?;
+        assert!(listed.contains("#1") && listed.contains("manual"));
+
+        storage.update_note_body(note_id, "unbalanced")?;
+        let restored = run_backup(
+            &config,
+            &storage,
+            BackupCommand::Restore(BackupRestoreArgs { backup: "1".into() }),
+        )?;
+        assert!(restored.contains("Previous state saved as backup #2"));
+        let note = storage.fetch_note_by_id(note_id)?.expect("note present");
+        assert_eq!(note.body, "balanced");
+
+        let missing = run_backup(
+            &config,
+            &storage,
+            BackupCommand::Restore(BackupRestoreArgs {
+                backup: "99".into(),
+            }),
+        );
+        assert!(missing.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn commands_commit_the_mirror_before_returning() -> TestResult {
+        let git_available = std::process::Command::new("git")
+            .arg("--version")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if !git_available {
+            return Ok(());
+        }
+        // A long interval means only the final flush can make the commit.
+        let (temp, storage) = setup_storage_with(|opts| {
+            opts.mirror.enabled = true;
+            opts.mirror.commit_interval_secs = 3_600;
+        })?;
+        let command = crate::cli::Commands::New(NewArgs {
+            title: Some("Standup".into()),
+            body: Some("Notes".into()),
+            pin: false,
+            notebook: None,
+        });
+        crate::cli::execute(
+            command,
+            Arc::new(AppConfig::default()),
+            storage.clone(),
+            temp_paths(temp.path()),
+        )?;
+
+        let notes = storage.fetch_recent_notes(10)?;
+        let id = notes
+            .iter()
+            .find(|note| note.title == "Standup")
+            .unwrap()
+            .id;
+        let repo = temp.path().join("mirror");
+        assert!(repo.join(format!("standup-{id}.md")).exists());
+        let log = std::process::Command::new("git")
+            .arg("-C")
+            .arg(&repo)
+            .args(["log", "--format=%s"])
+            .output()?;
+        assert!(log.status.success());
+        assert_eq!(String::from_utf8_lossy(&log.stdout).lines().count(), 1);
+        assert!(!storage.flush_mirror()?);
+        Ok(())
+    }
+
     fn setup_storage() -> TestResult<(TempDir, StorageHandle)> {
+        setup_storage_with(|_| {})
+    }
+
+    fn setup_storage_with(
+        configure: impl FnOnce(&mut StorageOptions),
+    ) -> TestResult<(TempDir, StorageHandle)> {
         let temp = TempDir::new().context("creating temp dir")?;
         let root = temp.path();
-        let paths = ConfigPaths {
+        let paths = temp_paths(root);
+        let mut storage_opts = StorageOptions {
+            database_path: paths.database_path.clone(),
+            backup_dir: paths.backup_dir.clone(),
+            backup_on_exit: false,
+            ..StorageOptions::default()
+        };
+        storage_opts.mirror.repo_dir = root.join("mirror");
+        configure(&mut storage_opts);
+
+        let handle = storage::init(&paths, &storage_opts)?;
+        Ok((temp, handle))
+    }
+
+    fn temp_paths(root: &std::path::Path) -> ConfigPaths {
+        ConfigPaths {
             config_dir: root.join("config"),
             config_file: root.join("config/config.toml"),
             data_dir: root.join("data"),
@@ -730,13 +2019,7 @@ mod tests {
             backup_dir: root.join("backups"),
             log_dir: root.join("logs"),
             state_dir: root.join("state"),
-        };
-        let mut storage_opts = StorageOptions::default();
-        storage_opts.database_path = paths.database_path.clone();
-        storage_opts.backup_dir = paths.backup_dir.clone();
-        storage_opts.backup_on_exit = false;
-
-        let handle = storage::init(&paths, &storage_opts)?;
-        Ok((temp, handle))
+            theme_dir: root.join("config/themes"),
+        }
     }
 }
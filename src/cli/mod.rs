@@ -8,12 +8,14 @@ use once_cell::sync::OnceCell;
 use tracing_subscriber::{fmt, EnvFilter};
 
 use crate::app::App;
-use crate::config::ConfigLoader;
-use crate::storage;
+use crate::config::{AppConfig, ConfigLoader, ConfigPaths};
+use crate::storage::{self, StorageHandle};
 
 pub mod commands;
 
-use self::commands::{NewArgs, SearchArgs, TagArgs};
+use self::commands::{
+    BackupArgs, ExportArgs, ImportArgs, MirrorArgs, NewArgs, SavedArgs, SearchArgs, TagArgs,
+};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -46,8 +48,18 @@ pub enum Commands {
     New(NewArgs),
     /// Run a non-interactive search and print matching note titles
     Search(SearchArgs),
+    /// List, run, and delete saved searches
+    Saved(SavedArgs),
     /// Manage note tags from the CLI
     Tag(TagArgs),
+    /// Create, list, restore, and prune database backups
+    Backup(BackupArgs),
+    /// Write notes out as files, e.g. one Markdown file per note
+    Export(ExportArgs),
+    /// Create notes from files, e.g. a directory of Markdown files
+    Import(ImportArgs),
+    /// Manage the git mirror of notes configured under [storage.mirror]
+    Mirror(MirrorArgs),
 }
 
 pub fn run() -> Result<()> {
@@ -69,16 +81,35 @@ pub fn run() -> Result<()> {
     let storage = storage::init(&paths, &config.storage)?;
 
     let config = Arc::new(config);
-    let command = cli.command.unwrap_or(Commands::Tui);
-    match command {
+    execute(cli.command.unwrap_or(Commands::Tui), config, storage, paths)
+}
+
+/// Runs one command against opened storage, then commits any saves the git
+/// mirror has batched so none are left behind when the process exits.
+pub(crate) fn execute(
+    command: Commands,
+    config: Arc<AppConfig>,
+    storage: StorageHandle,
+    paths: ConfigPaths,
+) -> Result<()> {
+    let result = match command {
         Commands::Tui => {
-            let mut app = App::new(config.clone(), storage.clone(), paths.clone())?;
+            let mut app = App::new(config.clone(), storage.clone(), paths)?;
             commands::run_tui(&mut app)
         }
         Commands::New(args) => commands::new_note(config.clone(), storage.clone(), args),
         Commands::Search(args) => commands::search_notes(config.clone(), storage.clone(), args),
-        Commands::Tag(args) => commands::handle_tag_command(config, storage, args),
+        Commands::Saved(args) => commands::handle_saved_command(config, storage.clone(), args),
+        Commands::Tag(args) => commands::handle_tag_command(config, storage.clone(), args),
+        Commands::Backup(args) => commands::handle_backup_command(config, storage.clone(), args),
+        Commands::Export(args) => commands::handle_export_command(config, storage.clone(), args),
+        Commands::Import(args) => commands::handle_import_command(config, storage.clone(), args),
+        Commands::Mirror(args) => commands::handle_mirror_command(config, storage.clone(), args),
+    };
+    if let Err(err) = storage.flush_mirror() {
+        tracing::warn!(?err, "failed to commit the git mirror");
     }
+    result
 }
 
 fn init_tracing(level: &str) -> Result<()> {
@@ -1,4 +1,6 @@
+use std::collections::BTreeMap;
 use std::env;
+use std::fmt;
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
@@ -8,7 +10,7 @@ use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use time::Duration;
 
-use crate::config::themes::ThemeRegistry;
+use crate::config::themes::{Theme, ThemeRegistry};
 
 pub mod themes;
 
@@ -73,6 +75,8 @@ pub struct ConfigPaths {
     pub backup_dir: PathBuf,
     pub log_dir: PathBuf,
     pub state_dir: PathBuf,
+    /// User theme files (`*.toml`) loaded on top of the built-in palettes.
+    pub theme_dir: PathBuf,
 }
 
 impl ConfigPaths {
@@ -108,6 +112,7 @@ impl ConfigPaths {
             .unwrap_or_else(|| data_root.join("state"));
         let log_dir = state_dir.join("logs");
         let backup_dir = data_root.join("backups");
+        let theme_dir = config_dir.join("themes");
 
         Ok(Self {
             config_dir,
@@ -118,6 +123,7 @@ impl ConfigPaths {
             backup_dir,
             log_dir,
             state_dir,
+            theme_dir,
         })
     }
 
@@ -129,6 +135,7 @@ impl ConfigPaths {
             &self.backup_dir,
             &self.log_dir,
             &self.state_dir,
+            &self.theme_dir,
         ] {
             fs::create_dir_all(dir)
                 .with_context(|| format!("creating application directory {}", dir.display()))?;
@@ -141,10 +148,16 @@ impl ConfigPaths {
 #[serde(default)]
 pub struct AppConfig {
     pub theme: ThemeName,
+    /// Palette resolved from `theme` once the theme directory has been read.
+    #[serde(skip)]
+    pub palette: Theme,
     pub preview_lines: u16,
     pub default_sort: SortSpec,
     pub auto_save: AutoSaveConfig,
     pub keybindings: KeybindingProfile,
+    /// Per-context overrides applied on top of the `keybindings` preset.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub keymap: KeymapOverrides,
     pub storage: StorageOptions,
     pub search: SearchOptions,
     pub retention_days: u32,
@@ -154,6 +167,7 @@ impl Default for AppConfig {
     fn default() -> Self {
         Self {
             theme: ThemeName::Dark,
+            palette: Theme::default(),
             preview_lines: 5,
             default_sort: SortSpec {
                 field: SortField::Updated,
@@ -161,6 +175,7 @@ impl Default for AppConfig {
             },
             auto_save: AutoSaveConfig::default(),
             keybindings: KeybindingProfile::Vim,
+            keymap: KeymapOverrides::new(),
             storage: StorageOptions::default(),
             search: SearchOptions::default(),
             retention_days: 30,
@@ -173,10 +188,12 @@ impl AppConfig {
         self.storage
             .resolve(paths)
             .context("resolving storage paths")?;
-        if !ThemeRegistry::default().contains(&self.theme) {
+        let themes = ThemeRegistry::load(&paths.theme_dir);
+        if !themes.contains(&self.theme) {
             tracing::warn!(?self.theme, "unknown theme in config, falling back to Dark");
             self.theme = ThemeName::Dark;
         }
+        self.palette = themes.resolve(&self.theme);
         Ok(())
     }
 }
@@ -225,6 +242,11 @@ pub struct StorageOptions {
     pub backup_dir: PathBuf,
     pub wal_autocheckpoint: u32,
     pub backup_on_exit: bool,
+    /// Number of most recent days that keep their newest backup during rotation.
+    pub backup_keep_daily: u32,
+    /// Number of most recent ISO weeks that keep their newest backup during rotation.
+    pub backup_keep_weekly: u32,
+    pub mirror: MirrorOptions,
 }
 
 impl Default for StorageOptions {
@@ -234,6 +256,9 @@ impl Default for StorageOptions {
             backup_dir: PathBuf::new(),
             wal_autocheckpoint: 1000,
             backup_on_exit: true,
+            backup_keep_daily: 7,
+            backup_keep_weekly: 4,
+            mirror: MirrorOptions::default(),
         }
     }
 }
@@ -246,16 +271,46 @@ impl StorageOptions {
         if self.backup_dir.as_os_str().is_empty() {
             self.backup_dir = paths.backup_dir.clone();
         }
+        if self.mirror.repo_dir.as_os_str().is_empty() {
+            self.mirror.repo_dir = paths.data_dir.join("mirror");
+        }
         Ok(())
     }
 }
 
+/// `[storage.mirror]`: a git repository holding every note outside the trash
+/// as a Markdown file, updated as notes are saved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MirrorOptions {
+    pub enabled: bool,
+    /// Working tree of the mirror, initialised with `git init` if it is not a
+    /// repository yet. Defaults to `mirror/` in the data directory.
+    pub repo_dir: PathBuf,
+    /// Saves are written to the mirror on the next tick but committed at most
+    /// once per this many seconds, plus once on exit; `0` commits on the tick
+    /// after every save.
+    pub commit_interval_secs: u64,
+}
+
+impl Default for MirrorOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            repo_dir: PathBuf::new(),
+            commit_interval_secs: 60,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct SearchOptions {
     pub max_results: usize,
     pub regex_default: bool,
     pub fuzzy_threshold: f32,
+    /// Distinct queries kept in the search history; `0` keeps none.
+    pub history_size: usize,
 }
 
 impl Default for SearchOptions {
@@ -264,40 +319,91 @@ impl Default for SearchOptions {
             max_results: 200,
             regex_default: false,
             fuzzy_threshold: 0.4,
+            history_size: 100,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, std::hash::Hash)]
-#[serde(rename_all = "kebab-case")]
+/// Built-in palettes plus any user theme found in [`ConfigPaths::theme_dir`].
+/// Names are matched case-insensitively, so `HighContrast`, `high_contrast` and
+/// `high-contrast` all select the same palette.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, std::hash::Hash, Default)]
+#[serde(from = "String", into = "String")]
 pub enum ThemeName {
+    #[default]
     Dark,
     Light,
     HighContrast,
     Solarized,
+    Custom(String),
 }
 
-impl Default for ThemeName {
-    fn default() -> Self {
-        ThemeName::Dark
+impl ThemeName {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ThemeName::Dark => "dark",
+            ThemeName::Light => "light",
+            ThemeName::HighContrast => "high-contrast",
+            ThemeName::Solarized => "solarized",
+            ThemeName::Custom(name) => name,
+        }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl From<String> for ThemeName {
+    fn from(value: String) -> Self {
+        let mut normalized = String::with_capacity(value.len() + 2);
+        let mut prev_lower = false;
+        for ch in value.trim().chars() {
+            if ch == '_' || ch == ' ' {
+                normalized.push('-');
+            } else if ch.is_uppercase() {
+                // CamelCase spellings from older docs (`HighContrast`) become kebab-case.
+                if prev_lower {
+                    normalized.push('-');
+                }
+                normalized.extend(ch.to_lowercase());
+            } else {
+                normalized.push(ch);
+            }
+            prev_lower = ch.is_lowercase() || ch.is_ascii_digit();
+        }
+        match normalized.as_str() {
+            "dark" => ThemeName::Dark,
+            "light" => ThemeName::Light,
+            "high-contrast" => ThemeName::HighContrast,
+            "solarized" => ThemeName::Solarized,
+            _ => ThemeName::Custom(normalized),
+        }
+    }
+}
+
+impl From<ThemeName> for String {
+    fn from(value: ThemeName) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl fmt::Display for ThemeName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// `[keymap.<context>]` tables mapping key sequences (`"g g"`, `"ctrl-x ctrl-s"`)
+/// to action names (`"select-first"`); `"none"` removes a preset binding.
+pub type KeymapOverrides = BTreeMap<String, BTreeMap<String, String>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "kebab-case")]
 pub enum KeybindingProfile {
+    #[default]
     Vim,
     Emacs,
     Custom,
 }
 
-impl Default for KeybindingProfile {
-    fn default() -> Self {
-        KeybindingProfile::Vim
-    }
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct SortSpec {
     pub field: SortField,
@@ -313,29 +419,52 @@ impl Default for SortSpec {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl SortSpec {
+    /// The order the list's sort action steps through: each field newest (or A→Z)
+    /// first, then reversed, before moving on to the next field.
+    pub fn next(self) -> Self {
+        use SortDirection::{Ascending, Descending};
+        let (field, direction) = match (self.field, self.direction) {
+            (SortField::Updated, Descending) => (SortField::Updated, Ascending),
+            (SortField::Updated, Ascending) => (SortField::Created, Descending),
+            (SortField::Created, Descending) => (SortField::Created, Ascending),
+            (SortField::Created, Ascending) => (SortField::Title, Ascending),
+            (SortField::Title, Ascending) => (SortField::Title, Descending),
+            (SortField::Title, Descending) => (SortField::Updated, Descending),
+        };
+        Self { field, direction }
+    }
+
+    /// Short form for the status line, e.g. `updated ↓` or `title ↑`.
+    pub fn label(&self) -> String {
+        let field = match self.field {
+            SortField::Updated => "updated",
+            SortField::Created => "created",
+            SortField::Title => "title",
+        };
+        let arrow = match self.direction {
+            SortDirection::Ascending => "↑",
+            SortDirection::Descending => "↓",
+        };
+        format!("{field} {arrow}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "kebab-case")]
 pub enum SortField {
+    #[default]
     Updated,
     Created,
     Title,
 }
 
-impl Default for SortField {
-    fn default() -> Self {
-        SortField::Updated
-    }
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "kebab-case")]
 pub enum SortDirection {
+    #[serde(alias = "asc")]
     Ascending,
+    #[default]
+    #[serde(alias = "desc")]
     Descending,
 }
-
-impl Default for SortDirection {
-    fn default() -> Self {
-        SortDirection::Descending
-    }
-}
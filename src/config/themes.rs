@@ -1,32 +1,431 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use ratatui::style::{Color, Style};
+use serde::Deserialize;
 
 use super::ThemeName;
 
+/// Semantic colour slots used by the renderer. Widgets never pick raw colours;
+/// they ask for the slot that describes their role so every palette stays coherent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Default foreground for note text.
+    pub text: Color,
+    /// Background painted behind the main panes.
+    pub background: Color,
+    /// Border of the pane that currently has focus.
+    pub focus_border: Color,
+    pub selection_fg: Color,
+    pub selection_bg: Color,
+    /// Search matches inside titles, previews and tags.
+    pub highlight: Color,
+    pub pinned: Color,
+    pub archived: Color,
+    /// Edit-mode markers and the regex badge.
+    pub editing: Color,
+    pub tag: Color,
+    /// Secondary text such as timestamps and inline instructions.
+    pub muted: Color,
+    /// Key hints and other low-priority text.
+    pub hint: Color,
+    pub status_info: Color,
+    pub status_warning: Color,
+    pub status_error: Color,
+    pub status_success: Color,
+    pub overlay_bg: Color,
+    pub overlay_border: Color,
+    /// Markdown headings in the reader.
+    pub heading: Color,
+    /// Inline code spans and fenced code blocks.
+    pub code: Color,
+    pub link: Color,
+    /// Block quote bars and text.
+    pub quote: Color,
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            text: Color::Reset,
+            background: Color::Reset,
+            focus_border: Color::Cyan,
+            selection_fg: Color::Black,
+            selection_bg: Color::Blue,
+            highlight: Color::Yellow,
+            pinned: Color::Yellow,
+            archived: Color::Gray,
+            editing: Color::Magenta,
+            tag: Color::Green,
+            muted: Color::Gray,
+            hint: Color::DarkGray,
+            status_info: Color::Cyan,
+            status_warning: Color::Yellow,
+            status_error: Color::Red,
+            status_success: Color::Green,
+            overlay_bg: Color::Reset,
+            overlay_border: Color::Magenta,
+            heading: Color::LightCyan,
+            code: Color::LightRed,
+            link: Color::LightBlue,
+            quote: Color::Gray,
+        }
+    }
+
+    pub fn light() -> Self {
+        let amber = Color::Rgb(0xa0, 0x62, 0x00);
+        Self {
+            text: Color::Black,
+            background: Color::White,
+            focus_border: Color::Blue,
+            selection_fg: Color::Black,
+            selection_bg: Color::LightCyan,
+            highlight: Color::Magenta,
+            pinned: amber,
+            archived: Color::DarkGray,
+            editing: Color::Magenta,
+            tag: Color::Rgb(0x1f, 0x7a, 0x1f),
+            muted: Color::DarkGray,
+            hint: Color::Gray,
+            status_info: Color::Blue,
+            status_warning: amber,
+            status_error: Color::Red,
+            status_success: Color::Rgb(0x1f, 0x7a, 0x1f),
+            overlay_bg: Color::Rgb(0xee, 0xee, 0xee),
+            overlay_border: Color::Blue,
+            heading: Color::Blue,
+            code: Color::Rgb(0xa0, 0x30, 0x30),
+            link: Color::Rgb(0x00, 0x55, 0xaa),
+            quote: Color::DarkGray,
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            text: Color::White,
+            background: Color::Black,
+            focus_border: Color::LightYellow,
+            selection_fg: Color::Black,
+            selection_bg: Color::White,
+            highlight: Color::LightCyan,
+            pinned: Color::LightYellow,
+            archived: Color::White,
+            editing: Color::LightMagenta,
+            tag: Color::LightGreen,
+            muted: Color::White,
+            hint: Color::White,
+            status_info: Color::LightCyan,
+            status_warning: Color::LightYellow,
+            status_error: Color::LightRed,
+            status_success: Color::LightGreen,
+            overlay_bg: Color::Black,
+            overlay_border: Color::White,
+            heading: Color::LightYellow,
+            code: Color::LightMagenta,
+            link: Color::LightBlue,
+            quote: Color::White,
+        }
+    }
+
+    pub fn solarized() -> Self {
+        // Solarized dark: https://ethanschoonover.com/solarized/
+        let base03 = Color::Rgb(0x00, 0x2b, 0x36);
+        let base02 = Color::Rgb(0x07, 0x36, 0x42);
+        let base01 = Color::Rgb(0x58, 0x6e, 0x75);
+        let base00 = Color::Rgb(0x65, 0x7b, 0x83);
+        let base0 = Color::Rgb(0x83, 0x94, 0x96);
+        let base1 = Color::Rgb(0x93, 0xa1, 0xa1);
+        let yellow = Color::Rgb(0xb5, 0x89, 0x00);
+        let orange = Color::Rgb(0xcb, 0x4b, 0x16);
+        let red = Color::Rgb(0xdc, 0x32, 0x2f);
+        let magenta = Color::Rgb(0xd3, 0x36, 0x82);
+        let violet = Color::Rgb(0x6c, 0x71, 0xc4);
+        let blue = Color::Rgb(0x26, 0x8b, 0xd2);
+        let cyan = Color::Rgb(0x2a, 0xa1, 0x98);
+        let green = Color::Rgb(0x85, 0x99, 0x00);
+        Self {
+            text: base0,
+            background: base03,
+            focus_border: blue,
+            selection_fg: base1,
+            selection_bg: base02,
+            highlight: yellow,
+            pinned: yellow,
+            archived: base01,
+            editing: magenta,
+            tag: green,
+            muted: base00,
+            hint: base01,
+            status_info: cyan,
+            status_warning: orange,
+            status_error: red,
+            status_success: green,
+            overlay_bg: base03,
+            overlay_border: violet,
+            heading: blue,
+            code: cyan,
+            link: violet,
+            quote: base01,
+        }
+    }
+
+    /// Style for the main panes: theme text on the theme background.
+    pub fn base_style(&self) -> Style {
+        Style::default().fg(self.text).bg(self.background)
+    }
+
+    /// Style for popups drawn on top of the main panes.
+    pub fn overlay_style(&self) -> Style {
+        Style::default().fg(self.text).bg(self.overlay_bg)
+    }
+
+    /// Overrides one slot by name; slot names match the field names, with `-` accepted for `_`.
+    pub fn set_slot(&mut self, slot: &str, value: &str) -> Result<()> {
+        let color = Color::from_str(value.trim())
+            .map_err(|_| anyhow!("invalid colour '{value}' for slot '{slot}'"))?;
+        let target = match slot.replace('-', "_").as_str() {
+            "text" => &mut self.text,
+            "background" => &mut self.background,
+            "focus_border" => &mut self.focus_border,
+            "selection_fg" => &mut self.selection_fg,
+            "selection_bg" => &mut self.selection_bg,
+            "highlight" => &mut self.highlight,
+            "pinned" => &mut self.pinned,
+            "archived" => &mut self.archived,
+            "editing" => &mut self.editing,
+            "tag" => &mut self.tag,
+            "muted" => &mut self.muted,
+            "hint" => &mut self.hint,
+            "status_info" => &mut self.status_info,
+            "status_warning" => &mut self.status_warning,
+            "status_error" => &mut self.status_error,
+            "status_success" => &mut self.status_success,
+            "overlay_bg" => &mut self.overlay_bg,
+            "overlay_border" => &mut self.overlay_border,
+            "heading" => &mut self.heading,
+            "code" => &mut self.code,
+            "link" => &mut self.link,
+            "quote" => &mut self.quote,
+            other => bail!("unknown theme slot '{other}'"),
+        };
+        *target = color;
+        Ok(())
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+/// On-disk format for user themes (`<config_dir>/themes/*.toml`).
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    /// Defaults to the file stem.
+    name: Option<String>,
+    /// Palette to start from before applying `colors`; defaults to `dark`.
+    #[serde(default)]
+    extends: ThemeName,
+    #[serde(default)]
+    colors: BTreeMap<String, String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ThemeRegistry {
-    names: HashSet<ThemeName>,
+    themes: HashMap<ThemeName, Theme>,
 }
 
 impl ThemeRegistry {
+    /// Built-in palettes plus every theme file found in `theme_dir`. A broken file is
+    /// logged and skipped so a typo never prevents the app from starting.
+    pub fn load(theme_dir: &Path) -> Self {
+        let mut registry = Self::default();
+        if let Err(err) = registry.load_dir(theme_dir) {
+            tracing::warn!(?err, dir = %theme_dir.display(), "failed to read theme directory");
+        }
+        registry
+    }
+
     pub fn contains(&self, theme: &ThemeName) -> bool {
-        self.names.contains(theme)
+        self.themes.contains_key(theme)
     }
 
     pub fn all(&self) -> impl Iterator<Item = &ThemeName> {
-        self.names.iter()
+        self.themes.keys()
+    }
+
+    pub fn get(&self, theme: &ThemeName) -> Option<&Theme> {
+        self.themes.get(theme)
+    }
+
+    /// Palette for `theme`, falling back to the dark palette for unknown names.
+    pub fn resolve(&self, theme: &ThemeName) -> Theme {
+        self.get(theme).cloned().unwrap_or_default()
+    }
+
+    fn load_dir(&mut self, dir: &Path) -> Result<()> {
+        if !dir.exists() {
+            return Ok(());
+        }
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir).with_context(|| format!("listing {}", dir.display()))? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some("toml") {
+                paths.push(path);
+            }
+        }
+        // Sorted so a theme can extend another user theme that sorts before it.
+        paths.sort();
+        for path in paths {
+            if let Err(err) = self.load_file(&path) {
+                tracing::warn!(?err, path = %path.display(), "skipping invalid theme file");
+            }
+        }
+        Ok(())
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<ThemeName> {
+        let raw =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let file: ThemeFile = toml::from_str(&raw).context("parsing theme toml")?;
+        let name = file
+            .name
+            .or_else(|| {
+                path.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .map(str::to_string)
+            })
+            .map(ThemeName::from)
+            .context("theme file has no usable name")?;
+        let mut theme = self
+            .get(&file.extends)
+            .cloned()
+            .with_context(|| format!("theme extends unknown theme '{}'", file.extends))?;
+        for (slot, value) in &file.colors {
+            theme.set_slot(slot, value)?;
+        }
+        self.themes.insert(name.clone(), theme);
+        Ok(name)
     }
 }
 
 impl Default for ThemeRegistry {
     fn default() -> Self {
-        let names = [
+        let themes = [
+            (ThemeName::Dark, Theme::dark()),
+            (ThemeName::Light, Theme::light()),
+            (ThemeName::HighContrast, Theme::high_contrast()),
+            (ThemeName::Solarized, Theme::solarized()),
+        ]
+        .into_iter()
+        .collect();
+        Self { themes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn builtin_palettes_are_registered_and_distinct() {
+        let registry = ThemeRegistry::default();
+        for name in [
             ThemeName::Dark,
             ThemeName::Light,
             ThemeName::HighContrast,
             ThemeName::Solarized,
-        ]
-        .into_iter()
-        .collect();
-        Self { names }
+        ] {
+            assert!(registry.contains(&name), "missing {name}");
+        }
+        assert_ne!(
+            registry.resolve(&ThemeName::Dark),
+            registry.resolve(&ThemeName::Light)
+        );
+        assert_ne!(
+            registry.resolve(&ThemeName::HighContrast),
+            registry.resolve(&ThemeName::Solarized)
+        );
+        assert_eq!(
+            registry.resolve(&ThemeName::from("missing".to_string())),
+            Theme::dark()
+        );
+    }
+
+    #[test]
+    fn theme_names_accept_config_spellings() {
+        assert_eq!(
+            ThemeName::from("HighContrast".to_string()),
+            ThemeName::HighContrast
+        );
+        assert_eq!(
+            ThemeName::from("high_contrast".to_string()),
+            ThemeName::HighContrast
+        );
+        assert_eq!(
+            ThemeName::from("Solarized".to_string()),
+            ThemeName::Solarized
+        );
+        assert_eq!(
+            ThemeName::from("Nord Frost".to_string()),
+            ThemeName::Custom("nord-frost".into())
+        );
+        assert_eq!(String::from(ThemeName::HighContrast), "high-contrast");
+    }
+
+    #[test]
+    fn user_theme_files_extend_builtins() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(
+            dir.path().join("nord.toml"),
+            r##"
+extends = "light"
+
+[colors]
+focus-border = "#88c0d0"
+highlight = "light-yellow"
+"##,
+        )?;
+        std::fs::write(
+            dir.path().join("zz-night.toml"),
+            r#"
+name = "Midnight"
+extends = "nord"
+
+[colors]
+background = "16"
+"#,
+        )?;
+        std::fs::write(
+            dir.path().join("broken.toml"),
+            "[colors]\nfocus_border = \"not-a-colour\"\n",
+        )?;
+        std::fs::write(dir.path().join("typo.toml"), "[colors]\nborder = \"red\"\n")?;
+        std::fs::write(dir.path().join("notes.txt"), "ignored")?;
+
+        let registry = ThemeRegistry::load(dir.path());
+        let nord = registry
+            .get(&ThemeName::Custom("nord".into()))
+            .expect("nord theme loaded");
+        assert_eq!(nord.focus_border, Color::Rgb(0x88, 0xc0, 0xd0));
+        assert_eq!(nord.highlight, Color::LightYellow);
+        assert_eq!(nord.background, Theme::light().background);
+
+        let midnight = registry
+            .get(&ThemeName::Custom("midnight".into()))
+            .expect("midnight theme loaded");
+        assert_eq!(midnight.background, Color::Indexed(16));
+        assert_eq!(midnight.focus_border, nord.focus_border);
+
+        assert!(!registry.contains(&ThemeName::Custom("broken".into())));
+        assert!(!registry.contains(&ThemeName::Custom("typo".into())));
+        assert!(registry.contains(&ThemeName::Dark));
+        Ok(())
     }
 }
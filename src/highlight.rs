@@ -19,7 +19,7 @@ pub fn build_highlight_regex(tokens: &[String]) -> Option<Regex> {
     if unique.is_empty() {
         return None;
     }
-    unique.sort_by(|a, b| b.len().cmp(&a.len()));
+    unique.sort_by_key(|token| std::cmp::Reverse(token.len()));
     let pattern = unique
         .into_iter()
         .map(|token| regex::escape(&token))
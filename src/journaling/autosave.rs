@@ -560,6 +560,7 @@ mod tests {
         ConfigPaths {
             config_dir: config_dir.clone(),
             config_file: config_dir.join("config.toml"),
+            theme_dir: config_dir.join("themes"),
             data_dir: data_dir.clone(),
             database_path: data_dir.join("notes.db"),
             cache_dir,
@@ -570,10 +571,11 @@ mod tests {
     }
 
     fn storage_options(paths: &ConfigPaths) -> StorageOptions {
-        let mut options = StorageOptions::default();
-        options.database_path = paths.database_path.clone();
-        options.backup_dir = paths.backup_dir.clone();
-        options
+        StorageOptions {
+            database_path: paths.database_path.clone(),
+            backup_dir: paths.backup_dir.clone(),
+            ..StorageOptions::default()
+        }
     }
 
     #[test]
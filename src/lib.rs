@@ -3,8 +3,10 @@ pub mod cli;
 pub mod config;
 pub mod highlight;
 pub mod journaling;
+pub mod markdown;
 pub mod search;
 pub mod storage;
+pub mod transfer;
 pub mod ui;
 
 pub use config::{AppConfig, ConfigLoader, ConfigPaths};
@@ -0,0 +1,230 @@
+use std::fmt;
+use std::ops::Range;
+
+use once_cell::sync::Lazy;
+use ratatui::style::Style;
+use ratatui::text::Line;
+use regex::Regex;
+
+use super::renderer::patch_spans;
+
+/// `[[Note Title]]` or `[[#42]]`. The target stays on one line and cannot
+/// contain brackets.
+static WIKI_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\[\[([^\[\]\n]+)\]\]").expect("valid wiki link pattern"));
+
+/// What a wiki link points at: a note title (matched ignoring ASCII case, like
+/// SQLite's `NOCASE`) or a note id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    Title(String),
+    Id(i64),
+}
+
+impl fmt::Display for LinkTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkTarget::Title(title) => write!(f, "[[{title}]]"),
+            LinkTarget::Id(id) => write!(f, "[[#{id}]]"),
+        }
+    }
+}
+
+/// A wiki link found in note text; `range` covers the brackets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WikiLink {
+    pub target: LinkTarget,
+    pub range: Range<usize>,
+}
+
+/// Wiki links in `text`, in order. Surrounding spaces inside the brackets are
+/// ignored and `[[ ]]` is not a link.
+pub fn parse_links(text: &str) -> Vec<WikiLink> {
+    WIKI_LINK
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let whole = caps.get(0)?;
+            let target = parse_target(caps[1].trim())?;
+            Some(WikiLink {
+                target,
+                range: whole.range(),
+            })
+        })
+        .collect()
+}
+
+fn parse_target(inner: &str) -> Option<LinkTarget> {
+    if inner.is_empty() {
+        return None;
+    }
+    if let Some(digits) = inner.strip_prefix('#') {
+        if !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            if let Ok(id) = digits.parse() {
+                return Some(LinkTarget::Id(id));
+            }
+        }
+    }
+    Some(LinkTarget::Title(inner.to_string()))
+}
+
+/// Rewrites every `[[old]]` title link in `text` to `[[new]]`. Returns `None`
+/// when no link matched, so callers can skip untouched notes.
+pub fn rewrite_title_links(text: &str, old: &str, new: &str) -> Option<String> {
+    let mut rewritten = String::with_capacity(text.len());
+    let mut copied = 0;
+    for link in parse_links(text) {
+        let LinkTarget::Title(title) = &link.target else {
+            continue;
+        };
+        if !title.eq_ignore_ascii_case(old) {
+            continue;
+        }
+        rewritten.push_str(&text[copied..link.range.start]);
+        rewritten.push_str(&format!("[[{new}]]"));
+        copied = link.range.end;
+    }
+    if copied == 0 {
+        return None;
+    }
+    rewritten.push_str(&text[copied..]);
+    Some(rewritten)
+}
+
+/// Rewrites every `[[#id]]` link in `text` to the id `remap` returns for it,
+/// leaving links it returns `None` for alone. Returns `None` when nothing
+/// changed.
+pub fn rewrite_id_links(text: &str, remap: impl Fn(i64) -> Option<i64>) -> Option<String> {
+    let mut rewritten = String::with_capacity(text.len());
+    let mut copied = 0;
+    for link in parse_links(text) {
+        let LinkTarget::Id(id) = link.target else {
+            continue;
+        };
+        let Some(new_id) = remap(id).filter(|new_id| *new_id != id) else {
+            continue;
+        };
+        rewritten.push_str(&text[copied..link.range.start]);
+        rewritten.push_str(&LinkTarget::Id(new_id).to_string());
+        copied = link.range.end;
+    }
+    if copied == 0 {
+        return None;
+    }
+    rewritten.push_str(&text[copied..]);
+    Some(rewritten)
+}
+
+/// Patches `style` onto the wiki links in rendered reader lines. When
+/// `selected` is set, its style also goes on that link, counted from the top,
+/// so the reader shows which link Enter follows.
+pub fn style_wiki_links(
+    lines: Vec<Line<'static>>,
+    style: Style,
+    selected: Option<(usize, Style)>,
+) -> Vec<Line<'static>> {
+    let mut seen = 0;
+    lines
+        .into_iter()
+        .map(|line| {
+            let text: String = line
+                .spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect();
+            let matches: Vec<(usize, usize, Style)> = parse_links(&text)
+                .into_iter()
+                .map(|link| {
+                    let patch = match selected {
+                        Some((index, selected_style)) if index == seen => {
+                            style.patch(selected_style)
+                        }
+                        _ => style,
+                    };
+                    seen += 1;
+                    (link.range.start, link.range.end, patch)
+                })
+                .collect();
+            patch_spans(line, &text, &matches)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::Modifier;
+
+    #[test]
+    fn parses_title_and_id_links() {
+        let links =
+            parse_links("See [[ Inbox ]], [[#42]] and [[#4x]]; not [[]] or [[ ]] or [[a\nb]].");
+        let targets: Vec<_> = links.iter().map(|link| link.target.clone()).collect();
+        assert_eq!(
+            targets,
+            vec![
+                LinkTarget::Title("Inbox".into()),
+                LinkTarget::Id(42),
+                LinkTarget::Title("#4x".into()),
+            ]
+        );
+        assert_eq!(links[0].range, 4..15);
+        assert_eq!(LinkTarget::Id(42).to_string(), "[[#42]]");
+    }
+
+    #[test]
+    fn rewrites_only_matching_title_and_id_links() {
+        let text = "[[Old]] and [[ old ]], not [[Older]] or [[#1]].";
+        assert_eq!(
+            rewrite_title_links(text, "OLD", "New").as_deref(),
+            Some("[[New]] and [[New]], not [[Older]] or [[#1]].")
+        );
+        assert_eq!(rewrite_title_links(text, "Missing", "New"), None);
+
+        let text = "[[#1]], [[ #2 ]], [[#3]] and [[1]].";
+        let remap = |id| match id {
+            1 => Some(10),
+            2 => Some(20),
+            _ => None,
+        };
+        assert_eq!(
+            rewrite_id_links(text, remap).as_deref(),
+            Some("[[#10]], [[#20]], [[#3]] and [[1]].")
+        );
+        assert_eq!(rewrite_id_links("[[#3]]", remap), None);
+    }
+
+    #[test]
+    fn styles_the_selected_link() {
+        let lines = vec![
+            Line::from("a [[One]] b"),
+            Line::from(vec!["[[Tw".into(), "o]] [[#3]]".into()]),
+        ];
+        let link = Style::default().add_modifier(Modifier::UNDERLINED);
+        let selected = Style::default().add_modifier(Modifier::REVERSED);
+        let styled = style_wiki_links(lines, link, Some((1, selected)));
+        let pieces: Vec<(String, bool)> = styled[1]
+            .spans
+            .iter()
+            .map(|span| {
+                (
+                    span.content.to_string(),
+                    span.style.add_modifier.contains(Modifier::REVERSED),
+                )
+            })
+            .collect();
+        assert_eq!(
+            pieces,
+            vec![
+                ("[[Tw".to_string(), true),
+                ("o]]".to_string(), true),
+                (" ".to_string(), false),
+                ("[[#3]]".to_string(), false),
+            ]
+        );
+        assert_eq!(styled[0].spans[1].content, "[[One]]");
+        assert!(styled[0].spans[1]
+            .style
+            .add_modifier
+            .contains(Modifier::UNDERLINED));
+    }
+}
@@ -0,0 +1,7 @@
+mod links;
+mod renderer;
+
+pub use links::{
+    parse_links, rewrite_id_links, rewrite_title_links, style_wiki_links, LinkTarget, WikiLink,
+};
+pub use renderer::{highlight_spans, render_markdown};
@@ -0,0 +1,643 @@
+use pulldown_cmark::{Alignment, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use regex::Regex;
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::themes::Theme;
+
+const RULE_WIDTH: usize = 40;
+const CODE_INDENT: &str = "  ";
+
+/// Renders a Markdown note body into styled lines for the reader pane. Colours
+/// come from the theme's `heading`, `code`, `link`, `quote` and `muted` slots;
+/// search matches from `highlight` are patched on top of the Markdown styling so
+/// a match inside bold text stays bold.
+pub fn render_markdown(
+    source: &str,
+    theme: &Theme,
+    highlight: Option<&Regex>,
+    highlight_style: Style,
+) -> Vec<Line<'static>> {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS | Options::ENABLE_STRIKETHROUGH;
+    let mut renderer = Renderer::new(theme);
+    for event in Parser::new_ext(source, options) {
+        renderer.event(event);
+    }
+    let lines = renderer.finish();
+    match highlight {
+        Some(regex) => lines
+            .into_iter()
+            .map(|line| highlight_spans(line, regex, highlight_style))
+            .collect(),
+        None => lines,
+    }
+}
+
+/// Splits the spans of `line` around every match of `regex` (matched across span
+/// boundaries) and patches `highlight_style` onto the matched pieces.
+pub fn highlight_spans(
+    line: Line<'static>,
+    regex: &Regex,
+    highlight_style: Style,
+) -> Line<'static> {
+    let text: String = line
+        .spans
+        .iter()
+        .map(|span| span.content.as_ref())
+        .collect();
+    let matches: Vec<(usize, usize, Style)> = regex
+        .find_iter(&text)
+        .filter(|mat| !mat.is_empty())
+        .map(|mat| (mat.start(), mat.end(), highlight_style))
+        .collect();
+    patch_spans(line, &text, &matches)
+}
+
+/// Splits the spans of `line` (whose concatenated content is `text`) at the
+/// byte ranges in `matches`, which must be sorted and disjoint, and patches
+/// each range's style onto the pieces inside it.
+pub(super) fn patch_spans(
+    line: Line<'static>,
+    text: &str,
+    matches: &[(usize, usize, Style)],
+) -> Line<'static> {
+    if matches.is_empty() {
+        return line;
+    }
+
+    let Line {
+        spans: original,
+        style,
+        alignment,
+    } = line;
+    let mut spans = Vec::with_capacity(original.len() + matches.len() * 2);
+    let mut offset = 0;
+    for span in original {
+        let end = offset + span.content.len();
+        let mut cursor = offset;
+        for &(start, stop, patch) in matches {
+            if stop <= cursor || start >= end {
+                continue;
+            }
+            let from = start.max(cursor);
+            let to = stop.min(end);
+            if from > cursor {
+                spans.push(Span::styled(text[cursor..from].to_string(), span.style));
+            }
+            spans.push(Span::styled(
+                text[from..to].to_string(),
+                span.style.patch(patch),
+            ));
+            cursor = to;
+        }
+        if cursor < end {
+            spans.push(Span::styled(text[cursor..end].to_string(), span.style));
+        }
+        offset = end;
+    }
+    Line {
+        spans,
+        style,
+        alignment,
+    }
+}
+
+#[derive(Default)]
+struct Table {
+    alignments: Vec<Alignment>,
+    rows: Vec<Vec<Vec<Span<'static>>>>,
+    header_rows: usize,
+}
+
+struct Renderer<'t> {
+    theme: &'t Theme,
+    lines: Vec<Line<'static>>,
+    spans: Vec<Span<'static>>,
+    styles: Vec<Style>,
+    /// Open lists; ordered lists carry the number of their next item.
+    lists: Vec<Option<u64>>,
+    /// Width of each open item's marker, used to indent continuation lines.
+    indents: Vec<usize>,
+    /// Marker for the first line of the current list item, emitted on the next flush.
+    marker: Option<Span<'static>>,
+    quote_depth: usize,
+    in_code_block: bool,
+    /// Destination and visible text of each open link.
+    links: Vec<(String, String)>,
+    table: Option<Table>,
+    last_blank: bool,
+}
+
+impl<'t> Renderer<'t> {
+    fn new(theme: &'t Theme) -> Self {
+        Self {
+            theme,
+            lines: Vec::new(),
+            spans: Vec::new(),
+            styles: Vec::new(),
+            lists: Vec::new(),
+            indents: Vec::new(),
+            marker: None,
+            quote_depth: 0,
+            in_code_block: false,
+            links: Vec::new(),
+            table: None,
+            last_blank: false,
+        }
+    }
+
+    fn finish(mut self) -> Vec<Line<'static>> {
+        self.flush();
+        if self.lines.is_empty() {
+            self.lines.push(Line::from(""));
+        }
+        self.lines
+    }
+
+    fn event(&mut self, event: Event<'_>) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => self.text(&text),
+            Event::Code(code) => {
+                let style = self.style().fg(self.theme.code);
+                self.push_text(&code, style);
+            }
+            Event::InlineMath(math) | Event::DisplayMath(math) => {
+                let style = self.style().fg(self.theme.code);
+                self.push_text(&math, style);
+            }
+            Event::Html(html) | Event::InlineHtml(html) => {
+                let style = self.muted();
+                for (idx, line) in html.lines().enumerate() {
+                    if idx > 0 {
+                        self.flush();
+                    }
+                    self.push_text(line, style);
+                }
+            }
+            Event::FootnoteReference(name) => {
+                let style = self.muted();
+                self.push_text(&format!("[^{name}]"), style);
+            }
+            Event::SoftBreak => {
+                let style = self.style();
+                self.push_text(" ", style);
+            }
+            Event::HardBreak => self.flush(),
+            Event::Rule => {
+                self.flush();
+                self.gap();
+                let style = self.muted();
+                self.push_text(&"─".repeat(RULE_WIDTH), style);
+                self.flush();
+            }
+            Event::TaskListMarker(checked) => {
+                let (label, color) = if checked {
+                    ("[x] ", self.theme.status_success)
+                } else {
+                    ("[ ] ", self.theme.muted)
+                };
+                if let Some(indent) = self.indents.last_mut() {
+                    *indent = label.width();
+                }
+                self.marker = Some(Span::styled(label, Style::default().fg(color)));
+            }
+        }
+    }
+
+    fn start(&mut self, tag: Tag<'_>) {
+        match tag {
+            Tag::Paragraph => {
+                if self.table.is_none() {
+                    self.gap();
+                }
+            }
+            Tag::Heading { level, .. } => {
+                self.flush();
+                self.gap();
+                let mut style = Style::default()
+                    .fg(self.theme.heading)
+                    .add_modifier(Modifier::BOLD);
+                if level == HeadingLevel::H1 {
+                    style = style.add_modifier(Modifier::UNDERLINED);
+                }
+                self.styles.push(style);
+            }
+            Tag::BlockQuote(_) => {
+                self.flush();
+                self.gap();
+                self.quote_depth += 1;
+                let style = self.style().fg(self.theme.quote);
+                self.styles.push(style);
+            }
+            Tag::CodeBlock(_) => {
+                self.flush();
+                self.gap();
+                self.in_code_block = true;
+            }
+            Tag::HtmlBlock | Tag::FootnoteDefinition(_) | Tag::MetadataBlock(_) => {
+                self.flush();
+                self.gap();
+            }
+            Tag::List(start) => {
+                self.flush();
+                if self.lists.is_empty() {
+                    self.gap();
+                }
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush();
+                let nested = self.lists.len() > 1;
+                let label = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        let label = format!("{number}. ");
+                        *number += 1;
+                        label
+                    }
+                    _ if nested => "◦ ".to_string(),
+                    _ => "• ".to_string(),
+                };
+                self.indents.push(label.width());
+                self.marker = Some(Span::styled(label, self.muted()));
+            }
+            Tag::Table(alignments) => {
+                self.flush();
+                self.gap();
+                self.table = Some(Table {
+                    alignments,
+                    ..Table::default()
+                });
+            }
+            Tag::TableHead | Tag::TableRow => {
+                if let Some(table) = self.table.as_mut() {
+                    table.rows.push(Vec::new());
+                }
+            }
+            Tag::TableCell => {
+                if let Some(row) = self.table.as_mut().and_then(|table| table.rows.last_mut()) {
+                    row.push(Vec::new());
+                }
+            }
+            Tag::Emphasis => self.push_modifier(Modifier::ITALIC),
+            Tag::Strong => self.push_modifier(Modifier::BOLD),
+            Tag::Strikethrough => self.push_modifier(Modifier::CROSSED_OUT),
+            Tag::Link { dest_url, .. } => {
+                self.links.push((dest_url.to_string(), String::new()));
+                let style = self
+                    .style()
+                    .fg(self.theme.link)
+                    .add_modifier(Modifier::UNDERLINED);
+                self.styles.push(style);
+            }
+            Tag::Image { .. } => {
+                let style = self.muted();
+                self.push_text("[image: ", style);
+                self.styles.push(style);
+            }
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph => {
+                if self.table.is_none() {
+                    self.flush();
+                }
+            }
+            TagEnd::Heading(_) => {
+                self.styles.pop();
+                self.flush();
+            }
+            TagEnd::BlockQuote => {
+                self.flush();
+                self.styles.pop();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+            }
+            TagEnd::CodeBlock => {
+                self.in_code_block = false;
+            }
+            TagEnd::HtmlBlock | TagEnd::FootnoteDefinition | TagEnd::MetadataBlock(_) => {
+                self.flush();
+            }
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+            }
+            TagEnd::Item => {
+                self.flush();
+                self.indents.pop();
+                self.marker = None;
+            }
+            TagEnd::TableHead => {
+                if let Some(table) = self.table.as_mut() {
+                    table.header_rows = table.rows.len();
+                }
+            }
+            TagEnd::Table => {
+                if let Some(table) = self.table.take() {
+                    self.render_table(table);
+                }
+            }
+            TagEnd::TableRow | TagEnd::TableCell => {}
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => {
+                self.styles.pop();
+            }
+            TagEnd::Link => {
+                self.styles.pop();
+                if let Some((dest, text)) = self.links.pop() {
+                    if !dest.is_empty() && dest != text {
+                        let style = self.muted();
+                        self.push_text(&format!(" <{dest}>"), style);
+                    }
+                }
+            }
+            TagEnd::Image => {
+                self.styles.pop();
+                let style = self.muted();
+                self.push_text("]", style);
+            }
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.in_code_block {
+            let style = Style::default().fg(self.theme.code);
+            for line in text.lines() {
+                self.spans.push(Span::raw(CODE_INDENT));
+                self.spans.push(Span::styled(line.to_string(), style));
+                self.flush();
+            }
+            return;
+        }
+        let style = self.style();
+        self.push_text(text, style);
+    }
+
+    fn push_text(&mut self, text: &str, style: Style) {
+        if let Some((_, visible)) = self.links.last_mut() {
+            visible.push_str(text);
+        }
+        let span = Span::styled(text.to_string(), style);
+        match self
+            .table
+            .as_mut()
+            .and_then(|table| table.rows.last_mut())
+            .and_then(|row| row.last_mut())
+        {
+            Some(cell) => cell.push(span),
+            None => self.spans.push(span),
+        }
+    }
+
+    fn style(&self) -> Style {
+        self.styles.last().copied().unwrap_or_default()
+    }
+
+    fn muted(&self) -> Style {
+        Style::default().fg(self.theme.muted)
+    }
+
+    fn push_modifier(&mut self, modifier: Modifier) {
+        let style = self.style().add_modifier(modifier);
+        self.styles.push(style);
+    }
+
+    /// Quote bars plus list indentation (or the pending item marker) for a new line.
+    fn prefix(&mut self) -> Vec<Span<'static>> {
+        let mut spans = Vec::new();
+        if self.quote_depth > 0 {
+            spans.push(Span::styled(
+                "│ ".repeat(self.quote_depth),
+                Style::default().fg(self.theme.quote),
+            ));
+        }
+        let indent = match self.marker {
+            Some(_) => self.indents[..self.indents.len().saturating_sub(1)]
+                .iter()
+                .sum(),
+            None => self.indents.iter().sum(),
+        };
+        if indent > 0 {
+            spans.push(Span::raw(" ".repeat(indent)));
+        }
+        if let Some(marker) = self.marker.take() {
+            spans.push(marker);
+        }
+        spans
+    }
+
+    fn flush(&mut self) {
+        if self.spans.is_empty() && self.marker.is_none() {
+            return;
+        }
+        let mut spans = self.prefix();
+        spans.append(&mut self.spans);
+        self.lines.push(Line::from(spans));
+        self.last_blank = false;
+    }
+
+    /// Separates blocks with one blank line, except at the top of the note and
+    /// directly after a list marker.
+    fn gap(&mut self) {
+        if self.lines.is_empty() || self.last_blank || self.marker.is_some() {
+            return;
+        }
+        let line = if self.quote_depth > 0 {
+            Line::from(Span::styled(
+                "│".to_string() + &" │".repeat(self.quote_depth - 1),
+                Style::default().fg(self.theme.quote),
+            ))
+        } else {
+            Line::from("")
+        };
+        self.lines.push(line);
+        self.last_blank = true;
+    }
+
+    fn render_table(&mut self, table: Table) {
+        let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0);
+        let cell_width =
+            |cell: &[Span<'static>]| cell.iter().map(|span| span.content.width()).sum::<usize>();
+        let widths: Vec<usize> = (0..columns)
+            .map(|col| {
+                table
+                    .rows
+                    .iter()
+                    .filter_map(|row| row.get(col))
+                    .map(|cell| cell_width(cell))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let border = self.muted();
+
+        for (row_idx, row) in table.rows.into_iter().enumerate() {
+            let header = row_idx < table.header_rows;
+            let mut cells = row.into_iter();
+            for (col, width) in widths.iter().enumerate() {
+                if col > 0 {
+                    self.spans.push(Span::styled(" │ ", border));
+                }
+                let cell = cells.next().unwrap_or_default();
+                let padding = width.saturating_sub(cell_width(&cell));
+                let (left, right) = match table.alignments.get(col) {
+                    Some(Alignment::Right) => (padding, 0),
+                    Some(Alignment::Center) => (padding / 2, padding - padding / 2),
+                    _ => (0, padding),
+                };
+                if left > 0 {
+                    self.spans.push(Span::raw(" ".repeat(left)));
+                }
+                for span in cell {
+                    let style = if header {
+                        span.style.add_modifier(Modifier::BOLD)
+                    } else {
+                        span.style
+                    };
+                    self.spans.push(Span::styled(span.content, style));
+                }
+                if right > 0 {
+                    self.spans.push(Span::raw(" ".repeat(right)));
+                }
+            }
+            self.flush();
+            if header && row_idx + 1 == table.header_rows {
+                let separator = widths
+                    .iter()
+                    .map(|width| "─".repeat(*width))
+                    .collect::<Vec<_>>()
+                    .join("─┼─");
+                self.spans.push(Span::styled(separator, border));
+                self.flush();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::highlight::build_highlight_regex;
+
+    fn plain(lines: &[Line<'static>]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    fn span_with<'a>(lines: &'a [Line<'static>], text: &str) -> &'a Span<'static> {
+        lines
+            .iter()
+            .flat_map(|line| line.spans.iter())
+            .find(|span| span.content == text)
+            .unwrap_or_else(|| panic!("no span `{text}`"))
+    }
+
+    #[test]
+    fn renders_block_structure() {
+        let theme = Theme::dark();
+        let source = "# Title\n\nSome *soft*\ntext.\n\n- one\n- two\n  - nested\n\n1. first\n2. second\n\n- [x] done\n- [ ] todo\n\n> quoted\n> more\n\n```rust\nfn main() {}\n```\n\n---\n";
+        let lines = render_markdown(source, &theme, None, Style::default());
+        assert_eq!(
+            plain(&lines),
+            vec![
+                "Title",
+                "",
+                "Some soft text.",
+                "",
+                "• one",
+                "• two",
+                "  ◦ nested",
+                "",
+                "1. first",
+                "2. second",
+                "",
+                "[x] done",
+                "[ ] todo",
+                "",
+                "│ quoted more",
+                "",
+                "  fn main() {}",
+                "",
+                "─".repeat(RULE_WIDTH).as_str(),
+            ]
+        );
+
+        let heading = span_with(&lines, "Title");
+        assert_eq!(heading.style.fg, Some(theme.heading));
+        assert!(heading.style.add_modifier.contains(Modifier::UNDERLINED));
+        assert!(span_with(&lines, "soft")
+            .style
+            .add_modifier
+            .contains(Modifier::ITALIC));
+        assert_eq!(
+            span_with(&lines, "[x] ").style.fg,
+            Some(theme.status_success)
+        );
+        assert_eq!(span_with(&lines, "quoted").style.fg, Some(theme.quote));
+        assert_eq!(span_with(&lines, "fn main() {}").style.fg, Some(theme.code));
+    }
+
+    #[test]
+    fn renders_inline_code_links_and_tables() {
+        let theme = Theme::light();
+        let source = "Run `cargo test` via [docs](https://example.com) or <https://x.io>.\n\n| Key | Action |\n|:---:|-------:|\n| `q` | quit |\n| Tab | focus |\n";
+        let lines = render_markdown(source, &theme, None, Style::default());
+        assert_eq!(
+            plain(&lines),
+            vec![
+                "Run cargo test via docs <https://example.com> or https://x.io.",
+                "",
+                "Key │ Action",
+                "────┼───────",
+                " q  │   quit",
+                "Tab │  focus",
+            ]
+        );
+        assert_eq!(span_with(&lines, "cargo test").style.fg, Some(theme.code));
+        let link = span_with(&lines, "docs");
+        assert_eq!(link.style.fg, Some(theme.link));
+        assert!(link.style.add_modifier.contains(Modifier::UNDERLINED));
+        assert!(span_with(&lines, "Key")
+            .style
+            .add_modifier
+            .contains(Modifier::BOLD));
+    }
+
+    #[test]
+    fn search_highlights_layer_over_markdown_styles() {
+        let theme = Theme::dark();
+        let regex = build_highlight_regex(&["note".into()]).expect("regex");
+        let highlight = Style::default().fg(theme.highlight);
+        let lines = render_markdown("A **bold no**te here", &theme, Some(&regex), highlight);
+        let spans: Vec<_> = lines[0]
+            .spans
+            .iter()
+            .map(|span| (span.content.to_string(), span.style))
+            .collect();
+        assert_eq!(spans[0], ("A ".to_string(), Style::default()));
+        assert_eq!(spans[1].0, "bold ");
+        assert_eq!(spans[2].0, "no");
+        assert_eq!(spans[2].1.fg, Some(theme.highlight));
+        assert!(spans[2].1.add_modifier.contains(Modifier::BOLD));
+        assert_eq!(spans[3], ("te".to_string(), highlight));
+        assert_eq!(spans[4].0, " here");
+
+        assert_eq!(
+            plain(&render_markdown("", &theme, None, Style::default())),
+            vec![""]
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+const HISTORY_DIR: &str = "search-history";
+
+/// Recent distinct search queries for one database, oldest first. Persisted as
+/// JSON under `<state_dir>/search-history/`, one file per database path, so
+/// switching `NOTETUI_DATA` does not mix histories. A limit of zero keeps nothing.
+#[derive(Debug, Clone, Default)]
+pub struct SearchHistory {
+    path: Option<PathBuf>,
+    database: PathBuf,
+    limit: usize,
+    queries: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryFile {
+    /// The database the queries ran against, for anyone reading the file.
+    #[serde(default)]
+    database: PathBuf,
+    queries: Vec<String>,
+}
+
+impl SearchHistory {
+    /// Loads the history kept for `database_path`. A missing file starts an
+    /// empty history; a corrupt one is logged and replaced on the next save.
+    pub fn load(state_dir: &Path, database_path: &Path, limit: usize) -> Result<Self> {
+        let database =
+            fs::canonicalize(database_path).unwrap_or_else(|_| database_path.to_path_buf());
+        let path = history_path(state_dir, &database);
+        let mut queries = match fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice::<HistoryFile>(&bytes) {
+                Ok(file) => file.queries,
+                Err(err) => {
+                    tracing::warn!(?err, path = %path.display(), "ignoring corrupt search history");
+                    Vec::new()
+                }
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("reading search history {}", path.display()))
+            }
+        };
+        if queries.len() > limit {
+            queries.drain(..queries.len() - limit);
+        }
+        Ok(Self {
+            path: Some(path),
+            database,
+            limit,
+            queries,
+        })
+    }
+
+    /// A history that is never written to disk.
+    pub fn in_memory(limit: usize) -> Self {
+        Self {
+            path: None,
+            database: PathBuf::new(),
+            limit,
+            queries: Vec::new(),
+        }
+    }
+
+    /// Makes `query` the newest entry, dropping an earlier copy of it and the
+    /// oldest entries past the limit, then saves the file.
+    pub fn record(&mut self, query: &str) -> Result<()> {
+        let query = query.trim();
+        if self.limit == 0 || query.is_empty() {
+            return Ok(());
+        }
+        if self.queries.last().map(String::as_str) == Some(query) {
+            return Ok(());
+        }
+        self.queries.retain(|existing| existing != query);
+        self.queries.push(query.to_string());
+        if self.queries.len() > self.limit {
+            self.queries.drain(..self.queries.len() - self.limit);
+        }
+        self.save()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.queries.get(index).map(String::as_str)
+    }
+
+    /// The newest entry older than `before` that contains `needle`, ignoring case.
+    pub fn find_before(&self, needle: &str, before: usize) -> Option<usize> {
+        let needle = needle.to_lowercase();
+        self.queries[..before.min(self.queries.len())]
+            .iter()
+            .rposition(|query| query.to_lowercase().contains(&needle))
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("creating search history directory {}", dir.display()))?;
+        }
+        let file = HistoryFile {
+            database: self.database.clone(),
+            queries: self.queries.clone(),
+        };
+        let json = serde_json::to_vec_pretty(&file).context("encoding search history")?;
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, json)
+            .with_context(|| format!("writing search history {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, path)
+            .with_context(|| format!("replacing search history {}", path.display()))
+    }
+}
+
+/// `<state_dir>/search-history/<db stem>-<hash of the db path>.json`. The hash
+/// is FNV-1a so the name stays stable across builds.
+fn history_path(state_dir: &Path, database: &Path) -> PathBuf {
+    let hash = database
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    let stem = database
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "notes".to_string());
+    state_dir
+        .join(HISTORY_DIR)
+        .join(format!("{stem}-{hash:016x}.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn keeps_the_latest_distinct_queries_per_database() -> Result<()> {
+        let temp = TempDir::new()?;
+        let state = temp.path().join("state");
+        let db = temp.path().join("notes.db");
+        let mut history = SearchHistory::load(&state, &db, 3)?;
+        for query in ["alpha", "beta", "alpha", " gamma ", "", "delta"] {
+            history.record(query)?;
+        }
+        let queries = |history: &SearchHistory| {
+            (0..history.len())
+                .filter_map(|idx| history.get(idx).map(str::to_string))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(queries(&history), vec!["alpha", "gamma", "delta"]);
+
+        let reloaded = SearchHistory::load(&state, &db, 2)?;
+        assert_eq!(queries(&reloaded), vec!["gamma", "delta"]);
+        let other = SearchHistory::load(&state, &temp.path().join("other.db"), 3)?;
+        assert!(other.is_empty());
+
+        assert_eq!(history.find_before("A", history.len()), Some(2));
+        assert_eq!(history.find_before("a", 2), Some(1));
+        assert_eq!(history.find_before("alp", 0), None);
+        Ok(())
+    }
+}
@@ -1,7 +1,13 @@
 use std::cmp::{max, min};
 
 use time::format_description;
-use time::{Date, Duration, Time};
+use time::{Date, Duration, Month, OffsetDateTime, UtcOffset};
+
+use crate::config::SortSpec;
+
+mod history;
+
+pub use history::SearchHistory;
 
 #[derive(Debug, Clone, Default)]
 pub struct RangeFilter {
@@ -30,84 +36,574 @@ impl RangeFilter {
     }
 }
 
+/// Which slice of the notes table a query runs against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoteScope {
+    #[default]
+    Active,
+    Archived,
+    Trash,
+}
+
+/// Free text of a query, compiled by storage into an FTS5 `MATCH` expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextExpr {
+    /// A bare word, matched as a prefix (`plan` also finds `planning`).
+    Word {
+        text: String,
+        title_only: bool,
+    },
+    /// A quoted phrase: its words next to each other, in order.
+    Phrase {
+        text: String,
+        title_only: bool,
+    },
+    And(Vec<TextExpr>),
+    Or(Vec<TextExpr>),
+    Not(Box<TextExpr>),
+}
+
+impl TextExpr {
+    /// Whether the expression matches notes on its own rather than only
+    /// excluding them (`-draft`).
+    pub fn has_positive(&self) -> bool {
+        match self {
+            Self::Word { .. } | Self::Phrase { .. } => true,
+            Self::Not(_) => false,
+            Self::And(items) => items.iter().any(Self::has_positive),
+            Self::Or(items) => items.iter().all(Self::has_positive),
+        }
+    }
+
+    /// Words and phrases a match can contain, leaving out excluded ones.
+    pub fn positive_terms(&self) -> Vec<String> {
+        let mut terms = Vec::new();
+        self.collect_positive(&mut terms);
+        terms
+    }
+
+    fn collect_positive(&self, terms: &mut Vec<String>) {
+        match self {
+            Self::Word { text, .. } | Self::Phrase { text, .. } => terms.push(text.clone()),
+            Self::And(items) | Self::Or(items) => {
+                for item in items {
+                    item.collect_positive(terms);
+                }
+            }
+            Self::Not(_) => {}
+        }
+    }
+
+    /// Joins expressions with AND, flattening nested ANDs so exclusions sit
+    /// next to the terms they exclude from.
+    fn all(items: Vec<TextExpr>) -> TextExpr {
+        let mut flat = Vec::new();
+        for item in items {
+            match item {
+                Self::And(inner) => flat.extend(inner),
+                other => flat.push(other),
+            }
+        }
+        if flat.len() == 1 {
+            flat.remove(0)
+        } else {
+            Self::And(flat)
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct SearchQuery {
-    pub terms: Vec<String>,
-    pub title_terms: Vec<String>,
+    /// Words, phrases, exclusions and `OR` groups; `None` when the query only filters.
+    pub text: Option<TextExpr>,
+    /// Set by `tag:`; a tag also matches the tags nested under it, so
+    /// `project` matches `project/alpha`.
     pub tags: Vec<String>,
+    /// Set by `-tag:`; notes carrying any of these, or a tag nested under
+    /// one, are left out.
+    pub excluded_tags: Vec<String>,
     pub created: RangeFilter,
     pub updated: RangeFilter,
     pub regex_pattern: Option<String>,
+    /// Set by `is:archived` / `in:trash`; `None` leaves the choice to the caller.
+    pub scope: Option<NoteScope>,
+    pub pinned_only: bool,
+    /// Set by `notebook:`; a notebook path such as `Work/Projects`. Matches
+    /// notes in that notebook and the notebooks nested under it.
+    pub notebook: Option<String>,
+    /// List order for the results; `None` ranks text matches by relevance.
+    pub sort: Option<SortSpec>,
+    /// Widens misspelled words to vocabulary terms within this many edits per
+    /// character (see `search.fuzzy_threshold`); `0.0` searches exact words only.
+    pub fuzzy_threshold: f32,
 }
 
 impl SearchQuery {
+    /// Whether the text can match notes by itself (see [`TextExpr::has_positive`]).
     pub fn has_terms(&self) -> bool {
-        !self.terms.is_empty() || !self.title_terms.is_empty()
+        self.text.as_ref().is_some_and(TextExpr::has_positive)
     }
 
+    /// Whether anything narrows the notes besides matching text. Text made up
+    /// only of exclusions counts, since it filters the whole scope.
     pub fn has_filters(&self) -> bool {
-        !self.tags.is_empty() || self.created.has_range() || self.updated.has_range()
+        !self.tags.is_empty()
+            || !self.excluded_tags.is_empty()
+            || self.created.has_range()
+            || self.updated.has_range()
+            || self.scope.is_some()
+            || self.pinned_only
+            || self.notebook.is_some()
+            || self.text.as_ref().is_some_and(|text| !text.has_positive())
     }
 
     pub fn highlight_terms(&self) -> Vec<String> {
-        let mut terms = self.terms.clone();
-        terms.extend(self.title_terms.iter().cloned());
-        terms
+        self.text
+            .as_ref()
+            .map(TextExpr::positive_terms)
+            .unwrap_or_default()
     }
 }
 
-pub fn parse_query(input: &str) -> SearchQuery {
-    let mut query = SearchQuery::default();
+/// Why a query could not be parsed; `column` counts characters from 1.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{message} (column {column})")]
+pub struct QueryError {
+    pub message: String,
+    pub column: usize,
+}
+
+/// Parses the search language: bare words (prefix matches), `"exact phrases"`,
+/// `title:` words or phrases, `OR` between terms, parentheses for grouping and
+/// `-` to exclude a term, phrase, group or `tag:`. Terms next to each other must
+/// all match; `OR` binds tighter, so `tag:work budget OR invoice` finds work
+/// notes mentioning either word. The `tag:`, `is:`, `in:`, `notebook:`,
+/// `created:` and `updated:` qualifiers filter the whole query and cannot
+/// appear inside `OR` or parentheses. Dates resolve against the local clock and timezone.
+pub fn parse_query(input: &str) -> Result<SearchQuery, QueryError> {
+    parse_query_with(input, Calendar::local())
+}
+
+fn parse_query_with(input: &str, calendar: Calendar) -> Result<SearchQuery, QueryError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end: input.chars().count() + 1,
+        calendar,
+        query: SearchQuery::default(),
+    };
+    let items = parser.sequence(false)?;
+    let text: Vec<TextExpr> = items.into_iter().flatten().collect();
+    parser.query.text = match text.len() {
+        0 => None,
+        _ => Some(TextExpr::all(text)),
+    };
+    Ok(parser.query)
+}
+
+pub fn regex_pattern_from_input(input: &str) -> Option<String> {
+    let mut parts = Vec::new();
     for raw in input.split_whitespace() {
-        if raw.is_empty() {
+        let unprefixed = raw.strip_prefix('-').unwrap_or(raw);
+        if QUALIFIERS
+            .iter()
+            .any(|key| unprefixed.starts_with(&format!("{key}:")))
+        {
             continue;
         }
-        if let Some(tag) = raw.strip_prefix("tag:") {
-            if let Some(value) = sanitize_term(tag) {
-                query.tags.push(value.to_lowercase());
-            }
+        parts.push(raw.to_string());
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" "))
+    }
+}
+
+const QUALIFIERS: &[&str] = &["tag", "title", "is", "in", "notebook", "created", "updated"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Or,
+    Minus,
+    Term {
+        qualifier: Option<&'static str>,
+        text: String,
+        quoted: bool,
+    },
+}
+
+/// Splits the input into tokens, each paired with its 1-based column.
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+    while idx < chars.len() {
+        let ch = chars[idx];
+        let column = idx + 1;
+        if ch.is_whitespace() {
+            idx += 1;
             continue;
         }
-        if let Some(term) = raw.strip_prefix("title:") {
-            if let Some(value) = sanitize_term(term) {
-                query.title_terms.push(value);
+        match ch {
+            '(' => {
+                tokens.push((column, Token::Open));
+                idx += 1;
+            }
+            ')' => {
+                tokens.push((column, Token::Close));
+                idx += 1;
+            }
+            '-' => {
+                if chars
+                    .get(idx + 1)
+                    .map_or(true, |next| next.is_whitespace() || *next == ')')
+                {
+                    return Err(error("`-` must be followed by what to exclude", column));
+                }
+                tokens.push((column, Token::Minus));
+                idx += 1;
+            }
+            '"' => {
+                let (text, next) = read_quoted(&chars, idx)?;
+                tokens.push((
+                    column,
+                    Token::Term {
+                        qualifier: None,
+                        text,
+                        quoted: true,
+                    },
+                ));
+                idx = next;
+            }
+            _ => {
+                let start = idx;
+                while idx < chars.len()
+                    && !chars[idx].is_whitespace()
+                    && !matches!(chars[idx], '(' | ')' | '"')
+                {
+                    idx += 1;
+                }
+                let run: String = chars[start..idx].iter().collect();
+                if run == "OR" {
+                    tokens.push((column, Token::Or));
+                    continue;
+                }
+                let qualifier = run.split_once(':').and_then(|(key, _)| {
+                    QUALIFIERS
+                        .iter()
+                        .copied()
+                        .find(|candidate| *candidate == key)
+                });
+                let Some(key) = qualifier else {
+                    tokens.push((
+                        column,
+                        Token::Term {
+                            qualifier: None,
+                            text: run,
+                            quoted: false,
+                        },
+                    ));
+                    continue;
+                };
+                let value = &run[key.len() + 1..];
+                if value.is_empty() && chars.get(idx) == Some(&'"') {
+                    let (text, next) = read_quoted(&chars, idx)?;
+                    tokens.push((
+                        column,
+                        Token::Term {
+                            qualifier: Some(key),
+                            text,
+                            quoted: true,
+                        },
+                    ));
+                    idx = next;
+                } else {
+                    tokens.push((
+                        column,
+                        Token::Term {
+                            qualifier: Some(key),
+                            text: value.to_string(),
+                            quoted: false,
+                        },
+                    ));
+                }
             }
-            continue;
         }
-        if let Some(range) = raw.strip_prefix("created:") {
-            let parsed = parse_date_range(range);
-            query.created.merge(parsed);
-            continue;
+    }
+    Ok(tokens)
+}
+
+/// Reads the quoted string opening at `start`, returning it and the index after
+/// the closing quote.
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize), QueryError> {
+    let Some(len) = chars[start + 1..].iter().position(|ch| *ch == '"') else {
+        return Err(error("unclosed quote", start + 1));
+    };
+    let text: String = chars[start + 1..start + 1 + len].iter().collect();
+    Ok((text.trim().to_string(), start + len + 2))
+}
+
+fn error(message: impl Into<String>, column: usize) -> QueryError {
+    QueryError {
+        message: message.into(),
+        column,
+    }
+}
+
+/// A parsed operand: text to match, or `None` for a qualifier (already applied
+/// to the query) or a word with nothing searchable in it.
+type Item = Option<TextExpr>;
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    end: usize,
+    calendar: Calendar,
+    query: SearchQuery,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.end, |(column, _)| *column)
+    }
+
+    /// Operands up to the end of input, or up to the `)` closing a group.
+    fn sequence(&mut self, grouped: bool) -> Result<Vec<Item>, QueryError> {
+        let mut items = Vec::new();
+        loop {
+            match self.peek() {
+                None if grouped => return Err(error("unclosed `(`", self.end)),
+                None => return Ok(items),
+                Some(Token::Close) if grouped => return Ok(items),
+                Some(Token::Close) => return Err(error("unmatched `)`", self.column())),
+                Some(_) => items.push(self.disjunction(grouped)?),
+            }
         }
-        if let Some(range) = raw.strip_prefix("updated:") {
-            let parsed = parse_date_range(range);
-            query.updated.merge(parsed);
-            continue;
+    }
+
+    fn disjunction(&mut self, grouped: bool) -> Result<Item, QueryError> {
+        let column = self.column();
+        let first = self.unary(grouped)?;
+        if self.peek() != Some(&Token::Or) {
+            return Ok(first);
+        }
+        let mut operands = vec![(column, first)];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            let column = self.column();
+            if matches!(self.peek(), None | Some(Token::Or | Token::Close)) {
+                return Err(error("`OR` needs a term on both sides", column));
+            }
+            operands.push((column, self.unary(grouped)?));
+        }
+        let mut alternatives = Vec::new();
+        for (column, operand) in operands {
+            match operand {
+                Some(expr) if expr.has_positive() => alternatives.push(expr),
+                Some(_) => return Err(error("`OR` cannot join exclusions", column)),
+                None => {
+                    return Err(error(
+                        "`OR` can only join words, phrases and groups, not qualifiers",
+                        column,
+                    ))
+                }
+            }
+        }
+        Ok(Some(TextExpr::Or(alternatives)))
+    }
+
+    fn unary(&mut self, grouped: bool) -> Result<Item, QueryError> {
+        if self.peek() != Some(&Token::Minus) {
+            return self.primary(grouped);
         }
-        if let Some(value) = sanitize_term(raw) {
-            query.terms.push(value);
+        let column = self.column();
+        self.pos += 1;
+        match self.peek() {
+            Some(Token::Minus) => Err(error("`-` cannot be repeated", self.column())),
+            Some(Token::Term {
+                qualifier: Some(key),
+                ..
+            }) if *key != "title" => {
+                let key = *key;
+                if key != "tag" {
+                    return Err(error(
+                        format!("`-{key}:` is not supported; only `-tag:` can exclude a qualifier"),
+                        column,
+                    ));
+                }
+                if grouped {
+                    return Err(error(
+                        "`-tag:` applies to the whole query and cannot go inside parentheses",
+                        column,
+                    ));
+                }
+                let Some(Token::Term { text, quoted, .. }) = self.next_token() else {
+                    unreachable!("peeked a term");
+                };
+                let tag = tag_value(&text, quoted)
+                    .ok_or_else(|| error("`-tag:` needs a tag name", column))?;
+                self.query.excluded_tags.push(tag);
+                Ok(None)
+            }
+            _ => match self.primary(grouped)? {
+                Some(expr) if expr.has_positive() => Ok(Some(TextExpr::Not(Box::new(expr)))),
+                Some(_) => Err(error(
+                    "an excluded group needs a term that is not excluded",
+                    column,
+                )),
+                None => Err(error("nothing searchable to exclude", column)),
+            },
+        }
+    }
+
+    fn primary(&mut self, grouped: bool) -> Result<Item, QueryError> {
+        let column = self.column();
+        match self.next_token() {
+            Some(Token::Open) => {
+                let items = self.sequence(true)?;
+                self.pos += 1; // the closing `)`
+                let items: Vec<TextExpr> = items.into_iter().flatten().collect();
+                if items.is_empty() {
+                    return Err(error("empty group `()`", column));
+                }
+                Ok(Some(TextExpr::all(items)))
+            }
+            Some(Token::Term {
+                qualifier,
+                text,
+                quoted,
+            }) => self.term(qualifier, text, quoted, grouped, column),
+            Some(Token::Or) => Err(error("`OR` needs a term on both sides", column)),
+            Some(Token::Close) | Some(Token::Minus) | None => {
+                Err(error("expected a word, phrase or group", column))
+            }
         }
     }
-    query
+
+    fn term(
+        &mut self,
+        qualifier: Option<&'static str>,
+        text: String,
+        quoted: bool,
+        grouped: bool,
+        column: usize,
+    ) -> Result<Item, QueryError> {
+        let Some(key) = qualifier else {
+            return text_term(text, quoted, false, column);
+        };
+        if key == "title" {
+            if text.is_empty() {
+                return Err(error("`title:` needs a word or a quoted phrase", column));
+            }
+            return text_term(text, quoted, true, column);
+        }
+        if grouped {
+            return Err(error(
+                format!("`{key}:` applies to the whole query and cannot go inside parentheses"),
+                column,
+            ));
+        }
+        if self.peek() == Some(&Token::Or) {
+            return Err(error(
+                "`OR` can only join words, phrases and groups, not qualifiers",
+                column,
+            ));
+        }
+        match key {
+            "tag" => {
+                let tag = tag_value(&text, quoted)
+                    .ok_or_else(|| error("`tag:` needs a tag name", column))?;
+                self.query.tags.push(tag);
+            }
+            "is" => match text.to_lowercase().as_str() {
+                "archived" => self.query.scope = Some(NoteScope::Archived),
+                "pinned" => self.query.pinned_only = true,
+                _ => {
+                    return Err(error(
+                        format!("unknown `is:{text}`; use `is:archived` or `is:pinned`"),
+                        column,
+                    ))
+                }
+            },
+            "in" => {
+                if !text.eq_ignore_ascii_case("trash") {
+                    return Err(error(
+                        format!("unknown `in:{text}`; use `in:trash`"),
+                        column,
+                    ));
+                }
+                self.query.scope = Some(NoteScope::Trash);
+            }
+            "notebook" => {
+                let path = text.trim();
+                if path.is_empty() {
+                    return Err(error("`notebook:` needs a notebook path", column));
+                }
+                if self.query.notebook.is_some() {
+                    return Err(error("a query can name only one `notebook:`", column));
+                }
+                self.query.notebook = Some(path.to_string());
+            }
+            _ => {
+                let range = parse_date_range(&text, &self.calendar).ok_or_else(|| {
+                    error(
+                        format!(
+                            "invalid `{key}:` range `{text}`; use a date (YYYY-MM-DD, YYYY-MM, \
+                             today, yesterday, this-week, last-month, ...), an age such as 7d, \
+                             or FROM..TO"
+                        ),
+                        column,
+                    )
+                })?;
+                if key == "created" {
+                    self.query.created.merge(range);
+                } else {
+                    self.query.updated.merge(range);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(_, token)| token.clone());
+        self.pos += 1;
+        token
+    }
 }
 
-pub fn regex_pattern_from_input(input: &str) -> Option<String> {
-    let mut parts = Vec::new();
-    for raw in input.split_whitespace() {
-        if raw.starts_with("tag:")
-            || raw.starts_with("title:")
-            || raw.starts_with("created:")
-            || raw.starts_with("updated:")
-        {
-            continue;
+fn text_term(
+    text: String,
+    quoted: bool,
+    title_only: bool,
+    column: usize,
+) -> Result<Item, QueryError> {
+    if quoted {
+        if text.is_empty() {
+            return Err(error("empty phrase `\"\"`", column));
         }
-        parts.push(raw.to_string());
+        return Ok(Some(TextExpr::Phrase { text, title_only }));
     }
-    if parts.is_empty() {
-        None
+    Ok(sanitize_term(&text).map(|text| TextExpr::Word { text, title_only }))
+}
+
+fn tag_value(text: &str, quoted: bool) -> Option<String> {
+    let tag = if quoted {
+        Some(text.to_string()).filter(|tag| !tag.is_empty())
     } else {
-        Some(parts.join(" "))
-    }
+        sanitize_term(text)
+    };
+    tag.map(|tag| tag.to_lowercase())
 }
 
 fn sanitize_term(raw: &str) -> Option<String> {
@@ -122,45 +618,416 @@ fn sanitize_term(raw: &str) -> Option<String> {
     }
 }
 
-fn parse_date_range(spec: &str) -> RangeFilter {
+/// Maps calendar dates in date filters to instants in the user's timezone.
+#[derive(Debug, Clone, Copy)]
+pub struct Calendar {
+    now: OffsetDateTime,
+    offset_at: fn(OffsetDateTime) -> UtcOffset,
+}
+
+impl Calendar {
+    /// The system clock and timezone. Falls back to UTC when the local offset
+    /// cannot be determined (the `time` crate refuses while other threads run).
+    pub fn local() -> Self {
+        Self {
+            now: OffsetDateTime::now_utc(),
+            offset_at: |instant| UtcOffset::local_offset_at(instant).unwrap_or(UtcOffset::UTC),
+        }
+    }
+
+    /// The system clock with a timezone other than the system one.
+    pub fn with_offset(offset_at: fn(OffsetDateTime) -> UtcOffset) -> Self {
+        Self {
+            now: OffsetDateTime::now_utc(),
+            offset_at,
+        }
+    }
+
+    /// The local date containing `epoch`.
+    pub fn date_of(&self, epoch: i64) -> Option<Date> {
+        let instant = OffsetDateTime::from_unix_timestamp(epoch).ok()?;
+        Some(instant.to_offset((self.offset_at)(instant)).date())
+    }
+
+    fn today(&self) -> Date {
+        self.now.to_offset((self.offset_at)(self.now)).date()
+    }
+
+    /// When local midnight starts `date`. The offset depends on the instant
+    /// being resolved (daylight saving), so a first guess is refined once.
+    pub fn midnight(&self, date: Date) -> i64 {
+        let wall = date.midnight();
+        let guess = wall.assume_offset((self.offset_at)(wall.assume_utc()));
+        wall.assume_offset((self.offset_at)(guess)).unix_timestamp()
+    }
+
+    /// The local days `[from, to)` as instants.
+    fn days(&self, from: Date, to: Date) -> DatePoint {
+        DatePoint::Span(self.midnight(from), self.midnight(to))
+    }
+}
+
+/// One end of a date filter: a span of local days, or an instant for ages
+/// such as `7d` (which count back from now rather than from midnight).
+#[derive(Debug, Clone, Copy)]
+enum DatePoint {
+    Span(i64, i64),
+    Instant(i64),
+}
+
+impl DatePoint {
+    fn start(self) -> i64 {
+        match self {
+            Self::Span(start, _) | Self::Instant(start) => start,
+        }
+    }
+
+    fn end(self) -> i64 {
+        match self {
+            Self::Span(_, end) | Self::Instant(end) => end,
+        }
+    }
+}
+
+/// `None` unless every date in the spec parses and at least one is given. A
+/// single date covers its whole span; an age alone means "since then".
+fn parse_date_range(spec: &str, calendar: &Calendar) -> Option<RangeFilter> {
     let mut range = RangeFilter::default();
     let parts: Vec<&str> = spec.split("..").collect();
     match parts.as_slice() {
-        [single] => {
-            if let Some((from, to)) = parse_single_date(single) {
+        [single] => match parse_date_point(single, calendar)? {
+            DatePoint::Span(from, to) => {
                 range.from = Some(from);
                 range.to = Some(to);
             }
-        }
+            DatePoint::Instant(from) => range.from = Some(from),
+        },
         [from, to] => {
             if !from.is_empty() {
-                if let Some((start, _)) = parse_single_date(from) {
-                    range.from = Some(start);
-                }
+                range.from = Some(parse_date_point(from, calendar)?.start());
             }
             if !to.is_empty() {
-                if let Some((_, end)) = parse_single_date(to) {
-                    range.to = Some(end);
-                }
+                range.to = Some(parse_date_point(to, calendar)?.end());
             }
         }
-        _ => {}
+        _ => return None,
     }
-    range
+    range.has_range().then_some(range)
 }
 
-fn parse_single_date(input: &str) -> Option<(i64, i64)> {
-    static FORMAT: once_cell::sync::Lazy<Vec<format_description::FormatItem<'static>>> =
+fn parse_date_point(input: &str, calendar: &Calendar) -> Option<DatePoint> {
+    static DAY: once_cell::sync::Lazy<Vec<format_description::FormatItem<'static>>> =
         once_cell::sync::Lazy::new(|| {
             format_description::parse("[year]-[month]-[day]")
                 .expect("valid date format description")
         });
-    let date = Date::parse(input, &*FORMAT).ok()?;
-    let from = date.with_time(Time::MIDNIGHT).assume_utc().unix_timestamp();
-    let to = date
-        .checked_add(Duration::days(1))?
-        .with_time(Time::MIDNIGHT)
-        .assume_utc()
-        .unix_timestamp();
-    Some((from, to))
+    let input = input.to_ascii_lowercase();
+    let today = calendar.today();
+    let monday = today - Duration::days(i64::from(today.weekday().number_days_from_monday()));
+    let point = match input.as_str() {
+        "today" => calendar.days(today, today.next_day()?),
+        "yesterday" => calendar.days(today.previous_day()?, today),
+        "this-week" => calendar.days(monday, monday + Duration::weeks(1)),
+        "last-week" => calendar.days(monday - Duration::weeks(1), monday),
+        "this-month" => month_span(calendar, today.year(), today.month())?,
+        "last-month" => {
+            let year = match today.month() {
+                Month::January => today.year() - 1,
+                _ => today.year(),
+            };
+            month_span(calendar, year, today.month().previous())?
+        }
+        "this-year" => year_span(calendar, today.year())?,
+        "last-year" => year_span(calendar, today.year() - 1)?,
+        _ => {
+            if let Ok(date) = Date::parse(&input, &*DAY) {
+                calendar.days(date, date.next_day()?)
+            } else if let Some((year, month)) = input.split_once('-') {
+                let year = year.parse().ok().filter(|_| year.len() == 4)?;
+                let month = Month::try_from(month.parse::<u8>().ok()?).ok()?;
+                month_span(calendar, year, month)?
+            } else {
+                let unit = match input.chars().last()? {
+                    'h' => Duration::HOUR,
+                    'd' => Duration::DAY,
+                    'w' => Duration::WEEK,
+                    _ => return None,
+                };
+                let count: i32 = input[..input.len() - 1]
+                    .parse()
+                    .ok()
+                    .filter(|count| *count >= 0)?;
+                DatePoint::Instant((calendar.now - unit.checked_mul(count)?).unix_timestamp())
+            }
+        }
+    };
+    Some(point)
+}
+
+fn month_span(calendar: &Calendar, year: i32, month: Month) -> Option<DatePoint> {
+    let first = Date::from_calendar_date(year, month, 1).ok()?;
+    let next = match month {
+        Month::December => Date::from_calendar_date(year + 1, Month::January, 1).ok()?,
+        _ => Date::from_calendar_date(year, month.next(), 1).ok()?,
+    };
+    Some(calendar.days(first, next))
+}
+
+fn year_span(calendar: &Calendar, year: i32) -> Option<DatePoint> {
+    let first = Date::from_calendar_date(year, Month::January, 1).ok()?;
+    let next = Date::from_calendar_date(year + 1, Month::January, 1).ok()?;
+    Some(calendar.days(first, next))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::{datetime, offset};
+
+    fn word(text: &str) -> TextExpr {
+        TextExpr::Word {
+            text: text.into(),
+            title_only: false,
+        }
+    }
+
+    /// Thursday 2026-10-15 01:30 in a UTC+2 zone, still the 14th in UTC.
+    fn calendar() -> Calendar {
+        Calendar {
+            now: datetime!(2026-10-14 23:30 UTC),
+            offset_at: |_| offset!(+2),
+        }
+    }
+
+    fn range(spec: &str, calendar: Calendar) -> (Option<i64>, Option<i64>) {
+        let query = parse_query_with(&format!("updated:{spec}"), calendar).expect(spec);
+        (query.updated.from, query.updated.to)
+    }
+
+    fn at(instant: OffsetDateTime) -> Option<i64> {
+        Some(instant.unix_timestamp())
+    }
+
+    fn parse_error(input: &str) -> String {
+        parse_query(input).expect_err(input).to_string()
+    }
+
+    #[test]
+    fn parses_phrases_groups_and_exclusions() {
+        let query =
+            parse_query(r#"tag:work "weekly plan" (budget OR title:invoice) -draft -tag:old"#)
+                .expect("valid query");
+        assert_eq!(query.tags, vec!["work"]);
+        assert_eq!(query.excluded_tags, vec!["old"]);
+        assert_eq!(
+            query.text,
+            Some(TextExpr::And(vec![
+                TextExpr::Phrase {
+                    text: "weekly plan".into(),
+                    title_only: false,
+                },
+                TextExpr::Or(vec![
+                    word("budget"),
+                    TextExpr::Word {
+                        text: "invoice".into(),
+                        title_only: true,
+                    },
+                ]),
+                TextExpr::Not(Box::new(word("draft"))),
+            ]))
+        );
+        assert_eq!(
+            query.highlight_terms(),
+            vec!["weekly plan", "budget", "invoice"]
+        );
+
+        // OR binds tighter than the implicit AND; `or` is an ordinary word.
+        let query = parse_query("a b OR c or").expect("valid query");
+        assert_eq!(
+            query.text,
+            Some(TextExpr::And(vec![
+                word("a"),
+                TextExpr::Or(vec![word("b"), word("c")]),
+                word("or"),
+            ]))
+        );
+
+        let query = parse_query("-draft is:pinned").expect("valid query");
+        assert!(!query.has_terms());
+        assert!(query.has_filters());
+    }
+
+    #[test]
+    fn parses_notebook_qualifier() {
+        let query = parse_query(r#"notebook:"Work/Big Plans" budget"#).expect("valid query");
+        assert_eq!(query.notebook.as_deref(), Some("Work/Big Plans"));
+        assert_eq!(query.text, Some(word("budget")));
+        assert!(query.has_filters());
+
+        assert!(parse_error(r#"notebook:"  ""#).contains("needs a notebook path"));
+        assert!(parse_error("notebook:a notebook:b").contains("only one `notebook:`"));
+        assert!(parse_error("(a notebook:b)").contains("cannot go inside parentheses"));
+    }
+
+    #[test]
+    fn reports_errors_with_columns() {
+        assert_eq!(parse_error(r#"plan "weekly"#), "unclosed quote (column 6)");
+        assert_eq!(parse_error("(a OR b"), "unclosed `(` (column 8)");
+        assert_eq!(parse_error("a) b"), "unmatched `)` (column 2)");
+        assert_eq!(
+            parse_error("a OR"),
+            "`OR` needs a term on both sides (column 5)"
+        );
+        assert_eq!(
+            parse_error("a - b"),
+            "`-` must be followed by what to exclude (column 3)"
+        );
+        assert_eq!(
+            parse_error("a OR -b"),
+            "`OR` cannot join exclusions (column 6)"
+        );
+        assert_eq!(parse_error("()"), "empty group `()` (column 1)");
+        assert!(parse_error("tag:a OR b").contains("not qualifiers"));
+        assert!(parse_error("(a tag:b)").contains("cannot go inside parentheses"));
+        assert!(parse_error("-is:pinned").contains("only `-tag:`"));
+        assert!(parse_error("is:starred").contains("is:archived"));
+        assert!(parse_error("created:2024-13-01").contains("invalid `created:` range"));
+    }
+
+    #[test]
+    fn relative_dates_follow_local_midnights() {
+        let local = calendar();
+        assert_eq!(
+            range("today", local),
+            (
+                at(datetime!(2026-10-14 22:00 UTC)),
+                at(datetime!(2026-10-15 22:00 UTC))
+            )
+        );
+        assert_eq!(
+            range("yesterday..today", local),
+            (
+                at(datetime!(2026-10-13 22:00 UTC)),
+                at(datetime!(2026-10-15 22:00 UTC))
+            )
+        );
+        assert_eq!(
+            range("2026-10-15", local),
+            range("today", local),
+            "explicit dates use local midnight too"
+        );
+        // Weeks start on Monday.
+        assert_eq!(
+            range("this-week", local),
+            (
+                at(datetime!(2026-10-11 22:00 UTC)),
+                at(datetime!(2026-10-18 22:00 UTC))
+            )
+        );
+        assert_eq!(
+            range("last-week", local),
+            (
+                at(datetime!(2026-10-04 22:00 UTC)),
+                at(datetime!(2026-10-11 22:00 UTC))
+            )
+        );
+        assert_eq!(
+            range("2026-09", local),
+            (
+                at(datetime!(2026-08-31 22:00 UTC)),
+                at(datetime!(2026-09-30 22:00 UTC))
+            )
+        );
+        assert_eq!(range("last-month", local), range("2026-09", local));
+        assert_eq!(
+            range("2026-12", local),
+            (
+                at(datetime!(2026-11-30 22:00 UTC)),
+                at(datetime!(2026-12-31 22:00 UTC))
+            )
+        );
+        // Ages count back from now; as a range end they cap it instead.
+        assert_eq!(
+            range("7d", local),
+            (at(datetime!(2026-10-07 23:30 UTC)), None)
+        );
+        assert_eq!(
+            range("2w..36h", local),
+            (
+                at(datetime!(2026-09-30 23:30 UTC)),
+                at(datetime!(2026-10-13 11:30 UTC))
+            )
+        );
+
+        let new_year = Calendar {
+            now: datetime!(2027-01-04 09:00 UTC),
+            ..local
+        };
+        assert_eq!(range("last-month", new_year), range("2026-12", local));
+        assert_eq!(
+            range("last-year", new_year),
+            (
+                at(datetime!(2025-12-31 22:00 UTC)),
+                at(datetime!(2026-12-31 22:00 UTC))
+            )
+        );
+        assert_eq!(
+            range("last-week", new_year),
+            (
+                at(datetime!(2026-12-27 22:00 UTC)),
+                at(datetime!(2027-01-03 22:00 UTC))
+            )
+        );
+    }
+
+    #[test]
+    fn daylight_saving_changes_shift_midnight() {
+        // Central European time: UTC+1, then UTC+2 from 2026-03-29 01:00 UTC.
+        let cet = Calendar {
+            now: datetime!(2026-03-29 12:00 UTC),
+            offset_at: |instant| {
+                if instant < datetime!(2026-03-29 01:00 UTC) {
+                    offset!(+1)
+                } else {
+                    offset!(+2)
+                }
+            },
+        };
+        assert_eq!(
+            range("today", cet),
+            (
+                at(datetime!(2026-03-28 23:00 UTC)),
+                at(datetime!(2026-03-29 22:00 UTC))
+            ),
+            "the day the clocks go forward is 23 hours long"
+        );
+        assert_eq!(
+            range("2026-03", cet),
+            (
+                at(datetime!(2026-02-28 23:00 UTC)),
+                at(datetime!(2026-03-31 22:00 UTC))
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_dates() {
+        for spec in [
+            "2026-13",
+            "2026-02-30",
+            "7x",
+            "soon",
+            "..",
+            "26-09",
+            "today..later",
+        ] {
+            let message = parse_query_with(&format!("created:{spec}"), calendar())
+                .expect_err(spec)
+                .to_string();
+            assert!(
+                message.contains("invalid `created:` range"),
+                "{spec}: {message}"
+            );
+        }
+    }
 }
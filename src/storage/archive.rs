@@ -0,0 +1,507 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use super::{links, StorageHandle};
+use crate::markdown::rewrite_id_links;
+
+/// Every table holding user data, with rows keyed by their ids in the
+/// database they came from. Note links are left out: they are derived from
+/// note bodies and rebuilt on import.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Archive {
+    #[serde(default)]
+    pub notebooks: Vec<ArchivedNotebook>,
+    #[serde(default)]
+    pub tags: Vec<ArchivedTag>,
+    #[serde(default)]
+    pub notes: Vec<ArchivedNote>,
+    #[serde(default)]
+    pub note_tags: Vec<ArchivedNoteTag>,
+    #[serde(default)]
+    pub revisions: Vec<ArchivedRevision>,
+    #[serde(default)]
+    pub saved_searches: Vec<ArchivedSavedSearch>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedNotebook {
+    pub id: i64,
+    pub name: String,
+    pub parent_id: Option<i64>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedTag {
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedNote {
+    pub id: i64,
+    pub title: String,
+    pub body: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub pinned: bool,
+    pub archived: bool,
+    /// Set while the note is in the trash.
+    pub deleted_at: Option<i64>,
+    pub notebook_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedNoteTag {
+    pub note_id: i64,
+    pub tag_id: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedRevision {
+    pub note_id: i64,
+    pub title: String,
+    pub body: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedSavedSearch {
+    pub name: String,
+    pub query: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// How an archive meets the notes already in the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveImportMode {
+    /// Add the archive to the database. Notes get fresh ids (and `[[#id]]`
+    /// links to them are rewritten); tags, notebooks and saved searches with
+    /// the same names are reused.
+    Merge,
+    /// Empty the database first, so it ends up as the archive with the
+    /// archive's ids. Backups are not touched.
+    Replace,
+}
+
+/// Rows an archive import created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArchiveImportSummary {
+    pub notes: usize,
+    /// Merged notes left out because a note with the same title, body and
+    /// creation time already existed.
+    pub duplicates: usize,
+    pub tags: usize,
+    pub notebooks: usize,
+    pub revisions: usize,
+    pub saved_searches: usize,
+}
+
+impl StorageHandle {
+    /// The whole database as an [`Archive`], read in one transaction so the
+    /// tables agree with each other.
+    pub fn export_archive(&self) -> Result<Archive> {
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+        let archive = read_archive(&tx)?;
+        tx.commit()?;
+        Ok(archive)
+    }
+
+    /// Loads `archive` in one transaction. With `dry_run` the transaction is
+    /// rolled back, so the summary reports what the import would create.
+    pub fn import_archive(
+        &self,
+        archive: &Archive,
+        mode: ArchiveImportMode,
+        dry_run: bool,
+    ) -> Result<ArchiveImportSummary> {
+        check_references(archive)?;
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+        let summary = match mode {
+            ArchiveImportMode::Merge => merge_archive(&tx, archive)?,
+            ArchiveImportMode::Replace => replace_with_archive(&tx, archive)?,
+        };
+        if !dry_run {
+            tx.commit()?;
+            self.mirror_all();
+        }
+        Ok(summary)
+    }
+}
+
+fn read_archive(conn: &Connection) -> Result<Archive> {
+    let mut stmt =
+        conn.prepare("SELECT id, name, parent_id, created_at FROM notebooks ORDER BY id")?;
+    let notebooks = stmt
+        .query_map([], |row| {
+            Ok(ArchivedNotebook {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                parent_id: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("reading notebooks")?;
+
+    let mut stmt = conn.prepare("SELECT id, name FROM tags ORDER BY id")?;
+    let tags = stmt
+        .query_map([], |row| {
+            Ok(ArchivedTag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("reading tags")?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, title, body, created_at, updated_at, pinned, archived, deleted_at, notebook_id
+         FROM notes ORDER BY id",
+    )?;
+    let notes = stmt
+        .query_map([], |row| {
+            Ok(ArchivedNote {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                body: row.get(2)?,
+                created_at: row.get(3)?,
+                updated_at: row.get(4)?,
+                pinned: row.get::<_, i64>(5)? != 0,
+                archived: row.get::<_, i64>(6)? != 0,
+                deleted_at: row.get(7)?,
+                notebook_id: row.get(8)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("reading notes")?;
+
+    let mut stmt =
+        conn.prepare("SELECT note_id, tag_id FROM note_tags ORDER BY note_id, tag_id")?;
+    let note_tags = stmt
+        .query_map([], |row| {
+            Ok(ArchivedNoteTag {
+                note_id: row.get(0)?,
+                tag_id: row.get(1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("reading note tags")?;
+
+    let mut stmt =
+        conn.prepare("SELECT note_id, title, body, created_at FROM note_revisions ORDER BY id")?;
+    let revisions = stmt
+        .query_map([], |row| {
+            Ok(ArchivedRevision {
+                note_id: row.get(0)?,
+                title: row.get(1)?,
+                body: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("reading note revisions")?;
+
+    let mut stmt =
+        conn.prepare("SELECT name, query, created_at, updated_at FROM saved_searches ORDER BY id")?;
+    let saved_searches = stmt
+        .query_map([], |row| {
+            Ok(ArchivedSavedSearch {
+                name: row.get(0)?,
+                query: row.get(1)?,
+                created_at: row.get(2)?,
+                updated_at: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("reading saved searches")?;
+
+    Ok(Archive {
+        notebooks,
+        tags,
+        notes,
+        note_tags,
+        revisions,
+        saved_searches,
+    })
+}
+
+/// Rejects archives whose rows point at ids the archive does not contain,
+/// before anything is written.
+fn check_references(archive: &Archive) -> Result<()> {
+    let notebooks: HashSet<i64> = archive.notebooks.iter().map(|nb| nb.id).collect();
+    let tags: HashSet<i64> = archive.tags.iter().map(|tag| tag.id).collect();
+    let notes: HashSet<i64> = archive.notes.iter().map(|note| note.id).collect();
+    if notebooks.len() != archive.notebooks.len()
+        || tags.len() != archive.tags.len()
+        || notes.len() != archive.notes.len()
+    {
+        bail!("archive repeats a notebook, tag or note id");
+    }
+    for notebook in &archive.notebooks {
+        if let Some(parent) = notebook.parent_id.filter(|id| !notebooks.contains(id)) {
+            bail!(
+                "notebook {} refers to missing notebook {parent}",
+                notebook.id
+            );
+        }
+    }
+    if parents_first(&archive.notebooks).len() != archive.notebooks.len() {
+        bail!("archive notebooks are nested in a cycle");
+    }
+    for note in &archive.notes {
+        if let Some(notebook) = note.notebook_id.filter(|id| !notebooks.contains(id)) {
+            bail!("note {} refers to missing notebook {notebook}", note.id);
+        }
+    }
+    for link in &archive.note_tags {
+        if !notes.contains(&link.note_id) || !tags.contains(&link.tag_id) {
+            bail!(
+                "note tag ({}, {}) refers to a missing note or tag",
+                link.note_id,
+                link.tag_id
+            );
+        }
+    }
+    for revision in &archive.revisions {
+        if !notes.contains(&revision.note_id) {
+            bail!("revision refers to missing note {}", revision.note_id);
+        }
+    }
+    Ok(())
+}
+
+fn replace_with_archive(conn: &Connection, archive: &Archive) -> Result<ArchiveImportSummary> {
+    // Rows go in by id, so parents may follow their children.
+    conn.pragma_update(None, "defer_foreign_keys", true)?;
+    conn.execute_batch(
+        "DELETE FROM note_links;
+         DELETE FROM note_tags;
+         DELETE FROM note_revisions;
+         DELETE FROM notes;
+         DELETE FROM tags;
+         DELETE FROM notebooks;
+         DELETE FROM saved_searches;",
+    )
+    .context("clearing the database")?;
+
+    for notebook in &archive.notebooks {
+        conn.execute(
+            "INSERT INTO notebooks (id, name, parent_id, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                notebook.id,
+                notebook.name,
+                notebook.parent_id,
+                notebook.created_at
+            ],
+        )
+        .with_context(|| format!("inserting notebook '{}'", notebook.name))?;
+    }
+    for tag in &archive.tags {
+        conn.execute(
+            "INSERT INTO tags (id, name) VALUES (?1, ?2)",
+            params![tag.id, tag.name],
+        )
+        .with_context(|| format!("inserting tag '{}'", tag.name))?;
+    }
+    for note in &archive.notes {
+        insert_note(conn, note.id, note, &note.body, note.notebook_id)?;
+    }
+    let mut summary = ArchiveImportSummary {
+        notes: archive.notes.len(),
+        tags: archive.tags.len(),
+        notebooks: archive.notebooks.len(),
+        ..ArchiveImportSummary::default()
+    };
+    let note_ids: HashMap<i64, i64> = archive.notes.iter().map(|n| (n.id, n.id)).collect();
+    let tag_ids: HashMap<i64, i64> = archive.tags.iter().map(|t| (t.id, t.id)).collect();
+    insert_note_rows(conn, archive, &note_ids, &tag_ids, &mut summary)?;
+    links::rebuild_links(conn)?;
+    Ok(summary)
+}
+
+fn merge_archive(conn: &Connection, archive: &Archive) -> Result<ArchiveImportSummary> {
+    let mut summary = ArchiveImportSummary::default();
+
+    let mut notebook_ids: HashMap<i64, i64> = HashMap::new();
+    for notebook in parents_first(&archive.notebooks) {
+        let parent = notebook.parent_id.map(|id| notebook_ids[&id]);
+        let existing: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM notebooks
+                 WHERE COALESCE(parent_id, 0) = COALESCE(?1, 0) AND name = ?2",
+                params![parent, notebook.name],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let local = match existing {
+            Some(id) => id,
+            None => {
+                conn.execute(
+                    "INSERT INTO notebooks (name, parent_id, created_at) VALUES (?1, ?2, ?3)",
+                    params![notebook.name, parent, notebook.created_at],
+                )
+                .with_context(|| format!("inserting notebook '{}'", notebook.name))?;
+                summary.notebooks += 1;
+                conn.last_insert_rowid()
+            }
+        };
+        notebook_ids.insert(notebook.id, local);
+    }
+
+    let mut tag_ids: HashMap<i64, i64> = HashMap::new();
+    for tag in &archive.tags {
+        summary.tags += conn
+            .execute(
+                "INSERT OR IGNORE INTO tags (name) VALUES (?1)",
+                params![tag.name],
+            )
+            .with_context(|| format!("inserting tag '{}'", tag.name))?;
+        let local: i64 = conn.query_row(
+            "SELECT id FROM tags WHERE name = ?1",
+            params![tag.name],
+            |row| row.get(0),
+        )?;
+        tag_ids.insert(tag.id, local);
+    }
+
+    // New ids are handed out up front so `[[#id]]` links between archived
+    // notes can be rewritten before the bodies are stored.
+    let mut next_id: i64 =
+        conn.query_row("SELECT COALESCE(MAX(id), 0) + 1 FROM notes", [], |row| {
+            row.get(0)
+        })?;
+    let mut note_ids: HashMap<i64, i64> = HashMap::new();
+    let mut link_ids: HashMap<i64, i64> = HashMap::new();
+    for note in &archive.notes {
+        let existing: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM notes WHERE title = ?1 AND body = ?2 AND created_at = ?3",
+                params![note.title, note.body, note.created_at],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match existing {
+            Some(id) => {
+                link_ids.insert(note.id, id);
+                summary.duplicates += 1;
+            }
+            None => {
+                note_ids.insert(note.id, next_id);
+                link_ids.insert(note.id, next_id);
+                next_id += 1;
+            }
+        }
+    }
+    for note in &archive.notes {
+        let Some(&local) = note_ids.get(&note.id) else {
+            continue;
+        };
+        let body = rewrite_id_links(&note.body, |id| link_ids.get(&id).copied())
+            .unwrap_or_else(|| note.body.clone());
+        let notebook = note.notebook_id.map(|id| notebook_ids[&id]);
+        insert_note(conn, local, note, &body, notebook)?;
+        links::sync_links(conn, local, &body)?;
+        summary.notes += 1;
+    }
+    insert_note_rows(conn, archive, &note_ids, &tag_ids, &mut summary)?;
+    Ok(summary)
+}
+
+fn insert_note(
+    conn: &Connection,
+    id: i64,
+    note: &ArchivedNote,
+    body: &str,
+    notebook_id: Option<i64>,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO notes
+            (id, title, body, created_at, updated_at, pinned, archived, deleted_at, notebook_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            id,
+            note.title,
+            body,
+            note.created_at,
+            note.updated_at,
+            note.pinned,
+            note.archived,
+            note.deleted_at,
+            notebook_id
+        ],
+    )
+    .with_context(|| format!("inserting note '{}'", note.title))?;
+    Ok(())
+}
+
+/// Tags, revisions and saved searches for the notes in `note_ids` (archive
+/// id to local id); rows of notes left out of the import are skipped.
+fn insert_note_rows(
+    conn: &Connection,
+    archive: &Archive,
+    note_ids: &HashMap<i64, i64>,
+    tag_ids: &HashMap<i64, i64>,
+    summary: &mut ArchiveImportSummary,
+) -> Result<()> {
+    for link in &archive.note_tags {
+        let Some(&note_id) = note_ids.get(&link.note_id) else {
+            continue;
+        };
+        conn.execute(
+            "INSERT OR IGNORE INTO note_tags (note_id, tag_id) VALUES (?1, ?2)",
+            params![note_id, tag_ids[&link.tag_id]],
+        )
+        .context("linking tag to note")?;
+    }
+    for revision in &archive.revisions {
+        let Some(&note_id) = note_ids.get(&revision.note_id) else {
+            continue;
+        };
+        conn.execute(
+            "INSERT INTO note_revisions (note_id, title, body, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![note_id, revision.title, revision.body, revision.created_at],
+        )
+        .context("inserting note revision")?;
+        summary.revisions += 1;
+    }
+    for saved in &archive.saved_searches {
+        summary.saved_searches += conn
+            .execute(
+                "INSERT OR IGNORE INTO saved_searches (name, query, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![saved.name, saved.query, saved.created_at, saved.updated_at],
+            )
+            .with_context(|| format!("inserting saved search '{}'", saved.name))?;
+    }
+    Ok(())
+}
+
+/// `notebooks` ordered so every notebook follows its parent. Notebooks whose
+/// parent is missing or caught in a cycle are left out.
+fn parents_first(notebooks: &[ArchivedNotebook]) -> Vec<&ArchivedNotebook> {
+    let mut placed: HashSet<i64> = HashSet::new();
+    let mut ordered = Vec::with_capacity(notebooks.len());
+    loop {
+        let before = ordered.len();
+        for notebook in notebooks {
+            if placed.contains(&notebook.id) {
+                continue;
+            }
+            if notebook.parent_id.map_or(true, |id| placed.contains(&id)) {
+                placed.insert(notebook.id);
+                ordered.push(notebook);
+            }
+        }
+        if ordered.len() == before {
+            return ordered;
+        }
+    }
+}
@@ -0,0 +1,512 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use rusqlite::backup::Backup;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use time::macros::format_description;
+use time::{Date, OffsetDateTime, UtcOffset};
+
+use super::{schema, StorageHandle};
+use crate::config::StorageOptions;
+
+const BACKUP_PAGES_PER_STEP: std::os::raw::c_int = 256;
+const BACKUP_STEP_PAUSE: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupKind {
+    Manual,
+    Exit,
+    PreRestore,
+}
+
+impl BackupKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BackupKind::Manual => "manual",
+            BackupKind::Exit => "exit",
+            BackupKind::PreRestore => "pre-restore",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "exit" => BackupKind::Exit,
+            "pre-restore" => BackupKind::PreRestore,
+            _ => BackupKind::Manual,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupRecord {
+    pub id: i64,
+    pub created_at: i64,
+    pub path: PathBuf,
+    pub kind: BackupKind,
+}
+
+impl BackupRecord {
+    /// Size of the backup file, or `None` when it has been removed from disk.
+    pub fn size_bytes(&self) -> Option<u64> {
+        fs::metadata(&self.path).ok().map(|meta| meta.len())
+    }
+}
+
+/// Rotation policy for `prune_backups`. Only automatic exit backups rotate
+/// unless `include_manual` is set; manual and pre-restore copies otherwise stay
+/// until removed explicitly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackupRotation {
+    pub keep_daily: u32,
+    pub keep_weekly: u32,
+    pub include_manual: bool,
+}
+
+impl BackupRotation {
+    /// The configured policy, covering exit backups only.
+    pub fn from_options(options: &StorageOptions) -> Self {
+        Self {
+            keep_daily: options.backup_keep_daily,
+            keep_weekly: options.backup_keep_weekly,
+            include_manual: false,
+        }
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.keep_daily == 0 && self.keep_weekly == 0
+    }
+}
+
+impl StorageHandle {
+    /// Copies the live database into `backup_dir` with the SQLite online backup
+    /// API and records the copy in the `backups` table.
+    pub fn create_backup(&self, kind: BackupKind) -> Result<BackupRecord> {
+        let dir = &self.options.backup_dir;
+        fs::create_dir_all(dir)
+            .with_context(|| format!("creating backup directory {}", dir.display()))?;
+        let now = OffsetDateTime::now_utc();
+        let target = unique_backup_path(dir, now, kind);
+
+        let conn = self.connect()?;
+        copy_database(&conn, &target)?;
+        let check = Connection::open(&target)
+            .with_context(|| format!("opening backup {}", target.display()))?;
+        verify_integrity(&check)
+            .with_context(|| format!("verifying backup {}", target.display()))?;
+        drop(check);
+
+        let path_str = target.to_string_lossy().into_owned();
+        conn.execute(
+            "INSERT INTO backups (created_at, path, kind) VALUES (?1, ?2, ?3)",
+            params![now.unix_timestamp(), path_str, kind.as_str()],
+        )
+        .context("recording backup")?;
+        Ok(BackupRecord {
+            id: conn.last_insert_rowid(),
+            created_at: now.unix_timestamp(),
+            path: target,
+            kind,
+        })
+    }
+
+    /// Recorded backups, newest first.
+    pub fn list_backups(&self) -> Result<Vec<BackupRecord>> {
+        self.with_connection(load_backup_records)
+    }
+
+    pub fn fetch_backup(&self, backup_id: i64) -> Result<Option<BackupRecord>> {
+        self.with_connection(|conn| {
+            let record = conn
+                .query_row(
+                    "SELECT id, created_at, path, kind FROM backups WHERE id = ?1",
+                    params![backup_id],
+                    map_backup_row,
+                )
+                .optional()?;
+            Ok(record)
+        })
+    }
+
+    /// Applies the daily/weekly rotation policy, deleting backup files (and their
+    /// rows) that fall outside it. Rows whose file has already vanished are
+    /// dropped as well. Zero for both limits disables rotation.
+    pub fn prune_backups(&self, rotation: BackupRotation) -> Result<Vec<BackupRecord>> {
+        let records = self.list_backups()?;
+        let mut expired: HashSet<i64> = records
+            .iter()
+            .filter(|record| !record.path.exists())
+            .map(|record| record.id)
+            .collect();
+        if !rotation.is_disabled() {
+            let candidates: Vec<BackupRecord> = records
+                .iter()
+                .filter(|record| !expired.contains(&record.id))
+                .filter(|record| rotation.include_manual || record.kind == BackupKind::Exit)
+                .cloned()
+                .collect();
+            expired.extend(rotation_victims(
+                &candidates,
+                rotation.keep_daily,
+                rotation.keep_weekly,
+                |instant| UtcOffset::local_offset_at(instant).unwrap_or(UtcOffset::UTC),
+            ));
+        }
+
+        let mut pruned = Vec::new();
+        let conn = self.connect()?;
+        for record in records {
+            if !expired.contains(&record.id) {
+                continue;
+            }
+            match fs::remove_file(&record.path) {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("removing backup {}", record.path.display()))
+                }
+            }
+            conn.execute("DELETE FROM backups WHERE id = ?1", params![record.id])
+                .context("deleting backup record")?;
+            pruned.push(record);
+        }
+        Ok(pruned)
+    }
+
+    /// Replaces the live database with the contents of `source`. The source is
+    /// integrity-checked first and the current database is backed up, so a
+    /// bad restore can itself be rolled back. Returns that safety backup.
+    pub fn restore_backup(&self, source: &Path) -> Result<BackupRecord> {
+        if !source.is_file() {
+            bail!("backup {} does not exist", source.display());
+        }
+        // Opened read-write: the FTS5 integrity check needs a writable handle.
+        let backup = Connection::open_with_flags(source, OpenFlags::SQLITE_OPEN_READ_WRITE)
+            .with_context(|| format!("opening backup {}", source.display()))?;
+        verify_integrity(&backup)
+            .with_context(|| format!("verifying backup {}", source.display()))?;
+        schema::ensure_supported(schema::current_version(&backup)?)?;
+        backup
+            .query_row("SELECT COUNT(*) FROM notes", [], |row| row.get::<_, i64>(0))
+            .with_context(|| format!("{} is not a notes database", source.display()))?;
+
+        let safety = self.create_backup(BackupKind::PreRestore)?;
+        // The backups catalog describes files on disk rather than note content,
+        // so it survives the restore untouched.
+        let catalog = self.list_backups()?;
+
+        let mut conn = self.connect()?;
+        {
+            let step = Backup::new(&backup, &mut conn).context("starting restore")?;
+            step.run_to_completion(BACKUP_PAGES_PER_STEP, BACKUP_STEP_PAUSE, None)
+                .with_context(|| format!("restoring from {}", source.display()))?;
+        }
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .context("setting journal_mode=WAL")?;
+        super::migrate(&mut conn)?;
+
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM backups", [])?;
+        for record in &catalog {
+            tx.execute(
+                "INSERT INTO backups (id, created_at, path, kind) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    record.id,
+                    record.created_at,
+                    record.path.to_string_lossy(),
+                    record.kind.as_str()
+                ],
+            )?;
+        }
+        tx.commit().context("restoring backup catalog")?;
+        verify_integrity(&conn).context("verifying restored database")?;
+        // The restored vocabulary generation may coincide with the cached one.
+        *self.vocab.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        self.mirror_all();
+        Ok(safety)
+    }
+}
+
+/// Copies `source` into a fresh database file at `target` using the online
+/// backup API, which is safe while other connections hold the WAL open.
+pub(super) fn copy_database(source: &Connection, target: &Path) -> Result<()> {
+    let mut dest = Connection::open(target)
+        .with_context(|| format!("creating backup file {}", target.display()))?;
+    let backup = Backup::new(source, &mut dest).context("starting backup")?;
+    backup
+        .run_to_completion(BACKUP_PAGES_PER_STEP, BACKUP_STEP_PAUSE, None)
+        .with_context(|| format!("writing backup {}", target.display()))?;
+    drop(backup);
+    // The copied header carries the live database's WAL flag; switch the copy to
+    // a rollback journal so it stays a single self-contained file.
+    dest.pragma_update(None, "journal_mode", "DELETE")
+        .context("setting backup journal mode")?;
+    Ok(())
+}
+
+fn verify_integrity(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let problems: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    if problems.len() == 1 && problems[0] == "ok" {
+        return Ok(());
+    }
+    bail!("integrity check failed: {}", problems.join("; "))
+}
+
+fn load_backup_records(conn: &Connection) -> Result<Vec<BackupRecord>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, created_at, path, kind
+         FROM backups
+         ORDER BY created_at DESC, id DESC",
+    )?;
+    let rows = stmt.query_map([], map_backup_row)?;
+    let mut records = Vec::new();
+    for row in rows {
+        records.push(row?);
+    }
+    Ok(records)
+}
+
+fn map_backup_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<BackupRecord> {
+    let path: String = row.get(2)?;
+    let kind: String = row.get(3)?;
+    Ok(BackupRecord {
+        id: row.get(0)?,
+        created_at: row.get(1)?,
+        path: PathBuf::from(path),
+        kind: BackupKind::parse(&kind),
+    })
+}
+
+fn unique_backup_path(dir: &Path, now: OffsetDateTime, kind: BackupKind) -> PathBuf {
+    let stamp = now
+        .format(format_description!(
+            "[year][month][day]-[hour][minute][second]"
+        ))
+        .unwrap_or_else(|_| now.unix_timestamp().to_string());
+    let base = format!("notes-{stamp}-{}", kind.as_str());
+    let mut candidate = dir.join(format!("{base}.db"));
+    let mut counter = 1;
+    while candidate.exists() {
+        candidate = dir.join(format!("{base}-{counter}.db"));
+        counter += 1;
+    }
+    candidate
+}
+
+/// Ids of backups outside the rotation policy. The newest backup of each of
+/// the `keep_daily` most recent days and of each of the `keep_weekly` most
+/// recent ISO weeks survives; everything else is expired. Days and weeks
+/// follow the UTC offset `offset_at` gives for each backup's time. `records`
+/// must be ordered newest first.
+fn rotation_victims(
+    records: &[BackupRecord],
+    keep_daily: u32,
+    keep_weekly: u32,
+    offset_at: impl Fn(OffsetDateTime) -> UtcOffset,
+) -> Vec<i64> {
+    let mut days: Vec<Date> = Vec::new();
+    let mut weeks: Vec<(i32, u8)> = Vec::new();
+    let mut keep = HashSet::new();
+    for record in records {
+        let instant = OffsetDateTime::from_unix_timestamp(record.created_at)
+            .unwrap_or(OffsetDateTime::UNIX_EPOCH);
+        let date = instant.to_offset(offset_at(instant)).date();
+        if !days.contains(&date) && days.len() < keep_daily as usize {
+            days.push(date);
+            keep.insert(record.id);
+        }
+        let (year, week, _) = date.to_iso_week_date();
+        if !weeks.contains(&(year, week)) && weeks.len() < keep_weekly as usize {
+            weeks.push((year, week));
+            keep.insert(record.id);
+        }
+    }
+    records
+        .iter()
+        .filter(|record| !keep.contains(&record.id))
+        .map(|record| record.id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigPaths;
+    use tempfile::TempDir;
+
+    const DAY: i64 = 86_400;
+    // 2024-01-01 was a Monday, so every seven days starts a new ISO week.
+    const MONDAY: i64 = 1_704_067_200;
+
+    fn utc(_: OffsetDateTime) -> UtcOffset {
+        UtcOffset::UTC
+    }
+
+    fn record(id: i64, created_at: i64) -> BackupRecord {
+        BackupRecord {
+            id,
+            created_at,
+            path: PathBuf::from(format!("/tmp/backup-{id}.db")),
+            kind: BackupKind::Manual,
+        }
+    }
+
+    #[test]
+    fn rotation_keeps_newest_per_day_and_week() {
+        // Two backups on each of the first 15 days of January, newest first.
+        let mut records = Vec::new();
+        let mut id = 100;
+        for day in (0..15).rev() {
+            records.push(record(id, MONDAY + day * DAY + 3_600 * 20));
+            records.push(record(id + 1, MONDAY + day * DAY + 3_600 * 8));
+            id += 2;
+        }
+
+        let victims = rotation_victims(&records, 3, 2, utc);
+        let kept: Vec<i64> = records
+            .iter()
+            .map(|record| record.id)
+            .filter(|id| !victims.contains(id))
+            .collect();
+        // Jan 15, 14 and 13 (daily); the two weekly slots land on Jan 15 and
+        // Jan 14, which the daily slots already keep.
+        assert_eq!(kept, vec![100, 102, 104]);
+
+        let victims = rotation_victims(&records, 1, 3, utc);
+        let kept: Vec<i64> = records
+            .iter()
+            .map(|record| record.id)
+            .filter(|id| !victims.contains(id))
+            .collect();
+        // Jan 15 (week 3), Jan 14 (week 2) and Jan 7 (week 1).
+        assert_eq!(kept, vec![100, 102, 116]);
+    }
+
+    #[test]
+    fn rotation_with_zero_limits_expires_everything() {
+        let records = vec![record(1, MONDAY), record(2, MONDAY - DAY)];
+        assert_eq!(rotation_victims(&records, 0, 0, utc), vec![1, 2]);
+    }
+
+    #[test]
+    fn rotation_buckets_days_in_the_local_timezone() {
+        // 03:00 and 20:00 UTC on Jan 1 and 03:00 UTC on Jan 2; five hours
+        // west of UTC the first falls on Dec 31 and the others on Jan 1.
+        let records = vec![
+            record(1, MONDAY + DAY + 3 * 3_600),
+            record(2, MONDAY + 20 * 3_600),
+            record(3, MONDAY + 3 * 3_600),
+        ];
+        assert_eq!(rotation_victims(&records, 2, 0, utc), vec![3]);
+        let new_york = |_| UtcOffset::from_hms(-5, 0, 0).unwrap();
+        assert_eq!(rotation_victims(&records, 2, 0, new_york), vec![2]);
+    }
+
+    fn init_storage() -> Result<(TempDir, StorageHandle)> {
+        let temp = TempDir::new()?;
+        let base = temp.path();
+        let paths = ConfigPaths {
+            config_dir: base.join("config"),
+            config_file: base.join("config/config.toml"),
+            data_dir: base.join("data"),
+            database_path: base.join("data/notes.db"),
+            cache_dir: base.join("cache"),
+            backup_dir: base.join("backups"),
+            log_dir: base.join("logs"),
+            state_dir: base.join("state"),
+            theme_dir: base.join("config/themes"),
+        };
+        paths.ensure_directories()?;
+        let options = StorageOptions {
+            database_path: paths.database_path.clone(),
+            backup_dir: paths.backup_dir.clone(),
+            ..StorageOptions::default()
+        };
+        let storage = super::super::init(&paths, &options)?;
+        Ok((temp, storage))
+    }
+
+    #[test]
+    fn create_and_restore_round_trip() -> Result<()> {
+        let (_temp, storage) = init_storage()?;
+        let note_id = storage.create_note("Keep", "original body", false)?;
+        let backup = storage.create_backup(BackupKind::Manual)?;
+        assert!(backup.path.exists());
+        assert_eq!(storage.list_backups()?, vec![backup.clone()]);
+
+        storage.update_note_body(note_id, "damaged body")?;
+        let doomed = storage.create_note("Added later", "", false)?;
+
+        let safety = storage.restore_backup(&backup.path)?;
+        assert_eq!(safety.kind, BackupKind::PreRestore);
+        let note = storage.fetch_note_by_id(note_id)?.expect("note restored");
+        assert_eq!(note.body, "original body");
+        assert!(storage.fetch_note_by_id(doomed)?.is_none());
+
+        // The catalog reflects both backups even though the restored copy
+        // predates the safety backup.
+        let ids: Vec<i64> = storage.list_backups()?.iter().map(|r| r.id).collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&backup.id) && ids.contains(&safety.id));
+        Ok(())
+    }
+
+    #[test]
+    fn restore_rejects_corrupt_backup() -> Result<()> {
+        let (temp, storage) = init_storage()?;
+        let note_id = storage.create_note("Keep", "live body", false)?;
+        let bogus = temp.path().join("bogus.db");
+        fs::write(&bogus, b"definitely not sqlite")?;
+
+        assert!(storage.restore_backup(&bogus).is_err());
+        assert!(storage.list_backups()?.is_empty(), "no safety backup taken");
+        let note = storage.fetch_note_by_id(note_id)?.expect("note untouched");
+        assert_eq!(note.body, "live body");
+        Ok(())
+    }
+
+    #[test]
+    fn prune_drops_missing_files_and_rotates() -> Result<()> {
+        let (_temp, storage) = init_storage()?;
+        let missing = storage.create_backup(BackupKind::Exit)?;
+        let manual = storage.create_backup(BackupKind::Manual)?;
+        let older_exit = storage.create_backup(BackupKind::Exit)?;
+        let newest_exit = storage.create_backup(BackupKind::Exit)?;
+        fs::remove_file(&missing.path)?;
+
+        let disabled = BackupRotation {
+            keep_daily: 0,
+            keep_weekly: 0,
+            include_manual: true,
+        };
+        assert_eq!(storage.prune_backups(disabled)?, vec![missing]);
+
+        // Same-day exit backups collapse to the newest one; manual copies stay.
+        let policy = BackupRotation {
+            keep_daily: 7,
+            keep_weekly: 4,
+            include_manual: false,
+        };
+        assert_eq!(storage.prune_backups(policy)?, vec![older_exit.clone()]);
+        assert!(!older_exit.path.exists());
+        assert_eq!(
+            storage.list_backups()?,
+            vec![newest_exit.clone(), manual.clone()]
+        );
+
+        let everything = BackupRotation {
+            include_manual: true,
+            ..policy
+        };
+        assert_eq!(storage.prune_backups(everything)?, vec![manual]);
+        assert_eq!(storage.list_backups()?, vec![newest_exit]);
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+
+/// Most neighbours a single query token expands to, closest first.
+const MAX_EXPANSIONS: usize = 8;
+
+/// Trigram index over every token in `fts_notes`, read through the `fts_vocab`
+/// (fts5vocab) table. Built in memory on the first fuzzy search and rebuilt when
+/// `fts_vocab_state.generation` shows that note text has changed since.
+#[derive(Debug, Default)]
+pub(super) struct VocabIndex {
+    generation: i64,
+    terms: Vec<String>,
+    trigrams: HashMap<String, Vec<usize>>,
+}
+
+impl VocabIndex {
+    pub(super) fn generation(conn: &Connection) -> Result<i64> {
+        conn.query_row("SELECT generation FROM fts_vocab_state", [], |row| {
+            row.get(0)
+        })
+        .context("reading vocabulary generation")
+    }
+
+    pub(super) fn load(conn: &Connection, generation: i64) -> Result<Self> {
+        let mut stmt = conn.prepare("SELECT term FROM fts_vocab")?;
+        let terms = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()
+            .context("reading search vocabulary")?;
+        Ok(Self::build(generation, terms))
+    }
+
+    fn build(generation: i64, terms: Vec<String>) -> Self {
+        let mut trigrams: HashMap<String, Vec<usize>> = HashMap::new();
+        for (idx, term) in terms.iter().enumerate() {
+            for trigram in trigrams_of(term) {
+                let postings = trigrams.entry(trigram).or_default();
+                if postings.last() != Some(&idx) {
+                    postings.push(idx);
+                }
+            }
+        }
+        Self {
+            generation,
+            terms,
+            trigrams,
+        }
+    }
+
+    pub(super) fn is_current(&self, generation: i64) -> bool {
+        self.generation == generation
+    }
+
+    /// Vocabulary terms within `threshold` of `token`: an edit distance of at most
+    /// `threshold` × the token's length (so `0.4` lets a five-letter word differ by
+    /// two edits). Terms the prefix query already matches are left out.
+    pub(super) fn neighbours(&self, token: &str, threshold: f32) -> Vec<String> {
+        let token = token.to_lowercase();
+        let length = token.chars().count();
+        let max_distance = (threshold.clamp(0.0, 1.0) * length as f32).floor() as usize;
+        if max_distance == 0 {
+            return Vec::new();
+        }
+
+        let mut candidates = HashSet::new();
+        for trigram in trigrams_of(&token) {
+            if let Some(postings) = self.trigrams.get(&trigram) {
+                candidates.extend(postings.iter().copied());
+            }
+        }
+
+        let mut matches: Vec<(usize, &str)> = candidates
+            .into_iter()
+            .map(|idx| self.terms[idx].as_str())
+            .filter(|term| !term.starts_with(&token))
+            .filter(|term| term.chars().count().abs_diff(length) <= max_distance)
+            .filter_map(|term| {
+                let distance = edit_distance(&token, term);
+                (distance <= max_distance).then_some((distance, term))
+            })
+            .collect();
+        matches.sort();
+        matches
+            .into_iter()
+            .take(MAX_EXPANSIONS)
+            .map(|(_, term)| term.to_string())
+            .collect()
+    }
+}
+
+/// Character trigrams of `term` padded with a boundary marker, so short words
+/// and word starts/ends still produce grams.
+fn trigrams_of(term: &str) -> Vec<String> {
+    let chars: Vec<char> = std::iter::once('$')
+        .chain(term.chars())
+        .chain(std::iter::once('$'))
+        .collect();
+    chars
+        .windows(3)
+        .map(|window| window.iter().collect())
+        .collect()
+}
+
+/// Levenshtein distance counting an adjacent transposition as one edit
+/// (optimal string alignment), over characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(terms: &[&str]) -> VocabIndex {
+        VocabIndex::build(1, terms.iter().map(|term| term.to_string()).collect())
+    }
+
+    #[test]
+    fn neighbours_respect_the_threshold() {
+        let vocab = index(&["receive", "recipe", "deceive", "meeting", "meetings"]);
+        assert_eq!(
+            vocab.neighbours("recieve", 0.4),
+            vec!["receive", "deceive", "recipe"]
+        );
+        assert_eq!(vocab.neighbours("recieve", 0.2), vec!["receive"]);
+        assert!(vocab.neighbours("recieve", 0.0).is_empty());
+        // Prefix matches are already covered by `meet*`.
+        assert!(vocab.neighbours("meet", 0.4).is_empty());
+        assert_eq!(vocab.neighbours("MEETNG", 0.4), vec!["meeting", "meetings"]);
+        assert!(
+            vocab.neighbours("ab", 0.4).is_empty(),
+            "too short to expand"
+        );
+    }
+
+    #[test]
+    fn edit_distance_counts_transpositions_once() {
+        assert_eq!(edit_distance("recieve", "receive"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("über", "uber"), 1);
+    }
+}
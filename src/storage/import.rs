@@ -0,0 +1,79 @@
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection};
+
+use super::{links, notebooks, StorageHandle};
+
+/// A note to create with the metadata it had before it was imported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedNote {
+    pub title: String,
+    pub body: String,
+    pub tags: Vec<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub pinned: bool,
+    pub archived: bool,
+    /// Notebook path such as `Work/Projects`, created if missing.
+    pub notebook: Option<String>,
+}
+
+impl StorageHandle {
+    /// Creates every note in one transaction, keeping its timestamps, and
+    /// returns the new ids in order. Nothing is written if any note fails.
+    pub fn import_notes(&self, notes: &[ImportedNote]) -> Result<Vec<i64>> {
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+        let mut ids = Vec::with_capacity(notes.len());
+        for note in notes {
+            ids.push(insert_note(&tx, note)?);
+        }
+        tx.commit()?;
+        self.mirror_notes(&ids);
+        Ok(ids)
+    }
+}
+
+fn insert_note(conn: &Connection, note: &ImportedNote) -> Result<i64> {
+    let title = note.title.trim();
+    if title.is_empty() {
+        bail!("note title cannot be empty");
+    }
+    let notebook_id = match &note.notebook {
+        Some(path) => Some(notebooks::ensure_notebook(conn, path)?),
+        None => None,
+    };
+    conn.execute(
+        "INSERT INTO notes (title, body, created_at, updated_at, pinned, archived, notebook_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            title,
+            note.body,
+            note.created_at,
+            note.updated_at,
+            note.pinned,
+            note.archived,
+            notebook_id
+        ],
+    )
+    .with_context(|| format!("inserting note '{title}'"))?;
+    let note_id = conn.last_insert_rowid();
+    for tag in &note.tags {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            continue;
+        }
+        conn.execute(
+            "INSERT OR IGNORE INTO tags (name) VALUES (?1)",
+            params![tag],
+        )
+        .context("inserting tag")?;
+        conn.execute(
+            "INSERT OR IGNORE INTO note_tags (note_id, tag_id)
+             SELECT ?1, id FROM tags WHERE name = ?2",
+            params![note_id, tag],
+        )
+        .context("linking tag to note")?;
+    }
+    links::sync_links(conn, note_id, &note.body)?;
+    Ok(note_id)
+}
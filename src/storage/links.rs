@@ -0,0 +1,229 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use super::{record_revision, StorageHandle};
+use crate::markdown::{parse_links, rewrite_title_links, LinkTarget};
+
+/// A note at either end of a wiki link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkedNote {
+    pub id: i64,
+    pub title: String,
+    pub archived: bool,
+}
+
+/// A wiki link written in a note body and the note it currently resolves to,
+/// if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteLink {
+    pub target: LinkTarget,
+    pub note: Option<LinkedNote>,
+}
+
+impl StorageHandle {
+    /// The links in a note's body, in the order they appear.
+    pub fn outgoing_links(&self, note_id: i64) -> Result<Vec<NoteLink>> {
+        self.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT target_title, target_id FROM note_links
+                 WHERE source_id = ?1
+                 ORDER BY position",
+            )?;
+            let targets = stmt
+                .query_map(params![note_id], |row| {
+                    let title: Option<String> = row.get(0)?;
+                    let id: Option<i64> = row.get(1)?;
+                    Ok(match (title, id) {
+                        (Some(title), _) => LinkTarget::Title(title),
+                        (None, id) => LinkTarget::Id(id.unwrap_or_default()),
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()
+                .context("reading note links")?;
+            targets
+                .into_iter()
+                .map(|target| {
+                    let note = resolve(conn, &target)?;
+                    Ok(NoteLink { target, note })
+                })
+                .collect()
+        })
+    }
+
+    /// Notes outside the trash whose bodies link to `note_id`, by title or by
+    /// id, ordered by title. A title link only counts when this note is the
+    /// one it resolves to.
+    pub fn backlinks(&self, note_id: i64) -> Result<Vec<LinkedNote>> {
+        self.with_connection(|conn| {
+            let title: Option<String> = conn
+                .query_row(
+                    "SELECT title FROM notes WHERE id = ?1 AND deleted_at IS NULL",
+                    params![note_id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let Some(title) = title else {
+                return Ok(Vec::new());
+            };
+            let mut stmt = conn.prepare(
+                "SELECT DISTINCT n.id, n.title, n.archived
+                 FROM note_links l
+                 JOIN notes n ON n.id = l.source_id
+                 WHERE n.deleted_at IS NULL
+                   AND n.id != ?1
+                   AND (
+                       l.target_id = ?1
+                       OR (
+                           l.target_title = ?2
+                           AND NOT EXISTS (
+                               SELECT 1 FROM notes o
+                               WHERE o.deleted_at IS NULL
+                                 AND o.title = ?2 COLLATE NOCASE
+                                 AND o.id < ?1
+                           )
+                       )
+                   )
+                 ORDER BY n.title COLLATE NOCASE, n.id",
+            )?;
+            let notes = stmt
+                .query_map(params![note_id, title], map_linked_row)?
+                .collect::<Result<Vec<_>, _>>()
+                .context("reading backlinks")?;
+            Ok(notes)
+        })
+    }
+
+    /// The note a link points to: the note with that id, or the oldest note
+    /// with that title ignoring case. Notes in the trash are never targets.
+    pub fn resolve_link(&self, target: &LinkTarget) -> Result<Option<LinkedNote>> {
+        self.with_connection(|conn| resolve(conn, target))
+    }
+}
+
+fn resolve(conn: &Connection, target: &LinkTarget) -> Result<Option<LinkedNote>> {
+    let note = match target {
+        LinkTarget::Id(id) => conn
+            .query_row(
+                "SELECT id, title, archived FROM notes
+                 WHERE id = ?1 AND deleted_at IS NULL",
+                params![id],
+                map_linked_row,
+            )
+            .optional()?,
+        LinkTarget::Title(title) => conn
+            .query_row(
+                "SELECT id, title, archived FROM notes
+                 WHERE title = ?1 COLLATE NOCASE AND deleted_at IS NULL
+                 ORDER BY id
+                 LIMIT 1",
+                params![title],
+                map_linked_row,
+            )
+            .optional()?,
+    };
+    Ok(note)
+}
+
+/// Replaces the stored links of `note_id` with the ones in `body`.
+pub(super) fn sync_links(conn: &Connection, note_id: i64, body: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM note_links WHERE source_id = ?1",
+        params![note_id],
+    )
+    .context("clearing note links")?;
+    let mut insert = conn.prepare(
+        "INSERT INTO note_links (source_id, position, target_title, target_id)
+         VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for (position, link) in parse_links(body).into_iter().enumerate() {
+        let (title, id) = match link.target {
+            LinkTarget::Title(title) => (Some(title), None),
+            LinkTarget::Id(id) => (None, Some(id)),
+        };
+        insert
+            .execute(params![note_id, position as i64, title, id])
+            .context("recording note link")?;
+    }
+    Ok(())
+}
+
+/// Re-derives every note's links from its body.
+pub(super) fn rebuild_links(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT id, body FROM notes")?;
+    let notes = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("reading notes for link index")?;
+    for (id, body) in notes {
+        sync_links(conn, id, &body)?;
+    }
+    Ok(())
+}
+
+/// After `note_id` was renamed from `old` to `new`, rewrites `[[old]]` links
+/// that pointed at it to `[[new]]`, recording a revision of each rewritten
+/// note. Links are left alone when they resolved to an older note with the
+/// same title, or when `new` would not read back as a title link. Rewritten
+/// notes count as updated now (the `notes_touch_updated` trigger sees to
+/// that). Returns the number of notes rewritten.
+pub(super) fn rewrite_renamed_links(
+    conn: &Connection,
+    note_id: i64,
+    old: &str,
+    new: &str,
+) -> Result<usize> {
+    let round_trips = parse_links(&format!("[[{new}]]"))
+        .first()
+        .is_some_and(|link| link.target == LinkTarget::Title(new.to_string()));
+    if !round_trips {
+        return Ok(0);
+    }
+    let shadowed = conn
+        .query_row(
+            "SELECT 1 FROM notes
+             WHERE title = ?1 COLLATE NOCASE AND deleted_at IS NULL AND id < ?2",
+            params![old, note_id],
+            |_row| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if shadowed {
+        return Ok(0);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT id, body FROM notes
+         WHERE id IN (SELECT source_id FROM note_links WHERE target_title = ?1)",
+    )?;
+    let sources = stmt
+        .query_map(params![old], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("reading notes linking to the old title")?;
+    let mut rewritten = 0;
+    for (source_id, body) in sources {
+        let Some(body) = rewrite_title_links(&body, old, new) else {
+            continue;
+        };
+        record_revision(conn, source_id, false)?;
+        conn.execute(
+            "UPDATE notes SET body = ?1 WHERE id = ?2",
+            params![body, source_id],
+        )
+        .context("rewriting links to a renamed note")?;
+        sync_links(conn, source_id, &body)?;
+        rewritten += 1;
+    }
+    Ok(rewritten)
+}
+
+fn map_linked_row(row: &Row<'_>) -> rusqlite::Result<LinkedNote> {
+    Ok(LinkedNote {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        archived: row.get::<_, i64>(2)? != 0,
+    })
+}
@@ -0,0 +1,649 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use super::{NoteRecord, StorageHandle};
+use crate::config::MirrorOptions;
+use crate::search::SearchQuery;
+use crate::transfer::{self, markdown, markdown::ExportReport};
+
+/// Fallback identity for commits, used for whichever of these keys git has
+/// no value for, so the mirror works on machines that never set them.
+const FALLBACK_IDENTITY: [(&str, &str); 2] = [
+    ("user.name", "notetui"),
+    ("user.email", "notetui@localhost"),
+];
+
+/// What rebuilding the mirror did: the files it rewrote or removed, and
+/// whether that produced a commit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MirrorRebuild {
+    pub files: ExportReport,
+    pub committed: bool,
+}
+
+/// A git working tree holding every note outside the trash as a Markdown
+/// file named like `export --format markdown` names them.
+///
+/// Saves only queue the ids of the notes they touched; the files are written
+/// when [`StorageHandle::sync_mirror`] drains the queue, which the TUI does
+/// on every tick. Commits are batched to at most one per `commit_interval`
+/// and run as git processes that later syncs check on without waiting, so
+/// neither a save nor a tick blocks on git. [`StorageHandle::flush_mirror`]
+/// writes and commits whatever is left, waiting for git.
+///
+/// There is no worker thread on purpose: the `time` crate stops reporting
+/// the local offset once a second thread runs (see `Calendar::local`).
+pub(super) struct Mirror {
+    dir: PathBuf,
+    commit_interval: Duration,
+    state: Mutex<MirrorState>,
+}
+
+struct MirrorState {
+    /// Set once the directory exists and holds a repository.
+    ready: bool,
+    /// Notes saved since the queue was last drained.
+    queued: BTreeSet<i64>,
+    /// Set after a change that may touch any note.
+    queued_all: bool,
+    /// Mirror files by note id. Read from the directory by
+    /// [`Mirror::prepare`] and by full rewrites, then kept up to date by every
+    /// write, so a save does not rescan the directory.
+    files: HashMap<i64, Vec<PathBuf>>,
+    /// Files written or removed since the last commit started.
+    changed: HashSet<PathBuf>,
+    /// `-c key=value` arguments filling in the commit identity git lacks,
+    /// worked out once by [`Mirror::prepare`].
+    identity: Vec<String>,
+    /// The batch commit in progress, if any.
+    commit: Option<BatchCommit>,
+    last_commit: Instant,
+}
+
+/// A commit made one git process at a time, so it can be checked on without
+/// waiting for it.
+struct BatchCommit {
+    step: CommitStep,
+    child: Child,
+    message: String,
+    /// The changed files the commit covers, pending again if it fails.
+    files: HashSet<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommitStep {
+    /// `git add --all`
+    Staging,
+    /// `git diff --cached --quiet`, which exits with 1 when something is
+    /// staged.
+    Checking,
+    /// `git commit`
+    Committing,
+}
+
+impl CommitStep {
+    fn command(self) -> &'static str {
+        match self {
+            Self::Staging => "git add",
+            Self::Checking => "git diff",
+            Self::Committing => "git commit",
+        }
+    }
+}
+
+impl Mirror {
+    pub(super) fn new(options: &MirrorOptions) -> Self {
+        Self {
+            dir: options.repo_dir.clone(),
+            commit_interval: Duration::from_secs(options.commit_interval_secs),
+            state: Mutex::new(MirrorState {
+                ready: false,
+                queued: BTreeSet::new(),
+                queued_all: false,
+                files: HashMap::new(),
+                changed: HashSet::new(),
+                identity: Vec::new(),
+                commit: None,
+                // The first batch waits a full interval too, so a short CLI
+                // run makes a single commit when it flushes.
+                last_commit: Instant::now(),
+            }),
+        }
+    }
+
+    /// Queues `note_ids` (every note with `None`) for the next sync.
+    fn queue(&self, note_ids: Option<&[i64]>) {
+        let mut state = self.lock();
+        match note_ids {
+            Some(ids) => state.queued.extend(ids),
+            None => state.queued_all = true,
+        }
+    }
+
+    /// Writes the queued notes, checks on a running commit, and starts the
+    /// next one once the batch interval has passed.
+    fn sync(&self, storage: &StorageHandle) -> Result<()> {
+        let mut state = self.lock();
+        self.drain(&mut state, storage)?;
+        self.finish_commit(&mut state, false)?;
+        if state.commit.is_none()
+            && !state.changed.is_empty()
+            && state.last_commit.elapsed() >= self.commit_interval
+        {
+            self.start_commit(&mut state, None)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&self, storage: &StorageHandle) -> Result<bool> {
+        let mut state = self.lock();
+        self.drain(&mut state, storage)?;
+        let mut committed = self.finish_running(&mut state);
+        if !state.changed.is_empty() {
+            self.start_commit(&mut state, None)?;
+            committed |= self.finish_commit(&mut state, true)?.unwrap_or(false);
+        }
+        Ok(committed)
+    }
+
+    fn rebuild(&self, storage: &StorageHandle) -> Result<MirrorRebuild> {
+        let mut state = self.lock();
+        // The full rewrite below covers anything still queued.
+        state.queued.clear();
+        state.queued_all = false;
+        self.prepare(&mut state)?;
+        self.finish_running(&mut state);
+        let files = self.write_all(&mut state, storage)?;
+        self.start_commit(&mut state, Some("Rebuild mirror"))?;
+        let committed = self.finish_commit(&mut state, true)?.unwrap_or(false);
+        Ok(MirrorRebuild { files, committed })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MirrorState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Creates the directory and repository on first use, reads the files
+    /// already in it and settles the commit identity; returns whether the
+    /// repository is new.
+    fn prepare(&self, state: &mut MirrorState) -> Result<bool> {
+        if state.ready {
+            return Ok(false);
+        }
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("creating mirror directory {}", self.dir.display()))?;
+        let created = !self.dir.join(".git").exists();
+        if created {
+            self.git(&["init", "--quiet"])?;
+            tracing::info!(path = %self.dir.display(), "initialised git mirror");
+        }
+        state.files = markdown::exported_files(&self.dir)?;
+        state.identity = FALLBACK_IDENTITY
+            .into_iter()
+            .filter(|(key, _)| self.git(&["config", key]).is_err())
+            .flat_map(|(key, value)| ["-c".to_string(), format!("{key}={value}")])
+            .collect();
+        state.ready = true;
+        Ok(created)
+    }
+
+    /// Writes the notes queued since the last drain.
+    fn drain(&self, state: &mut MirrorState, storage: &StorageHandle) -> Result<()> {
+        if !state.queued_all && state.queued.is_empty() {
+            return Ok(());
+        }
+        // Taken before anything can fail, so a broken mirror reports each
+        // save once instead of on every tick.
+        let all = std::mem::take(&mut state.queued_all);
+        let note_ids = std::mem::take(&mut state.queued);
+        // A new mirror starts out with every note, not just the saved ones.
+        let created = self.prepare(state)?;
+        if all || created {
+            self.write_all(state, storage)?;
+        } else {
+            let mut report = ExportReport::default();
+            for note_id in note_ids {
+                let note = storage.fetch_note_by_id(note_id)?;
+                self.write_note(state, note_id, note, &mut report)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rewrites every note outside the trash and removes the files of notes
+    /// that are gone. The directory is read again first, so files edited or
+    /// deleted by hand are put right.
+    fn write_all(&self, state: &mut MirrorState, storage: &StorageHandle) -> Result<ExportReport> {
+        state.files = markdown::exported_files(&self.dir)?;
+        let notes = transfer::select_notes(storage, &SearchQuery::default())?;
+        let live: HashSet<i64> = notes.iter().map(|note| note.id).collect();
+        let gone: Vec<i64> = state
+            .files
+            .keys()
+            .copied()
+            .filter(|id| !live.contains(id))
+            .collect();
+        let mut report = ExportReport::default();
+        for note in notes {
+            self.write_note(state, note.id, Some(note), &mut report)?;
+        }
+        for note_id in gone {
+            self.write_note(state, note_id, None, &mut report)?;
+        }
+        Ok(report)
+    }
+
+    /// Brings the file of `note_id` up to date with `note` (`None` when it is
+    /// in the trash or deleted) and removes the files it leaves behind, such
+    /// as the one named after an old title.
+    fn write_note(
+        &self,
+        state: &mut MirrorState,
+        note_id: i64,
+        note: Option<NoteRecord>,
+        report: &mut ExportReport,
+    ) -> Result<()> {
+        let kept = match note.map(mirrored) {
+            Some(note) => {
+                let path = self.dir.join(markdown::file_name(&note));
+                let rendered = markdown::render_note(&note);
+                if fs::read_to_string(&path).ok().as_deref() == Some(rendered.as_str()) {
+                    report.unchanged += 1;
+                } else {
+                    fs::write(&path, rendered)
+                        .with_context(|| format!("writing {}", path.display()))?;
+                    state.changed.insert(path.clone());
+                    report.written += 1;
+                }
+                Some(path)
+            }
+            None => None,
+        };
+        let previous = state.files.remove(&note_id).unwrap_or_default();
+        for path in previous
+            .into_iter()
+            .filter(|path| Some(path) != kept.as_ref())
+        {
+            remove_file(&path)?;
+            state.changed.insert(path);
+            report.removed += 1;
+        }
+        if let Some(path) = kept {
+            state.files.insert(note_id, vec![path]);
+        }
+        Ok(())
+    }
+
+    /// Starts committing everything in the working tree. `message` defaults
+    /// to a count of the files changed since the last commit.
+    fn start_commit(&self, state: &mut MirrorState, message: Option<&str>) -> Result<()> {
+        // Failed attempts wait for the next interval too, so a broken git
+        // setup is not retried on every tick.
+        state.last_commit = Instant::now();
+        let files = std::mem::take(&mut state.changed);
+        let message = match message {
+            Some(message) => message.to_string(),
+            None => format!(
+                "Update {} note file{}",
+                files.len(),
+                if files.len() == 1 { "" } else { "s" }
+            ),
+        };
+        match self.spawn_git(&["add", "--all"]) {
+            Ok(child) => {
+                state.commit = Some(BatchCommit {
+                    step: CommitStep::Staging,
+                    child,
+                    message,
+                    files,
+                });
+                Ok(())
+            }
+            Err(err) => {
+                state.changed.extend(files);
+                Err(err)
+            }
+        }
+    }
+
+    /// Moves the running commit along as far as its git processes allow,
+    /// waiting for them when `wait` is set. Returns whether it made a commit
+    /// once it is over, or `None` while it is still running or when there is
+    /// none.
+    fn finish_commit(&self, state: &mut MirrorState, wait: bool) -> Result<Option<bool>> {
+        loop {
+            let Some(commit) = state.commit.as_mut() else {
+                return Ok(None);
+            };
+            let status = if wait {
+                commit.child.wait().map(Some)
+            } else {
+                commit.child.try_wait()
+            };
+            let Some(status) = status.context("waiting for git")? else {
+                return Ok(None);
+            };
+            let mut commit = state.commit.take().expect("commit checked above");
+            let next = match (commit.step, status.code()) {
+                (CommitStep::Staging, Some(0)) => CommitStep::Checking,
+                (CommitStep::Checking, Some(0)) => return Ok(Some(false)),
+                (CommitStep::Checking, Some(1)) => CommitStep::Committing,
+                (CommitStep::Committing, Some(0)) => return Ok(Some(true)),
+                (step, _) => {
+                    state.changed.extend(commit.files);
+                    return Err(self.step_failed(step, status));
+                }
+            };
+            let spawned = match next {
+                CommitStep::Checking => self.spawn_git(&["diff", "--cached", "--quiet"]),
+                _ => {
+                    let mut args: Vec<&str> = state.identity.iter().map(String::as_str).collect();
+                    args.extend(["commit", "--quiet", "--message", &commit.message]);
+                    self.spawn_git(&args)
+                }
+            };
+            match spawned {
+                Ok(child) => {
+                    commit.step = next;
+                    commit.child = child;
+                    state.commit = Some(commit);
+                }
+                Err(err) => {
+                    state.changed.extend(commit.files);
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    /// Waits for a commit started by an earlier sync; returns whether it
+    /// committed. A failure is only logged, since its files are pending again
+    /// and go into the commit that follows.
+    fn finish_running(&self, state: &mut MirrorState) -> bool {
+        self.finish_commit(state, true)
+            .unwrap_or_else(|err| {
+                tracing::warn!(?err, "failed to commit the git mirror");
+                None
+            })
+            .unwrap_or(false)
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .output()
+            .context("running git (is it installed?)")?;
+        if !output.status.success() {
+            bail!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Starts git without waiting for it. Its stderr goes to a file rather
+    /// than a pipe, since nothing reads a pipe until git exits and a full one
+    /// would stall it.
+    fn spawn_git(&self, args: &[&str]) -> Result<Child> {
+        let stderr = File::create(self.stderr_path()).map_or_else(|_| Stdio::null(), Stdio::from);
+        Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(stderr)
+            .spawn()
+            .context("running git (is it installed?)")
+    }
+
+    /// Where [`Mirror::spawn_git`] sends stderr: inside `.git`, out of the
+    /// working tree that gets committed.
+    fn stderr_path(&self) -> PathBuf {
+        self.dir.join(".git").join("notetui-stderr")
+    }
+
+    /// The error for a commit step that exited with `status`, with what git
+    /// printed to stderr.
+    fn step_failed(&self, step: CommitStep, status: ExitStatus) -> anyhow::Error {
+        let stderr = fs::read_to_string(self.stderr_path()).unwrap_or_default();
+        anyhow!("{} failed ({status}): {}", step.command(), stderr.trim())
+    }
+}
+
+impl StorageHandle {
+    /// Queues `note_ids` for the mirror after they were saved. Nothing is
+    /// written until the next [`StorageHandle::sync_mirror`].
+    pub(super) fn mirror_notes(&self, note_ids: &[i64]) {
+        if let Some(mirror) = &self.mirror {
+            mirror.queue(Some(note_ids));
+        }
+    }
+
+    /// Queues every note for the mirror after a change that may touch any of
+    /// them, such as a tag rename or an import.
+    pub(super) fn mirror_all(&self) {
+        if let Some(mirror) = &self.mirror {
+            mirror.queue(None);
+        }
+    }
+
+    /// Writes the notes saved since the last call into the mirror and moves
+    /// batch commits along without waiting for git. Meant to be called
+    /// regularly, such as on every TUI tick; a no-op when the mirror is off.
+    pub fn sync_mirror(&self) -> Result<()> {
+        match &self.mirror {
+            Some(mirror) => mirror.sync(self),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes and commits mirror changes still waiting for their batch,
+    /// waiting for git. Returns whether a commit was made; `false` when the
+    /// mirror is off or up to date.
+    pub fn flush_mirror(&self) -> Result<bool> {
+        match &self.mirror {
+            Some(mirror) => mirror.flush(self),
+            None => Ok(false),
+        }
+    }
+
+    /// Rewrites the mirror from the database, removing files of notes that
+    /// are gone, and commits the result straight away.
+    pub fn rebuild_mirror(&self) -> Result<MirrorRebuild> {
+        let Some(mirror) = &self.mirror else {
+            bail!(
+                "the git mirror is off; set `enabled = true` under [storage.mirror] in the config"
+            );
+        };
+        mirror.rebuild(self)
+    }
+}
+
+/// The note as mirrored. Tags are sorted so a note reads back identically
+/// however the database happened to order them.
+fn mirrored(mut note: NoteRecord) -> NoteRecord {
+    note.tags.sort();
+    note
+}
+
+/// Removes a mirror file; one already deleted by hand is not an error.
+fn remove_file(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("removing {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ConfigPaths, StorageOptions};
+    use crate::storage::BackupKind;
+    use tempfile::TempDir;
+
+    /// The mirror shells out to git; machines without it skip these tests.
+    fn has_git() -> bool {
+        Command::new("git")
+            .arg("--version")
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+
+    fn init_storage() -> Result<(TempDir, StorageHandle)> {
+        let temp = TempDir::new()?;
+        let base = temp.path();
+        let paths = ConfigPaths {
+            config_dir: base.join("config"),
+            config_file: base.join("config/config.toml"),
+            data_dir: base.join("data"),
+            database_path: base.join("data/notes.db"),
+            cache_dir: base.join("cache"),
+            backup_dir: base.join("backups"),
+            log_dir: base.join("logs"),
+            state_dir: base.join("state"),
+            theme_dir: base.join("config/themes"),
+        };
+        paths.ensure_directories()?;
+        let mut options = StorageOptions {
+            database_path: paths.database_path.clone(),
+            backup_dir: paths.backup_dir.clone(),
+            ..StorageOptions::default()
+        };
+        options.mirror = MirrorOptions {
+            enabled: true,
+            repo_dir: base.join("mirror"),
+            commit_interval_secs: 3_600,
+        };
+        let storage = super::super::init(&paths, &options)?;
+        Ok((temp, storage))
+    }
+
+    fn file_names(dir: &Path) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if name.ends_with(".md") {
+                names.push(name);
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn commit_count(dir: &Path) -> Result<usize> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["rev-list", "--count", "--all"])
+            .output()?;
+        assert!(output.status.success());
+        Ok(String::from_utf8_lossy(&output.stdout).trim().parse()?)
+    }
+
+    #[test]
+    fn hand_made_copies_are_not_mirrored() -> Result<()> {
+        if !has_git() {
+            return Ok(());
+        }
+        let (temp, storage) = init_storage()?;
+        let repo = temp.path().join("mirror");
+        let plan = storage.create_note("Plan", "first draft", false)?;
+        storage.sync_mirror()?;
+        // A copy edited by hand keeps the note's front matter, not its name.
+        let mirrored = fs::read_to_string(repo.join(format!("plan-{plan}.md")))?;
+        fs::write(
+            repo.join("plan-copy.md"),
+            mirrored.replace("first draft", "my own notes"),
+        )?;
+
+        storage.rename_note_title(plan, "Roadmap")?;
+        storage.sync_mirror()?;
+        let names = file_names(&repo)?;
+        assert!(names.contains(&format!("roadmap-{plan}.md")), "{names:?}");
+        assert!(!names.contains(&format!("plan-{plan}.md")), "{names:?}");
+        assert!(names.contains(&"plan-copy.md".to_string()), "{names:?}");
+
+        storage.soft_delete_note(plan)?;
+        let rebuild = storage.rebuild_mirror()?;
+        assert_eq!(rebuild.files.removed, 1);
+        let copy = fs::read_to_string(repo.join("plan-copy.md"))?;
+        assert!(copy.contains("my own notes"), "{copy}");
+        Ok(())
+    }
+
+    #[test]
+    fn trashed_and_deleted_notes_lose_their_files() -> Result<()> {
+        if !has_git() {
+            return Ok(());
+        }
+        let (temp, storage) = init_storage()?;
+        let repo = temp.path().join("mirror");
+        let backup = storage.create_backup(BackupKind::Manual)?;
+        let kept = storage.create_note("Kept", "", false)?;
+        let binned = storage.create_note("Binned", "", false)?;
+        storage.sync_mirror()?;
+        assert!(repo.join(format!("binned-{binned}.md")).exists());
+
+        storage.soft_delete_note(binned)?;
+        storage.sync_mirror()?;
+        assert!(!repo.join(format!("binned-{binned}.md")).exists());
+        storage.restore_note(binned)?;
+        storage.sync_mirror()?;
+        assert!(repo.join(format!("binned-{binned}.md")).exists());
+
+        // Restoring a backup from before both notes deletes them outright.
+        storage.restore_backup(&backup.path)?;
+        storage.sync_mirror()?;
+        let names = file_names(&repo)?;
+        assert!(!names.contains(&format!("kept-{kept}.md")), "{names:?}");
+        assert!(!names.contains(&format!("binned-{binned}.md")), "{names:?}");
+        assert!(storage.flush_mirror()?);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failed_commits_are_reported_and_retried() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        if !has_git() {
+            return Ok(());
+        }
+        let (temp, storage) = init_storage()?;
+        let repo = temp.path().join("mirror");
+        storage.create_note("Plan", "", false)?;
+        storage.sync_mirror()?;
+        let hook = repo.join(".git/hooks/pre-commit");
+        fs::create_dir_all(hook.parent().expect("hooks dir"))?;
+        fs::write(&hook, "#!/bin/sh\necho 'hook says no' >&2\nexit 1\n")?;
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755))?;
+
+        let err = storage.flush_mirror().unwrap_err();
+        let message = err.to_string();
+        assert!(message.starts_with("git commit failed"), "{message}");
+        assert!(message.contains("hook says no"), "{message}");
+        assert_eq!(commit_count(&repo)?, 0);
+
+        // The failed batch is pending again rather than stuck mid-commit.
+        fs::remove_file(&hook)?;
+        storage.sync_mirror()?;
+        assert!(storage.flush_mirror()?);
+        assert_eq!(commit_count(&repo)?, 1);
+        assert!(!storage.flush_mirror()?);
+        Ok(())
+    }
+}
@@ -1,23 +1,52 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, PoisonError};
 
 use anyhow::{bail, Context, Result};
 use regex::{Regex, RegexBuilder};
 use rusqlite::config::DbConfig;
-use rusqlite::{params, Connection, OptionalExtension};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
 use time::OffsetDateTime;
 
-use crate::config::{ConfigPaths, StorageOptions};
-use crate::search::SearchQuery;
-
+use crate::config::{ConfigPaths, SortDirection, SortField, SortSpec, StorageOptions};
+use crate::search::{NoteScope, SearchQuery, TextExpr};
+
+mod archive;
+mod backup;
+mod fuzzy;
+mod import;
+mod links;
+mod mirror;
+mod notebooks;
+mod saved;
 mod schema;
+mod tags;
+
+pub use archive::{
+    Archive, ArchiveImportMode, ArchiveImportSummary, ArchivedNote, ArchivedNoteTag,
+    ArchivedNotebook, ArchivedRevision, ArchivedSavedSearch, ArchivedTag,
+};
+pub use backup::{BackupKind, BackupRecord, BackupRotation};
+pub use import::ImportedNote;
+pub use links::{LinkedNote, NoteLink};
+pub use mirror::MirrorRebuild;
+pub use notebooks::{Notebook, NOTEBOOK_SEPARATOR};
+pub use saved::SavedSearch;
+pub use tags::{tag_ancestors, tag_in_branch, tag_sort_key, TagNode, TagUsage, TAG_SEPARATOR};
+
+use fuzzy::VocabIndex;
+use mirror::Mirror;
 
 const TAG_DELIMITER: &str = "|:|";
 const FTS_ROW_LIMIT: usize = 200;
 const BM25_TITLE_WEIGHT: f64 = 0.2;
 const BM25_BODY_WEIGHT: f64 = 1.0;
+/// Body saves landing within this window of the previous revision are folded
+/// into it so autosave flushes do not flood the history.
+const REVISION_COALESCE_SECS: i64 = 120;
+const REVISION_LIMIT_PER_NOTE: i64 = 200;
 
 #[derive(Debug, Clone, Copy)]
 pub struct WalCheckpointStats {
@@ -38,18 +67,73 @@ pub struct NoteRecord {
     pub archived: bool,
     pub tags: Vec<String>,
     pub deleted_at: Option<i64>,
+    pub notebook_id: Option<i64>,
+    /// Matched only through a typo-tolerant expansion of a search token.
+    pub fuzzy: bool,
+}
+
+/// A note's position in a list order: the values of its sort columns (see
+/// [`sort_columns`]). Pages of the list are fetched relative to one of these
+/// (keyset pagination) rather than by row offset, so reading deep into a large
+/// list costs the same as reading its first page and concurrent edits cannot
+/// shift rows between pages. A cursor is only meaningful for the scope and
+/// [`SortSpec`] it was built for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListCursor(Vec<Value>);
+
+impl ListCursor {
+    pub fn of(record: &NoteRecord, sort: &SortSpec) -> Self {
+        let id = Value::Integer(record.id);
+        if let Some(deleted_at) = record.deleted_at {
+            return Self(vec![Value::Integer(deleted_at), id]);
+        }
+        let key = match sort.field {
+            SortField::Updated => Value::Integer(record.updated_at),
+            SortField::Created => Value::Integer(record.created_at),
+            SortField::Title => Value::Text(record.title.clone()),
+        };
+        Self(vec![Value::Integer(i64::from(record.pinned)), key, id])
+    }
+}
+
+/// Where a page of the note list starts. Pages always come back in list order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageAnchor {
+    /// The first rows of the list.
+    Start,
+    /// The last rows of the list.
+    End,
+    /// Rows after the cursor.
+    After(ListCursor),
+    /// Rows before the cursor, nearest last.
+    Before(ListCursor),
+    /// The cursor's own row, if it still sorts there, followed by the rows after it.
+    From(ListCursor),
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteRevision {
+    pub id: i64,
+    pub note_id: i64,
+    pub title: String,
+    pub body: String,
+    pub created_at: i64,
+}
+
+/// What renaming a tag did. `descendants` counts the nested tags that moved
+/// along with it.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TagRenameOutcome {
     Renamed {
         from: String,
         to: String,
+        descendants: usize,
     },
     Merged {
         from: String,
         to: String,
         reassigned: usize,
+        descendants: usize,
     },
 }
 
@@ -63,6 +147,10 @@ pub struct TagDeleteOutcome {
 pub struct StorageHandle {
     db_path: Arc<PathBuf>,
     options: Arc<StorageOptions>,
+    /// Built on the first fuzzy search and shared by clones of the handle.
+    vocab: Arc<Mutex<Option<VocabIndex>>>,
+    /// Set when `[storage.mirror]` is enabled; shared by clones of the handle.
+    mirror: Option<Arc<Mirror>>,
 }
 
 impl StorageHandle {
@@ -104,147 +192,194 @@ impl StorageHandle {
     }
 
     pub fn fetch_recent_notes(&self, limit: usize) -> Result<Vec<NoteRecord>> {
-        self.with_connection(|conn| {
+        self.fetch_scoped_notes(
+            NoteScope::Active,
+            &SortSpec::default(),
+            RowFilter::default(),
+            limit,
+            0,
+        )
+    }
+
+    pub fn fetch_archived_notes(&self, limit: usize) -> Result<Vec<NoteRecord>> {
+        self.fetch_scoped_notes(
+            NoteScope::Archived,
+            &SortSpec::default(),
+            RowFilter::default(),
+            limit,
+            0,
+        )
+    }
+
+    pub fn fetch_trashed_notes(&self, limit: usize) -> Result<Vec<NoteRecord>> {
+        self.fetch_scoped_notes(
+            NoteScope::Trash,
+            &SortSpec::default(),
+            RowFilter::default(),
+            limit,
+            0,
+        )
+    }
+
+    /// Up to `limit` notes of `scope` starting at `anchor`, in `sort` order.
+    pub fn fetch_note_page(
+        &self,
+        scope: NoteScope,
+        sort: &SortSpec,
+        anchor: &PageAnchor,
+        limit: usize,
+    ) -> Result<Vec<NoteRecord>> {
+        let columns = sort_columns(scope, sort);
+        let (keyset, mut values, reversed) = match anchor {
+            PageAnchor::Start => (String::new(), Vec::new(), false),
+            PageAnchor::End => (String::new(), Vec::new(), true),
+            PageAnchor::After(cursor) => (
+                keyset_condition(&columns, false, false),
+                cursor.0.clone(),
+                false,
+            ),
+            PageAnchor::Before(cursor) => (
+                keyset_condition(&columns, true, false),
+                cursor.0.clone(),
+                true,
+            ),
+            PageAnchor::From(cursor) => (
+                keyset_condition(&columns, false, true),
+                cursor.0.clone(),
+                false,
+            ),
+        };
+        values.push(Value::Integer(limit as i64));
+        let mut records = self.with_connection(|conn| {
             let sql = format!(
-                "SELECT n.id,
-                        n.title,
-                        n.body,
-                        n.created_at,
-                        n.updated_at,
-                        n.pinned,
-                        n.archived,
-                        COALESCE(GROUP_CONCAT(t.name, '{delim}'), ''),
-                        n.deleted_at
+                "SELECT {columns}
                  FROM notes n
-                 LEFT JOIN note_tags nt ON nt.note_id = n.id
-                 LEFT JOIN tags t ON t.id = nt.tag_id
-                 WHERE n.deleted_at IS NULL
-                   AND n.archived = 0
-                 GROUP BY n.id
-                 ORDER BY n.pinned DESC, n.updated_at DESC
-                 LIMIT ?1",
-                delim = TAG_DELIMITER
+                 WHERE {filter} {keyset}
+                 ORDER BY {order}
+                 LIMIT ?{limit}",
+                columns = note_columns(),
+                filter = scope_filter(scope, RowFilter::default()),
+                order = order_clause(&columns, reversed),
+                limit = values.len(),
             );
             let mut stmt = conn.prepare(&sql)?;
             let records = stmt
-                .query_map([limit as i64], |row| {
-                    let tags: String = row.get(7)?;
-                    Ok(NoteRecord {
-                        id: row.get(0)?,
-                        title: row.get(1)?,
-                        body: row.get(2)?,
-                        snippet: None,
-                        created_at: row.get(3)?,
-                        updated_at: row.get(4)?,
-                        pinned: row.get::<_, i64>(5)? != 0,
-                        archived: row.get::<_, i64>(6)? != 0,
-                        tags: parse_tags(&tags),
-                        deleted_at: row.get::<_, Option<i64>>(8)?,
-                    })
-                })?
-                .collect::<std::result::Result<Vec<_>, _>>()?;
+                .query_map(params_from_iter(values.iter()), read_note_row)?
+                .collect::<Result<Vec<_>, _>>()
+                .context("querying note page")?;
             Ok(records)
+        })?;
+        if reversed {
+            records.reverse();
+        }
+        Ok(records)
+    }
+
+    /// Number of notes in `scope`, or only those sorting before `before`.
+    pub fn count_notes(
+        &self,
+        scope: NoteScope,
+        sort: &SortSpec,
+        before: Option<&ListCursor>,
+    ) -> Result<usize> {
+        let (keyset, values) = match before {
+            Some(cursor) => (
+                keyset_condition(&sort_columns(scope, sort), true, false),
+                cursor.0.clone(),
+            ),
+            None => (String::new(), Vec::new()),
+        };
+        self.with_connection(|conn| {
+            let sql = format!(
+                "SELECT COUNT(*) FROM notes n WHERE {filter} {keyset}",
+                filter = scope_filter(scope, RowFilter::default()),
+            );
+            let count: i64 = conn
+                .query_row(&sql, params_from_iter(values.iter()), |row| row.get(0))
+                .context("counting notes")?;
+            Ok(count as usize)
         })
     }
 
-    fn fetch_notes_batch(&self, limit: usize, offset: usize) -> Result<Vec<NoteRecord>> {
+    /// Where `note_id` currently sorts, or `None` if it is not in `scope`.
+    pub fn list_cursor(
+        &self,
+        scope: NoteScope,
+        sort: &SortSpec,
+        note_id: i64,
+    ) -> Result<Option<ListCursor>> {
         self.with_connection(|conn| {
             let sql = format!(
-                "SELECT n.id,
-                        n.title,
-                        n.body,
-                        n.created_at,
-                        n.updated_at,
-                        n.pinned,
-                        n.archived,
-                        COALESCE(GROUP_CONCAT(t.name, '{delim}'), ''),
-                        n.deleted_at
-                 FROM notes n
-                 LEFT JOIN note_tags nt ON nt.note_id = n.id
-                 LEFT JOIN tags t ON t.id = nt.tag_id
-                 WHERE n.deleted_at IS NULL
-                   AND n.archived = 0
-                 GROUP BY n.id
-                 ORDER BY n.pinned DESC, n.updated_at DESC
-                 LIMIT ?1 OFFSET ?2",
-                delim = TAG_DELIMITER
+                "SELECT {columns} FROM notes n WHERE n.id = ?1 AND {filter}",
+                columns = note_columns(),
+                filter = scope_filter(scope, RowFilter::default()),
             );
-            let mut stmt = conn.prepare(&sql)?;
-            let records = stmt
-                .query_map(params![limit as i64, offset as i64], |row| {
-                    let tags: String = row.get(7)?;
-                    Ok(NoteRecord {
-                        id: row.get(0)?,
-                        title: row.get(1)?,
-                        body: row.get(2)?,
-                        snippet: None,
-                        created_at: row.get(3)?,
-                        updated_at: row.get(4)?,
-                        pinned: row.get::<_, i64>(5)? != 0,
-                        archived: row.get::<_, i64>(6)? != 0,
-                        tags: parse_tags(&tags),
-                        deleted_at: row.get::<_, Option<i64>>(8)?,
-                    })
-                })?
-                .collect::<Result<Vec<_>, _>>()?;
-            Ok(records)
+            let record = conn
+                .query_row(&sql, [note_id], read_note_row)
+                .optional()
+                .context("locating note in list")?;
+            Ok(record.map(|record| ListCursor::of(&record, sort)))
         })
     }
 
-    pub fn fetch_trashed_notes(&self, limit: usize) -> Result<Vec<NoteRecord>> {
+    fn fetch_scoped_notes(
+        &self,
+        scope: NoteScope,
+        sort: &SortSpec,
+        row_filter: RowFilter,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<NoteRecord>> {
         self.with_connection(|conn| {
             let sql = format!(
-                "SELECT n.id,
-                        n.title,
-                        n.body,
-                        n.created_at,
-                        n.updated_at,
-                        n.pinned,
-                        n.archived,
-                        COALESCE(GROUP_CONCAT(t.name, '{delim}'), ''),
-                        n.deleted_at
+                "SELECT {columns}
                  FROM notes n
-                 LEFT JOIN note_tags nt ON nt.note_id = n.id
-                 LEFT JOIN tags t ON t.id = nt.tag_id
-                 WHERE n.deleted_at IS NOT NULL
-                 GROUP BY n.id
-                 ORDER BY n.deleted_at DESC
-                 LIMIT ?1",
-                delim = TAG_DELIMITER
+                 WHERE {filter}
+                 ORDER BY {order}
+                 LIMIT ?1 OFFSET ?2",
+                columns = note_columns(),
+                filter = scope_filter(scope, row_filter),
+                order = order_clause(&sort_columns(scope, sort), false),
             );
             let mut stmt = conn.prepare(&sql)?;
             let records = stmt
-                .query_map([limit as i64], |row| {
-                    let tags: String = row.get(7)?;
-                    Ok(NoteRecord {
-                        id: row.get(0)?,
-                        title: row.get(1)?,
-                        body: row.get(2)?,
-                        snippet: None,
-                        created_at: row.get(3)?,
-                        updated_at: row.get(4)?,
-                        pinned: row.get::<_, i64>(5)? != 0,
-                        archived: row.get::<_, i64>(6)? != 0,
-                        deleted_at: row.get::<_, Option<i64>>(8)?,
-                        tags: parse_tags(&tags),
-                    })
-                })?
+                .query_map(params![limit as i64, offset as i64], read_note_row)?
                 .collect::<Result<Vec<_>, _>>()?;
             Ok(records)
         })
     }
 
     pub fn search_notes(&self, query: &SearchQuery, limit: usize) -> Result<Vec<NoteRecord>> {
+        let scope = query.scope.unwrap_or_default();
         if !query.has_terms() && !query.has_filters() && query.regex_pattern.is_none() {
-            return self.fetch_recent_notes(limit);
+            return self.fetch_scoped_notes(
+                scope,
+                &query.sort.unwrap_or_default(),
+                RowFilter::default(),
+                limit,
+                0,
+            );
         }
+        // An unknown notebook matches nothing rather than failing, so results
+        // simply empty out while a `notebook:` path is still being typed.
+        let notebook = match &query.notebook {
+            Some(path) => match self.find_notebook(path)? {
+                Some(notebook) => Some(notebook.id),
+                None => return Ok(Vec::new()),
+            },
+            None => None,
+        };
+        let row_filter = RowFilter {
+            pinned_only: query.pinned_only,
+            notebook,
+        };
 
         if query.regex_pattern.is_some() && !query.has_terms() {
             let regex = RegexBuilder::new(query.regex_pattern.as_deref().unwrap())
                 .case_insensitive(true)
                 .build()
                 .context("compiling regex search pattern")?;
-            return self.search_regex_only(query, limit, regex);
+            return self.search_regex_only(query, row_filter, limit, regex);
         }
 
         let regex = if let Some(pattern) = query.regex_pattern.as_deref() {
@@ -260,9 +395,22 @@ impl StorageHandle {
 
         let fetch_limit = limit.max(FTS_ROW_LIMIT);
         let mut notes = if query.has_terms() {
-            self.search_with_terms(query, fetch_limit)?
+            self.search_with_terms(query, row_filter, fetch_limit)?
         } else {
-            self.fetch_recent_notes(fetch_limit)?
+            let mut notes = self.fetch_scoped_notes(
+                scope,
+                &query.sort.unwrap_or_default(),
+                row_filter,
+                fetch_limit,
+                0,
+            )?;
+            // Text made only of exclusions (`-draft`) cannot run as a MATCH of
+            // its own, so the notes it names are looked up and dropped.
+            if let Some(excluded) = query.text.as_ref().and_then(exclusion_expression) {
+                let excluded = self.matching_ids(&excluded)?;
+                notes.retain(|note| !excluded.contains(&note.id));
+            }
+            notes
         };
 
         apply_filters(&mut notes, query);
@@ -275,15 +423,113 @@ impl StorageHandle {
         Ok(notes)
     }
 
-    fn search_with_terms(&self, query: &SearchQuery, limit: usize) -> Result<Vec<NoteRecord>> {
-        let Some(match_expr) = build_match_expression(query) else {
+    /// Runs the exact FTS query, then, when `query.fuzzy_threshold` is set and
+    /// the page is not full, a second query with misspelled tokens widened to
+    /// their vocabulary neighbours. Notes only the second query finds follow the
+    /// exact hits and are flagged [`NoteRecord::fuzzy`].
+    fn search_with_terms(
+        &self,
+        query: &SearchQuery,
+        row_filter: RowFilter,
+        limit: usize,
+    ) -> Result<Vec<NoteRecord>> {
+        let Some(match_expr) = query
+            .text
+            .as_ref()
+            .and_then(|text| match_expression(text, &HashMap::new()))
+        else {
             return Ok(Vec::new());
         };
+        let mut notes = self.match_notes(query, row_filter, &match_expr, limit)?;
+        if query.fuzzy_threshold <= 0.0 || notes.len() >= limit {
+            return Ok(notes);
+        }
+        let expansions = self.fuzzy_expansions(query)?;
+        if expansions.is_empty() {
+            return Ok(notes);
+        }
+        let Some(fuzzy_expr) = query
+            .text
+            .as_ref()
+            .and_then(|text| match_expression(text, &expansions))
+        else {
+            return Ok(notes);
+        };
+        let exact: HashSet<i64> = notes.iter().map(|note| note.id).collect();
+        let fuzzy = self
+            .match_notes(query, row_filter, &fuzzy_expr, limit)?
+            .into_iter()
+            .filter(|note| !exact.contains(&note.id))
+            .map(|note| NoteRecord {
+                fuzzy: true,
+                ..note
+            });
+        notes.extend(fuzzy);
+        notes.truncate(limit);
+        Ok(notes)
+    }
+
+    /// Vocabulary neighbours for each searched word (lowercased) that has any
+    /// within the query's threshold. Phrases and excluded words stay exact.
+    fn fuzzy_expansions(&self, query: &SearchQuery) -> Result<HashMap<String, Vec<String>>> {
+        let tokens: Vec<String> = query
+            .highlight_terms()
+            .into_iter()
+            .filter(|term| !term.contains(char::is_whitespace))
+            .map(|term| term.to_lowercase())
+            .collect();
+        if tokens.is_empty() {
+            return Ok(HashMap::new());
+        }
+        self.with_connection(|conn| {
+            let generation = VocabIndex::generation(conn)?;
+            let mut cached = self.vocab.lock().unwrap_or_else(PoisonError::into_inner);
+            let index = match cached.take() {
+                Some(index) if index.is_current(generation) => index,
+                _ => VocabIndex::load(conn, generation)?,
+            };
+            let expansions = tokens
+                .into_iter()
+                .filter_map(|token| {
+                    let neighbours = index.neighbours(&token, query.fuzzy_threshold);
+                    (!neighbours.is_empty()).then_some((token, neighbours))
+                })
+                .collect();
+            *cached = Some(index);
+            Ok(expansions)
+        })
+    }
+
+    fn matching_ids(&self, match_expr: &str) -> Result<HashSet<i64>> {
+        self.with_connection(|conn| {
+            let mut stmt = conn.prepare("SELECT rowid FROM fts_notes WHERE fts_notes MATCH ?1")?;
+            let ids = stmt
+                .query_map(params![match_expr], |row| row.get(0))?
+                .collect::<Result<HashSet<i64>, _>>()
+                .context("querying excluded notes")?;
+            Ok(ids)
+        })
+    }
+
+    fn match_notes(
+        &self,
+        query: &SearchQuery,
+        row_filter: RowFilter,
+        match_expr: &str,
+        limit: usize,
+    ) -> Result<Vec<NoteRecord>> {
         let title_priority_tokens = query
             .highlight_terms()
             .into_iter()
             .map(|token| token.to_lowercase())
             .collect::<Vec<_>>();
+        let scope = query.scope.unwrap_or_default();
+        let order = match &query.sort {
+            Some(sort) => order_clause(&sort_columns(scope, sort), false),
+            None => format!(
+                "n.pinned DESC, bm25(fts_notes, {BM25_TITLE_WEIGHT}, {BM25_BODY_WEIGHT}), n.updated_at DESC"
+            ),
+        };
         self.with_connection(|conn| {
             let sql = format!(
                 "SELECT n.id,
@@ -300,19 +546,17 @@ impl StorageHandle {
                             WHERE nt2.note_id = n.id
                         ), '') AS tags,
                         n.deleted_at,
-                        snippet(fts_notes, -1, '', '', ' ... ', 20) AS snippet
+                        snippet(fts_notes, -1, '', '', ' ... ', 20) AS snippet,
+                        n.notebook_id
                  FROM fts_notes
                  INNER JOIN notes n ON n.id = fts_notes.rowid
-                 WHERE n.deleted_at IS NULL
-                   AND n.archived = 0
+                 WHERE {filter}
                    AND fts_notes MATCH ?1
-                 ORDER BY n.pinned DESC,
-                          bm25(fts_notes, {title_weight}, {body_weight}),
-                          n.updated_at DESC
+                 ORDER BY {order}
                  LIMIT ?2",
                 delim = TAG_DELIMITER,
-                title_weight = BM25_TITLE_WEIGHT,
-                body_weight = BM25_BODY_WEIGHT
+                filter = scope_filter(scope, row_filter),
+                order = order,
             );
             let mut stmt = conn.prepare(&sql)?;
             let rows = stmt.query_map(
@@ -337,13 +581,15 @@ impl StorageHandle {
                         archived: row.get::<_, i64>(6)? != 0,
                         tags: parse_tags(&tags),
                         deleted_at,
+                        notebook_id: row.get(10)?,
+                        fuzzy: false,
                     })
                 },
             )?;
             let notes = rows
                 .collect::<Result<Vec<_>, _>>()
                 .context("querying search results")?;
-            if title_priority_tokens.is_empty() {
+            if title_priority_tokens.is_empty() || query.sort.is_some() {
                 Ok(notes)
             } else {
                 Ok(prioritize_title_matches(notes, &title_priority_tokens))
@@ -354,6 +600,7 @@ impl StorageHandle {
     fn search_regex_only(
         &self,
         query: &SearchQuery,
+        row_filter: RowFilter,
         limit: usize,
         regex: Regex,
     ) -> Result<Vec<NoteRecord>> {
@@ -361,7 +608,13 @@ impl StorageHandle {
         let mut offset = 0usize;
         let batch_size = limit.max(FTS_ROW_LIMIT);
         loop {
-            let mut batch = self.fetch_notes_batch(batch_size, offset)?;
+            let mut batch = self.fetch_scoped_notes(
+                query.scope.unwrap_or_default(),
+                &query.sort.unwrap_or_default(),
+                row_filter,
+                batch_size,
+                offset,
+            )?;
             if batch.is_empty() {
                 break;
             }
@@ -391,7 +644,9 @@ impl StorageHandle {
                 bail!("note {note_id} not found");
             }
             Ok(())
-        })
+        })?;
+        self.mirror_notes(&[note_id]);
+        Ok(())
     }
 
     pub fn set_note_archived(&self, note_id: i64, archived: bool) -> Result<()> {
@@ -406,7 +661,9 @@ impl StorageHandle {
                 bail!("note {note_id} not found");
             }
             Ok(())
-        })
+        })?;
+        self.mirror_notes(&[note_id]);
+        Ok(())
     }
 
     pub fn create_note(&self, title: &str, body: &str, pinned: bool) -> Result<i64> {
@@ -414,7 +671,7 @@ impl StorageHandle {
         if trimmed.is_empty() {
             bail!("note title cannot be empty");
         }
-        self.with_connection(|conn| {
+        let note_id = self.with_connection(|conn| {
             let now = OffsetDateTime::now_utc().unix_timestamp();
             conn.execute(
                 "INSERT INTO notes (title, body, created_at, updated_at, pinned, archived)
@@ -422,8 +679,12 @@ impl StorageHandle {
                 params![trimmed, body, now, if pinned { 1 } else { 0 }],
             )
             .context("inserting note")?;
-            Ok(conn.last_insert_rowid())
-        })
+            let note_id = conn.last_insert_rowid();
+            links::sync_links(conn, note_id, body)?;
+            Ok(note_id)
+        })?;
+        self.mirror_notes(&[note_id]);
+        Ok(note_id)
     }
 
     pub fn add_tag_to_note(&self, note_id: i64, tag_name: &str) -> Result<()> {
@@ -451,7 +712,9 @@ impl StorageHandle {
             )
             .context("linking tag to note")?;
             Ok(())
-        })
+        })?;
+        self.mirror_notes(&[note_id]);
+        Ok(())
     }
 
     pub fn remove_tag_from_note(&self, note_id: i64, tag_name: &str) -> Result<()> {
@@ -470,9 +733,13 @@ impl StorageHandle {
                 bail!("tag '{tag}' not associated with note {note_id}");
             }
             Ok(())
-        })
+        })?;
+        self.mirror_notes(&[note_id]);
+        Ok(())
     }
 
+    /// Renames a tag together with every tag nested under it, in one
+    /// transaction. Tags whose new name is taken are merged into that tag.
     pub fn rename_tag(&self, current: &str, new_name: &str) -> Result<TagRenameOutcome> {
         let from = current.trim();
         let to = new_name.trim();
@@ -481,52 +748,9 @@ impl StorageHandle {
         }
         let mut conn = self.connect()?;
         let tx = conn.transaction()?;
-        let source_id: i64 = tx
-            .query_row(
-                "SELECT id FROM tags WHERE name = ?1",
-                params![from],
-                |row| row.get(0),
-            )
-            .optional()?
-            .ok_or_else(|| anyhow::anyhow!("tag '{from}' not found"))?;
-
-        let existing: Option<i64> = tx
-            .query_row("SELECT id FROM tags WHERE name = ?1", params![to], |row| {
-                row.get(0)
-            })
-            .optional()?;
-
-        let outcome = match existing {
-            Some(target_id) if target_id != source_id => {
-                let reassigned = tx.execute(
-                    "INSERT OR IGNORE INTO note_tags (note_id, tag_id)
-                     SELECT note_id, ?1 FROM note_tags WHERE tag_id = ?2",
-                    params![target_id, source_id],
-                )?;
-                tx.execute(
-                    "DELETE FROM note_tags WHERE tag_id = ?1",
-                    params![source_id],
-                )?;
-                tx.execute("DELETE FROM tags WHERE id = ?1", params![source_id])?;
-                TagRenameOutcome::Merged {
-                    from: from.to_string(),
-                    to: to.to_string(),
-                    reassigned,
-                }
-            }
-            _ => {
-                tx.execute(
-                    "UPDATE tags SET name = ?1 WHERE id = ?2",
-                    params![to, source_id],
-                )?;
-                TagRenameOutcome::Renamed {
-                    from: from.to_string(),
-                    to: to.to_string(),
-                }
-            }
-        };
-
+        let outcome = tags::rename_branch(&tx, from, to)?;
         tx.commit()?;
+        self.mirror_all();
         Ok(outcome)
     }
 
@@ -547,27 +771,50 @@ impl StorageHandle {
         let detached = tx.execute("DELETE FROM note_tags WHERE tag_id = ?1", params![tag_id])?;
         tx.execute("DELETE FROM tags WHERE id = ?1", params![tag_id])?;
         tx.commit()?;
+        self.mirror_all();
         Ok(TagDeleteOutcome {
             tag: tag.to_string(),
             detached,
         })
     }
 
-    pub fn rename_note_title(&self, note_id: i64, title: &str) -> Result<()> {
+    /// Renames a note and rewrites `[[old title]]` links in other notes to the
+    /// new title. Returns the number of notes whose links were rewritten.
+    pub fn rename_note_title(&self, note_id: i64, title: &str) -> Result<usize> {
         let trimmed = title.trim();
         if trimmed.is_empty() {
             bail!("note title cannot be empty");
         }
-        self.with_connection(|conn| {
-            let updated = conn.execute(
-                "UPDATE notes SET title = ?1 WHERE id = ?2 AND deleted_at IS NULL",
-                params![trimmed, note_id],
-            )?;
-            if updated == 0 {
-                bail!("note {note_id} not found");
-            }
-            Ok(())
-        })
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+        let current: String = tx
+            .query_row(
+                "SELECT title FROM notes WHERE id = ?1 AND deleted_at IS NULL",
+                params![note_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow::anyhow!("note {note_id} not found"))?;
+        let changed = current != trimmed;
+        if changed {
+            record_revision(&tx, note_id, false)?;
+        }
+        tx.execute(
+            "UPDATE notes SET title = ?1 WHERE id = ?2",
+            params![trimmed, note_id],
+        )?;
+        let rewritten = if changed {
+            links::rewrite_renamed_links(&tx, note_id, &current, trimmed)?
+        } else {
+            0
+        };
+        tx.commit()?;
+        if rewritten == 0 {
+            self.mirror_notes(&[note_id]);
+        } else {
+            self.mirror_all();
+        }
+        Ok(rewritten)
     }
 
     pub fn tag_exists(&self, name: &str) -> Result<bool> {
@@ -587,18 +834,100 @@ impl StorageHandle {
     }
 
     pub fn update_note_body(&self, note_id: i64, body: &str) -> Result<()> {
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+        let current: String = tx
+            .query_row(
+                "SELECT body FROM notes WHERE id = ?1 AND deleted_at IS NULL",
+                params![note_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow::anyhow!("note {note_id} not found"))?;
+        if current != body {
+            record_revision(&tx, note_id, true)?;
+        }
+        tx.execute(
+            "UPDATE notes SET body = ?1 WHERE id = ?2",
+            params![body, note_id],
+        )?;
+        links::sync_links(&tx, note_id, body)?;
+        tx.commit()?;
+        self.mirror_notes(&[note_id]);
+        Ok(())
+    }
+
+    /// Revisions for a note, newest first. Each revision holds the title and
+    /// body as they were before the change that superseded them.
+    pub fn list_note_revisions(&self, note_id: i64) -> Result<Vec<NoteRevision>> {
         self.with_connection(|conn| {
-            let updated = conn.execute(
-                "UPDATE notes SET body = ?1 WHERE id = ?2 AND deleted_at IS NULL",
-                params![body, note_id],
+            let mut stmt = conn.prepare(
+                "SELECT id, note_id, title, body, created_at
+                 FROM note_revisions
+                 WHERE note_id = ?1
+                 ORDER BY id DESC",
             )?;
-            if updated == 0 {
-                bail!("note {note_id} not found");
+            let rows = stmt.query_map(params![note_id], map_revision_row)?;
+            let mut revisions = Vec::new();
+            for row in rows {
+                revisions.push(row?);
             }
-            Ok(())
+            Ok(revisions)
+        })
+    }
+
+    pub fn fetch_note_revision(&self, revision_id: i64) -> Result<Option<NoteRevision>> {
+        self.with_connection(|conn| {
+            let revision = conn
+                .query_row(
+                    "SELECT id, note_id, title, body, created_at
+                     FROM note_revisions
+                     WHERE id = ?1",
+                    params![revision_id],
+                    map_revision_row,
+                )
+                .optional()?;
+            Ok(revision)
         })
     }
 
+    /// Rolls the note back to a stored revision. The content being replaced is
+    /// recorded first so the restore itself can be undone.
+    pub fn restore_note_revision(&self, note_id: i64, revision_id: i64) -> Result<()> {
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+        let (title, body): (String, String) = tx
+            .query_row(
+                "SELECT title, body FROM note_revisions WHERE id = ?1 AND note_id = ?2",
+                params![revision_id, note_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .ok_or_else(|| {
+                anyhow::anyhow!("revision {revision_id} not found for note {note_id}")
+            })?;
+        let exists = tx
+            .query_row(
+                "SELECT 1 FROM notes WHERE id = ?1 AND deleted_at IS NULL",
+                params![note_id],
+                |_row| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if !exists {
+            bail!("note {note_id} not found");
+        }
+        record_revision(&tx, note_id, false)?;
+        tx.execute(
+            "UPDATE notes SET title = ?1, body = ?2 WHERE id = ?3",
+            params![title, body, note_id],
+        )?;
+        links::sync_links(&tx, note_id, &body)?;
+        tx.commit()?;
+        self.mirror_notes(&[note_id]);
+        Ok(())
+    }
+
     pub fn fetch_note_by_id(&self, note_id: i64) -> Result<Option<NoteRecord>> {
         self.with_connection(|conn| {
             let sql = format!(
@@ -610,7 +939,8 @@ impl StorageHandle {
                         n.pinned,
                         n.archived,
                         COALESCE(GROUP_CONCAT(t.name, '{delim}'), '') AS tags,
-                        n.deleted_at
+                        n.deleted_at,
+                        n.notebook_id
                  FROM notes n
                  LEFT JOIN note_tags nt ON nt.note_id = n.id
                  LEFT JOIN tags t ON t.id = nt.tag_id
@@ -633,6 +963,8 @@ impl StorageHandle {
                         archived: row.get::<_, i64>(6)? != 0,
                         tags: parse_tags(&tags),
                         deleted_at: row.get::<_, Option<i64>>(8)?,
+                        notebook_id: row.get(9)?,
+                        fuzzy: false,
                     })
                 })
                 .optional()?;
@@ -641,13 +973,17 @@ impl StorageHandle {
     }
 
     pub fn restore_all_trash(&self) -> Result<usize> {
-        self.with_connection(|conn| {
+        let count = self.with_connection(|conn| {
             let count = conn.execute(
                 "UPDATE notes SET deleted_at = NULL WHERE deleted_at IS NOT NULL",
                 [],
             )?;
             Ok(count)
-        })
+        })?;
+        if count > 0 {
+            self.mirror_all();
+        }
+        Ok(count)
     }
 
     pub fn purge_all_trash(&self) -> Result<usize> {
@@ -683,7 +1019,9 @@ impl StorageHandle {
                 bail!("note {note_id} not found");
             }
             Ok(())
-        })
+        })?;
+        self.mirror_notes(&[note_id]);
+        Ok(())
     }
 
     pub fn list_all_tags(&self) -> Result<Vec<String>> {
@@ -705,54 +1043,284 @@ impl StorageHandle {
                 bail!("note {note_id} not found in trash");
             }
             Ok(())
-        })
+        })?;
+        self.mirror_notes(&[note_id]);
+        Ok(())
     }
 }
 
-fn build_match_expression(query: &SearchQuery) -> Option<String> {
-    let mut clauses = Vec::new();
-    if let Some(clause) = build_clause(None, &query.terms) {
-        clauses.push(clause);
-    }
-    if let Some(clause) = build_clause(Some("title"), &query.title_terms) {
-        clauses.push(clause);
-    }
-    if clauses.is_empty() {
-        None
-    } else {
-        Some(clauses.join(" AND "))
+/// Snapshots the note's current title/body into `note_revisions`. Identical
+/// consecutive snapshots are skipped, and when `coalesce` is set a snapshot
+/// taken within `REVISION_COALESCE_SECS` of the previous one is folded into it.
+fn record_revision(conn: &Connection, note_id: i64, coalesce: bool) -> Result<()> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let (title, body): (String, String) = conn
+        .query_row(
+            "SELECT title, body FROM notes WHERE id = ?1",
+            params![note_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .context("loading note for revision snapshot")?;
+    let latest: Option<(String, String, i64)> = conn
+        .query_row(
+            "SELECT title, body, created_at FROM note_revisions
+             WHERE note_id = ?1
+             ORDER BY id DESC
+             LIMIT 1",
+            params![note_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+    if let Some((last_title, last_body, last_at)) = latest {
+        if last_title == title && last_body == body {
+            return Ok(());
+        }
+        if coalesce && now - last_at < REVISION_COALESCE_SECS {
+            return Ok(());
+        }
     }
-}
+    conn.execute(
+        "INSERT INTO note_revisions (note_id, title, body, created_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![note_id, title, body, now],
+    )
+    .context("recording note revision")?;
+    conn.execute(
+        "DELETE FROM note_revisions
+         WHERE note_id = ?1
+           AND id NOT IN (
+               SELECT id FROM note_revisions
+               WHERE note_id = ?1
+               ORDER BY id DESC
+               LIMIT ?2
+           )",
+        params![note_id, REVISION_LIMIT_PER_NOTE],
+    )
+    .context("pruning note revisions")?;
+    Ok(())
+}
 
-fn build_clause(column: Option<&str>, terms: &[String]) -> Option<String> {
-    let mut parts = Vec::new();
-    for term in terms {
-        let trimmed = term.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
-        let escaped = trimmed.replace('"', "\"\"");
-        let has_whitespace = trimmed.chars().any(|ch| ch.is_whitespace());
-        let fragment = if has_whitespace {
-            if let Some(col) = column {
-                format!("{col}:\"{escaped}\"")
+fn map_revision_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<NoteRevision> {
+    Ok(NoteRevision {
+        id: row.get(0)?,
+        note_id: row.get(1)?,
+        title: row.get(2)?,
+        body: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+/// Conditions a search adds to its scope in SQL, so they apply before the row
+/// limit rather than to a fetched page.
+#[derive(Debug, Clone, Copy, Default)]
+struct RowFilter {
+    pinned_only: bool,
+    /// Notes filed in this notebook or in one nested under it.
+    notebook: Option<i64>,
+}
+
+fn scope_filter(scope: NoteScope, row_filter: RowFilter) -> String {
+    let mut filter = match scope {
+        NoteScope::Active => "n.deleted_at IS NULL AND n.archived = 0",
+        NoteScope::Archived => "n.deleted_at IS NULL AND n.archived = 1",
+        NoteScope::Trash => "n.deleted_at IS NOT NULL",
+    }
+    .to_string();
+    if row_filter.pinned_only {
+        filter.push_str(" AND n.pinned = 1");
+    }
+    if let Some(notebook_id) = row_filter.notebook {
+        filter.push_str(&format!(
+            " AND n.notebook_id IN ({})",
+            notebooks::subtree_ids_sql(notebook_id)
+        ));
+    }
+    filter
+}
+
+/// Columns that define a list order, most significant first, each paired with
+/// whether it sorts descending. Pinned notes lead live lists whatever the sort;
+/// the trash always lists the most recently deleted first. The note id breaks
+/// ties so every row has a distinct position for keyset pagination.
+fn sort_columns(scope: NoteScope, sort: &SortSpec) -> Vec<(&'static str, bool)> {
+    if scope == NoteScope::Trash {
+        return vec![("n.deleted_at", true), ("n.id", true)];
+    }
+    let descending = sort.direction == SortDirection::Descending;
+    let column = match sort.field {
+        SortField::Updated => "n.updated_at",
+        SortField::Created => "n.created_at",
+        SortField::Title => "n.title COLLATE NOCASE",
+    };
+    vec![
+        ("n.pinned", true),
+        (column, descending),
+        ("n.id", descending),
+    ]
+}
+
+fn order_clause(columns: &[(&str, bool)], reversed: bool) -> String {
+    columns
+        .iter()
+        .map(|(column, descending)| {
+            let direction = if *descending != reversed {
+                "DESC"
             } else {
-                format!("\"{escaped}\"")
+                "ASC"
+            };
+            format!("{column} {direction}")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `AND (...)` selecting rows that sort after a cursor bound to `?1..?n` (or
+/// before it when `reversed`), optionally including the cursor's own row. The
+/// columns sort in mixed directions, so this spells out the lexicographic
+/// comparison rather than comparing row values.
+fn keyset_condition(columns: &[(&str, bool)], reversed: bool, inclusive: bool) -> String {
+    let equal_up_to = |end: usize| -> Vec<String> {
+        columns[..end]
+            .iter()
+            .enumerate()
+            .map(|(idx, (column, _))| format!("{column} = ?{}", idx + 1))
+            .collect()
+    };
+    let mut alternatives: Vec<String> = columns
+        .iter()
+        .enumerate()
+        .map(|(idx, (column, descending))| {
+            let operator = if *descending != reversed { "<" } else { ">" };
+            let mut terms = equal_up_to(idx);
+            terms.push(format!("{column} {operator} ?{}", idx + 1));
+            terms.join(" AND ")
+        })
+        .collect();
+    if inclusive {
+        alternatives.push(equal_up_to(columns.len()).join(" AND "));
+    }
+    format!("AND (({}))", alternatives.join(") OR ("))
+}
+
+/// The column list read by [`read_note_row`].
+fn note_columns() -> String {
+    format!(
+        "n.id,
+         n.title,
+         n.body,
+         n.created_at,
+         n.updated_at,
+         n.pinned,
+         n.archived,
+         COALESCE((
+             SELECT GROUP_CONCAT(t.name, '{TAG_DELIMITER}')
+             FROM note_tags nt
+             INNER JOIN tags t ON t.id = nt.tag_id
+             WHERE nt.note_id = n.id
+         ), ''),
+         n.deleted_at,
+         n.notebook_id"
+    )
+}
+
+/// Maps a row selected with [`note_columns`] onto a record.
+fn read_note_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<NoteRecord> {
+    let tags: String = row.get(7)?;
+    Ok(NoteRecord {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        body: row.get(2)?,
+        snippet: None,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+        pinned: row.get::<_, i64>(5)? != 0,
+        archived: row.get::<_, i64>(6)? != 0,
+        tags: parse_tags(&tags),
+        deleted_at: row.get::<_, Option<i64>>(8)?,
+        notebook_id: row.get(9)?,
+        fuzzy: false,
+    })
+}
+
+/// FTS5 expression for `expr`, or `None` when it only excludes. A word listed in
+/// `expansions` also matches its fuzzy neighbours: `("word"* OR "neighbour")`.
+/// Exclusions become `NOT` after the terms they sit beside, since FTS5 has no
+/// unary NOT.
+fn match_expression(expr: &TextExpr, expansions: &HashMap<String, Vec<String>>) -> Option<String> {
+    match expr {
+        TextExpr::Word { text, title_only } => {
+            let word = format!("{}*", quote_fts(text));
+            let word = match expansions.get(&text.to_lowercase()) {
+                Some(neighbours) => {
+                    let alternatives = neighbours
+                        .iter()
+                        .map(|neighbour| format!(" OR {}", quote_fts(neighbour)))
+                        .collect::<String>();
+                    format!("({word}{alternatives})")
+                }
+                None => word,
+            };
+            Some(column_filter(*title_only, word))
+        }
+        TextExpr::Phrase { text, title_only } => Some(column_filter(*title_only, quote_fts(text))),
+        TextExpr::Or(items) => {
+            let alternatives = items
+                .iter()
+                .map(|item| match_expression(item, expansions))
+                .collect::<Option<Vec<_>>>()?;
+            Some(format!("({})", alternatives.join(" OR ")))
+        }
+        TextExpr::And(items) => {
+            let required = items
+                .iter()
+                .filter(|item| !matches!(item, TextExpr::Not(_)))
+                .map(|item| match_expression(item, expansions))
+                .collect::<Option<Vec<_>>>()?;
+            if required.is_empty() {
+                return None;
             }
-        } else {
-            let token = escaped.replace(':', " ");
-            if let Some(col) = column {
-                format!("{col}:{token}*")
-            } else {
-                format!("{token}*")
+            let mut expression = format!("({})", required.join(" AND "));
+            for item in items {
+                if let TextExpr::Not(inner) = item {
+                    expression.push_str(" NOT ");
+                    expression.push_str(&match_expression(inner, &HashMap::new())?);
+                }
             }
-        };
-        parts.push(fragment);
+            Some(format!("({expression})"))
+        }
+        TextExpr::Not(_) => None,
     }
-    if parts.is_empty() {
-        None
+}
+
+/// FTS5 expression matching any note that an exclusion-only `expr` leaves out.
+fn exclusion_expression(expr: &TextExpr) -> Option<String> {
+    let excluded: Vec<&TextExpr> = match expr {
+        TextExpr::Not(inner) => vec![inner],
+        TextExpr::And(items) => items
+            .iter()
+            .filter_map(|item| match item {
+                TextExpr::Not(inner) => Some(&**inner),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    let alternatives = excluded
+        .into_iter()
+        .map(|inner| match_expression(inner, &HashMap::new()))
+        .collect::<Option<Vec<_>>>()?;
+    (!alternatives.is_empty()).then(|| alternatives.join(" OR "))
+}
+
+fn quote_fts(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+fn column_filter(title_only: bool, expression: String) -> String {
+    if title_only {
+        format!("title:{expression}")
     } else {
-        Some(parts.join(" AND "))
+        expression
     }
 }
 
@@ -790,11 +1358,171 @@ fn title_contains_any(title: &str, tokens: &[String]) -> bool {
         .any(|token| !token.is_empty() && haystack.contains(token))
 }
 
+fn apply_filters(notes: &mut Vec<NoteRecord>, query: &SearchQuery) {
+    if !query.has_filters() {
+        return;
+    }
+
+    notes.retain(|note| {
+        let in_branch = |branch: &String| note.tags.iter().any(|tag| tag_in_branch(tag, branch));
+        if !query.tags.iter().all(in_branch) {
+            return false;
+        }
+        if query.excluded_tags.iter().any(in_branch) {
+            return false;
+        }
+
+        if let Some(from) = query.created.from {
+            if note.created_at < from {
+                return false;
+            }
+        }
+        if let Some(to) = query.created.to {
+            if note.created_at >= to {
+                return false;
+            }
+        }
+
+        if let Some(from) = query.updated.from {
+            if note.updated_at < from {
+                return false;
+            }
+        }
+        if let Some(to) = query.updated.to {
+            if note.updated_at >= to {
+                return false;
+            }
+        }
+
+        true
+    });
+}
+
+pub fn init(paths: &ConfigPaths, storage: &StorageOptions) -> Result<StorageHandle> {
+    let db_path = &paths.database_path;
+    let existed = db_path.exists();
+    if let Some(parent) = db_path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("creating data directory {}", parent.display()))?;
+    }
+    let mut conn = Connection::open(db_path)
+        .with_context(|| format!("opening database {}", db_path.display()))?;
+    prepare_connection(&conn, storage)?;
+    let version = schema::current_version(&conn)?;
+    schema::ensure_supported(version)?;
+    if existed && schema::needs_migration(version) {
+        backup_before_migration(&conn, &storage.backup_dir, version)?;
+    }
+    migrate(&mut conn)?;
+    if !existed {
+        seed_initial_notes(&conn)?;
+    }
+    Ok(StorageHandle {
+        db_path: Arc::new(db_path.clone()),
+        options: Arc::new(storage.clone()),
+        vocab: Arc::new(Mutex::new(None)),
+        mirror: storage
+            .mirror
+            .enabled
+            .then(|| Arc::new(Mirror::new(&storage.mirror))),
+    })
+}
+
+/// Applies pending schema migrations. A database from before note links were
+/// indexed also has its links indexed, which the migration itself cannot do
+/// since it would have to parse every body.
+pub(super) fn migrate(conn: &mut Connection) -> Result<()> {
+    let version = schema::current_version(conn)?;
+    schema::apply(conn)?;
+    if version < schema::NOTE_LINKS_VERSION {
+        links::rebuild_links(conn).context("indexing note links")?;
+    }
+    Ok(())
+}
+
+fn prepare_connection(conn: &Connection, storage: &StorageOptions) -> Result<()> {
+    conn.set_db_config(DbConfig::SQLITE_DBCONFIG_ENABLE_FKEY, true)
+        .context("enabling foreign keys")?;
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .context("setting journal_mode=WAL")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .context("setting synchronous=NORMAL")?;
+    conn.pragma_update(
+        None,
+        "wal_autocheckpoint",
+        storage.wal_autocheckpoint.to_string(),
+    )
+    .context("setting wal_autocheckpoint")?;
+    Ok(())
+}
+
+/// Snapshots the database before upgrading it so a failed or unwanted
+/// migration can be rolled back by hand.
+fn backup_before_migration(conn: &Connection, backup_dir: &Path, from: i64) -> Result<PathBuf> {
+    fs::create_dir_all(backup_dir)
+        .with_context(|| format!("creating backup directory {}", backup_dir.display()))?;
+    let stamp = OffsetDateTime::now_utc().unix_timestamp();
+    let target = backup_dir.join(format!(
+        "notes-pre-migration-v{from}-to-v{}-{stamp}.db",
+        schema::latest_version()
+    ));
+    backup::copy_database(conn, &target).context("writing pre-migration backup")?;
+    tracing::info!(path = %target.display(), from, "saved pre-migration backup");
+    Ok(target)
+}
+
+fn seed_initial_notes(conn: &Connection) -> Result<()> {
+    let existing: Option<i64> = conn
+        .query_row("SELECT id FROM notes LIMIT 1", [], |row| row.get(0))
+        .optional()
+        .context("checking for existing notes")?;
+    if existing.is_some() {
+        return Ok(());
+    }
+
+    tracing::info!("seeding first-run notes");
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let notes = [
+        (
+            "Welcome to Notes TUI",
+            r#"# Welcome to Notes TUI
+
+This is your new note space. Press `?` inside the app to see keyboard shortcuts.
+"#,
+        ),
+        (
+            "Keyboard Shortcuts",
+            r#"# Keyboard Highlights
+
+- `j` / `k`: move up or down the list
+- `Enter`: open the selected note
+- `e`: edit the note
+- `/`: start a quick search
+- `:`: command palette
+- `q`: quit
+"#,
+        ),
+        ("Inbox", "Capture quick thoughts here.\n"),
+    ];
+
+    for (title, body) in notes {
+        conn.execute(
+            "INSERT INTO notes (title, body, created_at, updated_at, pinned, archived)
+             VALUES (?1, ?2, ?3, ?3, 0, 0)",
+            params![title, body, now],
+        )
+        .context("inserting seed note")?;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::{ConfigPaths, StorageOptions};
-    use crate::search::SearchQuery;
+    use crate::markdown::LinkTarget;
+    use crate::search::{parse_query, SearchQuery};
     use tempfile::TempDir;
 
     fn temp_paths(root: &TempDir) -> ConfigPaths {
@@ -808,6 +1536,7 @@ mod tests {
         ConfigPaths {
             config_dir: config_dir.clone(),
             config_file: config_dir.join("config.toml"),
+            theme_dir: config_dir.join("themes"),
             data_dir: data_dir.clone(),
             database_path: data_dir.join("notes.db"),
             cache_dir,
@@ -818,10 +1547,11 @@ mod tests {
     }
 
     fn storage_options(paths: &ConfigPaths) -> StorageOptions {
-        let mut options = StorageOptions::default();
-        options.database_path = paths.database_path.clone();
-        options.backup_dir = paths.backup_dir.clone();
-        options
+        StorageOptions {
+            database_path: paths.database_path.clone(),
+            backup_dir: paths.backup_dir.clone(),
+            ..StorageOptions::default()
+        }
     }
 
     fn init_storage() -> anyhow::Result<(TempDir, StorageHandle)> {
@@ -842,7 +1572,7 @@ mod tests {
         let outcome = storage.rename_tag("alpha", "beta")?;
         assert!(matches!(
             outcome,
-            TagRenameOutcome::Renamed { ref from, ref to }
+            TagRenameOutcome::Renamed { ref from, ref to, .. }
                 if from == "alpha" && to == "beta"
         ));
 
@@ -872,6 +1602,7 @@ mod tests {
                 from,
                 to,
                 reassigned,
+                ..
             } => {
                 assert_eq!(from, "alpha");
                 assert_eq!(to, "beta");
@@ -908,6 +1639,110 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn nested_tags_filter_count_and_rename_as_a_branch() -> anyhow::Result<()> {
+        let (_temp, storage) = init_storage()?;
+        let alpha = storage.create_note("Alpha", "status report", false)?;
+        let beta = storage.create_note("Beta", "status report", false)?;
+        let side = storage.create_note("Side", "status report", false)?;
+        storage.add_tag_to_note(alpha, "project/alpha")?;
+        storage.add_tag_to_note(beta, "project/beta")?;
+        storage.add_tag_to_note(beta, "project/beta/ui")?;
+        storage.add_tag_to_note(side, "project-x")?;
+        storage.add_tag_to_note(side, "work/beta")?;
+
+        let ids = |input: &str| -> anyhow::Result<Vec<i64>> {
+            let query = parse_query(input)?;
+            let mut ids: Vec<i64> = storage
+                .search_notes(&query, 20)?
+                .into_iter()
+                .map(|note| note.id)
+                .collect();
+            ids.sort_unstable();
+            Ok(ids)
+        };
+        assert_eq!(ids("status tag:Project")?, vec![alpha, beta]);
+        assert_eq!(ids("status tag:project/beta")?, vec![beta]);
+        assert_eq!(ids("status -tag:project")?, vec![side]);
+
+        let tree: Vec<(String, usize, usize, bool)> = storage
+            .tag_tree()?
+            .into_iter()
+            .map(|node| (node.name, node.depth, node.note_count, node.exists))
+            .collect();
+        assert_eq!(
+            tree,
+            vec![
+                ("project".to_string(), 0, 2, false),
+                ("project/alpha".to_string(), 1, 1, true),
+                ("project/beta".to_string(), 1, 1, true),
+                ("project/beta/ui".to_string(), 2, 1, true),
+                ("project-x".to_string(), 0, 1, true),
+                ("work".to_string(), 0, 1, false),
+                ("work/beta".to_string(), 1, 1, true),
+            ]
+        );
+
+        let err = storage.rename_tag("project", "project/old").unwrap_err();
+        assert!(err.to_string().contains("under itself"));
+        let outcome = storage.rename_tag("project", "work")?;
+        assert_eq!(
+            outcome,
+            TagRenameOutcome::Renamed {
+                from: "project".into(),
+                to: "work".into(),
+                descendants: 3,
+            }
+        );
+        let mut tags = storage.list_all_tags()?;
+        tags.sort();
+        assert_eq!(
+            tags,
+            vec!["project-x", "work/alpha", "work/beta", "work/beta/ui"]
+        );
+        assert_eq!(ids("status tag:work/beta")?, vec![beta, side]);
+
+        storage.add_tag_to_note(side, "archive")?;
+        storage.add_tag_to_note(side, "archive/beta")?;
+        match storage.rename_tag("work", "archive")? {
+            TagRenameOutcome::Merged {
+                reassigned,
+                descendants,
+                ..
+            } => {
+                assert_eq!(descendants, 3);
+                assert_eq!(reassigned, 1);
+            }
+            other => panic!("expected merged outcome, got {other:?}"),
+        }
+        assert_eq!(ids("status tag:archive")?, vec![alpha, beta, side]);
+        assert!(!storage.tag_exists("work/beta")?);
+        Ok(())
+    }
+
+    #[test]
+    fn renaming_a_branch_ignores_case() -> anyhow::Result<()> {
+        let (_temp, storage) = init_storage()?;
+        let note = storage.create_note("Plan", "", false)?;
+        storage.add_tag_to_note(note, "Project")?;
+        storage.add_tag_to_note(note, "project/alpha")?;
+        storage.add_tag_to_note(note, "PROJECT/Beta")?;
+
+        let outcome = storage.rename_tag("Project", "work")?;
+        assert_eq!(
+            outcome,
+            TagRenameOutcome::Renamed {
+                from: "Project".into(),
+                to: "work".into(),
+                descendants: 2,
+            }
+        );
+        let mut tags = storage.list_all_tags()?;
+        tags.sort();
+        assert_eq!(tags, vec!["work", "work/Beta", "work/alpha"]);
+        Ok(())
+    }
+
     #[test]
     fn purge_expired_trash_skips_when_retention_zero() -> anyhow::Result<()> {
         let (_temp, storage) = init_storage()?;
@@ -930,8 +1765,7 @@ mod tests {
         let body_hit =
             storage.create_note("Weekly notes", "Discuss nimbus project rollout", false)?;
 
-        let mut query = SearchQuery::default();
-        query.terms = vec!["nimbus".into(), "project".into()];
+        let query = parse_query("nimbus project")?;
 
         let results = storage.search_notes(&query, 10)?;
         assert!(results.len() >= 2, "expected at least two search results");
@@ -945,8 +1779,7 @@ mod tests {
         let (_temp, storage) = init_storage()?;
         let _note = storage.create_note("QuasarNotebook", "plain body", false)?;
 
-        let mut query = SearchQuery::default();
-        query.terms = vec!["QuasarNotebook".into()];
+        let query = parse_query("QuasarNotebook")?;
 
         let results = storage.search_notes(&query, 5)?;
         assert!(!results.is_empty(), "expected at least one result");
@@ -962,6 +1795,38 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn fuzzy_search_ranks_misspelled_matches_after_exact_hits() -> anyhow::Result<()> {
+        let (_temp, storage) = init_storage()?;
+        let typo = storage.create_note("Typo", "please recieve the parcel", false)?;
+        let spelled = storage.create_note("Receive invoices", "from the vendor", false)?;
+        let _unrelated = storage.create_note("Recipes", "soup", false)?;
+
+        let mut query = parse_query("recieve")?;
+        let exact = storage.search_notes(&query, 10)?;
+        assert_eq!(exact.iter().map(|n| n.id).collect::<Vec<_>>(), vec![typo]);
+
+        query.fuzzy_threshold = 0.4;
+        let results = storage.search_notes(&query, 10)?;
+        let hits: Vec<_> = results.iter().map(|n| (n.id, n.fuzzy)).collect();
+        assert_eq!(hits, vec![(typo, false), (spelled, true)]);
+
+        // New text invalidates the cached vocabulary; column filters expand too.
+        let later = storage.create_note("Receiver setup", "antenna", false)?;
+        let query = SearchQuery {
+            fuzzy_threshold: 0.4,
+            ..parse_query("title:recieve")?
+        };
+        let mut ids: Vec<_> = storage
+            .search_notes(&query, 10)?
+            .iter()
+            .map(|n| (n.id, n.fuzzy))
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![(spelled, true), (later, true)]);
+        Ok(())
+    }
+
     #[test]
     fn regex_only_search_scans_beyond_recent_batch() -> anyhow::Result<()> {
         let (_temp, storage) = init_storage()?;
@@ -972,8 +1837,10 @@ mod tests {
             storage.rename_note_title(filler, &format!("Pinned filler {i} updated"))?;
         }
 
-        let mut query = SearchQuery::default();
-        query.regex_pattern = Some("foo[0-9]+bar".into());
+        let query = SearchQuery {
+            regex_pattern: Some("foo[0-9]+bar".into()),
+            ..SearchQuery::default()
+        };
 
         let results = storage.search_notes(&query, 5)?;
         assert!(!results.is_empty(), "expected regex match");
@@ -992,140 +1859,514 @@ mod tests {
         );
         Ok(())
     }
-}
 
-fn apply_filters(notes: &mut Vec<NoteRecord>, query: &SearchQuery) {
-    if !query.has_filters() {
-        return;
+    #[test]
+    fn legacy_database_is_backed_up_before_migrating() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let paths = temp_paths(&temp);
+        paths.ensure_directories()?;
+        let opts = storage_options(&paths);
+        // Databases created before versioning carry the baseline tables at
+        // user_version 0.
+        let legacy = Connection::open(&paths.database_path)?;
+        legacy.execute_batch(schema::MIGRATIONS[0].sql)?;
+        legacy.execute(
+            "INSERT INTO notes (title, body, created_at, updated_at) VALUES ('Legacy', 'keep me', 1, 1)",
+            [],
+        )?;
+        let note_id = legacy.last_insert_rowid();
+        drop(legacy);
+
+        let storage = init(&paths, &opts)?;
+        let version = storage.with_connection(schema::current_version)?;
+        assert_eq!(version, schema::latest_version());
+        let backups: Vec<_> = fs::read_dir(&paths.backup_dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with("notes-pre-migration-v0-")
+            })
+            .collect();
+        assert_eq!(backups.len(), 1, "expected one pre-migration backup");
+
+        let backup = Connection::open(backups[0].path())?;
+        let body: String =
+            backup.query_row("SELECT body FROM notes WHERE id = ?1", [note_id], |row| {
+                row.get(0)
+            })?;
+        assert_eq!(body, "keep me");
+
+        // Already-current databases are opened without another backup.
+        init(&paths, &opts)?;
+        assert_eq!(fs::read_dir(&paths.backup_dir)?.count(), 1);
+        Ok(())
     }
 
-    let tags_filter = if query.tags.is_empty() {
-        None
-    } else {
-        Some(
-            query
-                .tags
-                .iter()
-                .map(|tag| tag.to_lowercase())
-                .collect::<Vec<_>>(),
-        )
-    };
+    #[test]
+    fn init_refuses_database_from_newer_build() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let paths = temp_paths(&temp);
+        paths.ensure_directories()?;
+        let opts = storage_options(&paths);
+        let storage = init(&paths, &opts)?;
+        storage.with_connection(|conn| {
+            conn.pragma_update(None, "user_version", schema::latest_version() + 1)?;
+            Ok(())
+        })?;
 
-    notes.retain(|note| {
-        if let Some(filter_tags) = &tags_filter {
-            let note_tags: HashSet<String> =
-                note.tags.iter().map(|tag| tag.to_lowercase()).collect();
-            for tag in filter_tags {
-                if !note_tags.contains(tag) {
-                    return false;
-                }
-            }
-        }
+        let Err(err) = init(&paths, &opts) else {
+            panic!("newer schema must be refused");
+        };
+        assert!(format!("{err:#}").contains("newer than this build"));
+        Ok(())
+    }
 
-        if let Some(from) = query.created.from {
-            if note.created_at < from {
-                return false;
-            }
-        }
-        if let Some(to) = query.created.to {
-            if note.created_at >= to {
-                return false;
-            }
-        }
+    #[test]
+    fn body_updates_record_coalesced_revisions() -> anyhow::Result<()> {
+        let (_temp, storage) = init_storage()?;
+        let note_id = storage.create_note("Draft", "v1", false)?;
 
-        if let Some(from) = query.updated.from {
-            if note.updated_at < from {
-                return false;
+        storage.update_note_body(note_id, "v2")?;
+        storage.update_note_body(note_id, "v3")?;
+        storage.update_note_body(note_id, "v3")?;
+        let revisions = storage.list_note_revisions(note_id)?;
+        assert_eq!(revisions.len(), 1, "rapid flushes should coalesce");
+        assert_eq!(revisions[0].body, "v1");
+
+        // Once the coalescing window has passed the next flush snapshots again.
+        storage.with_connection(|conn| {
+            conn.execute(
+                "UPDATE note_revisions SET created_at = created_at - ?1",
+                params![REVISION_COALESCE_SECS + 1],
+            )?;
+            Ok(())
+        })?;
+        storage.update_note_body(note_id, "v4")?;
+        let bodies: Vec<String> = storage
+            .list_note_revisions(note_id)?
+            .into_iter()
+            .map(|revision| revision.body)
+            .collect();
+        assert_eq!(bodies, vec!["v3".to_string(), "v1".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn rename_and_restore_revisions_round_trip() -> anyhow::Result<()> {
+        let (_temp, storage) = init_storage()?;
+        let note_id = storage.create_note("Original", "first body", false)?;
+        storage.update_note_body(note_id, "clobbered")?;
+        storage.rename_note_title(note_id, "Renamed")?;
+
+        let revisions = storage.list_note_revisions(note_id)?;
+        assert_eq!(revisions.len(), 2, "renames are never coalesced");
+        assert_eq!(revisions[0].title, "Original");
+        assert_eq!(revisions[0].body, "clobbered");
+        let oldest = revisions.last().cloned().expect("oldest revision");
+        assert_eq!(oldest.body, "first body");
+        assert_eq!(
+            storage.fetch_note_revision(oldest.id)?.as_ref(),
+            Some(&oldest)
+        );
+
+        storage.restore_note_revision(note_id, oldest.id)?;
+        let note = storage.fetch_note_by_id(note_id)?.expect("note exists");
+        assert_eq!(note.title, "Original");
+        assert_eq!(note.body, "first body");
+
+        let latest = &storage.list_note_revisions(note_id)?[0];
+        assert_eq!(latest.title, "Renamed");
+        assert_eq!(latest.body, "clobbered");
+
+        let other = storage.create_note("Other", "", false)?;
+        assert!(storage.restore_note_revision(other, oldest.id).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn scope_qualifiers_reach_archived_pinned_and_trashed_notes() -> anyhow::Result<()> {
+        let (_temp, storage) = init_storage()?;
+        let archived = storage.create_note("Shelved quokka", "old plans", false)?;
+        storage.set_note_archived(archived, true)?;
+        let pinned = storage.create_note("Pinned quokka", "current plans", true)?;
+        let plain = storage.create_note("Plain quokka", "more plans", false)?;
+        let trashed = storage.create_note("Binned quokka", "stale plans", false)?;
+        storage.soft_delete_note(trashed)?;
+
+        let ids = |input: &str| -> anyhow::Result<Vec<i64>> {
+            let query = parse_query(input)?;
+            Ok(storage
+                .search_notes(&query, 20)?
+                .into_iter()
+                .map(|note| note.id)
+                .collect())
+        };
+
+        assert_eq!(ids("quokka")?, vec![pinned, plain]);
+        assert_eq!(ids("quokka is:archived")?, vec![archived]);
+        assert_eq!(ids("is:archived")?, vec![archived]);
+        assert_eq!(ids("quokka is:pinned")?, vec![pinned]);
+        assert_eq!(ids("in:trash")?, vec![trashed]);
+        assert_eq!(ids("stale in:trash")?, vec![trashed]);
+
+        let archived_list = storage.fetch_archived_notes(10)?;
+        assert_eq!(archived_list.len(), 1);
+        assert!(archived_list[0].archived);
+        Ok(())
+    }
+
+    #[test]
+    fn query_grammar_compiles_phrases_exclusions_and_groups() -> anyhow::Result<()> {
+        let (_temp, storage) = init_storage()?;
+        let launch = storage.create_note("Launch plan", "ship the e-mail campaign today", false)?;
+        let retro = storage.create_note("Retro", "plan ship dates, campaign later", false)?;
+        let draft = storage.create_note("Draft plan", "campaign ideas", false)?;
+        storage.add_tag_to_note(draft, "draft")?;
+        let budget = storage.create_note("Budget", "invoice totals", false)?;
+
+        let ids = |input: &str| -> anyhow::Result<Vec<i64>> {
+            let mut ids: Vec<i64> = storage
+                .search_notes(&parse_query(input)?, 20)?
+                .into_iter()
+                .map(|note| note.id)
+                .collect();
+            ids.sort();
+            Ok(ids)
+        };
+
+        assert_eq!(ids("\"ship the\"")?, vec![launch]);
+        assert_eq!(ids("ship campaign")?, vec![launch, retro]);
+        assert_eq!(ids("campaign -ship")?, vec![draft]);
+        assert_eq!(ids("campaign -\"plan ship\"")?, vec![launch, draft]);
+        assert_eq!(ids("campaign -tag:draft")?, vec![launch, retro]);
+        assert_eq!(ids("ideas OR invoice")?, vec![draft, budget]);
+        assert_eq!(ids("(ship today) OR invoice")?, vec![launch, budget]);
+        assert_eq!(ids("title:plan -(ideas OR today)")?, Vec::<i64>::new());
+        assert_eq!(ids("e-mail")?, vec![launch]);
+        // Exclusions alone filter the whole scope.
+        assert!(ids("-campaign")?.contains(&budget));
+        assert!(!ids("-campaign")?.contains(&launch));
+        assert!(!ids("-(campaign OR invoice)")?.contains(&budget));
+        Ok(())
+    }
+
+    #[test]
+    fn keyset_pages_walk_the_list_in_order() -> anyhow::Result<()> {
+        let (_temp, storage) = init_storage()?;
+        storage.with_connection(|conn| {
+            for idx in 0..40i64 {
+                // Repeated timestamps force the id tie-break to keep pages disjoint.
+                conn.execute(
+                    "INSERT INTO notes (title, body, created_at, updated_at, pinned, deleted_at)
+                     VALUES (?1, '', ?5, ?2, ?3, ?4)",
+                    params![
+                        // Mixed case checks that title order ignores it.
+                        if idx % 2 == 0 {
+                            format!("note {}", 40 - idx)
+                        } else {
+                            format!("Note {idx}")
+                        },
+                        1_000 + idx / 3,
+                        i64::from(idx % 7 == 0),
+                        (idx % 5 == 0).then_some(2_000 + idx / 2),
+                        500 - idx % 4,
+                    ],
+                )?;
             }
-        }
-        if let Some(to) = query.updated.to {
-            if note.updated_at >= to {
-                return false;
+            Ok(())
+        })?;
+
+        // Every mode the sort action cycles through.
+        let sorts =
+            std::iter::successors(Some(SortSpec::default()), |sort| Some(sort.next())).take(6);
+        for sort in sorts {
+            for scope in [NoteScope::Active, NoteScope::Trash] {
+                let expected: Vec<i64> = storage
+                    .fetch_scoped_notes(scope, &sort, RowFilter::default(), 1_000, 0)?
+                    .iter()
+                    .map(|note| note.id)
+                    .collect();
+                assert_eq!(storage.count_notes(scope, &sort, None)?, expected.len());
+
+                let mut forward = Vec::new();
+                let mut anchor = PageAnchor::Start;
+                loop {
+                    let page = storage.fetch_note_page(scope, &sort, &anchor, 3)?;
+                    let Some(last) = page.last() else { break };
+                    anchor = PageAnchor::After(ListCursor::of(last, &sort));
+                    forward.extend(page.iter().map(|note| note.id));
+                }
+                assert_eq!(forward, expected, "{sort:?} {scope:?}");
+
+                let mut backward = Vec::new();
+                let mut anchor = PageAnchor::End;
+                loop {
+                    let page = storage.fetch_note_page(scope, &sort, &anchor, 4)?;
+                    let Some(first) = page.first() else { break };
+                    anchor = PageAnchor::Before(ListCursor::of(first, &sort));
+                    backward.splice(0..0, page.iter().map(|note| note.id));
+                }
+                assert_eq!(backward, expected, "{sort:?} {scope:?}");
+
+                let middle = expected[expected.len() / 2];
+                let cursor = storage
+                    .list_cursor(scope, &sort, middle)?
+                    .expect("note listed");
+                assert_eq!(
+                    storage.count_notes(scope, &sort, Some(&cursor))?,
+                    expected.len() / 2
+                );
+                let from = storage.fetch_note_page(scope, &sort, &PageAnchor::From(cursor), 2)?;
+                assert_eq!(from[0].id, middle);
             }
         }
 
-        true
-    });
-}
+        let by_title = SortSpec {
+            field: SortField::Title,
+            direction: SortDirection::Ascending,
+        };
+        let titles: Vec<(bool, String)> = storage
+            .fetch_note_page(NoteScope::Active, &by_title, &PageAnchor::Start, 1_000)?
+            .into_iter()
+            .map(|note| (note.pinned, note.title))
+            .collect();
+        let mut sorted = titles.clone();
+        sorted.sort_by_key(|(pinned, title)| (!pinned, title.to_lowercase()));
+        assert_eq!(titles, sorted, "pinned first, then titles ignoring case");
+
+        let sort = SortSpec::default();
+        let trashed = storage.fetch_note_page(NoteScope::Trash, &sort, &PageAnchor::Start, 1)?;
+        assert_eq!(
+            storage.list_cursor(NoteScope::Active, &sort, trashed[0].id)?,
+            None
+        );
+        Ok(())
+    }
 
-pub fn init(paths: &ConfigPaths, storage: &StorageOptions) -> Result<StorageHandle> {
-    let db_path = &paths.database_path;
-    let existed = db_path.exists();
-    if let Some(parent) = db_path.parent() {
-        fs::create_dir_all(parent)
-            .with_context(|| format!("creating data directory {}", parent.display()))?;
+    #[test]
+    fn saved_searches_upsert_by_name_ignoring_case() -> anyhow::Result<()> {
+        let (_temp, storage) = init_storage()?;
+        let first = storage.save_search("Work", "tag:work -draft")?;
+        storage.save_search("inbox", "is:pinned")?;
+        let replaced = storage.save_search("WORK", "tag:work updated:7d")?;
+        assert_eq!(replaced.id, first.id);
+        assert_eq!(replaced.name, "Work", "the original spelling is kept");
+
+        let saved: Vec<(String, String)> = storage
+            .list_saved_searches()?
+            .into_iter()
+            .map(|saved| (saved.name, saved.query))
+            .collect();
+        assert_eq!(
+            saved,
+            vec![
+                ("inbox".to_string(), "is:pinned".to_string()),
+                ("Work".to_string(), "tag:work updated:7d".to_string()),
+            ]
+        );
+        assert!(storage.fetch_saved_search("work")?.is_some());
+        assert!(storage.save_search("  ", "budget").is_err());
+        assert!(storage.save_search("empty", " ").is_err());
+
+        assert!(storage.delete_saved_search("work")?);
+        assert!(!storage.delete_saved_search("work")?);
+        assert_eq!(storage.list_saved_searches()?.len(), 1);
+        Ok(())
     }
-    let conn = Connection::open(db_path)
-        .with_context(|| format!("opening database {}", db_path.display()))?;
-    prepare_connection(&conn, storage)?;
-    schema::apply(&conn)?;
-    if !existed {
-        seed_initial_notes(&conn)?;
+
+    #[test]
+    fn note_links_track_bodies_and_resolve_backlinks() -> anyhow::Result<()> {
+        let (temp, storage) = init_storage()?;
+        let target = storage.create_note("Project Plan", "the plan", false)?;
+        let source = storage.create_note("Standup", "See [[project plan]] and [[#999]].", false)?;
+        let by_id = storage.create_note("Retro", &format!("Follows [[#{target}]]"), false)?;
+
+        let links = storage.outgoing_links(source)?;
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].note.as_ref().map(|note| note.id), Some(target));
+        assert_eq!(links[1].target, LinkTarget::Id(999));
+        assert!(links[1].note.is_none(), "dangling links stay listed");
+
+        let titles = |notes: Vec<LinkedNote>| -> Vec<String> {
+            notes.into_iter().map(|note| note.title).collect()
+        };
+        assert_eq!(titles(storage.backlinks(target)?), vec!["Retro", "Standup"]);
+
+        // A newer note with the same title does not steal title links.
+        let twin = storage.create_note("Project plan", "", false)?;
+        assert_eq!(titles(storage.backlinks(twin)?), Vec::<String>::new());
+
+        storage.update_note_body(source, "No links any more")?;
+        storage.soft_delete_note(by_id)?;
+        assert!(storage.backlinks(target)?.is_empty());
+
+        // Databases from before the links table get indexed on open.
+        storage.update_note_body(source, "Back to [[Project Plan]]")?;
+        storage.with_connection(downgrade_to_v7)?;
+        let paths = temp_paths(&temp);
+        let reopened = init(&paths, &storage_options(&paths))?;
+        assert_eq!(titles(reopened.backlinks(target)?), vec!["Standup"]);
+        Ok(())
     }
-    Ok(StorageHandle {
-        db_path: Arc::new(db_path.clone()),
-        options: Arc::new(storage.clone()),
-    })
-}
 
-fn prepare_connection(conn: &Connection, storage: &StorageOptions) -> Result<()> {
-    conn.set_db_config(DbConfig::SQLITE_DBCONFIG_ENABLE_FKEY, true)
-        .context("enabling foreign keys")?;
-    conn.pragma_update(None, "journal_mode", "WAL")
-        .context("setting journal_mode=WAL")?;
-    conn.pragma_update(None, "synchronous", "NORMAL")
-        .context("setting synchronous=NORMAL")?;
-    conn.pragma_update(
-        None,
-        "wal_autocheckpoint",
-        storage.wal_autocheckpoint.to_string(),
-    )
-    .context("setting wal_autocheckpoint")?;
-    Ok(())
-}
+    /// Turns a current database back into one from before note links.
+    fn downgrade_to_v7(conn: &Connection) -> anyhow::Result<()> {
+        conn.execute_batch(
+            "DROP TABLE note_links;
+             DROP INDEX notes_notebook_idx;
+             ALTER TABLE notes DROP COLUMN notebook_id;
+             DROP TABLE notebooks;
+             PRAGMA user_version = 7;",
+        )?;
+        Ok(())
+    }
 
-fn seed_initial_notes(conn: &Connection) -> Result<()> {
-    let existing: Option<i64> = conn
-        .query_row("SELECT id FROM notes LIMIT 1", [], |row| row.get(0))
-        .optional()
-        .context("checking for existing notes")?;
-    if existing.is_some() {
-        return Ok(());
+    #[test]
+    fn restoring_a_backup_from_before_links_indexes_them() -> anyhow::Result<()> {
+        let (temp, storage) = init_storage()?;
+        let target = storage.create_note("Plan", "", false)?;
+        let source = storage.create_note("Standup", &format!("See [[#{target}]]"), false)?;
+        let backup = storage.create_backup(BackupKind::Manual)?;
+        downgrade_to_v7(&Connection::open(&backup.path)?)?;
+        storage.update_note_body(source, "No links")?;
+        assert!(storage.backlinks(target)?.is_empty());
+
+        storage.restore_backup(&backup.path)?;
+        let sources = |storage: &StorageHandle| -> anyhow::Result<Vec<i64>> {
+            Ok(storage
+                .backlinks(target)?
+                .into_iter()
+                .map(|note| note.id)
+                .collect())
+        };
+        assert_eq!(sources(&storage)?, vec![source]);
+        // Already current, so reopening leaves the index as restored.
+        let paths = temp_paths(&temp);
+        assert_eq!(
+            sources(&init(&paths, &storage_options(&paths))?)?,
+            vec![source]
+        );
+        Ok(())
     }
 
-    tracing::info!("seeding first-run notes");
-    let now = OffsetDateTime::now_utc().unix_timestamp();
-    let notes = [
-        (
-            "Welcome to Notes TUI",
-            r#"# Welcome to Notes TUI
+    #[test]
+    fn renaming_a_note_rewrites_links_to_it() -> anyhow::Result<()> {
+        let (_temp, storage) = init_storage()?;
+        let target = storage.create_note("Draft", "", false)?;
+        let source = storage.create_note("Index", "- [[draft]]\n- [[Drafts]]", false)?;
+        let other = storage.create_note("Log", "Started [[Draft]] today", false)?;
+        storage.with_connection(|conn| {
+            conn.execute("UPDATE notes SET updated_at = 0", [])?;
+            Ok(())
+        })?;
 
-This is your new note space. Press `?` inside the app to see keyboard shortcuts.
-"#,
-        ),
-        (
-            "Keyboard Shortcuts",
-            r#"# Keyboard Highlights
+        assert_eq!(storage.rename_note_title(target, "Final")?, 2);
+        let body = |id| -> anyhow::Result<String> {
+            Ok(storage.fetch_note_by_id(id)?.expect("note exists").body)
+        };
+        assert_eq!(body(source)?, "- [[Final]]\n- [[Drafts]]");
+        assert_eq!(body(other)?, "Started [[Final]] today");
+        let touched = storage.fetch_note_by_id(other)?.expect("note exists");
+        assert!(touched.updated_at > 0, "rewriting a link counts as an edit");
+        assert_eq!(storage.backlinks(target)?.len(), 2);
+        assert_eq!(
+            storage.list_note_revisions(other)?[0].body,
+            "Started [[Draft]] today",
+            "the rewrite can be undone from history"
+        );
 
-- `j` / `k`: move up or down the list
-- `Enter`: open the selected note
-- `e`: edit the note
-- `/`: start a quick search
-- `:`: command palette
-- `q`: quit
-"#,
-        ),
-        ("Inbox", "Capture quick thoughts here.\n"),
-    ];
+        // Titles that would not survive as a link leave bodies alone.
+        assert_eq!(storage.rename_note_title(target, "Final [v2]")?, 0);
+        assert_eq!(body(other)?, "Started [[Final]] today");
+        Ok(())
+    }
 
-    for (title, body) in notes {
-        conn.execute(
-            "INSERT INTO notes (title, body, created_at, updated_at, pinned, archived)
-             VALUES (?1, ?2, ?3, ?3, 0, 0)",
-            params![title, body, now],
-        )
-        .context("inserting seed note")?;
+    #[test]
+    fn notebooks_nest_and_filter_searches() -> anyhow::Result<()> {
+        let (_temp, storage) = init_storage()?;
+        let projects = storage.create_notebook(" Work / Projects ")?;
+        assert_eq!(projects.path, "Work/Projects");
+        assert_eq!(projects.depth, 1);
+        let work = storage.find_notebook("work")?.expect("parent was created");
+        assert_eq!(projects.parent_id, Some(work.id));
+        assert_eq!(storage.create_notebook("WORK/projects")?.id, projects.id);
+        let home = storage.create_notebook("Home")?;
+        assert!(storage.create_notebook(" / ").is_err());
+
+        let paths: Vec<String> = storage
+            .list_notebooks()?
+            .into_iter()
+            .map(|notebook| notebook.path)
+            .collect();
+        assert_eq!(paths, vec!["Home", "Work", "Work/Projects"]);
+
+        let memo = storage.create_note("Memo", "budget review", false)?;
+        let plan = storage.create_note("Plan", "budget draft", false)?;
+        let list = storage.create_note("Groceries", "budget food", false)?;
+        storage.move_note_to_notebook(memo, Some(work.id))?;
+        storage.move_note_to_notebook(plan, Some(projects.id))?;
+        storage.move_note_to_notebook(list, Some(home.id))?;
+        assert!(storage.move_note_to_notebook(memo, Some(9_999)).is_err());
+
+        let ids = |input: &str| -> anyhow::Result<Vec<i64>> {
+            let query = parse_query(input)?;
+            let mut ids: Vec<i64> = storage
+                .search_notes(&query, 20)?
+                .into_iter()
+                .map(|note| note.id)
+                .collect();
+            ids.sort_unstable();
+            Ok(ids)
+        };
+        assert_eq!(ids("notebook:work")?, vec![memo, plan]);
+        assert_eq!(ids("budget notebook:Work/Projects")?, vec![plan]);
+        assert_eq!(ids("notebook:Missing")?, Vec::<i64>::new());
+        let fetched = storage.fetch_note_by_id(plan)?.expect("note exists");
+        assert_eq!(fetched.notebook_id, Some(projects.id));
+
+        let err = storage.delete_notebook(work.id).unwrap_err();
+        assert!(err.to_string().contains("still contains other notebooks"));
+        assert_eq!(storage.delete_notebook(projects.id)?, 1);
+        assert_eq!(ids("notebook:Work")?, vec![memo, plan]);
+        assert_eq!(storage.delete_notebook(work.id)?, 2);
+        let memo = storage.fetch_note_by_id(memo)?.expect("note exists");
+        assert_eq!(memo.notebook_id, None);
+        Ok(())
     }
 
-    Ok(())
+    #[test]
+    fn deleting_a_parent_notebook_is_refused() -> anyhow::Result<()> {
+        let (_temp, storage) = init_storage()?;
+        let projects = storage.create_notebook("Work/Projects")?;
+        let work = storage.find_notebook("Work")?.expect("parent was created");
+        let plan = storage.create_note("Plan", "", false)?;
+        storage.move_note_to_notebook(plan, Some(work.id))?;
+
+        let err = storage.delete_notebook(work.id).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "notebook 'Work' still contains other notebooks; delete or move them first"
+        );
+        // Nothing changed: the child still hangs off its parent and the note
+        // stays put.
+        let paths: Vec<String> = storage
+            .list_notebooks()?
+            .into_iter()
+            .map(|notebook| notebook.path)
+            .collect();
+        assert_eq!(paths, vec!["Work", "Work/Projects"]);
+        let plan = storage.fetch_note_by_id(plan)?.expect("note exists");
+        assert_eq!(plan.notebook_id, Some(work.id));
+        assert_eq!(
+            storage
+                .find_notebook("Work/Projects")?
+                .map(|notebook| notebook.parent_id),
+            Some(Some(work.id))
+        );
+        assert_eq!(storage.delete_notebook(projects.id)?, 0);
+        assert_eq!(storage.delete_notebook(work.id)?, 1);
+        Ok(())
+    }
 }
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use time::OffsetDateTime;
+
+use super::StorageHandle;
+
+/// Separates notebook names in a path such as `Work/Projects`.
+pub const NOTEBOOK_SEPARATOR: char = '/';
+
+/// A folder for notes. Notebooks nest through `parent_id`; `path` joins the
+/// names from the top level down with [`NOTEBOOK_SEPARATOR`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notebook {
+    pub id: i64,
+    pub name: String,
+    pub parent_id: Option<i64>,
+    pub path: String,
+    /// Zero for top-level notebooks.
+    pub depth: usize,
+    /// Notes outside the trash filed directly in this notebook.
+    pub note_count: usize,
+}
+
+impl StorageHandle {
+    /// Every notebook, each followed by the ones nested in it, siblings ordered
+    /// by name.
+    pub fn list_notebooks(&self) -> Result<Vec<Notebook>> {
+        self.with_connection(load_notebooks)
+    }
+
+    /// The notebook at `path`, matching each name ignoring case.
+    pub fn find_notebook(&self, path: &str) -> Result<Option<Notebook>> {
+        let Ok(segments) = path_segments(path) else {
+            return Ok(None);
+        };
+        let wanted = segments.join(&NOTEBOOK_SEPARATOR.to_string());
+        Ok(self
+            .list_notebooks()?
+            .into_iter()
+            .find(|notebook| notebook.path.eq_ignore_ascii_case(&wanted)))
+    }
+
+    /// Returns the notebook at `path`, creating it and any missing parents.
+    pub fn create_notebook(&self, path: &str) -> Result<Notebook> {
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+        let notebook_id = ensure_notebook(&tx, path)?;
+        let notebooks = load_notebooks(&tx)?;
+        tx.commit()?;
+        notebooks
+            .into_iter()
+            .find(|notebook| notebook.id == notebook_id)
+            .context("reading created notebook")
+    }
+
+    /// Deletes a notebook with no notebooks nested in it. Its notes, trashed
+    /// ones included, move up to the parent notebook (or out of any notebook
+    /// at the top level); returns how many moved. `parent_id` has no
+    /// `ON DELETE` action, so a notebook that still has children is refused
+    /// here rather than leaving them pointing at a missing parent.
+    pub fn delete_notebook(&self, notebook_id: i64) -> Result<usize> {
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+        let (name, parent): (String, Option<i64>) = tx
+            .query_row(
+                "SELECT name, parent_id FROM notebooks WHERE id = ?1",
+                params![notebook_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow::anyhow!("notebook {notebook_id} not found"))?;
+        let has_children = tx
+            .query_row(
+                "SELECT 1 FROM notebooks WHERE parent_id = ?1 LIMIT 1",
+                params![notebook_id],
+                |_row| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if has_children {
+            bail!("notebook '{name}' still contains other notebooks; delete or move them first");
+        }
+        let moved = tx.execute(
+            "UPDATE notes SET notebook_id = ?1 WHERE notebook_id = ?2",
+            params![parent, notebook_id],
+        )?;
+        tx.execute("DELETE FROM notebooks WHERE id = ?1", params![notebook_id])?;
+        tx.commit()?;
+        Ok(moved)
+    }
+
+    /// Files a note in `notebook_id`, or takes it out of any notebook with
+    /// `None`. Moving does not change the note's `updated_at`.
+    pub fn move_note_to_notebook(&self, note_id: i64, notebook_id: Option<i64>) -> Result<()> {
+        self.with_connection(|conn| {
+            if let Some(notebook_id) = notebook_id {
+                let exists = conn
+                    .query_row(
+                        "SELECT 1 FROM notebooks WHERE id = ?1",
+                        params![notebook_id],
+                        |_row| Ok(()),
+                    )
+                    .optional()?
+                    .is_some();
+                if !exists {
+                    bail!("notebook {notebook_id} not found");
+                }
+            }
+            let updated = conn
+                .execute(
+                    "UPDATE notes SET notebook_id = ?1 WHERE id = ?2",
+                    params![notebook_id, note_id],
+                )
+                .context("moving note")?;
+            if updated == 0 {
+                bail!("note {note_id} not found");
+            }
+            Ok(())
+        })
+    }
+}
+
+/// The id of the notebook at `path`, creating it and any missing parents.
+pub(super) fn ensure_notebook(conn: &Connection, path: &str) -> Result<i64> {
+    let segments = path_segments(path)?;
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let mut parent: Option<i64> = None;
+    for name in &segments {
+        let existing: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM notebooks
+                 WHERE COALESCE(parent_id, 0) = COALESCE(?1, 0) AND name = ?2",
+                params![parent, name],
+                |row| row.get(0),
+            )
+            .optional()?;
+        parent = Some(match existing {
+            Some(id) => id,
+            None => {
+                conn.execute(
+                    "INSERT INTO notebooks (name, parent_id, created_at) VALUES (?1, ?2, ?3)",
+                    params![name, parent, now],
+                )
+                .with_context(|| format!("creating notebook '{name}'"))?;
+                conn.last_insert_rowid()
+            }
+        });
+    }
+    parent.context("notebook path cannot be empty")
+}
+
+/// SQL selecting `notebook_id` and the ids of every notebook nested under it.
+pub(super) fn subtree_ids_sql(notebook_id: i64) -> String {
+    format!(
+        "WITH RECURSIVE subtree(id) AS (
+             SELECT {notebook_id}
+             UNION ALL
+             SELECT b.id FROM notebooks b INNER JOIN subtree s ON b.parent_id = s.id
+         )
+         SELECT id FROM subtree"
+    )
+}
+
+/// The names in `path`, trimmed, with empty segments dropped.
+fn path_segments(path: &str) -> Result<Vec<String>> {
+    let segments: Vec<String> = path
+        .split(NOTEBOOK_SEPARATOR)
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_string)
+        .collect();
+    if segments.is_empty() {
+        bail!("notebook path cannot be empty");
+    }
+    Ok(segments)
+}
+
+fn load_notebooks(conn: &Connection) -> Result<Vec<Notebook>> {
+    let mut stmt = conn.prepare(
+        "SELECT b.id, b.name, b.parent_id,
+                (SELECT COUNT(*) FROM notes n
+                 WHERE n.notebook_id = b.id AND n.deleted_at IS NULL)
+         FROM notebooks b
+         ORDER BY b.name COLLATE NOCASE, b.id",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<i64>>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("reading notebooks")?;
+
+    let mut children: HashMap<Option<i64>, Vec<usize>> = HashMap::new();
+    for (idx, (_, _, parent, _)) in rows.iter().enumerate() {
+        children.entry(*parent).or_default().push(idx);
+    }
+    let mut notebooks = Vec::with_capacity(rows.len());
+    // Depth-first from the top level; each entry carries its parent's path.
+    let mut stack: Vec<(usize, String, usize)> = children
+        .get(&None)
+        .into_iter()
+        .flatten()
+        .rev()
+        .map(|&idx| (idx, String::new(), 0))
+        .collect();
+    while let Some((idx, parent_path, depth)) = stack.pop() {
+        let (id, name, parent_id, count) = &rows[idx];
+        let path = if parent_path.is_empty() {
+            name.clone()
+        } else {
+            format!("{parent_path}{NOTEBOOK_SEPARATOR}{name}")
+        };
+        if let Some(nested) = children.get(&Some(*id)) {
+            stack.extend(
+                nested
+                    .iter()
+                    .rev()
+                    .map(|&child| (child, path.clone(), depth + 1)),
+            );
+        }
+        notebooks.push(Notebook {
+            id: *id,
+            name: name.clone(),
+            parent_id: *parent_id,
+            path,
+            depth,
+            note_count: *count as usize,
+        });
+    }
+    Ok(notebooks)
+}
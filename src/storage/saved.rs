@@ -0,0 +1,102 @@
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, OptionalExtension, Row};
+use time::OffsetDateTime;
+
+use super::StorageHandle;
+
+/// A search query stored under a name. Names are unique ignoring case; the
+/// query is kept as typed and parsed again each time it runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedSearch {
+    pub id: i64,
+    pub name: String,
+    pub query: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl StorageHandle {
+    /// Stores `query` under `name`, replacing the query of an existing saved
+    /// search with the same name.
+    pub fn save_search(&self, name: &str, query: &str) -> Result<SavedSearch> {
+        let name = name.trim();
+        let query = query.trim();
+        if name.is_empty() {
+            bail!("saved search name cannot be empty");
+        }
+        if query.is_empty() {
+            bail!("cannot save an empty search");
+        }
+        self.with_connection(|conn| {
+            let now = OffsetDateTime::now_utc().unix_timestamp();
+            conn.execute(
+                "INSERT INTO saved_searches (name, query, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?3)
+                 ON CONFLICT(name) DO UPDATE SET
+                    query = excluded.query,
+                    updated_at = excluded.updated_at",
+                params![name, query, now],
+            )
+            .with_context(|| format!("saving search '{name}'"))?;
+            let saved = conn.query_row(
+                "SELECT id, name, query, created_at, updated_at
+                 FROM saved_searches WHERE name = ?1",
+                params![name],
+                map_saved_row,
+            )?;
+            Ok(saved)
+        })
+    }
+
+    /// Saved searches ordered by name.
+    pub fn list_saved_searches(&self) -> Result<Vec<SavedSearch>> {
+        self.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, name, query, created_at, updated_at
+                 FROM saved_searches ORDER BY name COLLATE NOCASE, id",
+            )?;
+            let saved = stmt
+                .query_map([], map_saved_row)?
+                .collect::<Result<Vec<_>, _>>()
+                .context("reading saved searches")?;
+            Ok(saved)
+        })
+    }
+
+    pub fn fetch_saved_search(&self, name: &str) -> Result<Option<SavedSearch>> {
+        self.with_connection(|conn| {
+            let saved = conn
+                .query_row(
+                    "SELECT id, name, query, created_at, updated_at
+                     FROM saved_searches WHERE name = ?1",
+                    params![name.trim()],
+                    map_saved_row,
+                )
+                .optional()?;
+            Ok(saved)
+        })
+    }
+
+    /// Removes the saved search called `name`; `false` when there was none.
+    pub fn delete_saved_search(&self, name: &str) -> Result<bool> {
+        self.with_connection(|conn| {
+            let removed = conn
+                .execute(
+                    "DELETE FROM saved_searches WHERE name = ?1",
+                    params![name.trim()],
+                )
+                .with_context(|| format!("deleting saved search '{}'", name.trim()))?;
+            Ok(removed > 0)
+        })
+    }
+}
+
+fn map_saved_row(row: &Row<'_>) -> rusqlite::Result<SavedSearch> {
+    Ok(SavedSearch {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        query: row.get(2)?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
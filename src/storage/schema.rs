@@ -1,10 +1,21 @@
-use anyhow::{Context, Result};
-use rusqlite::Connection;
+use anyhow::{bail, Context, Result};
+use rusqlite::{Connection, TransactionBehavior};
 
-pub fn apply(conn: &Connection) -> Result<()> {
-    conn.execute_batch(
-        r#"
-        PRAGMA foreign_keys = ON;
+/// A single forward-only schema step. Versions start at 1 and must be contiguous;
+/// the database records the last applied step in `PRAGMA user_version`.
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "baseline notes, tags, backups and fts tables",
+        // Uses IF NOT EXISTS so databases created before versioning (user_version = 0)
+        // adopt the baseline without errors.
+        sql: r#"
         CREATE TABLE IF NOT EXISTS notes (
             id INTEGER PRIMARY KEY,
             title TEXT NOT NULL,
@@ -65,7 +76,277 @@ pub fn apply(conn: &Connection) -> Result<()> {
             UPDATE notes SET updated_at = strftime('%s', 'now') WHERE id = new.id;
         END;
         "#,
-    )
-    .context("applying schema migrations")?;
+    },
+    Migration {
+        version: 2,
+        description: "note revision history",
+        sql: r#"
+        CREATE TABLE note_revisions (
+            id INTEGER PRIMARY KEY,
+            note_id INTEGER NOT NULL,
+            title TEXT NOT NULL,
+            body TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX note_revisions_note_idx ON note_revisions(note_id, id);
+        "#,
+    },
+    Migration {
+        version: 3,
+        description: "backup kinds",
+        sql: r#"
+        ALTER TABLE backups ADD COLUMN kind TEXT NOT NULL DEFAULT 'manual';
+        "#,
+    },
+    Migration {
+        version: 4,
+        description: "list order indexes",
+        // Match `scope_keys` so keyset pages are index range scans.
+        sql: r#"
+        CREATE INDEX notes_list_idx ON notes(archived, pinned, updated_at, id)
+            WHERE deleted_at IS NULL;
+        CREATE INDEX notes_trash_idx ON notes(deleted_at, id)
+            WHERE deleted_at IS NOT NULL;
+        "#,
+    },
+    Migration {
+        version: 5,
+        description: "created and title sort indexes",
+        sql: r#"
+        CREATE INDEX notes_list_created_idx ON notes(archived, pinned, created_at, id)
+            WHERE deleted_at IS NULL;
+        CREATE INDEX notes_list_title_idx ON notes(archived, pinned, title COLLATE NOCASE, id)
+            WHERE deleted_at IS NULL;
+        "#,
+    },
+    Migration {
+        version: 6,
+        description: "fts vocabulary for fuzzy search",
+        // `generation` moves whenever note text may have changed, telling the
+        // in-memory trigram index built from `fts_vocab` to rebuild.
+        sql: r#"
+        CREATE VIRTUAL TABLE fts_vocab USING fts5vocab(fts_notes, 'row');
+
+        CREATE TABLE fts_vocab_state (generation INTEGER NOT NULL);
+        INSERT INTO fts_vocab_state (generation) VALUES (0);
+
+        CREATE TRIGGER notes_vocab_ai AFTER INSERT ON notes BEGIN
+            UPDATE fts_vocab_state SET generation = generation + 1;
+        END;
+
+        CREATE TRIGGER notes_vocab_ad AFTER DELETE ON notes BEGIN
+            UPDATE fts_vocab_state SET generation = generation + 1;
+        END;
+
+        CREATE TRIGGER notes_vocab_au AFTER UPDATE OF title, body ON notes BEGIN
+            UPDATE fts_vocab_state SET generation = generation + 1;
+        END;
+        "#,
+    },
+    Migration {
+        version: 7,
+        description: "saved searches",
+        sql: r#"
+        CREATE TABLE saved_searches (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            query TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        "#,
+    },
+    Migration {
+        version: 8,
+        description: "note links",
+        // Rows are derived from note bodies; `init` fills them for existing
+        // notes after this step runs (see `NOTE_LINKS_VERSION`).
+        sql: r#"
+        CREATE TABLE note_links (
+            source_id INTEGER NOT NULL,
+            position INTEGER NOT NULL,
+            target_title TEXT COLLATE NOCASE,
+            target_id INTEGER,
+            PRIMARY KEY (source_id, position),
+            FOREIGN KEY (source_id) REFERENCES notes(id) ON DELETE CASCADE
+        );
+        CREATE INDEX note_links_title_idx ON note_links(target_title)
+            WHERE target_title IS NOT NULL;
+        CREATE INDEX note_links_id_idx ON note_links(target_id)
+            WHERE target_id IS NOT NULL;
+        "#,
+    },
+    Migration {
+        version: 9,
+        description: "notebooks",
+        // Sibling names are unique ignoring case; top-level notebooks share the
+        // `COALESCE(parent_id, 0)` key since NULLs never collide in an index.
+        sql: r#"
+        CREATE TABLE notebooks (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL COLLATE NOCASE,
+            parent_id INTEGER REFERENCES notebooks(id),
+            created_at INTEGER NOT NULL
+        );
+        CREATE UNIQUE INDEX notebooks_name_idx ON notebooks(COALESCE(parent_id, 0), name);
+
+        ALTER TABLE notes ADD COLUMN notebook_id INTEGER
+            REFERENCES notebooks(id) ON DELETE SET NULL;
+        CREATE INDEX notes_notebook_idx ON notes(notebook_id)
+            WHERE notebook_id IS NOT NULL;
+        "#,
+    },
+];
+
+/// The migration that added `note_links`. Databases older than this have
+/// their links indexed from the note bodies once the migration has run.
+pub const NOTE_LINKS_VERSION: i64 = 8;
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS
+        .last()
+        .map(|migration| migration.version)
+        .unwrap_or(0)
+}
+
+pub fn current_version(conn: &Connection) -> Result<i64> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+        .context("reading schema version")
+}
+
+/// Fails when the database was written by a newer build; running older code
+/// against it could silently drop columns or misread data.
+pub fn ensure_supported(version: i64) -> Result<()> {
+    refuse_newer(version, latest_version())
+}
+
+fn refuse_newer(version: i64, latest: i64) -> Result<()> {
+    if version > latest {
+        bail!(
+            "database schema version {version} is newer than this build supports ({latest}); \
+             upgrade notetui before opening this database"
+        );
+    }
     Ok(())
 }
+
+pub fn needs_migration(version: i64) -> bool {
+    version < latest_version()
+}
+
+pub fn apply(conn: &mut Connection) -> Result<()> {
+    apply_migrations(conn, MIGRATIONS)
+}
+
+fn apply_migrations(conn: &mut Connection, migrations: &[Migration]) -> Result<()> {
+    let current = current_version(conn)?;
+    refuse_newer(current, migrations.last().map(|m| m.version).unwrap_or(0))?;
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .context("starting migration transaction")?;
+        tx.execute_batch(migration.sql).with_context(|| {
+            format!(
+                "applying schema migration {} ({})",
+                migration.version, migration.description
+            )
+        })?;
+        tx.pragma_update(None, "user_version", migration.version)
+            .context("recording schema version")?;
+        tx.commit()
+            .with_context(|| format!("committing schema migration {}", migration.version))?;
+        tracing::info!(
+            version = migration.version,
+            description = migration.description,
+            "applied schema migration"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table_exists(conn: &Connection, name: &str) -> bool {
+        conn.query_row(
+            "SELECT 1 FROM sqlite_master WHERE name = ?1",
+            [name],
+            |_row| Ok(()),
+        )
+        .is_ok()
+    }
+
+    #[test]
+    fn migrations_are_contiguous() {
+        for (idx, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, idx as i64 + 1);
+        }
+    }
+
+    #[test]
+    fn fresh_database_reaches_latest_version() -> Result<()> {
+        let mut conn = Connection::open_in_memory()?;
+        apply(&mut conn)?;
+        assert_eq!(current_version(&conn)?, latest_version());
+        assert!(table_exists(&conn, "notes"));
+        assert!(table_exists(&conn, "fts_notes"));
+
+        // Re-running is a no-op once the version is recorded.
+        apply(&mut conn)?;
+        assert_eq!(current_version(&conn)?, latest_version());
+        Ok(())
+    }
+
+    #[test]
+    fn unversioned_legacy_database_adopts_baseline() -> Result<()> {
+        let mut conn = Connection::open_in_memory()?;
+        conn.execute_batch(MIGRATIONS[0].sql)?;
+        conn.execute(
+            "INSERT INTO notes (title, body, created_at, updated_at) VALUES ('Old', 'kept', 1, 1)",
+            [],
+        )?;
+        assert_eq!(current_version(&conn)?, 0);
+
+        apply(&mut conn)?;
+        assert_eq!(current_version(&conn)?, latest_version());
+        let body: String = conn.query_row("SELECT body FROM notes", [], |row| row.get(0))?;
+        assert_eq!(body, "kept");
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_database_from_newer_build() -> Result<()> {
+        let mut conn = Connection::open_in_memory()?;
+        conn.pragma_update(None, "user_version", latest_version() + 1)?;
+        let err = apply(&mut conn).expect_err("newer schema must be rejected");
+        assert!(err.to_string().contains("newer than this build"));
+        assert!(ensure_supported(latest_version() + 1).is_err());
+        assert!(ensure_supported(latest_version()).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn failed_migration_rolls_back_and_keeps_version() -> Result<()> {
+        let mut conn = Connection::open_in_memory()?;
+        let steps = [
+            Migration {
+                version: 1,
+                description: "create table",
+                sql: "CREATE TABLE first (id INTEGER PRIMARY KEY);",
+            },
+            Migration {
+                version: 2,
+                description: "broken step",
+                sql: "CREATE TABLE second (id INTEGER PRIMARY KEY); SELECT * FROM missing_table;",
+            },
+        ];
+        assert!(apply_migrations(&mut conn, &steps).is_err());
+        assert_eq!(current_version(&conn)?, 1);
+        assert!(table_exists(&conn, "first"));
+        assert!(!table_exists(&conn, "second"));
+        Ok(())
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{StorageHandle, TagRenameOutcome};
+
+/// Separates the levels of a nested tag such as `project/alpha`.
+pub const TAG_SEPARATOR: char = '/';
+
+/// A row of the tag tree. Parents that only exist through nested tags (a
+/// `project` above `project/alpha`) are listed too, with `exists` unset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagNode {
+    pub name: String,
+    /// Zero for top-level tags.
+    pub depth: usize,
+    /// Notes outside the trash tagged with this tag or any tag nested under it.
+    pub note_count: usize,
+    pub exists: bool,
+}
+
+/// How much a tag is used, split by where its notes are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagUsage {
+    pub name: String,
+    pub active: usize,
+    pub archived: usize,
+    pub trashed: usize,
+    /// The latest `updated_at` among the notes carrying the tag, trash included.
+    pub last_used: Option<i64>,
+}
+
+impl TagUsage {
+    /// Unused tags are on no note outside the trash.
+    pub fn is_unused(&self) -> bool {
+        self.active == 0 && self.archived == 0
+    }
+}
+
+impl StorageHandle {
+    /// Every tag with its note counts, ordered by name ignoring case.
+    pub fn tag_usage(&self) -> Result<Vec<TagUsage>> {
+        self.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT t.name,
+                        COUNT(n.id) FILTER (WHERE n.deleted_at IS NULL AND n.archived = 0),
+                        COUNT(n.id) FILTER (WHERE n.deleted_at IS NULL AND n.archived != 0),
+                        COUNT(n.id) FILTER (WHERE n.deleted_at IS NOT NULL),
+                        MAX(n.updated_at)
+                 FROM tags t
+                 LEFT JOIN note_tags nt ON nt.tag_id = t.id
+                 LEFT JOIN notes n ON n.id = nt.note_id
+                 GROUP BY t.id
+                 ORDER BY t.name COLLATE NOCASE, t.id",
+            )?;
+            let usage = stmt
+                .query_map([], |row| {
+                    Ok(TagUsage {
+                        name: row.get(0)?,
+                        active: row.get::<_, i64>(1)? as usize,
+                        archived: row.get::<_, i64>(2)? as usize,
+                        trashed: row.get::<_, i64>(3)? as usize,
+                        last_used: row.get(4)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()
+                .context("reading tag usage")?;
+            Ok(usage)
+        })
+    }
+
+    /// Deletes several tags in one transaction, detaching them from every note.
+    /// Names that match no tag are skipped; returns how many tags went away.
+    pub fn delete_tags(&self, names: &[String]) -> Result<usize> {
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+        let mut deleted = 0;
+        for name in names {
+            let tag_id: Option<i64> = tx
+                .query_row(
+                    "SELECT id FROM tags WHERE name = ?1",
+                    params![name.trim()],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let Some(tag_id) = tag_id else {
+                continue;
+            };
+            tx.execute("DELETE FROM note_tags WHERE tag_id = ?1", params![tag_id])?;
+            tx.execute("DELETE FROM tags WHERE id = ?1", params![tag_id])?;
+            deleted += 1;
+        }
+        tx.commit()?;
+        if deleted > 0 {
+            self.mirror_all();
+        }
+        Ok(deleted)
+    }
+
+    /// Every tag in tree order: each tag followed by the ones nested under it,
+    /// siblings ordered by name ignoring case.
+    pub fn tag_tree(&self) -> Result<Vec<TagNode>> {
+        self.with_connection(load_tag_tree)
+    }
+}
+
+/// Whether `tag` is `branch` or nested under it, ignoring case.
+pub fn tag_in_branch(tag: &str, branch: &str) -> bool {
+    let tag = tag.to_lowercase();
+    let branch = branch.trim_end_matches(TAG_SEPARATOR).to_lowercase();
+    match tag.strip_prefix(&branch) {
+        Some(rest) => rest.is_empty() || rest.starts_with(TAG_SEPARATOR),
+        None => false,
+    }
+}
+
+/// Orders tags so that every tag sorts right before the ones nested under it.
+pub fn tag_sort_key(tag: &str) -> Vec<String> {
+    tag.split(TAG_SEPARATOR).map(str::to_lowercase).collect()
+}
+
+/// `tag` and every tag it is nested under, outermost first.
+pub fn tag_ancestors(tag: &str) -> impl Iterator<Item = &str> {
+    tag.match_indices(TAG_SEPARATOR)
+        .map(move |(idx, _)| &tag[..idx])
+        .chain(std::iter::once(tag))
+}
+
+/// Renames the tag `from` and every tag nested under it, so `from/x` becomes
+/// `to/x`. A tag whose new name is already taken is merged into that tag.
+/// `from` itself need not exist as long as something is nested under it.
+pub(super) fn rename_branch(conn: &Connection, from: &str, to: &str) -> Result<TagRenameOutcome> {
+    if tag_in_branch(to, from) && !to.eq_ignore_ascii_case(from) {
+        bail!("cannot move tag '{from}' under itself");
+    }
+    // Branches match ignoring case, as `tag_in_branch` and `tag:` do.
+    let mut stmt = conn.prepare(
+        "SELECT id, name FROM tags
+         WHERE name = ?1 COLLATE NOCASE
+            OR substr(name, 1, length(?1) + 1) COLLATE NOCASE = ?1 || ?2
+         ORDER BY length(name), id",
+    )?;
+    let branch = stmt
+        .query_map(params![from, TAG_SEPARATOR.to_string()], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("reading nested tags")?;
+    if branch.is_empty() {
+        bail!("tag '{from}' not found");
+    }
+    let target_existed = conn
+        .query_row("SELECT 1 FROM tags WHERE name = ?1", params![to], |_row| {
+            Ok(())
+        })
+        .optional()?
+        .is_some();
+
+    let mut reassigned = 0;
+    let mut descendants = 0;
+    for (source_id, name) in branch {
+        let renamed = format!("{to}{}", &name[from.len()..]);
+        if !name.eq_ignore_ascii_case(from) {
+            descendants += 1;
+        }
+        let existing: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM tags WHERE name = ?1",
+                params![renamed],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match existing {
+            Some(target_id) if target_id != source_id => {
+                reassigned += conn.execute(
+                    "INSERT OR IGNORE INTO note_tags (note_id, tag_id)
+                     SELECT note_id, ?1 FROM note_tags WHERE tag_id = ?2",
+                    params![target_id, source_id],
+                )?;
+                conn.execute(
+                    "DELETE FROM note_tags WHERE tag_id = ?1",
+                    params![source_id],
+                )?;
+                conn.execute("DELETE FROM tags WHERE id = ?1", params![source_id])?;
+            }
+            _ => {
+                conn.execute(
+                    "UPDATE tags SET name = ?1 WHERE id = ?2",
+                    params![renamed, source_id],
+                )
+                .with_context(|| format!("renaming tag '{name}'"))?;
+            }
+        }
+    }
+
+    let (from, to) = (from.to_string(), to.to_string());
+    Ok(if target_existed && !from.eq_ignore_ascii_case(&to) {
+        TagRenameOutcome::Merged {
+            from,
+            to,
+            reassigned,
+            descendants,
+        }
+    } else {
+        TagRenameOutcome::Renamed {
+            from,
+            to,
+            descendants,
+        }
+    })
+}
+
+fn load_tag_tree(conn: &Connection) -> Result<Vec<TagNode>> {
+    let mut stmt = conn.prepare("SELECT name FROM tags")?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()
+        .context("reading tags")?;
+    let mut stmt = conn.prepare(
+        "SELECT t.name, nt.note_id
+         FROM note_tags nt
+         JOIN tags t ON t.id = nt.tag_id
+         JOIN notes n ON n.id = nt.note_id
+         WHERE n.deleted_at IS NULL",
+    )?;
+    let tagged = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("reading tagged notes")?;
+
+    // Branches are keyed by lowercased name, matching how `tag:` filters.
+    let mut branch_notes: HashMap<String, HashSet<i64>> = HashMap::new();
+    for (name, note_id) in &tagged {
+        for branch in tag_ancestors(name) {
+            branch_notes
+                .entry(branch.to_lowercase())
+                .or_default()
+                .insert(*note_id);
+        }
+    }
+
+    let existing: HashSet<&str> = names.iter().map(String::as_str).collect();
+    let mut nodes: BTreeMap<Vec<String>, TagNode> = BTreeMap::new();
+    for name in &names {
+        for branch in tag_ancestors(name) {
+            nodes
+                .entry(tag_sort_key(branch))
+                .or_insert_with(|| TagNode {
+                    name: branch.to_string(),
+                    depth: branch.matches(TAG_SEPARATOR).count(),
+                    note_count: branch_notes
+                        .get(&branch.to_lowercase())
+                        .map_or(0, HashSet::len),
+                    exists: existing.contains(branch),
+                });
+        }
+    }
+    Ok(nodes.into_values().collect())
+}
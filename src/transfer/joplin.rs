@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use super::markdown::push_tag;
+use super::{ImportBatch, ImportCandidate, Skipped};
+use crate::markdown::{parse_links, LinkTarget};
+use crate::storage::{ImportedNote, NOTEBOOK_SEPARATOR};
+
+/// Item types in a RAW export, from the `type_` field.
+const TYPE_NOTE: &str = "1";
+const TYPE_FOLDER: &str = "2";
+const TYPE_TAG: &str = "5";
+const TYPE_NOTE_TAG: &str = "6";
+/// Notebooks nested deeper than this are assumed to loop.
+const MAX_FOLDER_DEPTH: usize = 64;
+
+/// A `key: value` line of an item's metadata block.
+static FIELD_LINE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[a-z][a-z0-9_]*:(?: |$)").expect("valid field pattern"));
+/// `[label](:/id)` links to other items; a leading `!` marks an image.
+static ITEM_LINK: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(!?)\[[^\]\n]*\]\(:/([0-9a-f]{32})(?:#[^)\s]*)?\)")
+        .expect("valid item link pattern")
+});
+
+/// One file of a RAW export: a title line, an optional body, then a block of
+/// metadata fields.
+#[derive(Debug)]
+struct Item {
+    path: PathBuf,
+    title: String,
+    body: String,
+    fields: HashMap<String, String>,
+}
+
+impl Item {
+    fn field(&self, key: &str) -> Option<&str> {
+        self.fields
+            .get(key)
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        self.field(key).is_some_and(|value| value != "0")
+    }
+}
+
+/// Reads a Joplin RAW export directory: one `<id>.md` file per note,
+/// notebook, tag and note-tag link. Notebooks keep their nesting, tags are
+/// attached to their notes, and `[label](:/id)` links between notes become
+/// `[[Title]]` links. Encrypted items, conflict copies and notes in Joplin's
+/// trash are reported as skipped; resources are left out.
+pub fn read_raw_export(dir: &Path) -> Result<ImportBatch> {
+    if !dir.is_dir() {
+        bail!("{} is not a directory", dir.display());
+    }
+    let mut entries = fs::read_dir(dir)
+        .with_context(|| format!("reading {}", dir.display()))?
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("reading {}", dir.display()))?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let mut batch = ImportBatch::default();
+    let mut items = Vec::new();
+    for entry in entries {
+        let path = PathBuf::from(entry.file_name());
+        if !entry.file_type()?.is_file()
+            || path.extension().and_then(|ext| ext.to_str()) != Some("md")
+        {
+            continue;
+        }
+        match fs::read_to_string(entry.path()) {
+            Ok(text) => match parse_item(path.clone(), &text) {
+                Some(item) => items.push(item),
+                None => batch.skipped.push(Skipped {
+                    path,
+                    reason: "not a Joplin item".to_string(),
+                }),
+            },
+            Err(_) => batch.skipped.push(Skipped {
+                path,
+                reason: "not UTF-8 text".to_string(),
+            }),
+        }
+    }
+
+    let of_type = |kind: &'static str| {
+        items
+            .iter()
+            .filter(move |item| item.field("type_") == Some(kind))
+    };
+    let folders: HashMap<&str, &Item> = of_type(TYPE_FOLDER)
+        .filter_map(|item| Some((item.field("id")?, item)))
+        .collect();
+    let tags: HashMap<&str, &str> = of_type(TYPE_TAG)
+        .filter(|item| !item.flag("encryption_applied"))
+        .filter_map(|item| Some((item.field("id")?, item.title.as_str())))
+        .collect();
+    let mut note_tags: HashMap<&str, Vec<&str>> = HashMap::new();
+    for link in of_type(TYPE_NOTE_TAG) {
+        if let (Some(note), Some(tag)) = (link.field("note_id"), link.field("tag_id")) {
+            if let Some(name) = tags.get(tag) {
+                note_tags.entry(note).or_default().push(name);
+            }
+        }
+    }
+    let titles: HashMap<&str, String> = of_type(TYPE_NOTE)
+        .filter_map(|item| Some((item.field("id")?, note_title(item))))
+        .collect();
+
+    for item in of_type(TYPE_NOTE) {
+        let note = if item.flag("encryption_applied") {
+            Err(anyhow::anyhow!("encrypted"))
+        } else if item.flag("is_conflict") {
+            Err(anyhow::anyhow!("conflict copy"))
+        } else if item.flag("deleted_time") {
+            Err(anyhow::anyhow!("in the Joplin trash"))
+        } else {
+            item_note(
+                item,
+                &folders,
+                &titles,
+                note_tags.get(item.field("id").unwrap_or_default()),
+            )
+        };
+        match note {
+            Ok(note) => batch.notes.push(ImportCandidate {
+                path: item.path.clone(),
+                note,
+            }),
+            Err(err) => batch.skipped.push(Skipped {
+                path: item.path.clone(),
+                reason: format!("{err:#}"),
+            }),
+        }
+    }
+    Ok(batch)
+}
+
+fn item_note(
+    item: &Item,
+    folders: &HashMap<&str, &Item>,
+    titles: &HashMap<&str, String>,
+    tags: Option<&Vec<&str>>,
+) -> Result<ImportedNote> {
+    let created_at = item_timestamp(item, &["user_created_time", "created_time"])?;
+    let updated_at = item_timestamp(item, &["user_updated_time", "updated_time"])?;
+    let (Some(created_at), Some(updated_at)) = (created_at, updated_at) else {
+        bail!("missing created_time or updated_time");
+    };
+    let mut note_tags = Vec::new();
+    for tag in tags.into_iter().flatten() {
+        push_tag(&mut note_tags, tag);
+    }
+    Ok(ImportedNote {
+        title: note_title(item),
+        body: rewrite_item_links(&item.body, titles),
+        tags: note_tags,
+        created_at,
+        updated_at,
+        pinned: false,
+        archived: false,
+        notebook: item
+            .field("parent_id")
+            .and_then(|id| folder_path(id, folders)),
+    })
+}
+
+/// Splits a RAW export file into its title, body and metadata fields;
+/// `None` when it does not end in a metadata block with an `id` and `type_`.
+fn parse_item(path: PathBuf, text: &str) -> Option<Item> {
+    let text = text.trim_end_matches('\n');
+    let mut fields_start = text.len();
+    let mut fields = HashMap::new();
+    for line in text.rsplit('\n') {
+        if !FIELD_LINE.is_match(line) {
+            break;
+        }
+        let (key, value) = line.split_once(':')?;
+        fields.insert(key.to_string(), value.trim().to_string());
+        // Each field line goes with the newline before it, so the content
+        // left over ends in the newline of the blank separator line.
+        fields_start -= (line.len() + 1).min(fields_start);
+    }
+    if !fields.contains_key("id") || !fields.contains_key("type_") {
+        return None;
+    }
+    let content = text[..fields_start].strip_suffix('\n').unwrap_or("");
+    let (title, body) = content.split_once("\n\n").unwrap_or((content, ""));
+    Some(Item {
+        path,
+        title: title.trim().to_string(),
+        body: body.to_string(),
+        fields,
+    })
+}
+
+/// Joplin allows untitled notes; they borrow their first line of text.
+fn note_title(item: &Item) -> String {
+    if !item.title.is_empty() {
+        return item.title.clone();
+    }
+    item.body
+        .lines()
+        .map(|line| line.trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or("Untitled")
+        .to_string()
+}
+
+fn item_timestamp(item: &Item, keys: &[&str]) -> Result<Option<i64>> {
+    let Some((key, value)) = keys
+        .iter()
+        .find_map(|key| item.field(key).map(|value| (key, value)))
+    else {
+        return Ok(None);
+    };
+    let parsed = OffsetDateTime::parse(value, &Rfc3339)
+        .with_context(|| format!("invalid `{key}` '{value}'"))?;
+    Ok(Some(parsed.unix_timestamp()))
+}
+
+/// The notebook path of the folder `id`, from the top level down.
+fn folder_path(id: &str, folders: &HashMap<&str, &Item>) -> Option<String> {
+    let mut names = Vec::new();
+    let mut current = folders.get(id);
+    while let Some(folder) = current {
+        if names.len() == MAX_FOLDER_DEPTH {
+            break;
+        }
+        let name = folder.title.replace(NOTEBOOK_SEPARATOR, "-");
+        names.push(if name.trim().is_empty() {
+            "Untitled".to_string()
+        } else {
+            name
+        });
+        current = folder
+            .field("parent_id")
+            .and_then(|parent| folders.get(parent));
+    }
+    names.reverse();
+    (!names.is_empty()).then(|| names.join(&NOTEBOOK_SEPARATOR.to_string()))
+}
+
+/// Rewrites `[label](:/id)` links to notes in the export as `[[Title]]`
+/// links. Images, resources and titles that would not read back as a link
+/// are left alone.
+fn rewrite_item_links(body: &str, titles: &HashMap<&str, String>) -> String {
+    ITEM_LINK
+        .replace_all(body, |caps: &Captures<'_>| {
+            let whole = caps[0].to_string();
+            if &caps[1] == "!" {
+                return whole;
+            }
+            let Some(title) = titles.get(&caps[2]) else {
+                return whole;
+            };
+            let link = format!("[[{title}]]");
+            let round_trips = parse_links(&link)
+                .first()
+                .is_some_and(|parsed| parsed.target == LinkTarget::Title(title.clone()));
+            if round_trips {
+                link
+            } else {
+                whole
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const NOTE_ID: &str = "0123456789abcdef0123456789abcdef";
+    const OTHER_ID: &str = "fedcba9876543210fedcba9876543210";
+    const PARENT_ID: &str = "11111111111111111111111111111111";
+    const CHILD_ID: &str = "22222222222222222222222222222222";
+    const TAG_ID: &str = "33333333333333333333333333333333";
+
+    fn item(title: &str, body: &str, fields: &[(&str, &str)]) -> String {
+        let props: Vec<String> = fields.iter().map(|(k, v)| format!("{k}: {v}")).collect();
+        let mut parts = vec![title.to_string()];
+        if !body.is_empty() {
+            parts.push(body.to_string());
+        }
+        parts.push(props.join("\n"));
+        parts.join("\n\n")
+    }
+
+    #[test]
+    fn reads_notes_notebooks_and_tags() -> Result<()> {
+        let dir = TempDir::new()?;
+        let write = |id: &str, text: String| fs::write(dir.path().join(format!("{id}.md")), text);
+        write(
+            NOTE_ID,
+            item(
+                "Trip plan",
+                "Pack [the list](:/fedcba9876543210fedcba9876543210).\n\n![map](:/44444444444444444444444444444444)",
+                &[
+                    ("id", NOTE_ID),
+                    ("parent_id", CHILD_ID),
+                    ("created_time", "2021-03-04T10:11:12.345Z"),
+                    ("updated_time", "2021-03-05T00:00:00.000Z"),
+                    ("user_created_time", "2020-01-01T00:00:00.000Z"),
+                    ("is_conflict", "0"),
+                    ("deleted_time", "0"),
+                    ("type_", "1"),
+                ],
+            ),
+        )?;
+        write(
+            OTHER_ID,
+            item(
+                "",
+                "# Packing list\nsocks",
+                &[
+                    ("id", OTHER_ID),
+                    ("parent_id", ""),
+                    ("created_time", "2021-03-04T10:11:12.345Z"),
+                    ("updated_time", "2021-03-04T10:11:12.345Z"),
+                    ("type_", "1"),
+                ],
+            ),
+        )?;
+        write(
+            PARENT_ID,
+            item(
+                "Travel",
+                "",
+                &[("id", PARENT_ID), ("parent_id", ""), ("type_", "2")],
+            ),
+        )?;
+        write(
+            CHILD_ID,
+            item(
+                "2021/Spring",
+                "",
+                &[("id", CHILD_ID), ("parent_id", PARENT_ID), ("type_", "2")],
+            ),
+        )?;
+        write(
+            TAG_ID,
+            item("holiday", "", &[("id", TAG_ID), ("type_", "5")]),
+        )?;
+        write(
+            "55555555555555555555555555555555",
+            item(
+                "",
+                "",
+                &[
+                    ("id", "5555"),
+                    ("note_id", NOTE_ID),
+                    ("tag_id", TAG_ID),
+                    ("type_", "6"),
+                ],
+            ),
+        )?;
+        write(
+            "66666666666666666666666666666666",
+            item(
+                "Clash",
+                "body",
+                &[
+                    ("id", "6666"),
+                    ("created_time", "2021-03-04T10:11:12.345Z"),
+                    ("updated_time", "2021-03-04T10:11:12.345Z"),
+                    ("is_conflict", "1"),
+                    ("type_", "1"),
+                ],
+            ),
+        )?;
+        fs::write(dir.path().join("README.md"), "not an item")?;
+        fs::create_dir(dir.path().join("resources"))?;
+
+        let batch = read_raw_export(dir.path())?;
+        assert_eq!(batch.notes.len(), 2);
+        let trip = &batch.notes[0].note;
+        assert_eq!(trip.title, "Trip plan");
+        assert_eq!(
+            trip.body,
+            "Pack [[Packing list]].\n\n![map](:/44444444444444444444444444444444)"
+        );
+        assert_eq!(trip.notebook.as_deref(), Some("Travel/2021-Spring"));
+        assert_eq!(trip.tags, vec!["holiday"]);
+        assert_eq!(trip.created_at, 1_577_836_800);
+        assert_eq!(trip.updated_at, 1_614_902_400);
+        let list = &batch.notes[1].note;
+        assert_eq!(list.title, "Packing list");
+        assert_eq!(list.notebook, None);
+
+        let skipped: Vec<(String, &str)> = batch
+            .skipped
+            .iter()
+            .map(|skip| (skip.path.display().to_string(), skip.reason.as_str()))
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![
+                ("README.md".to_string(), "not a Joplin item"),
+                (
+                    "66666666666666666666666666666666.md".to_string(),
+                    "conflict copy"
+                ),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::OffsetDateTime;
+
+use crate::storage::Archive;
+
+/// Names the file type in the envelope, so other JSON is not mistaken for an
+/// archive.
+pub const ARCHIVE_FORMAT: &str = "notetui-archive";
+/// The archive layout written by this build. Bump it when the layout
+/// changes, and teach [`upgrade`] to bring older archives forward.
+pub const ARCHIVE_VERSION: u64 = 1;
+
+/// The top level of an archive file: what it is and which layout it uses,
+/// then the tables.
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    format: String,
+    version: u64,
+    #[serde(default)]
+    exported_at: i64,
+    #[serde(flatten)]
+    archive: Archive,
+}
+
+/// Writes `archive` to `path` as a single JSON document.
+pub fn write_archive(archive: Archive, path: &Path) -> Result<()> {
+    let envelope = Envelope {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        exported_at: OffsetDateTime::now_utc().unix_timestamp(),
+        archive,
+    };
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+    let json = serde_json::to_string_pretty(&envelope).context("serialising archive")?;
+    fs::write(path, json + "\n").with_context(|| format!("writing {}", path.display()))
+}
+
+/// Reads an archive written by this or an earlier build.
+pub fn read_archive(path: &Path) -> Result<Archive> {
+    let text = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let value: Value = serde_json::from_str(&text)
+        .with_context(|| format!("{} is not valid JSON", path.display()))?;
+    if value.get("format").and_then(Value::as_str) != Some(ARCHIVE_FORMAT) {
+        bail!("{} is not a notetui archive", path.display());
+    }
+    let Some(version) = value.get("version").and_then(Value::as_u64) else {
+        bail!("{} has no archive version", path.display());
+    };
+    if version > ARCHIVE_VERSION {
+        bail!(
+            "{} uses archive version {version}, but this build reads up to version {ARCHIVE_VERSION}",
+            path.display()
+        );
+    }
+    let envelope: Envelope = serde_json::from_value(upgrade(value, version)?)
+        .with_context(|| format!("reading archive {}", path.display()))?;
+    Ok(envelope.archive)
+}
+
+/// Brings an archive of an older `version` up to [`ARCHIVE_VERSION`], one
+/// version at a time.
+fn upgrade(value: Value, version: u64) -> Result<Value> {
+    match version {
+        ARCHIVE_VERSION => Ok(value),
+        other => bail!("archive version {other} is not supported"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{ArchivedNote, ArchivedTag};
+    use tempfile::TempDir;
+
+    #[test]
+    fn round_trips_and_checks_the_envelope() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("nested/notes.json");
+        let archive = Archive {
+            tags: vec![ArchivedTag {
+                id: 3,
+                name: "work".into(),
+            }],
+            notes: vec![ArchivedNote {
+                id: 7,
+                title: "Plan".into(),
+                body: "[[#7]]\n".into(),
+                created_at: 1,
+                updated_at: 2,
+                pinned: true,
+                archived: false,
+                deleted_at: Some(3),
+                notebook_id: None,
+            }],
+            ..Archive::default()
+        };
+        write_archive(archive.clone(), &path)?;
+        assert_eq!(read_archive(&path)?, archive);
+        let text = fs::read_to_string(&path)?;
+        assert!(text.contains("\"format\": \"notetui-archive\""), "{text}");
+
+        fs::write(&path, r#"{"format": "notetui-archive", "version": 99}"#)?;
+        let err = read_archive(&path).expect_err("newer version");
+        assert!(err.to_string().contains("archive version 99"), "{err}");
+        fs::write(&path, r#"{"notes": []}"#)?;
+        let err = read_archive(&path).expect_err("no envelope");
+        assert!(
+            err.to_string().contains("is not a notetui archive"),
+            "{err}"
+        );
+        // Tables missing from the file read as empty.
+        fs::write(
+            &path,
+            r#"{"format": "notetui-archive", "version": 1, "exported_at": 0}"#,
+        )?;
+        assert_eq!(read_archive(&path)?, Archive::default());
+        Ok(())
+    }
+}
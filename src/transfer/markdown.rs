@@ -0,0 +1,688 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use std::time::UNIX_EPOCH;
+
+use anyhow::{anyhow, bail, Context, Result};
+use time::macros::format_description;
+use time::{format_description::well_known::Rfc3339, Date, OffsetDateTime};
+
+use super::{ImportBatch, ImportCandidate, Skipped};
+use crate::search::Calendar;
+use crate::storage::{ImportedNote, NoteRecord, TAG_SEPARATOR};
+
+/// Marks the start and end of a front matter block.
+const FRONT_MATTER_FENCE: &str = "---";
+/// Longest slug kept from a title, in characters.
+const SLUG_MAX_CHARS: usize = 60;
+
+/// What an export did to the files in its directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportReport {
+    pub written: usize,
+    /// Files left alone because they already held the note as rendered.
+    pub unchanged: usize,
+    /// Files of earlier exports removed because their note was renamed.
+    pub removed: usize,
+}
+
+/// Writes one Markdown file per note into `dir`, creating it if needed.
+///
+/// An incremental export treats `dir` as the result of an earlier export: a
+/// file already holding a note exactly as rendered is not rewritten, and the
+/// file an exported note was written to under an earlier title is removed.
+/// Other files in `dir`, including those of notes outside `notes`, are never
+/// touched.
+pub fn export_markdown(
+    notes: &[NoteRecord],
+    dir: &Path,
+    incremental: bool,
+) -> Result<ExportReport> {
+    fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    let previous = if incremental {
+        exported_files(dir)?
+    } else {
+        HashMap::new()
+    };
+
+    let mut report = ExportReport::default();
+    let mut kept = HashSet::new();
+    for note in notes {
+        let path = dir.join(file_name(note));
+        let rendered = render_note(note);
+        let current = fs::read_to_string(&path).ok();
+        if incremental && current.as_deref() == Some(rendered.as_str()) {
+            report.unchanged += 1;
+        } else {
+            fs::write(&path, rendered).with_context(|| format!("writing {}", path.display()))?;
+            report.written += 1;
+        }
+        kept.insert(path);
+    }
+
+    let exported: HashSet<i64> = notes.iter().map(|note| note.id).collect();
+    let stale = previous
+        .into_iter()
+        .filter(|(id, _)| exported.contains(id))
+        .flat_map(|(_, paths)| paths);
+    for path in stale.filter(|path| !kept.contains(path)) {
+        fs::remove_file(&path).with_context(|| format!("removing {}", path.display()))?;
+        report.removed += 1;
+    }
+    Ok(report)
+}
+
+/// The file a note is exported to: its slugged title followed by its id, so
+/// names stay stable across exports and never collide.
+pub fn file_name(note: &NoteRecord) -> String {
+    exported_name(&note.title, note.id)
+}
+
+fn exported_name(title: &str, id: i64) -> String {
+    format!("{}-{id}.md", slug(title))
+}
+
+/// Lowercases `title` and joins its runs of letters and digits with `-`.
+/// Titles without any fall back to `note`.
+pub fn slug(title: &str) -> String {
+    let mut slug = String::new();
+    for ch in title.chars().flat_map(char::to_lowercase) {
+        if slug.chars().count() >= SLUG_MAX_CHARS {
+            break;
+        }
+        if ch.is_alphanumeric() {
+            slug.push(ch);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "note".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// A note as Markdown: a YAML front matter block with its metadata, then the
+/// body. Strings are written as JSON, which YAML reads as quoted scalars and
+/// flow sequences.
+pub fn render_note(note: &NoteRecord) -> String {
+    let mut out = String::new();
+    out.push_str(FRONT_MATTER_FENCE);
+    out.push('\n');
+    out.push_str(&format!("id: {}\n", note.id));
+    out.push_str(&format!("title: {}\n", quoted(&note.title)));
+    let tags: Vec<String> = note.tags.iter().map(|tag| quoted(tag)).collect();
+    out.push_str(&format!("tags: [{}]\n", tags.join(", ")));
+    out.push_str(&format!("created: {}\n", format_timestamp(note.created_at)));
+    out.push_str(&format!("updated: {}\n", format_timestamp(note.updated_at)));
+    out.push_str(&format!("pinned: {}\n", note.pinned));
+    out.push_str(&format!("archived: {}\n", note.archived));
+    out.push_str(FRONT_MATTER_FENCE);
+    out.push_str("\n\n");
+    out.push_str(&note.body);
+    if !note.body.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+/// A value in a front matter block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrontMatterValue {
+    Text(String),
+    List(Vec<String>),
+}
+
+/// The top-level fields of a front matter block, in order. Values may be
+/// plain or quoted scalars, `[a, b]` flow lists, or `- item` lists under an
+/// empty `key:`; anything nested deeper is ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrontMatter {
+    fields: Vec<(String, FrontMatterValue)>,
+}
+
+impl FrontMatter {
+    /// The value of `key`, ignoring case.
+    pub fn get(&self, key: &str) -> Option<&FrontMatterValue> {
+        self.fields
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value)
+    }
+
+    /// The value of `key` if it is a non-empty scalar.
+    pub fn text(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            FrontMatterValue::Text(text) if !text.is_empty() => Some(text),
+            _ => None,
+        }
+    }
+
+    /// The items of `key`; a scalar is read as a comma-separated list.
+    pub fn list(&self, key: &str) -> Vec<String> {
+        match self.get(key) {
+            Some(FrontMatterValue::List(items)) => items.clone(),
+            Some(FrontMatterValue::Text(text)) => text
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Splits a leading front matter block off `text`, returning its fields and
+/// the rest of the text. `None` when the text does not open with a closed
+/// block.
+pub fn split_front_matter(text: &str) -> Option<(FrontMatter, &str)> {
+    let rest = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let rest = rest.strip_prefix(FRONT_MATTER_FENCE)?;
+    let rest = rest
+        .strip_prefix('\n')
+        .or_else(|| rest.strip_prefix("\r\n"))?;
+    let mut front = FrontMatter::default();
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line == FRONT_MATTER_FENCE || line == "..." {
+            return Some((front, &rest[offset..]));
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let Some(item) = trimmed.strip_prefix('-') {
+            if let Some((_, FrontMatterValue::List(items))) = front.fields.last_mut() {
+                let item = unquote(item.trim());
+                if !item.is_empty() {
+                    items.push(item);
+                }
+            }
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            let value = if value.is_empty() {
+                FrontMatterValue::List(Vec::new())
+            } else if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                FrontMatterValue::List(split_flow_list(items))
+            } else {
+                FrontMatterValue::Text(unquote(value))
+            };
+            front.fields.push((key.trim().to_string(), value));
+        }
+    }
+    None
+}
+
+/// Turns a Markdown file's text, its path under the import root and its
+/// modification time into a note.
+pub type NoteParser = fn(&str, &Path, i64) -> Result<ImportedNote>;
+
+/// Reads a note from the Markdown file at `relative` under an import root.
+///
+/// The title comes from the front matter, else the first `# ` heading, else
+/// the file name. Missing dates fall back to `modified`, the file's
+/// modification time. Front matter tags are kept (a leading `#` dropped) and
+/// the directories above the file become one nested tag, so
+/// `work/projects/plan.md` is tagged `work/projects`.
+pub fn parse_markdown_note(text: &str, relative: &Path, modified: i64) -> Result<ImportedNote> {
+    let (front, body) = front_matter_and_body(text);
+    let title = front
+        .text("title")
+        .map(str::trim)
+        .filter(|title| !title.is_empty())
+        .map(str::to_string)
+        .or_else(|| {
+            body.lines()
+                .find_map(|line| line.strip_prefix("# "))
+                .map(str::trim)
+                .filter(|heading| !heading.is_empty())
+                .map(str::to_string)
+        })
+        .or_else(|| file_title(relative))
+        .context("no title in front matter, heading or file name")?;
+    let mut note = note_from_front_matter(&front, title, body, modified)?;
+    if let Some(folder) = folder_path(relative, TAG_SEPARATOR) {
+        push_tag(&mut note.tags, &folder);
+    }
+    Ok(note)
+}
+
+/// Splits `text` into its front matter (empty when there is none) and the
+/// body, dropping the blank line that usually follows the block.
+pub(super) fn front_matter_and_body(text: &str) -> (FrontMatter, &str) {
+    match split_front_matter(text) {
+        Some((front, rest)) => {
+            let body = rest
+                .strip_prefix('\n')
+                .or_else(|| rest.strip_prefix("\r\n"))
+                .unwrap_or(rest);
+            (front, body)
+        }
+        None => (
+            FrontMatter::default(),
+            text.strip_prefix('\u{feff}').unwrap_or(text),
+        ),
+    }
+}
+
+/// A note titled `title` with `body`, taking its dates, flags and tags from
+/// `front`. Missing dates fall back to `modified`.
+pub(super) fn note_from_front_matter(
+    front: &FrontMatter,
+    title: String,
+    body: &str,
+    modified: i64,
+) -> Result<ImportedNote> {
+    let calendar = Calendar::local();
+    let updated_field = front_timestamp(front, &["updated", "modified", "updated_at"], &calendar)?;
+    let created_field = front_timestamp(front, &["created", "date", "created_at"], &calendar)?;
+    let created_at = created_field.or(updated_field).unwrap_or(modified);
+    let updated_at = updated_field.unwrap_or(modified.max(created_at));
+    let mut tags = Vec::new();
+    for tag in front.list("tags") {
+        push_tag(&mut tags, tag.trim_start_matches('#'));
+    }
+    Ok(ImportedNote {
+        title,
+        body: body.to_string(),
+        tags,
+        created_at,
+        updated_at,
+        pinned: front_flag(front, "pinned")?,
+        archived: front_flag(front, "archived")?,
+        notebook: None,
+    })
+}
+
+/// Adds `tag` to `tags` unless it is empty or already there, ignoring case.
+pub(super) fn push_tag(tags: &mut Vec<String>, tag: &str) {
+    let tag = tag.trim();
+    if !tag.is_empty() && !tags.iter().any(|seen| seen.eq_ignore_ascii_case(tag)) {
+        tags.push(tag.to_string());
+    }
+}
+
+/// The name of the file at `relative` without its extension.
+pub(super) fn file_title(relative: &Path) -> Option<String> {
+    relative
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().trim().to_string())
+        .filter(|stem| !stem.is_empty())
+}
+
+/// The directories above `relative` joined with `separator`; `None` for a
+/// file at the import root.
+pub(super) fn folder_path(relative: &Path, separator: char) -> Option<String> {
+    let parts: Vec<String> = relative
+        .parent()?
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy().trim().to_string())
+        .filter(|part| !part.is_empty())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(&separator.to_string()))
+}
+
+/// Reads every Markdown file under `dir` as a plain Markdown note (see
+/// [`parse_markdown_note`]).
+pub fn read_markdown_dir(dir: &Path) -> Result<ImportBatch> {
+    read_markdown_tree(dir, parse_markdown_note)
+}
+
+/// Reads every Markdown file under `dir` with `parse`, walking
+/// subdirectories in name order. Hidden files and directories are left out,
+/// as are files that are not Markdown; Markdown files that cannot be read as
+/// notes are reported as skipped.
+pub fn read_markdown_tree(dir: &Path, parse: NoteParser) -> Result<ImportBatch> {
+    if !dir.is_dir() {
+        bail!("{} is not a directory", dir.display());
+    }
+    let mut batch = ImportBatch::default();
+    walk_markdown_dir(dir, Path::new(""), parse, &mut batch)?;
+    Ok(batch)
+}
+
+fn walk_markdown_dir(
+    root: &Path,
+    relative: &Path,
+    parse: NoteParser,
+    batch: &mut ImportBatch,
+) -> Result<()> {
+    let dir = root.join(relative);
+    let mut entries = fs::read_dir(&dir)
+        .with_context(|| format!("reading {}", dir.display()))?
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("reading {}", dir.display()))?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = relative.join(&name);
+        // Symlinked directories are not followed, so links cannot loop.
+        if entry.file_type()?.is_dir() {
+            walk_markdown_dir(root, &path, parse, batch)?;
+            continue;
+        }
+        let is_markdown = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| {
+                ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown")
+            });
+        if !is_markdown {
+            continue;
+        }
+        match read_markdown_file(&root.join(&path), &path, parse) {
+            Ok(note) => batch.notes.push(ImportCandidate { path, note }),
+            Err(err) => batch.skipped.push(Skipped {
+                path,
+                reason: format!("{err:#}"),
+            }),
+        }
+    }
+    Ok(())
+}
+
+fn read_markdown_file(path: &Path, relative: &Path, parse: NoteParser) -> Result<ImportedNote> {
+    let metadata = fs::metadata(path).context("reading file metadata")?;
+    if !metadata.is_file() {
+        bail!("not a regular file");
+    }
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or_else(
+            || OffsetDateTime::now_utc().unix_timestamp(),
+            |age| age.as_secs() as i64,
+        );
+    let bytes = fs::read(path).context("reading file")?;
+    let text = String::from_utf8(bytes).map_err(|_| anyhow!("not UTF-8 text"))?;
+    parse(&text, relative, modified)
+}
+
+/// The first of `keys` present in `front` as a timestamp: RFC 3339, a
+/// `YYYY-MM-DD` day (local midnight in `calendar`, as date filters read it),
+/// or seconds since the epoch.
+fn front_timestamp(front: &FrontMatter, keys: &[&str], calendar: &Calendar) -> Result<Option<i64>> {
+    let Some((key, value)) = keys
+        .iter()
+        .find_map(|key| front.text(key).map(|value| (key, value.trim())))
+    else {
+        return Ok(None);
+    };
+    if let Ok(epoch) = value.parse::<i64>() {
+        return Ok(Some(epoch));
+    }
+    if let Ok(dt) = OffsetDateTime::parse(value, &Rfc3339) {
+        return Ok(Some(dt.unix_timestamp()));
+    }
+    if let Ok(date) = Date::parse(value, format_description!("[year]-[month]-[day]")) {
+        return Ok(Some(calendar.midnight(date)));
+    }
+    bail!("invalid `{key}` date '{value}'")
+}
+
+fn front_flag(front: &FrontMatter, key: &str) -> Result<bool> {
+    match front.text(key).map(str::to_lowercase).as_deref() {
+        None | Some("false" | "no" | "off") => Ok(false),
+        Some("true" | "yes" | "on") => Ok(true),
+        Some(other) => bail!("invalid `{key}` value '{other}'"),
+    }
+}
+
+/// Splits the inside of a `[a, "b, c"]` flow list on commas outside quotes.
+fn split_flow_list(items: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    for ch in items.chars() {
+        match (ch, quote) {
+            ('"' | '\'', None) => {
+                quote = Some(ch);
+                current.push(ch);
+            }
+            (_, Some(open)) if ch == open => {
+                quote = None;
+                current.push(ch);
+            }
+            (',', None) => parts.push(std::mem::take(&mut current)),
+            _ => current.push(ch),
+        }
+    }
+    parts.push(current);
+    parts
+        .iter()
+        .map(|part| unquote(part.trim()))
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// The text of a YAML scalar: double-quoted values are read as JSON strings
+/// and single-quoted ones unescape `''`.
+fn unquote(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        return serde_json::from_str(value)
+            .unwrap_or_else(|_| value[1..value.len() - 1].to_string());
+    }
+    if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        return value[1..value.len() - 1].replace("''", "'");
+    }
+    value.to_string()
+}
+
+fn quoted(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| format!("\"{value}\""))
+}
+
+fn format_timestamp(epoch: i64) -> String {
+    OffsetDateTime::from_unix_timestamp(epoch)
+        .ok()
+        .and_then(|dt| dt.format(&Rfc3339).ok())
+        .unwrap_or_else(|| epoch.to_string())
+}
+
+/// The files directly in `dir` written by an earlier export, grouped by note
+/// id: those whose front matter `id` and `title` give back their own name
+/// under [`file_name`]. Hand-made files that merely carry an `id` are not
+/// counted.
+pub fn exported_files(dir: &Path) -> Result<HashMap<i64, Vec<PathBuf>>> {
+    let mut files: HashMap<i64, Vec<PathBuf>> = HashMap::new();
+    let entries = fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("md") {
+            continue;
+        }
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let Some((front, _)) = split_front_matter(&text) else {
+            continue;
+        };
+        let (Some(id), Some(title)) = (
+            front.text("id").and_then(|id| id.parse::<i64>().ok()),
+            front.text("title"),
+        ) else {
+            continue;
+        };
+        if path.file_name().and_then(|name| name.to_str()) == Some(&exported_name(title, id)) {
+            files.entry(id).or_default().push(path);
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn note(id: i64, title: &str, body: &str) -> NoteRecord {
+        NoteRecord {
+            id,
+            title: title.to_string(),
+            body: body.to_string(),
+            snippet: None,
+            created_at: 1_700_000_000,
+            updated_at: 1_700_000_600,
+            pinned: false,
+            archived: false,
+            tags: Vec::new(),
+            deleted_at: None,
+            notebook_id: None,
+            fuzzy: false,
+        }
+    }
+
+    #[test]
+    fn renders_front_matter_and_slugged_names() {
+        let mut plan = note(7, "Q3 \"Launch\" Plan: v2!", "# Plan\nShip it.");
+        plan.tags = vec!["project/alpha".into(), "work".into()];
+        plan.pinned = true;
+
+        assert_eq!(file_name(&plan), "q3-launch-plan-v2-7.md");
+        assert_eq!(slug("  ...  "), "note");
+        assert_eq!(slug("Café Notes"), "café-notes");
+        assert_eq!(slug(&"x".repeat(100)).len(), SLUG_MAX_CHARS);
+
+        let rendered = render_note(&plan);
+        assert_eq!(
+            rendered,
+            "---\n\
+             id: 7\n\
+             title: \"Q3 \\\"Launch\\\" Plan: v2!\"\n\
+             tags: [\"project/alpha\", \"work\"]\n\
+             created: 2023-11-14T22:13:20Z\n\
+             updated: 2023-11-14T22:23:20Z\n\
+             pinned: true\n\
+             archived: false\n\
+             ---\n\
+             \n\
+             # Plan\nShip it.\n"
+        );
+        let (front, body) = split_front_matter(&rendered).expect("front matter");
+        assert_eq!(front.text("id"), Some("7"));
+        assert_eq!(front.text("title"), Some("Q3 \"Launch\" Plan: v2!"));
+        assert_eq!(front.list("tags"), vec!["project/alpha", "work"]);
+        assert_eq!(body, "\n# Plan\nShip it.\n");
+        assert!(split_front_matter("# No front matter\n").is_none());
+    }
+
+    #[test]
+    fn incremental_export_rewrites_only_changed_notes() -> Result<()> {
+        let dir = TempDir::new()?;
+        let out = dir.path().join("export");
+        let mut notes = vec![note(1, "Alpha", "one"), note(2, "Beta", "two")];
+        let report = export_markdown(&notes, &out, true)?;
+        assert_eq!(report.written, 2);
+        fs::write(out.join("readme.md"), "kept as is")?;
+        // A hand-made copy carrying the note's id is not the export's file.
+        let copy = render_note(&notes[0]);
+        fs::write(out.join("alpha-copy.md"), &copy)?;
+
+        let report = export_markdown(&notes, &out, true)?;
+        assert_eq!(
+            report,
+            ExportReport {
+                written: 0,
+                unchanged: 2,
+                removed: 0
+            }
+        );
+
+        notes[0].title = "Alpha renamed".into();
+        notes.remove(1);
+        let report = export_markdown(&notes, &out, true)?;
+        assert_eq!(
+            report,
+            ExportReport {
+                written: 1,
+                unchanged: 0,
+                removed: 1
+            }
+        );
+        let mut names: Vec<String> = fs::read_dir(&out)?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect::<Result<_>>()?;
+        names.sort();
+        // Beta left the selection, but its file is not this export's to delete.
+        assert_eq!(
+            names,
+            vec![
+                "alpha-copy.md",
+                "alpha-renamed-1.md",
+                "beta-2.md",
+                "readme.md"
+            ]
+        );
+
+        // A full export rewrites every note.
+        assert_eq!(export_markdown(&notes, &out, false)?.written, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn parses_front_matter_with_fallbacks_and_folder_tags() -> Result<()> {
+        let text = "---\n\
+                    title: 'It''s: a plan'\n\
+                    tags:\n  - \"#work\"\n  - planning\n\
+                    created: 2024-03-01\n\
+                    updated: 2024-03-02T10:00:00+02:00\n\
+                    pinned: yes\n\
+                    ---\n\n# Heading\nBody\n";
+        let note = parse_markdown_note(text, Path::new("Work/Q1/plan.md"), 0)?;
+        assert_eq!(note.title, "It's: a plan");
+        assert_eq!(note.body, "# Heading\nBody\n");
+        assert_eq!(note.tags, vec!["work", "planning", "Work/Q1"]);
+        let march_first = Date::from_calendar_date(2024, time::Month::March, 1)?;
+        assert_eq!(note.created_at, Calendar::local().midnight(march_first));
+        assert_eq!(note.updated_at, 1_709_366_400);
+        assert!(note.pinned && !note.archived);
+
+        let note = parse_markdown_note("intro\n# Second heading\n", Path::new("a.md"), 42)?;
+        assert_eq!(note.title, "Second heading");
+        assert!(note.tags.is_empty());
+        assert_eq!((note.created_at, note.updated_at), (42, 42));
+
+        let text = "---\ntags: [a, \"b, c\"]\ndate: 1700000000\n---\nplain text";
+        let note = parse_markdown_note(text, Path::new("inbox/draft idea.md"), 1_800_000_000)?;
+        assert_eq!(note.title, "draft idea");
+        assert_eq!(note.tags, vec!["a", "b, c", "inbox"]);
+        assert_eq!(note.created_at, 1_700_000_000);
+        assert_eq!(note.updated_at, 1_800_000_000);
+
+        let err = parse_markdown_note("---\ncreated: soon\n---\n", Path::new("x.md"), 0)
+            .expect_err("bad date");
+        assert_eq!(err.to_string(), "invalid `created` date 'soon'");
+        Ok(())
+    }
+
+    #[test]
+    fn date_only_front_matter_starts_at_local_midnight() -> Result<()> {
+        let (front, _) = front_matter_and_body("---\ncreated: 2024-03-01\n---\n");
+        let utc = Calendar::with_offset(|_| time::UtcOffset::UTC);
+        let tokyo = Calendar::with_offset(|_| time::UtcOffset::from_hms(9, 0, 0).unwrap());
+        assert_eq!(
+            front_timestamp(&front, &["created"], &utc)?,
+            Some(1_709_251_200)
+        );
+        assert_eq!(
+            front_timestamp(&front, &["created"], &tokyo)?,
+            Some(1_709_251_200 - 9 * 3_600)
+        );
+        Ok(())
+    }
+}
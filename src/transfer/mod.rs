@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::search::{NoteScope, SearchQuery};
+use crate::storage::{ImportedNote, NoteRecord, StorageHandle};
+
+pub mod joplin;
+pub mod json;
+pub mod markdown;
+pub mod obsidian;
+
+/// A note read from a file, waiting to be imported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportCandidate {
+    /// Where the note came from, relative to the import root.
+    pub path: PathBuf,
+    pub note: ImportedNote,
+}
+
+/// A file an import left out, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// Everything read from an import source, before it reaches storage.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportBatch {
+    pub notes: Vec<ImportCandidate>,
+    pub skipped: Vec<Skipped>,
+}
+
+/// What a note left out of an import duplicates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DuplicateOf {
+    /// A note already in the database.
+    Note(i64),
+    /// A file earlier in the same import.
+    File(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+    pub path: PathBuf,
+    pub of: DuplicateOf,
+}
+
+/// The outcome of an import. In a dry run `imported` counts the notes that
+/// would have been created and `note_ids` stays empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub note_ids: Vec<i64>,
+    pub duplicates: Vec<Duplicate>,
+    pub skipped: Vec<Skipped>,
+}
+
+/// The notes an export covers, ordered by id. Without a query every note
+/// outside the trash is included; a query picks its matches, and without an
+/// `is:` / `in:` qualifier it searches archived notes as well as active ones.
+pub fn select_notes(storage: &StorageHandle, query: &SearchQuery) -> Result<Vec<NoteRecord>> {
+    let scopes = match query.scope {
+        Some(scope) => vec![scope],
+        None => vec![NoteScope::Active, NoteScope::Archived],
+    };
+    let mut notes = Vec::new();
+    for scope in scopes {
+        let scoped = SearchQuery {
+            scope: Some(scope),
+            ..query.clone()
+        };
+        notes.extend(storage.search_notes(&scoped, i64::MAX as usize)?);
+    }
+    notes.sort_by_key(|note| note.id);
+    notes.dedup_by_key(|note| note.id);
+    Ok(notes)
+}
+
+/// Creates the batch's notes in one transaction, leaving out duplicates: a
+/// note with the same title and body (ignoring trailing whitespace) as a
+/// note outside the trash or as an earlier note in the batch. A dry run only
+/// reports what would happen.
+pub fn import_batch(
+    storage: &StorageHandle,
+    batch: ImportBatch,
+    dry_run: bool,
+) -> Result<ImportReport> {
+    let mut seen: HashMap<(String, String), DuplicateOf> = HashMap::new();
+    for note in select_notes(storage, &SearchQuery::default())? {
+        seen.entry(duplicate_key(&note.title, &note.body))
+            .or_insert(DuplicateOf::Note(note.id));
+    }
+
+    let mut report = ImportReport {
+        skipped: batch.skipped,
+        ..ImportReport::default()
+    };
+    let mut notes = Vec::new();
+    for candidate in batch.notes {
+        let key = duplicate_key(&candidate.note.title, &candidate.note.body);
+        if let Some(of) = seen.get(&key) {
+            report.duplicates.push(Duplicate {
+                path: candidate.path,
+                of: of.clone(),
+            });
+            continue;
+        }
+        seen.insert(key, DuplicateOf::File(candidate.path));
+        notes.push(candidate.note);
+    }
+
+    report.imported = notes.len();
+    if !dry_run && !notes.is_empty() {
+        report.note_ids = storage.import_notes(&notes)?;
+    }
+    Ok(report)
+}
+
+fn duplicate_key(title: &str, body: &str) -> (String, String) {
+    (title.trim().to_string(), body.trim_end().to_string())
+}
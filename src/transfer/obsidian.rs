@@ -0,0 +1,119 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use super::markdown::{
+    file_title, folder_path, front_matter_and_body, note_from_front_matter, push_tag,
+    read_markdown_tree,
+};
+use super::ImportBatch;
+use crate::storage::{ImportedNote, NOTEBOOK_SEPARATOR};
+
+/// `[[target#heading|alias]]`, with a leading `!` for embeds.
+static VAULT_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(!?)\[\[([^\[\]\n]+)\]\]").expect("valid vault link pattern"));
+/// A `#tag` at the start of a line or after whitespace.
+static INLINE_TAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?:^|\s)#([\p{L}\p{N}_/-]+)").expect("valid inline tag pattern"));
+static INLINE_CODE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"`[^`\n]*`").expect("valid inline code pattern"));
+
+/// Reads an Obsidian vault. Hidden folders such as `.obsidian` and `.trash`
+/// and non-Markdown attachments are left out.
+pub fn read_vault(dir: &Path) -> Result<ImportBatch> {
+    read_markdown_tree(dir, parse_vault_note)
+}
+
+/// Reads a vault note. Obsidian names notes by file, so the file name is the
+/// title and `[[links]]` between notes keep resolving. Folders become
+/// notebooks, and inline `#tags` join the front matter tags.
+pub fn parse_vault_note(text: &str, relative: &Path, modified: i64) -> Result<ImportedNote> {
+    let (front, body) = front_matter_and_body(text);
+    let title = file_title(relative).context("file has no name")?;
+    let body = rewrite_vault_links(body);
+    let mut note = note_from_front_matter(&front, title, &body, modified)?;
+    for tag in inline_tags(&body) {
+        push_tag(&mut note.tags, &tag);
+    }
+    note.notebook = folder_path(relative, NOTEBOOK_SEPARATOR);
+    Ok(note)
+}
+
+/// Rewrites `[[folder/Note#Heading|Alias]]` to `[[Note]]`, the form notetui
+/// resolves by title; the heading and alias are dropped. Embeds (`![[...]]`)
+/// and links within the same note (`[[#Heading]]`) are left alone.
+fn rewrite_vault_links(body: &str) -> String {
+    VAULT_LINK
+        .replace_all(body, |caps: &Captures<'_>| {
+            let whole = caps[0].to_string();
+            if &caps[1] == "!" {
+                return whole;
+            }
+            let target = caps[2].split('|').next().unwrap_or_default();
+            let target = target.split('#').next().unwrap_or_default();
+            let target = target.rsplit('/').next().unwrap_or_default().trim();
+            let target = target.strip_suffix(".md").unwrap_or(target);
+            if target.is_empty() {
+                whole
+            } else {
+                format!("[[{target}]]")
+            }
+        })
+        .into_owned()
+}
+
+/// Inline `#tags` in `body`, outside code. Like Obsidian, tags made only of
+/// digits (`#1`) do not count.
+fn inline_tags(body: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    let mut in_fence = false;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let line = INLINE_CODE.replace_all(line, " ");
+        for caps in INLINE_TAG.captures_iter(&line) {
+            let tag = caps[1].trim_matches('/');
+            if tag.chars().any(|ch| !ch.is_ascii_digit()) {
+                push_tag(&mut tags, tag);
+            }
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_links_tags_and_folders() -> Result<()> {
+        let text = "---\n\
+                    tags: [meeting]\n\
+                    ---\n\
+                    Met about [[Projects/Roadmap#Q3|the roadmap]] and [[#Notes]].\n\
+                    ![[diagram.png]] #project/alpha #1 see `#not-a-tag`\n\
+                    ```\n#also-not\n```\n\
+                    #Meeting again, and an issue#42.\n";
+        let note = parse_vault_note(text, Path::new("Work/Team/Standup 2024-05-01.md"), 9)?;
+        assert_eq!(note.title, "Standup 2024-05-01");
+        assert_eq!(note.notebook.as_deref(), Some("Work/Team"));
+        assert_eq!(note.tags, vec!["meeting", "project/alpha"]);
+        assert!(note
+            .body
+            .starts_with("Met about [[Roadmap]] and [[#Notes]].\n![[diagram.png]]"));
+        assert_eq!((note.created_at, note.updated_at), (9, 9));
+
+        let note = parse_vault_note("# Heading only\n", Path::new("Inbox.md"), 0)?;
+        assert_eq!(note.title, "Inbox");
+        assert_eq!(note.notebook, None);
+        Ok(())
+    }
+}
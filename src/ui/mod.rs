@@ -1,5 +1,5 @@
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
-use ratatui::style::{Color, Modifier, Style};
+use ratatui::style::{Modifier, Style};
 use ratatui::text::{Line, Span, Text};
 use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap};
 use ratatui::Frame;
@@ -13,22 +13,36 @@ use crate::app::state::{
     AppState, BulkTrashAction, EditorState, FocusPane, NoteSummary, OverlayState, TagEditorMode,
     TagInputKind,
 };
+use crate::config::themes::Theme;
 use crate::highlight::build_highlight_regex;
 use crate::journaling::AutoSaveStatus;
+use crate::markdown::{render_markdown, style_wiki_links};
+
+pub fn draw_app(frame: &mut Frame, state: &AppState, list_state: &mut ListState, theme: &Theme) {
+    frame.render_widget(Block::default().style(theme.base_style()), frame.size());
 
-pub fn draw_app(frame: &mut Frame, state: &AppState, list_state: &mut ListState) {
     let vertical = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Min(3), Constraint::Length(4)])
         .split(frame.size());
 
+    let main_area = if state.show_notebooks {
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(22), Constraint::Percentage(78)])
+            .split(vertical[0]);
+        render_notebook_tree(frame, state, theme, panes[0]);
+        panes[1]
+    } else {
+        vertical[0]
+    };
     let columns = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
-        .split(vertical[0]);
+        .split(main_area);
 
     let list_block_style = if matches!(state.focus, FocusPane::List) {
-        Style::default().fg(Color::Cyan)
+        Style::default().fg(theme.focus_border)
     } else {
         Style::default()
     };
@@ -36,8 +50,9 @@ pub fn draw_app(frame: &mut Frame, state: &AppState, list_state: &mut ListState)
     let tokens = state.search_tokens();
     let highlight_regex = build_highlight_regex(&tokens);
     let highlight_style = Style::default()
-        .fg(Color::Yellow)
+        .fg(theme.highlight)
         .add_modifier(Modifier::BOLD);
+    let tag_style = Style::default().fg(theme.tag);
 
     let mut items = Vec::with_capacity(state.notes.len());
     for note in &state.notes {
@@ -52,7 +67,7 @@ pub fn draw_app(frame: &mut Frame, state: &AppState, list_state: &mut ListState)
             title_spans.push(Span::styled(
                 label,
                 Style::default()
-                    .fg(Color::Magenta)
+                    .fg(theme.editing)
                     .add_modifier(Modifier::BOLD),
             ));
         }
@@ -60,7 +75,7 @@ pub fn draw_app(frame: &mut Frame, state: &AppState, list_state: &mut ListState)
             title_spans.push(Span::styled(
                 "★ ",
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(theme.pinned)
                     .add_modifier(Modifier::BOLD),
             ));
         }
@@ -68,10 +83,13 @@ pub fn draw_app(frame: &mut Frame, state: &AppState, list_state: &mut ListState)
             title_spans.push(Span::styled(
                 "[A] ",
                 Style::default()
-                    .fg(Color::Gray)
+                    .fg(theme.archived)
                     .add_modifier(Modifier::ITALIC),
             ));
         }
+        if note.fuzzy {
+            title_spans.push(Span::styled("≈ ", Style::default().fg(theme.muted)));
+        }
         title_spans.extend(highlight_line(
             &note.title,
             highlight_regex.as_ref(),
@@ -81,7 +99,7 @@ pub fn draw_app(frame: &mut Frame, state: &AppState, list_state: &mut ListState)
         let title_line = Line::from(title_spans);
         let meta_line = if state.show_trash {
             let mut spans = Vec::new();
-            let deleted_style = Style::default().fg(Color::Gray);
+            let deleted_style = Style::default().fg(theme.muted);
             let deleted_label = note
                 .deleted_label
                 .as_deref()
@@ -92,15 +110,15 @@ pub fn draw_app(frame: &mut Frame, state: &AppState, list_state: &mut ListState)
                 spans.push(Span::raw(" • "));
                 let status_style = if status.expired {
                     Style::default()
-                        .fg(Color::Red)
+                        .fg(theme.status_error)
                         .add_modifier(Modifier::BOLD | Modifier::ITALIC)
                 } else if status.indefinite {
                     Style::default()
-                        .fg(Color::Cyan)
+                        .fg(theme.status_info)
                         .add_modifier(Modifier::ITALIC)
                 } else {
                     Style::default()
-                        .fg(Color::Yellow)
+                        .fg(theme.status_warning)
                         .add_modifier(Modifier::ITALIC)
                 };
                 spans.push(Span::styled(status.label.clone(), status_style));
@@ -109,7 +127,7 @@ pub fn draw_app(frame: &mut Frame, state: &AppState, list_state: &mut ListState)
         } else {
             Line::from(Span::styled(
                 format!("Updated {}", note.updated_at),
-                Style::default().fg(Color::Gray),
+                Style::default().fg(theme.muted),
             ))
         };
         let mut preview_lines = Vec::new();
@@ -127,9 +145,12 @@ pub fn draw_app(frame: &mut Frame, state: &AppState, list_state: &mut ListState)
         let mut lines = Vec::with_capacity(2 + preview_lines.len());
         lines.push(title_line);
         lines.push(meta_line);
-        if let Some(tag_line) =
-            render_tag_line(&note.tags, highlight_regex.as_ref(), highlight_style)
-        {
+        if let Some(tag_line) = render_tag_line(
+            &note.tags,
+            highlight_regex.as_ref(),
+            highlight_style,
+            tag_style,
+        ) {
             lines.push(tag_line);
         }
         lines.extend(preview_lines);
@@ -138,13 +159,22 @@ pub fn draw_app(frame: &mut Frame, state: &AppState, list_state: &mut ListState)
     if items.is_empty() {
         if state.show_trash {
             items.push(ListItem::new("Trash is empty."));
+        } else if state.show_archive {
+            items.push(ListItem::new("Archive is empty."));
         } else {
             items.push(ListItem::new("No notes yet. Press `a` to create one."));
         }
     }
 
-    let list_title = if state.show_trash { "Trash" } else { "Notes" };
+    let list_title = if state.show_trash {
+        "Trash"
+    } else if state.show_archive {
+        "Archive"
+    } else {
+        "Notes"
+    };
     let list = List::new(items)
+        .style(theme.base_style())
         .block(
             Block::default()
                 .title(list_title)
@@ -153,15 +183,15 @@ pub fn draw_app(frame: &mut Frame, state: &AppState, list_state: &mut ListState)
         )
         .highlight_style(
             Style::default()
-                .bg(Color::Blue)
-                .fg(Color::Black)
+                .bg(theme.selection_bg)
+                .fg(theme.selection_fg)
                 .add_modifier(Modifier::BOLD),
         )
         .highlight_symbol("▸ ");
     frame.render_stateful_widget(list, columns[0], list_state);
 
     let detail_block_style = if matches!(state.focus, FocusPane::Reader) {
-        Style::default().fg(Color::Cyan)
+        Style::default().fg(theme.focus_border)
     } else {
         Style::default()
     };
@@ -181,7 +211,7 @@ pub fn draw_app(frame: &mut Frame, state: &AppState, list_state: &mut ListState)
                 header_spans.push(Span::styled(
                     label,
                     Style::default()
-                        .fg(Color::Magenta)
+                        .fg(theme.editing)
                         .add_modifier(Modifier::BOLD),
                 ));
             }
@@ -189,7 +219,7 @@ pub fn draw_app(frame: &mut Frame, state: &AppState, list_state: &mut ListState)
                 header_spans.push(Span::styled(
                     "★ ",
                     Style::default()
-                        .fg(Color::Yellow)
+                        .fg(theme.pinned)
                         .add_modifier(Modifier::BOLD),
                 ));
             }
@@ -197,7 +227,7 @@ pub fn draw_app(frame: &mut Frame, state: &AppState, list_state: &mut ListState)
                 header_spans.push(Span::styled(
                     "[A] ",
                     Style::default()
-                        .fg(Color::Gray)
+                        .fg(theme.archived)
                         .add_modifier(Modifier::ITALIC),
                 ));
             }
@@ -213,68 +243,208 @@ pub fn draw_app(frame: &mut Frame, state: &AppState, list_state: &mut ListState)
             } else {
                 format!("Updated {}", note.updated_at)
             };
-            lines.push(Line::from(Span::styled(
+            let mut meta_spans = vec![Span::styled(
                 updated_label,
-                Style::default().fg(Color::Gray),
-            )));
-            if let Some(tag_line) =
-                render_tag_line(&note.tags, highlight_regex.as_ref(), highlight_style)
-            {
+                Style::default().fg(theme.muted),
+            )];
+            if let Some(path) = state.selected_notebook_path() {
+                meta_spans.push(Span::styled(
+                    format!(" • in {path}"),
+                    Style::default().fg(theme.muted),
+                ));
+            }
+            lines.push(Line::from(meta_spans));
+            if let Some(tag_line) = render_tag_line(
+                &note.tags,
+                highlight_regex.as_ref(),
+                highlight_style,
+                tag_style,
+            ) {
                 lines.push(tag_line);
             }
             lines.push(Line::from(""));
-            let body_text = if editing_this_note {
-                state.editor_buffer().unwrap_or(note.body.as_str())
+            // The editor shows the raw buffer so cursor positions line up with the text.
+            if editing_this_note {
+                lines.extend(highlight_body(
+                    state.editor_buffer().unwrap_or(note.body.as_str()),
+                    highlight_regex.as_ref(),
+                    highlight_style,
+                ));
             } else {
-                note.body.as_str()
-            };
-            lines.extend(highlight_body(
-                body_text,
-                highlight_regex.as_ref(),
-                highlight_style,
-            ));
+                let link_style = Style::default()
+                    .fg(theme.link)
+                    .add_modifier(Modifier::UNDERLINED);
+                let selected_link = state
+                    .selected_link_index()
+                    .map(|index| (index, Style::default().add_modifier(Modifier::REVERSED)));
+                lines.extend(style_wiki_links(
+                    render_markdown(&note.body, theme, highlight_regex.as_ref(), highlight_style),
+                    link_style,
+                    selected_link,
+                ));
+            }
             Text::from(lines)
         })
         .unwrap_or_else(|| Text::from("Select a note to see its contents."));
 
-    let mut detail = Paragraph::new(preview_text).block(
-        Block::default()
-            .title("Preview")
-            .borders(Borders::ALL)
-            .border_style(detail_block_style),
-    );
+    let mut detail = Paragraph::new(preview_text)
+        .style(theme.base_style())
+        .block(
+            Block::default()
+                .title("Preview")
+                .borders(Borders::ALL)
+                .border_style(detail_block_style),
+        );
     if state.wrap_enabled() {
         detail = detail.wrap(Wrap { trim: false });
     }
     frame.render_widget(Clear, columns[1]);
-    frame.render_widget(detail, columns[1]);
+    let backlinks = state.backlinks();
+    let detail_area = if backlinks.is_empty() {
+        columns[1]
+    } else {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(5),
+                Constraint::Length(backlinks.len().min(MAX_BACKLINK_ROWS) as u16 + 2),
+            ])
+            .split(columns[1]);
+        render_backlinks(frame, state, theme, rows[1]);
+        rows[0]
+    };
+    frame.render_widget(detail, detail_area);
     if let (Some(note), Some(editor)) = (state.selected(), state.editor()) {
         if editor.note_id() == note.id {
             if let Some((cursor_x, cursor_y)) =
-                editor_cursor_screen_position(editor, note, columns[1], state.wrap_enabled())
+                editor_cursor_screen_position(editor, note, detail_area, state.wrap_enabled())
             {
                 frame.set_cursor(cursor_x, cursor_y);
             }
         }
     }
 
-    let status = build_status_line(state);
-    let status_paragraph = Paragraph::new(status).style(Style::default().fg(Color::Gray));
+    let status = build_status_line(state, theme);
+    let status_paragraph = Paragraph::new(status).style(Style::default().fg(theme.muted));
     frame.render_widget(status_paragraph, vertical[1]);
 
-    render_overlay(frame, state);
+    render_overlay(frame, state, theme);
+}
+
+/// Rows the backlinks panel grows to before the rest are summarised.
+const MAX_BACKLINK_ROWS: usize = 6;
+
+/// Notes linking to the selected one, listed under the reader.
+fn render_backlinks(frame: &mut Frame, state: &AppState, theme: &Theme, area: Rect) {
+    let backlinks = state.backlinks();
+    let mut lines: Vec<Line> = backlinks
+        .iter()
+        .take(MAX_BACKLINK_ROWS)
+        .map(|note| {
+            let mut spans = vec![Span::styled(
+                format!("← {}", note.title),
+                Style::default().fg(theme.link),
+            )];
+            if note.archived {
+                spans.push(Span::styled(
+                    " [A]",
+                    Style::default()
+                        .fg(theme.archived)
+                        .add_modifier(Modifier::ITALIC),
+                ));
+            }
+            Line::from(spans)
+        })
+        .collect();
+    if backlinks.len() > MAX_BACKLINK_ROWS {
+        lines.pop();
+        lines.push(Line::from(Span::styled(
+            format!("… and {} more", backlinks.len() - MAX_BACKLINK_ROWS + 1),
+            Style::default().fg(theme.muted),
+        )));
+    }
+    let panel = Paragraph::new(lines).style(theme.base_style()).block(
+        Block::default()
+            .title(format!("Backlinks ({})", backlinks.len()))
+            .borders(Borders::ALL),
+    );
+    frame.render_widget(panel, area);
+}
+
+/// The notebook pane: "All notes", then each notebook indented under its
+/// parent with a fold marker and its note count. The notebook the list is
+/// filtered to is bold.
+fn render_notebook_tree(frame: &mut Frame, state: &AppState, theme: &Theme, area: Rect) {
+    let tree = state.notebook_tree();
+    let active = state.search.notebook.as_deref();
+    let active_style = Style::default().add_modifier(Modifier::BOLD);
+    let mut items = vec![ListItem::new(Line::from(Span::styled(
+        "All notes",
+        if active.is_none() {
+            active_style
+        } else {
+            Style::default()
+        },
+    )))];
+    for notebook in tree.visible() {
+        let marker = if !tree.has_children(notebook.id) {
+            "  "
+        } else if tree.is_collapsed(notebook.id) {
+            "▸ "
+        } else {
+            "▾ "
+        };
+        let is_active = active.is_some_and(|path| path.eq_ignore_ascii_case(&notebook.path));
+        items.push(ListItem::new(Line::from(vec![
+            Span::raw(format!("{}{marker}", "  ".repeat(notebook.depth))),
+            Span::styled(
+                notebook.name.clone(),
+                if is_active {
+                    active_style
+                } else {
+                    Style::default()
+                },
+            ),
+            Span::styled(
+                format!(" {}", notebook.note_count),
+                Style::default().fg(theme.muted),
+            ),
+        ])));
+    }
+    let border_style = if matches!(state.focus, FocusPane::Notebooks) {
+        Style::default().fg(theme.focus_border)
+    } else {
+        Style::default()
+    };
+    let list = List::new(items)
+        .style(theme.base_style())
+        .block(
+            Block::default()
+                .title("Notebooks")
+                .borders(Borders::ALL)
+                .border_style(border_style),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(theme.selection_bg)
+                .fg(theme.selection_fg)
+                .add_modifier(Modifier::BOLD),
+        );
+    let mut list_state = ListState::default().with_selected(Some(tree.selected));
+    frame.render_stateful_widget(list, area, &mut list_state);
 }
 
-fn build_status_line(state: &AppState) -> Text<'static> {
-    let total = state.len();
+fn build_status_line(state: &AppState, theme: &Theme) -> Text<'static> {
+    let total = state.total();
     let position = if state.is_empty() {
         "0/0".to_string()
     } else {
-        format!("{}/{}", state.selected + 1, total)
+        format!("{}/{}", state.selected_position() + 1, total)
     };
     let focus = match state.focus {
         FocusPane::List => "List",
         FocusPane::Reader => "Reader",
+        FocusPane::Notebooks => "Notebooks",
     };
 
     let mut spans = vec![
@@ -283,14 +453,43 @@ fn build_status_line(state: &AppState) -> Text<'static> {
         Span::styled(position, Style::default().add_modifier(Modifier::BOLD)),
         Span::raw(" | Focus: "),
         Span::styled(focus, Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" | Sort: "),
+        Span::styled(
+            if state.show_trash {
+                "deleted ↓".to_string()
+            } else {
+                state.sort.label()
+            },
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
     ];
 
+    if let Some(keys) = &state.pending_keys {
+        spans.push(Span::raw(" | Keys: "));
+        spans.push(Span::styled(
+            format!("{keys} …"),
+            Style::default()
+                .fg(theme.status_info)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    if state.show_archive {
+        spans.push(Span::raw(" | View: "));
+        spans.push(Span::styled(
+            "Archive",
+            Style::default()
+                .fg(theme.archived)
+                .add_modifier(Modifier::BOLD | Modifier::ITALIC),
+        ));
+    }
+
     if state.show_trash {
         spans.push(Span::raw(" | View: "));
         spans.push(Span::styled(
             "Trash",
             Style::default()
-                .fg(Color::Red)
+                .fg(theme.status_error)
                 .add_modifier(Modifier::BOLD | Modifier::ITALIC),
         ));
         if let Some(note) = state.selected() {
@@ -298,15 +497,15 @@ fn build_status_line(state: &AppState) -> Text<'static> {
             let style = note.trash_status.as_ref().map(|status| {
                 if status.expired {
                     Style::default()
-                        .fg(Color::Red)
+                        .fg(theme.status_error)
                         .add_modifier(Modifier::BOLD | Modifier::ITALIC)
                 } else if status.indefinite {
                     Style::default()
-                        .fg(Color::Cyan)
+                        .fg(theme.status_info)
                         .add_modifier(Modifier::ITALIC)
                 } else {
                     Style::default()
-                        .fg(Color::Yellow)
+                        .fg(theme.status_warning)
                         .add_modifier(Modifier::ITALIC)
                 }
             });
@@ -319,7 +518,7 @@ fn build_status_line(state: &AppState) -> Text<'static> {
                     label_style,
                 ));
             } else {
-                spans.push(Span::styled("n/a", Style::default().fg(Color::Gray)));
+                spans.push(Span::styled("n/a", Style::default().fg(theme.muted)));
             }
         }
     }
@@ -332,17 +531,28 @@ fn build_status_line(state: &AppState) -> Text<'static> {
     {
         let label_style = if state.is_search_active() {
             Style::default()
-                .fg(Color::Cyan)
+                .fg(theme.focus_border)
                 .add_modifier(Modifier::BOLD)
         } else {
-            Style::default().fg(Color::Gray)
+            Style::default().fg(theme.muted)
         };
         spans.push(Span::raw(" | Search "));
         spans.push(Span::styled("/", label_style));
+        if let Some(reverse) = state.reverse_search() {
+            let label = if reverse.failed {
+                "failing reverse-i-search"
+            } else {
+                "reverse-i-search"
+            };
+            spans.push(Span::styled(
+                format!("({label})'{}': ", reverse.needle),
+                Style::default().fg(theme.hint),
+            ));
+        }
         if tokens.is_empty() && state.search_query().is_empty() {
             spans.push(Span::styled(
                 "(type to search)",
-                Style::default().fg(Color::DarkGray),
+                Style::default().fg(theme.hint),
             ));
         } else {
             spans.push(Span::styled(
@@ -351,14 +561,14 @@ fn build_status_line(state: &AppState) -> Text<'static> {
             ));
         }
         if state.is_search_active() {
-            spans.push(Span::styled(" ▌", Style::default().fg(Color::Cyan)));
+            spans.push(Span::styled(" ▌", Style::default().fg(theme.focus_border)));
         }
         if state.is_regex_enabled() {
             spans.push(Span::raw(" "));
             spans.push(Span::styled(
                 "[regex]",
                 Style::default()
-                    .fg(Color::Magenta)
+                    .fg(theme.editing)
                     .add_modifier(Modifier::BOLD),
             ));
         }
@@ -366,14 +576,14 @@ fn build_status_line(state: &AppState) -> Text<'static> {
             spans.push(Span::raw(" "));
             spans.push(Span::styled(
                 format!("[{chip}]"),
-                Style::default().fg(Color::Green),
+                Style::default().fg(theme.tag),
             ));
         }
         if let Some(error) = state.search_error() {
             spans.push(Span::raw(" "));
             spans.push(Span::styled(
                 format!("! {error}"),
-                Style::default().fg(Color::Red),
+                Style::default().fg(theme.status_error),
             ));
         }
     }
@@ -388,7 +598,7 @@ fn build_status_line(state: &AppState) -> Text<'static> {
         spans.push(Span::styled(
             edit_label,
             Style::default()
-                .fg(Color::Magenta)
+                .fg(theme.editing)
                 .add_modifier(Modifier::BOLD),
         ));
     }
@@ -396,7 +606,7 @@ fn build_status_line(state: &AppState) -> Text<'static> {
     spans.push(Span::raw(" | Wrap: "));
     spans.push(Span::styled(
         if state.wrap_enabled() { "on" } else { "off" },
-        Style::default().fg(Color::Gray),
+        Style::default().fg(theme.muted),
     ));
 
     match state.autosave_status() {
@@ -412,7 +622,7 @@ fn build_status_line(state: &AppState) -> Text<'static> {
                 spans.push(Span::raw(" "));
                 spans.push(Span::styled(
                     format_time_short(*ts),
-                    Style::default().fg(Color::Gray),
+                    Style::default().fg(theme.muted),
                 ));
             }
         }
@@ -421,20 +631,20 @@ fn build_status_line(state: &AppState) -> Text<'static> {
             spans.push(Span::styled(
                 "pending",
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(theme.status_warning)
                     .add_modifier(Modifier::BOLD),
             ));
             spans.push(Span::raw(" since "));
             spans.push(Span::styled(
                 format_time_short(*since),
-                Style::default().fg(Color::Gray),
+                Style::default().fg(theme.muted),
             ));
         }
         AutoSaveStatus::Error { message, .. } => {
             spans.push(Span::raw(" | Autosave: "));
             spans.push(Span::styled(
                 format!("error ({message})"),
-                Style::default().fg(Color::Red),
+                Style::default().fg(theme.status_error),
             ));
         }
     }
@@ -443,38 +653,37 @@ fn build_status_line(state: &AppState) -> Text<'static> {
         spans.push(Span::raw(" | "));
         spans.push(Span::styled(
             message.clone(),
-            Style::default().fg(Color::Cyan),
+            Style::default().fg(theme.status_info),
         ));
     }
 
     let mut lines = Vec::with_capacity(2);
     lines.push(Line::from(spans));
 
-    let mut keys_line1 = Vec::new();
-    keys_line1.push(Span::styled(
-        "Keys: ",
-        Style::default()
-            .fg(Color::Gray)
-            .add_modifier(Modifier::BOLD),
-    ));
-    keys_line1.push(Span::styled(
-        "j/k move • Tab focus • / search • Shift+R regex • a add • p pin • A archive",
-        Style::default().fg(Color::DarkGray),
-    ));
+    let keys_line1 = vec![
+        Span::styled(
+            "Keys: ",
+            Style::default()
+                .fg(theme.muted)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            "j/k move • Tab focus • / search • Shift+R regex • a add • p pin • A archive",
+            Style::default().fg(theme.hint),
+        ),
+    ];
     lines.push(Line::from(keys_line1));
 
-    let mut keys_line2 = Vec::new();
-    keys_line2.push(Span::styled(
+    let keys_line2 = vec![Span::styled(
         "      e edit • Ctrl-s save • Ctrl-z undo • Ctrl-y redo • Ctrl-←/→ word jump",
-        Style::default().fg(Color::DarkGray),
-    ));
+        Style::default().fg(theme.hint),
+    )];
     lines.push(Line::from(keys_line2));
 
-    let mut keys_line3 = Vec::new();
-    keys_line3.push(Span::styled(
-        "      Shift+W wrap • d delete • T trash view • q quit",
-        Style::default().fg(Color::DarkGray),
-    ));
+    let keys_line3 = vec![Span::styled(
+        "      Shift+W wrap • Shift+H history • d delete • T trash • V archive • s sort • : commands • ? help • q quit",
+        Style::default().fg(theme.hint),
+    )];
     lines.push(Line::from(keys_line3));
 
     Text::from(lines)
@@ -485,6 +694,13 @@ fn format_time_short(dt: OffsetDateTime) -> String {
         .unwrap_or_else(|_| dt.unix_timestamp().to_string())
 }
 
+fn format_date(epoch: i64) -> String {
+    OffsetDateTime::from_unix_timestamp(epoch)
+        .ok()
+        .and_then(|dt| dt.format(&format_description!("[year]-[month]-[day]")).ok())
+        .unwrap_or_else(|| epoch.to_string())
+}
+
 fn highlight_line(
     text: &str,
     regex: Option<&Regex>,
@@ -586,11 +802,11 @@ fn render_tag_line(
     tags: &[String],
     regex: Option<&Regex>,
     highlight_style: Style,
+    base_style: Style,
 ) -> Option<Line<'static>> {
     if tags.is_empty() {
         return None;
     }
-    let base_style = Style::default().fg(Color::Green);
     let mut spans = Vec::new();
     for (idx, tag) in tags.iter().enumerate() {
         let token = format!("#{tag}");
@@ -602,44 +818,11 @@ fn render_tag_line(
     Some(Line::from(spans))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::highlight::build_highlight_regex;
-    use ratatui::style::Style;
-    use ratatui::text::Span;
-
-    fn span_texts(spans: &[Span<'static>]) -> Vec<String> {
-        spans
-            .iter()
-            .map(|span| span.content.clone().into_owned())
-            .collect()
-    }
-
-    #[test]
-    fn highlight_regex_prefers_longer_tokens_first() {
-        let regex = build_highlight_regex(&["not".into(), "note".into()]).expect("regex");
-        let spans = highlight_line("notebook", Some(&regex), Style::default(), Style::default());
-        assert_eq!(
-            span_texts(&spans),
-            vec![String::from("note"), String::from("book")]
-        );
-    }
-
-    #[test]
-    fn highlight_regex_deduplicates_case_insensitive_tokens() {
-        let regex =
-            build_highlight_regex(&["Note".into(), "note".into(), "NOTE".into()]).expect("regex");
-        let spans = highlight_line("note", Some(&regex), Style::default(), Style::default());
-        assert_eq!(span_texts(&spans), vec![String::from("note")]);
-    }
-}
-
-fn render_overlay(frame: &mut Frame, state: &AppState) {
+fn render_overlay(frame: &mut Frame, state: &AppState, theme: &Theme) {
     match state.overlay() {
         Some(OverlayState::NewNote(draft)) => {
             let area = centered_rect(60, 30, frame.size());
-            frame.render_widget(Clear, area);
+            clear_overlay(frame, area, theme);
             let mut title_display = draft.title.clone();
             title_display.push('▌');
             let paragraph = Paragraph::new(vec![
@@ -652,21 +835,21 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                 Line::from(""),
                 Line::from(Span::styled(
                     "Enter to save • Esc to cancel",
-                    Style::default().fg(Color::Gray),
+                    Style::default().fg(theme.muted),
                 )),
             ])
             .block(
                 Block::default()
                     .title("New Note")
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Cyan)),
+                    .border_style(Style::default().fg(theme.focus_border)),
             )
             .wrap(Wrap { trim: false });
             frame.render_widget(paragraph, area);
         }
         Some(OverlayState::RenameNote(draft)) => {
             let area = centered_rect(60, 30, frame.size());
-            frame.render_widget(Clear, area);
+            clear_overlay(frame, area, theme);
             let mut title_display = draft.title.clone();
             title_display.push('▌');
             let paragraph = Paragraph::new(vec![
@@ -676,28 +859,28 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                 )),
                 Line::from(Span::styled(
                     format!("Note #{}, current title:", draft.note_id),
-                    Style::default().fg(Color::Gray),
+                    Style::default().fg(theme.muted),
                 )),
                 Line::from(""),
                 Line::from(title_display),
                 Line::from(""),
                 Line::from(Span::styled(
                     "Enter to save • Esc to cancel",
-                    Style::default().fg(Color::Gray),
+                    Style::default().fg(theme.muted),
                 )),
             ])
             .block(
                 Block::default()
                     .title("Rename Note")
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Cyan)),
+                    .border_style(Style::default().fg(theme.focus_border)),
             )
             .wrap(Wrap { trim: false });
             frame.render_widget(paragraph, area);
         }
         Some(OverlayState::DeleteNote(draft)) => {
             let area = centered_rect(60, 30, frame.size());
-            frame.render_widget(Clear, area);
+            clear_overlay(frame, area, theme);
             let paragraph = Paragraph::new(vec![
                 Line::from(Span::styled(
                     "Delete Note",
@@ -711,14 +894,14 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                 Line::from(""),
                 Line::from(Span::styled(
                     "Enter to confirm • Esc to cancel",
-                    Style::default().fg(Color::Gray),
+                    Style::default().fg(theme.muted),
                 )),
             ])
             .block(
                 Block::default()
                     .title(format!("Confirm Delete (#{})", draft.note_id))
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Red)),
+                    .border_style(Style::default().fg(theme.status_error)),
             )
             .wrap(Wrap { trim: false });
             frame.render_widget(paragraph, area);
@@ -735,30 +918,32 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                         Line::from(""),
                         Line::from("Enter or y restore • Esc cancel"),
                     ],
-                    Color::Green,
+                    theme.status_success,
                 ),
                 BulkTrashAction::PurgeAll => (
                     "Purge Trash",
                     vec![
                         Line::from(Span::styled(
                             "Permanently delete every trashed note?",
-                            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                            Style::default()
+                                .fg(theme.status_error)
+                                .add_modifier(Modifier::BOLD),
                         )),
                         Line::from(Span::styled(
                             "This cannot be undone.",
-                            Style::default().fg(Color::Red),
+                            Style::default().fg(theme.status_error),
                         )),
                         Line::from(""),
                         Line::from(Span::styled(
                             "Enter or y purge • Esc cancel",
-                            Style::default().fg(Color::Red),
+                            Style::default().fg(theme.status_error),
                         )),
                     ],
-                    Color::Red,
+                    theme.status_error,
                 ),
             };
             let area = centered_rect(50, 30, frame.size());
-            frame.render_widget(Clear, area);
+            clear_overlay(frame, area, theme);
             let paragraph = Paragraph::new(body_lines).block(
                 Block::default()
                     .title(title)
@@ -769,7 +954,7 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
         }
         Some(OverlayState::TagEditor(editor)) => {
             let area = centered_rect(60, 65, frame.size());
-            frame.render_widget(Clear, area);
+            clear_overlay(frame, area, theme);
 
             let layout = Layout::default()
                 .direction(Direction::Vertical)
@@ -805,7 +990,7 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                     "Tag Editor",
                     Style::default().add_modifier(Modifier::BOLD),
                 )),
-                Line::from(Span::styled(instructions, Style::default().fg(Color::Gray))),
+                Line::from(Span::styled(instructions, Style::default().fg(theme.muted))),
             ];
 
             if !editor.suggestions.is_empty() {
@@ -819,14 +1004,14 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                     .join("  ");
                 header_lines.push(Line::from(Span::styled(
                     format!("Suggestions (1-9): {chips}"),
-                    Style::default().fg(Color::Yellow),
+                    Style::default().fg(theme.highlight),
                 )));
             }
 
             let header = Paragraph::new(header_lines).block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Cyan)),
+                    .border_style(Style::default().fg(theme.focus_border)),
             );
             frame.render_widget(header, layout[0]);
 
@@ -839,7 +1024,7 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                     let style = if item.original {
                         Style::default()
                     } else {
-                        Style::default().fg(Color::Cyan)
+                        Style::default().fg(theme.status_info)
                     };
                     ListItem::new(Line::from(vec![
                         Span::styled(mark.to_string(), style.add_modifier(Modifier::BOLD)),
@@ -847,11 +1032,16 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                         Span::styled(
                             bulk.to_string(),
                             Style::default()
-                                .fg(Color::Yellow)
+                                .fg(theme.highlight)
                                 .add_modifier(Modifier::BOLD),
                         ),
                         Span::raw(" "),
-                        Span::styled(item.name.clone(), style),
+                        Span::raw("  ".repeat(item.depth)),
+                        Span::styled(item.label().to_string(), style),
+                        Span::styled(
+                            format!(" ({})", item.note_count),
+                            Style::default().fg(theme.muted),
+                        ),
                     ]))
                 })
                 .collect();
@@ -868,8 +1058,8 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                 )
                 .highlight_style(
                     Style::default()
-                        .bg(Color::Blue)
-                        .fg(Color::Black)
+                        .bg(theme.selection_bg)
+                        .fg(theme.selection_fg)
                         .add_modifier(Modifier::BOLD),
                 )
                 .highlight_symbol("▸ ");
@@ -882,7 +1072,7 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                     Paragraph::new(vec![
                         Line::from(Span::styled(
                             format!("New tag for note #{}", editor.note_id),
-                            Style::default().fg(Color::Cyan),
+                            Style::default().fg(theme.status_info),
                         )),
                         Line::from(display),
                     ])
@@ -893,7 +1083,7 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                     Paragraph::new(vec![
                         Line::from(Span::styled(
                             format!("Renaming '{}'", original),
-                            Style::default().fg(Color::Cyan),
+                            Style::default().fg(theme.status_info),
                         )),
                         Line::from(display),
                     ])
@@ -907,21 +1097,21 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                         format!("Merge {} tags into tag:", sources.len())
                     };
                     Paragraph::new(vec![
-                        Line::from(Span::styled(label, Style::default().fg(Color::Cyan))),
+                        Line::from(Span::styled(label, Style::default().fg(theme.status_info))),
                         Line::from(display),
                     ])
                 }
                 TagEditorMode::ConfirmDelete { tag } => Paragraph::new(vec![
                     Line::from(Span::styled(
                         format!("Delete tag '{}'", tag),
-                        Style::default().fg(Color::Red),
+                        Style::default().fg(theme.status_error),
                     )),
                     Line::from("Press y to confirm or n / Esc to cancel"),
                 ]),
                 TagEditorMode::Browse => Paragraph::new(vec![
                     Line::from(Span::styled(
                         format!("Editing tags for note #{}", editor.note_id),
-                        Style::default().fg(Color::Cyan),
+                        Style::default().fg(theme.status_info),
                     )),
                     Line::from(""),
                 ]),
@@ -929,20 +1119,20 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Cyan)),
+                    .border_style(Style::default().fg(theme.focus_border)),
             );
             frame.render_widget(input_para, layout[2]);
 
             let status_line = editor
                 .status
                 .as_ref()
-                .map(|msg| Span::styled(msg.clone(), Style::default().fg(Color::Yellow)))
+                .map(|msg| Span::styled(msg.clone(), Style::default().fg(theme.status_warning)))
                 .unwrap_or_else(|| Span::raw(" "));
             frame.render_widget(Paragraph::new(Line::from(status_line)), layout[3]);
         }
         Some(OverlayState::Recovery(overlay)) => {
             let area = centered_rect(70, 60, frame.size());
-            frame.render_widget(Clear, area);
+            clear_overlay(frame, area, theme);
 
             let mut lines = Vec::new();
             lines.push(Line::from(Span::styled(
@@ -951,7 +1141,7 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
             )));
             lines.push(Line::from(Span::styled(
                 "Enter restore • d discard • D discard all • j/k move • Esc close",
-                Style::default().fg(Color::Gray),
+                Style::default().fg(theme.muted),
             )));
             lines.push(Line::from(""));
 
@@ -964,14 +1154,14 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                     spans.push(Span::styled(
                         marker,
                         Style::default()
-                            .fg(Color::Cyan)
+                            .fg(theme.focus_border)
                             .add_modifier(Modifier::BOLD),
                     ));
                     spans.push(Span::raw(" "));
                     spans.push(Span::styled(
                         format!("Note #{}", entry.note_id),
                         if entry.missing {
-                            Style::default().fg(Color::Yellow)
+                            Style::default().fg(theme.status_warning)
                         } else {
                             Style::default().add_modifier(Modifier::BOLD)
                         },
@@ -980,7 +1170,7 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                     spans.push(Span::styled(
                         &entry.title,
                         if entry.missing {
-                            Style::default().fg(Color::Yellow)
+                            Style::default().fg(theme.status_warning)
                         } else {
                             Style::default()
                         },
@@ -990,19 +1180,19 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                         spans.push(Span::styled(
                             "[missing]",
                             Style::default()
-                                .fg(Color::Yellow)
+                                .fg(theme.status_warning)
                                 .add_modifier(Modifier::BOLD),
                         ));
                         spans.push(Span::raw("  "));
                     }
                     spans.push(Span::styled(
                         entry.saved_relative.clone(),
-                        Style::default().fg(Color::Cyan),
+                        Style::default().fg(theme.status_info),
                     ));
                     spans.push(Span::raw(" ("));
                     spans.push(Span::styled(
                         entry.saved_at.clone(),
-                        Style::default().fg(Color::Gray),
+                        Style::default().fg(theme.muted),
                     ));
                     spans.push(Span::raw(")"));
                     lines.push(Line::from(spans));
@@ -1010,7 +1200,7 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                     for preview in &entry.preview {
                         lines.push(Line::from(Span::styled(
                             format!("    {}", preview),
-                            Style::default().fg(Color::DarkGray),
+                            Style::default().fg(theme.hint),
                         )));
                     }
                     lines.push(Line::from(""));
@@ -1021,14 +1211,481 @@ fn render_overlay(frame: &mut Frame, state: &AppState) {
                 Block::default()
                     .title("Autosave Recovery")
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Magenta)),
+                    .border_style(Style::default().fg(theme.overlay_border)),
             );
             frame.render_widget(paragraph, area);
         }
+        Some(OverlayState::Revisions(overlay)) => {
+            let area = centered_rect(80, 70, frame.size());
+            clear_overlay(frame, area, theme);
+            let block = Block::default()
+                .title(format!("History: {}", overlay.note_title))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.overlay_border));
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(1)])
+                .split(inner);
+            frame.render_widget(
+                Paragraph::new(Line::from(Span::styled(
+                    "Enter restore • j/k move • PgUp/PgDn jump • Esc close",
+                    Style::default().fg(theme.muted),
+                ))),
+                rows[0],
+            );
+
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .split(rows[1]);
+
+            let mut lines = Vec::with_capacity(overlay.entries.len());
+            for (idx, entry) in overlay.entries.iter().enumerate() {
+                let selected = idx == overlay.selected;
+                let marker = if selected { "➤ " } else { "  " };
+                let style = if selected {
+                    Style::default()
+                        .fg(theme.focus_border)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                lines.push(Line::from(vec![
+                    Span::styled(marker, style),
+                    Span::styled(entry.saved_relative.clone(), style),
+                    Span::raw("  "),
+                    Span::styled(entry.title.clone(), Style::default().fg(theme.muted)),
+                ]));
+            }
+            // Keep the highlighted revision visible when the list is taller than the pane.
+            let visible = columns[0].height.saturating_sub(2) as usize;
+            let scroll = overlay
+                .selected
+                .saturating_sub(visible.saturating_sub(1))
+                .min(u16::MAX as usize) as u16;
+            let list = Paragraph::new(lines).scroll((scroll, 0)).block(
+                Block::default()
+                    .title("Revisions")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.hint)),
+            );
+            frame.render_widget(list, columns[0]);
+
+            let preview = match overlay.entries.get(overlay.selected) {
+                Some(entry) => {
+                    let mut lines = vec![
+                        Line::from(Span::styled(
+                            entry.title.clone(),
+                            Style::default().add_modifier(Modifier::BOLD),
+                        )),
+                        Line::from(Span::styled(
+                            entry.saved_at.clone(),
+                            Style::default().fg(theme.hint),
+                        )),
+                        Line::from(""),
+                    ];
+                    if entry.body.is_empty() {
+                        lines.push(Line::from(Span::styled(
+                            "(empty body)",
+                            Style::default().fg(theme.hint),
+                        )));
+                    } else {
+                        lines.extend(entry.body.lines().map(|line| Line::from(line.to_string())));
+                    }
+                    Paragraph::new(lines)
+                }
+                None => Paragraph::new("No revisions."),
+            }
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .title("Preview")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.hint)),
+            );
+            frame.render_widget(preview, columns[1]);
+        }
+        Some(OverlayState::SavedSearches(overlay)) => {
+            let area = centered_rect(70, 60, frame.size());
+            clear_overlay(frame, area, theme);
+            let block = Block::default()
+                .title("Saved Searches")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.overlay_border));
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(1)])
+                .split(inner);
+            frame.render_widget(
+                Paragraph::new(Line::from(Span::styled(
+                    "Enter run • j/k move • PgUp/PgDn jump • Esc close",
+                    Style::default().fg(theme.muted),
+                ))),
+                rows[0],
+            );
+
+            let name_width = overlay
+                .entries
+                .iter()
+                .map(|entry| entry.name.chars().count())
+                .max()
+                .unwrap_or(0);
+            let count_width = overlay
+                .entries
+                .iter()
+                .map(|entry| entry.count.chars().count())
+                .max()
+                .unwrap_or(0);
+            let mut lines = Vec::with_capacity(overlay.entries.len());
+            for (idx, entry) in overlay.entries.iter().enumerate() {
+                let selected = idx == overlay.selected;
+                let marker = if selected { "➤ " } else { "  " };
+                let style = if selected {
+                    Style::default()
+                        .fg(theme.focus_border)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                lines.push(Line::from(vec![
+                    Span::styled(marker, style),
+                    Span::styled(format!("{:<name_width$}", entry.name), style),
+                    Span::raw("  "),
+                    Span::styled(
+                        format!("{:>count_width$}", entry.count),
+                        Style::default().fg(theme.hint),
+                    ),
+                    Span::raw("  "),
+                    Span::styled(entry.query.clone(), Style::default().fg(theme.muted)),
+                ]));
+            }
+            let visible = rows[1].height as usize;
+            let scroll = overlay
+                .selected
+                .saturating_sub(visible.saturating_sub(1))
+                .min(u16::MAX as usize) as u16;
+            frame.render_widget(Paragraph::new(lines).scroll((scroll, 0)), rows[1]);
+        }
+        Some(OverlayState::TagBrowser(overlay)) => {
+            let area = centered_rect(70, 70, frame.size());
+            clear_overlay(frame, area, theme);
+            let title = format!(
+                "Tags • sorted by {}{}",
+                overlay.sort.label(),
+                if overlay.unused_only {
+                    " • unused only"
+                } else {
+                    ""
+                }
+            );
+            let block = Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.overlay_border));
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(2),
+                    Constraint::Min(1),
+                    Constraint::Length(1),
+                ])
+                .split(inner);
+            let visible = overlay.visible();
+            let name_width = visible
+                .iter()
+                .map(|entry| entry.usage.name.chars().count())
+                .max()
+                .unwrap_or(0)
+                .max(3);
+            frame.render_widget(
+                Paragraph::new(vec![
+                    Line::from(Span::styled(
+                        "Enter list notes • v mark • s sort • u unused • x delete • U delete unused • Esc close",
+                        Style::default().fg(theme.muted),
+                    )),
+                    Line::from(Span::styled(
+                        format!(
+                            "    {:<name_width$}  {:>6}  {:>8}  {:>5}  last used",
+                            "tag", "active", "archived", "trash"
+                        ),
+                        Style::default().fg(theme.hint),
+                    )),
+                ]),
+                rows[0],
+            );
+
+            let mut lines = Vec::with_capacity(visible.len());
+            for (idx, entry) in visible.iter().enumerate() {
+                let selected = idx == overlay.selected;
+                let marker = if selected { "➤ " } else { "  " };
+                let mark = if entry.marked { "*" } else { " " };
+                let style = if selected {
+                    Style::default()
+                        .fg(theme.focus_border)
+                        .add_modifier(Modifier::BOLD)
+                } else if entry.usage.is_unused() {
+                    Style::default().fg(theme.muted)
+                } else {
+                    Style::default()
+                };
+                let usage = &entry.usage;
+                lines.push(Line::from(vec![
+                    Span::styled(marker, style),
+                    Span::styled(
+                        format!("{mark} "),
+                        Style::default()
+                            .fg(theme.highlight)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(format!("{:<name_width$}", usage.name), style),
+                    Span::styled(
+                        format!(
+                            "  {:>6}  {:>8}  {:>5}  ",
+                            usage.active, usage.archived, usage.trashed
+                        ),
+                        Style::default().fg(theme.hint),
+                    ),
+                    Span::styled(
+                        usage
+                            .last_used
+                            .map_or_else(|| "never".to_string(), format_date),
+                        Style::default().fg(theme.muted),
+                    ),
+                ]));
+            }
+            if lines.is_empty() {
+                lines.push(Line::from(Span::styled(
+                    "No unused tags",
+                    Style::default().fg(theme.muted),
+                )));
+            }
+            let height = rows[1].height as usize;
+            let scroll = overlay
+                .selected
+                .saturating_sub(height.saturating_sub(1))
+                .min(u16::MAX as usize) as u16;
+            frame.render_widget(Paragraph::new(lines).scroll((scroll, 0)), rows[1]);
+
+            let footer = match &overlay.pending_delete {
+                Some(tags) => Line::from(Span::styled(
+                    format!(
+                        "Delete {} tag{}? y confirm • n / Esc cancel",
+                        tags.len(),
+                        if tags.len() == 1 { "" } else { "s" }
+                    ),
+                    Style::default().fg(theme.status_error),
+                )),
+                None => {
+                    let marked = overlay.entries.iter().filter(|entry| entry.marked).count();
+                    Line::from(Span::styled(
+                        format!("{} tags • {marked} marked", overlay.entries.len()),
+                        Style::default().fg(theme.muted),
+                    ))
+                }
+            };
+            frame.render_widget(Paragraph::new(footer), rows[2]);
+        }
+        Some(OverlayState::Palette(overlay)) => {
+            let area = centered_rect(70, 60, frame.size());
+            clear_overlay(frame, area, theme);
+            let block = Block::default()
+                .title("Command Palette")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.overlay_border));
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(1),
+                    Constraint::Length(1),
+                    Constraint::Min(1),
+                ])
+                .split(inner);
+            frame.render_widget(
+                Paragraph::new(Line::from(vec![
+                    Span::styled(": ", Style::default().fg(theme.highlight)),
+                    Span::raw(overlay.input.clone()),
+                    Span::styled("▌", Style::default().fg(theme.highlight)),
+                ])),
+                rows[0],
+            );
+            frame.render_widget(
+                Paragraph::new(Line::from(Span::styled(
+                    "Enter run • Tab complete • ↑/↓ move • Esc close",
+                    Style::default().fg(theme.muted),
+                ))),
+                rows[1],
+            );
+
+            let width = rows[2].width as usize;
+            let mut lines = Vec::with_capacity(overlay.matches.len());
+            for (position, idx) in overlay.matches.iter().enumerate() {
+                let entry = &overlay.entries[*idx];
+                let selected = position == overlay.selected;
+                let row_style = if selected {
+                    Style::default()
+                        .fg(theme.selection_fg)
+                        .bg(theme.selection_bg)
+                } else {
+                    Style::default()
+                };
+                let mut label = entry.name.clone();
+                if !entry.usage.is_empty() {
+                    label.push(' ');
+                    label.push_str(&entry.usage);
+                }
+                let label = format!("{label:<28} ");
+                let keys = if entry.keys.is_empty() {
+                    String::new()
+                } else {
+                    format!(" {}", entry.keys)
+                };
+                let used = label.width() + keys.width();
+                let description = truncate_to_width(entry.description, width.saturating_sub(used));
+                let padding = width.saturating_sub(used + description.width());
+                lines.push(Line::from(vec![
+                    Span::styled(label, row_style.add_modifier(Modifier::BOLD)),
+                    Span::styled(
+                        description,
+                        row_style.fg(if selected {
+                            theme.selection_fg
+                        } else {
+                            theme.muted
+                        }),
+                    ),
+                    Span::styled(" ".repeat(padding), row_style),
+                    Span::styled(keys, row_style.fg(theme.hint)),
+                ]));
+            }
+            if lines.is_empty() {
+                lines.push(Line::from(Span::styled(
+                    "No matching commands",
+                    Style::default().fg(theme.hint),
+                )));
+            }
+            let visible = rows[2].height as usize;
+            let scroll = overlay
+                .selected
+                .saturating_sub(visible.saturating_sub(1))
+                .min(u16::MAX as usize) as u16;
+            frame.render_widget(Paragraph::new(lines).scroll((scroll, 0)), rows[2]);
+        }
+        Some(OverlayState::Help(overlay)) => {
+            let area = centered_rect(70, 80, frame.size());
+            clear_overlay(frame, area, theme);
+            let block = Block::default()
+                .title(format!("Help: {}", overlay.topic.title()))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.overlay_border));
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(1),
+                    Constraint::Length(1),
+                    Constraint::Min(1),
+                ])
+                .split(inner);
+            frame.render_widget(
+                Paragraph::new(Line::from(vec![
+                    Span::styled("Filter: ", Style::default().fg(theme.highlight)),
+                    Span::raw(overlay.filter.clone()),
+                    Span::styled("▌", Style::default().fg(theme.highlight)),
+                ])),
+                rows[0],
+            );
+            frame.render_widget(
+                Paragraph::new(Line::from(Span::styled(
+                    "Type to filter • ↑/↓ PgUp/PgDn scroll • Esc close",
+                    Style::default().fg(theme.muted),
+                ))),
+                rows[1],
+            );
+
+            let entries = overlay.visible_entries();
+            let key_width = entries
+                .iter()
+                .map(|entry| entry.keys.width())
+                .max()
+                .unwrap_or(0)
+                .min(rows[2].width as usize / 2);
+            let mut lines: Vec<Line> = entries
+                .iter()
+                .map(|entry| {
+                    let keys = truncate_to_width(&entry.keys, key_width);
+                    let padding = key_width.saturating_sub(keys.width()) + 2;
+                    let mut spans = vec![
+                        Span::styled(
+                            keys,
+                            Style::default()
+                                .fg(theme.highlight)
+                                .add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw(" ".repeat(padding)),
+                        Span::raw(entry.description),
+                    ];
+                    if !entry.action.is_empty() {
+                        spans.push(Span::styled(
+                            format!("  ({})", entry.action),
+                            Style::default().fg(theme.hint),
+                        ));
+                    }
+                    Line::from(spans)
+                })
+                .collect();
+            if lines.is_empty() {
+                lines.push(Line::from(Span::styled(
+                    "No matching key bindings",
+                    Style::default().fg(theme.hint),
+                )));
+            }
+            let scroll = overlay.scroll.min(u16::MAX as usize) as u16;
+            frame.render_widget(Paragraph::new(lines).scroll((scroll, 0)), rows[2]);
+        }
         None => {}
     }
 }
 
+/// Cuts `text` to at most `max_width` columns, ending in `…` when shortened.
+fn truncate_to_width(text: &str, max_width: usize) -> String {
+    if text.width() <= max_width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        let width = grapheme.width();
+        if used + width + 1 > max_width {
+            break;
+        }
+        out.push_str(grapheme);
+        used += width;
+    }
+    if max_width > 0 {
+        out.push('…');
+    }
+    out
+}
+
+/// Blanks the popup area and paints it with the overlay background.
+fn clear_overlay(frame: &mut Frame, area: Rect, theme: &Theme) {
+    frame.render_widget(Clear, area);
+    frame.render_widget(Block::default().style(theme.overlay_style()), area);
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
     let vertical = Layout::default()
         .direction(Direction::Vertical)
@@ -1054,3 +1711,36 @@ fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
         )
         .split(vertical[1])[1]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::highlight::build_highlight_regex;
+    use ratatui::style::Style;
+    use ratatui::text::Span;
+
+    fn span_texts(spans: &[Span<'static>]) -> Vec<String> {
+        spans
+            .iter()
+            .map(|span| span.content.clone().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn highlight_regex_prefers_longer_tokens_first() {
+        let regex = build_highlight_regex(&["not".into(), "note".into()]).expect("regex");
+        let spans = highlight_line("notebook", Some(&regex), Style::default(), Style::default());
+        assert_eq!(
+            span_texts(&spans),
+            vec![String::from("note"), String::from("book")]
+        );
+    }
+
+    #[test]
+    fn highlight_regex_deduplicates_case_insensitive_tokens() {
+        let regex =
+            build_highlight_regex(&["Note".into(), "note".into(), "NOTE".into()]).expect("regex");
+        let spans = highlight_line("note", Some(&regex), Style::default(), Style::default());
+        assert_eq!(span_texts(&spans), vec![String::from("note")]);
+    }
+}